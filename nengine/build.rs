@@ -4,17 +4,106 @@ fn main() {
     let out_path = std::path::PathBuf::from(out_dir);
 
     if target_os == "linux" {
+        let has_wayland = std::env::var_os("CARGO_FEATURE_WAYLAND").is_some();
+        let has_xim = std::env::var_os("CARGO_FEATURE_XIM").is_some();
+
         println!("cargo:rustc-link-lib=xcb");
-        
-        bindgen::builder()
-            .header("src/ffi/xcb.h")
+        println!("cargo:rustc-link-lib=xcb-xkb");
+        println!("cargo:rustc-link-lib=xcb-xinput");
+        println!("cargo:rustc-link-lib=xcb-render");
+        println!("cargo:rustc-link-lib=xcb-randr");
+        println!("cargo:rustc-link-lib=xkbcommon");
+        println!("cargo:rustc-link-lib=xkbcommon-x11");
+
+        let mut xcb_builder = bindgen::builder().header("src/ffi/xcb.h");
+        if has_xim {
+            println!("cargo:rustc-link-lib=xcb-imdkit");
+            xcb_builder = xcb_builder.clang_arg("-DNENGINE_XIM");
+        }
+        xcb_builder
             .prepend_enum_name(false)
             .parse_callbacks(Box::new(bindgen::CargoCallbacks))
             .generate()
             .expect("Failed to generate bindings for XCB")
             .write_to_file(out_path.join("xcb_bindings.rs"))
             .expect("Failed to write bindings to a file");
+
+        if has_wayland {
+            println!("cargo:rustc-link-lib=wayland-client");
+            println!("cargo:rustc-link-lib=wayland-cursor");
+
+            generate_wayland_protocols(&out_path);
+
+            bindgen::builder()
+                .header("src/ffi/wayland.h")
+                .clang_arg(format!("-I{}", out_path.display()))
+                .prepend_enum_name(false)
+                .parse_callbacks(Box::new(bindgen::CargoCallbacks))
+                .generate()
+                .expect("Failed to generate bindings for Wayland")
+                .write_to_file(out_path.join("wayland_bindings.rs"))
+                .expect("Failed to write bindings to a file");
+        }
+
+        // Gamepads are read straight from the kernel, so there is nothing to
+        // link against.
+        bindgen::builder()
+            .header("src/ffi/evdev.h")
+            .prepend_enum_name(false)
+            .parse_callbacks(Box::new(bindgen::CargoCallbacks))
+            .generate()
+            .expect("Failed to generate bindings for evdev")
+            .write_to_file(out_path.join("evdev_bindings.rs"))
+            .expect("Failed to write bindings to a file");
     } else if target_os == "macos" {
         panic!("macOS is currently not supported!");
     }
 }
+
+/// The Wayland protocols outside of the core one, as their path inside of the
+/// wayland-protocols directory.
+const WAYLAND_PROTOCOLS: &[&str] = &[
+    "stable/xdg-shell/xdg-shell.xml",
+    "stable/viewporter/viewporter.xml",
+    "staging/fractional-scale/fractional-scale-v1.xml",
+    "unstable/xdg-decoration/xdg-decoration-unstable-v1.xml",
+    "unstable/relative-pointer/relative-pointer-unstable-v1.xml",
+    "unstable/pointer-constraints/pointer-constraints-unstable-v1.xml",
+    "unstable/text-input/text-input-unstable-v3.xml",
+];
+
+/// Runs wayland-scanner on every protocol, which gives a header and the C fi-
+/// le with the interface definitions. The C files are built into a static
+/// library, the headers are picked up by bindgen through `wayland.h`.
+fn generate_wayland_protocols(out_path: &std::path::Path) {
+    let output = std::process::Command::new("pkg-config")
+        .args(["--variable=pkgdatadir", "wayland-protocols"])
+        .output()
+        .expect("Failed to run pkg-config");
+    let protocols_dir = String::from_utf8(output.stdout).unwrap();
+    let protocols_dir = std::path::Path::new(protocols_dir.trim());
+
+    let mut build = cc::Build::new();
+    for protocol in WAYLAND_PROTOCOLS {
+        let xml = protocols_dir.join(protocol);
+        let name = xml.file_stem().unwrap().to_str().unwrap().to_owned();
+        println!("cargo:rerun-if-changed={}", xml.display());
+
+        for (kind, file) in [
+            ("client-header", format!("{}-client-protocol.h", name)),
+            ("private-code", format!("{}-protocol.c", name)),
+        ] {
+            let status = std::process::Command::new("wayland-scanner")
+                .arg(kind)
+                .arg(&xml)
+                .arg(out_path.join(&file))
+                .status()
+                .expect("Failed to run wayland-scanner");
+            assert!(status.success(), "wayland-scanner failed on {}", protocol);
+        }
+
+        build.file(out_path.join(format!("{}-protocol.c", name)));
+    }
+
+    build.compile("nengine_wayland_protocols");
+}
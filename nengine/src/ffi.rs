@@ -1,4 +1,8 @@
 /// FFI modules
 
 #[cfg(target_os = "linux")]
-pub mod xcb;
\ No newline at end of file
+pub mod xcb;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+pub mod wayland;
+#[cfg(target_os = "linux")]
+pub mod evdev;
\ No newline at end of file
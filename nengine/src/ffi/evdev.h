@@ -0,0 +1,14 @@
+#ifndef B92E41C7_6D3A_4F85_B1E0_7A4C2D8F5E16
+#define B92E41C7_6D3A_4F85_B1E0_7A4C2D8F5E16
+
+// For `O_NONBLOCK`, which devices are opened with.
+#include <fcntl.h>
+// For `ioctl`, which everything about an input device goes through.
+#include <sys/ioctl.h>
+// For watching `/dev/input`, which is how gamepads are hotplugged.
+#include <sys/inotify.h>
+
+#include <linux/input.h>
+#include <linux/uinput.h>
+
+#endif /* B92E41C7_6D3A_4F85_B1E0_7A4C2D8F5E16 */
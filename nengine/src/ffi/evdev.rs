@@ -0,0 +1,51 @@
+#![allow(non_upper_case_globals)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+include!(concat!(env!("OUT_DIR"), "/evdev_bindings.rs"));
+
+use std::mem::size_of;
+use std::os::raw::c_ulong;
+
+// The ioctl requests are function-like macros in the kernel headers, which
+// bindgen skips. These are the same macros, written out by hand. The layout
+// is the generic one, which x86 and ARM use.
+
+const IOC_NONE: c_ulong = 0;
+const IOC_WRITE: c_ulong = 1;
+const IOC_READ: c_ulong = 2;
+
+const fn ioc(direction: c_ulong, kind: u8, number: u32, size: usize) -> c_ulong {
+    (direction << 30) | ((size as c_ulong) << 16) | ((kind as c_ulong) << 8) | number as c_ulong
+}
+
+pub const EVIOCGID: c_ulong = ioc(IOC_READ, b'E', 0x02, size_of::<input_id>());
+pub const EVIOCSFF: c_ulong = ioc(IOC_WRITE, b'E', 0x80, size_of::<ff_effect>());
+
+/// Reads the name of the device into a buffer of `len` bytes.
+pub const fn EVIOCGNAME(len: usize) -> c_ulong {
+    ioc(IOC_READ, b'E', 0x06, len)
+}
+
+/// Reads the bits of the codes the device has for the event type `ev`.
+pub const fn EVIOCGBIT(ev: u32, len: usize) -> c_ulong {
+    ioc(IOC_READ, b'E', 0x20 + ev, len)
+}
+
+/// Reads the bits of the keys that are held down.
+pub const fn EVIOCGKEY(len: usize) -> c_ulong {
+    ioc(IOC_READ, b'E', 0x18, len)
+}
+
+/// Reads the range and current value of an absolute axis.
+pub const fn EVIOCGABS(abs: u32) -> c_ulong {
+    ioc(IOC_READ, b'E', 0x40 + abs, size_of::<input_absinfo>())
+}
+
+pub const UI_DEV_CREATE: c_ulong = ioc(IOC_NONE, b'U', 1, 0);
+pub const UI_DEV_DESTROY: c_ulong = ioc(IOC_NONE, b'U', 2, 0);
+pub const UI_DEV_SETUP: c_ulong = ioc(IOC_WRITE, b'U', 3, size_of::<uinput_setup>());
+pub const UI_ABS_SETUP: c_ulong = ioc(IOC_WRITE, b'U', 4, size_of::<uinput_abs_setup>());
+pub const UI_SET_EVBIT: c_ulong = ioc(IOC_WRITE, b'U', 100, size_of::<i32>());
+pub const UI_SET_KEYBIT: c_ulong = ioc(IOC_WRITE, b'U', 101, size_of::<i32>());
+pub const UI_SET_ABSBIT: c_ulong = ioc(IOC_WRITE, b'U', 103, size_of::<i32>());
@@ -0,0 +1,19 @@
+#ifndef E4B7D9A2_3C1F_4E8B_A6D0_5F2C8B1E7A93
+#define E4B7D9A2_3C1F_4E8B_A6D0_5F2C8B1E7A93
+
+// For `poll`, which waits for the compositor without blocking forever.
+#include <poll.h>
+
+#include <wayland-client.h>
+#include <wayland-cursor.h>
+
+// Generated by wayland-scanner in the build script.
+#include "xdg-shell-client-protocol.h"
+#include "viewporter-client-protocol.h"
+#include "fractional-scale-v1-client-protocol.h"
+#include "xdg-decoration-unstable-v1-client-protocol.h"
+#include "relative-pointer-unstable-v1-client-protocol.h"
+#include "pointer-constraints-unstable-v1-client-protocol.h"
+#include "text-input-unstable-v3-client-protocol.h"
+
+#endif /* E4B7D9A2_3C1F_4E8B_A6D0_5F2C8B1E7A93 */
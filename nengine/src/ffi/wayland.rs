@@ -0,0 +1,598 @@
+#![allow(non_upper_case_globals)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+include!(concat!(env!("OUT_DIR"), "/wayland_bindings.rs"));
+
+use std::ffi::c_void;
+use std::os::raw::c_char;
+
+// The requests of the protocols are `static inline` functions in the headers
+// made by wayland-scanner, which bindgen skips. These are the same functions,
+// written out by hand on top of `wl_proxy_marshal_flags`. Only the requests
+// the Nengine actually sends are here.
+
+/// Sends a request that creates a new object of `interface`.
+unsafe fn marshal_constructor<T>(
+    proxy: *mut T,
+    opcode: u32,
+    interface: *const wl_interface,
+) -> *mut wl_proxy {
+    let proxy = proxy as *mut wl_proxy;
+    wl_proxy_marshal_flags(
+        proxy,
+        opcode,
+        interface,
+        wl_proxy_get_version(proxy),
+        0,
+        std::ptr::null_mut::<c_void>(),
+    )
+}
+
+/// Sends a request that creates a new object of `interface` from another ob-
+/// ject, like `xdg_wm_base.get_xdg_surface`.
+unsafe fn marshal_constructor_for<T, U>(
+    proxy: *mut T,
+    opcode: u32,
+    interface: *const wl_interface,
+    object: *mut U,
+) -> *mut wl_proxy {
+    let proxy = proxy as *mut wl_proxy;
+    wl_proxy_marshal_flags(
+        proxy,
+        opcode,
+        interface,
+        wl_proxy_get_version(proxy),
+        0,
+        std::ptr::null_mut::<c_void>(),
+        object,
+    )
+}
+
+/// Sends a destructor request and destroys the proxy along with it.
+unsafe fn marshal_destructor<T>(proxy: *mut T, opcode: u32) {
+    let proxy = proxy as *mut wl_proxy;
+    wl_proxy_marshal_flags(
+        proxy,
+        opcode,
+        std::ptr::null(),
+        wl_proxy_get_version(proxy),
+        WL_MARSHAL_FLAG_DESTROY,
+    );
+}
+
+/// Converts the fixed-point numbers Wayland uses for coordinates.
+pub fn wl_fixed_to_double(value: wl_fixed_t) -> f64 {
+    f64::from(value) / 256.0
+}
+
+pub unsafe fn wl_display_get_registry(display: *mut wl_display) -> *mut wl_registry {
+    marshal_constructor(display, WL_DISPLAY_GET_REGISTRY, &wl_registry_interface) as *mut _
+}
+
+pub unsafe fn wl_registry_bind(
+    registry: *mut wl_registry,
+    name: u32,
+    interface: *const wl_interface,
+    version: u32,
+) -> *mut c_void {
+    wl_proxy_marshal_flags(
+        registry as *mut wl_proxy,
+        WL_REGISTRY_BIND,
+        interface,
+        version,
+        0,
+        name,
+        (*interface).name,
+        version,
+        std::ptr::null_mut::<c_void>(),
+    ) as *mut c_void
+}
+
+pub unsafe fn wl_compositor_create_surface(compositor: *mut wl_compositor) -> *mut wl_surface {
+    marshal_constructor(
+        compositor,
+        WL_COMPOSITOR_CREATE_SURFACE,
+        &wl_surface_interface,
+    ) as *mut _
+}
+
+pub unsafe fn wl_surface_destroy(surface: *mut wl_surface) {
+    marshal_destructor(surface, WL_SURFACE_DESTROY);
+}
+
+pub unsafe fn wl_surface_attach(surface: *mut wl_surface, buffer: *mut wl_buffer, x: i32, y: i32) {
+    wl_proxy_marshal_flags(
+        surface as *mut wl_proxy,
+        WL_SURFACE_ATTACH,
+        std::ptr::null(),
+        wl_proxy_get_version(surface as *mut wl_proxy),
+        0,
+        buffer,
+        x,
+        y,
+    );
+}
+
+pub unsafe fn wl_surface_damage(
+    surface: *mut wl_surface,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+) {
+    wl_proxy_marshal_flags(
+        surface as *mut wl_proxy,
+        WL_SURFACE_DAMAGE,
+        std::ptr::null(),
+        wl_proxy_get_version(surface as *mut wl_proxy),
+        0,
+        x,
+        y,
+        width,
+        height,
+    );
+}
+
+pub unsafe fn wl_surface_commit(surface: *mut wl_surface) {
+    wl_proxy_marshal_flags(
+        surface as *mut wl_proxy,
+        WL_SURFACE_COMMIT,
+        std::ptr::null(),
+        wl_proxy_get_version(surface as *mut wl_proxy),
+        0,
+    );
+}
+
+pub unsafe fn wl_shm_create_pool(shm: *mut wl_shm, fd: i32, size: i32) -> *mut wl_shm_pool {
+    wl_proxy_marshal_flags(
+        shm as *mut wl_proxy,
+        WL_SHM_CREATE_POOL,
+        &wl_shm_pool_interface,
+        wl_proxy_get_version(shm as *mut wl_proxy),
+        0,
+        std::ptr::null_mut::<c_void>(),
+        fd,
+        size,
+    ) as *mut _
+}
+
+pub unsafe fn wl_shm_pool_create_buffer(
+    pool: *mut wl_shm_pool,
+    offset: i32,
+    width: i32,
+    height: i32,
+    stride: i32,
+    format: u32,
+) -> *mut wl_buffer {
+    wl_proxy_marshal_flags(
+        pool as *mut wl_proxy,
+        WL_SHM_POOL_CREATE_BUFFER,
+        &wl_buffer_interface,
+        wl_proxy_get_version(pool as *mut wl_proxy),
+        0,
+        std::ptr::null_mut::<c_void>(),
+        offset,
+        width,
+        height,
+        stride,
+        format,
+    ) as *mut _
+}
+
+pub unsafe fn wl_shm_pool_destroy(pool: *mut wl_shm_pool) {
+    marshal_destructor(pool, WL_SHM_POOL_DESTROY);
+}
+
+pub unsafe fn wl_buffer_destroy(buffer: *mut wl_buffer) {
+    marshal_destructor(buffer, WL_BUFFER_DESTROY);
+}
+
+pub unsafe fn wl_seat_get_pointer(seat: *mut wl_seat) -> *mut wl_pointer {
+    marshal_constructor(seat, WL_SEAT_GET_POINTER, &wl_pointer_interface) as *mut _
+}
+
+pub unsafe fn wl_seat_get_keyboard(seat: *mut wl_seat) -> *mut wl_keyboard {
+    marshal_constructor(seat, WL_SEAT_GET_KEYBOARD, &wl_keyboard_interface) as *mut _
+}
+
+pub unsafe fn wl_pointer_set_cursor(
+    pointer: *mut wl_pointer,
+    serial: u32,
+    surface: *mut wl_surface,
+    hotspot_x: i32,
+    hotspot_y: i32,
+) {
+    wl_proxy_marshal_flags(
+        pointer as *mut wl_proxy,
+        WL_POINTER_SET_CURSOR,
+        std::ptr::null(),
+        wl_proxy_get_version(pointer as *mut wl_proxy),
+        0,
+        serial,
+        surface,
+        hotspot_x,
+        hotspot_y,
+    );
+}
+
+pub unsafe fn wl_pointer_release(pointer: *mut wl_pointer) {
+    marshal_destructor(pointer, WL_POINTER_RELEASE);
+}
+
+pub unsafe fn wl_keyboard_release(keyboard: *mut wl_keyboard) {
+    marshal_destructor(keyboard, WL_KEYBOARD_RELEASE);
+}
+
+pub unsafe fn xdg_wm_base_destroy(wm_base: *mut xdg_wm_base) {
+    marshal_destructor(wm_base, XDG_WM_BASE_DESTROY);
+}
+
+pub unsafe fn xdg_wm_base_get_xdg_surface(
+    wm_base: *mut xdg_wm_base,
+    surface: *mut wl_surface,
+) -> *mut xdg_surface {
+    marshal_constructor_for(
+        wm_base,
+        XDG_WM_BASE_GET_XDG_SURFACE,
+        &xdg_surface_interface,
+        surface,
+    ) as *mut _
+}
+
+pub unsafe fn xdg_wm_base_pong(wm_base: *mut xdg_wm_base, serial: u32) {
+    wl_proxy_marshal_flags(
+        wm_base as *mut wl_proxy,
+        XDG_WM_BASE_PONG,
+        std::ptr::null(),
+        wl_proxy_get_version(wm_base as *mut wl_proxy),
+        0,
+        serial,
+    );
+}
+
+pub unsafe fn xdg_surface_destroy(surface: *mut xdg_surface) {
+    marshal_destructor(surface, XDG_SURFACE_DESTROY);
+}
+
+pub unsafe fn xdg_surface_get_toplevel(surface: *mut xdg_surface) -> *mut xdg_toplevel {
+    marshal_constructor(surface, XDG_SURFACE_GET_TOPLEVEL, &xdg_toplevel_interface) as *mut _
+}
+
+pub unsafe fn xdg_surface_ack_configure(surface: *mut xdg_surface, serial: u32) {
+    wl_proxy_marshal_flags(
+        surface as *mut wl_proxy,
+        XDG_SURFACE_ACK_CONFIGURE,
+        std::ptr::null(),
+        wl_proxy_get_version(surface as *mut wl_proxy),
+        0,
+        serial,
+    );
+}
+
+pub unsafe fn xdg_toplevel_destroy(toplevel: *mut xdg_toplevel) {
+    marshal_destructor(toplevel, XDG_TOPLEVEL_DESTROY);
+}
+
+/// Sends one of the `xdg_toplevel` requests that take no arguments.
+unsafe fn xdg_toplevel_request(toplevel: *mut xdg_toplevel, opcode: u32) {
+    wl_proxy_marshal_flags(
+        toplevel as *mut wl_proxy,
+        opcode,
+        std::ptr::null(),
+        wl_proxy_get_version(toplevel as *mut wl_proxy),
+        0,
+    );
+}
+
+pub unsafe fn xdg_toplevel_set_parent(toplevel: *mut xdg_toplevel, parent: *mut xdg_toplevel) {
+    wl_proxy_marshal_flags(
+        toplevel as *mut wl_proxy,
+        XDG_TOPLEVEL_SET_PARENT,
+        std::ptr::null(),
+        wl_proxy_get_version(toplevel as *mut wl_proxy),
+        0,
+        parent,
+    );
+}
+
+pub unsafe fn xdg_toplevel_set_title(toplevel: *mut xdg_toplevel, title: *const c_char) {
+    wl_proxy_marshal_flags(
+        toplevel as *mut wl_proxy,
+        XDG_TOPLEVEL_SET_TITLE,
+        std::ptr::null(),
+        wl_proxy_get_version(toplevel as *mut wl_proxy),
+        0,
+        title,
+    );
+}
+
+pub unsafe fn xdg_toplevel_set_app_id(toplevel: *mut xdg_toplevel, app_id: *const c_char) {
+    wl_proxy_marshal_flags(
+        toplevel as *mut wl_proxy,
+        XDG_TOPLEVEL_SET_APP_ID,
+        std::ptr::null(),
+        wl_proxy_get_version(toplevel as *mut wl_proxy),
+        0,
+        app_id,
+    );
+}
+
+pub unsafe fn xdg_toplevel_set_max_size(toplevel: *mut xdg_toplevel, width: i32, height: i32) {
+    wl_proxy_marshal_flags(
+        toplevel as *mut wl_proxy,
+        XDG_TOPLEVEL_SET_MAX_SIZE,
+        std::ptr::null(),
+        wl_proxy_get_version(toplevel as *mut wl_proxy),
+        0,
+        width,
+        height,
+    );
+}
+
+pub unsafe fn xdg_toplevel_set_min_size(toplevel: *mut xdg_toplevel, width: i32, height: i32) {
+    wl_proxy_marshal_flags(
+        toplevel as *mut wl_proxy,
+        XDG_TOPLEVEL_SET_MIN_SIZE,
+        std::ptr::null(),
+        wl_proxy_get_version(toplevel as *mut wl_proxy),
+        0,
+        width,
+        height,
+    );
+}
+
+pub unsafe fn xdg_toplevel_set_maximized(toplevel: *mut xdg_toplevel) {
+    xdg_toplevel_request(toplevel, XDG_TOPLEVEL_SET_MAXIMIZED);
+}
+
+pub unsafe fn xdg_toplevel_unset_maximized(toplevel: *mut xdg_toplevel) {
+    xdg_toplevel_request(toplevel, XDG_TOPLEVEL_UNSET_MAXIMIZED);
+}
+
+/// Always lets the compositor pick the output.
+pub unsafe fn xdg_toplevel_set_fullscreen(toplevel: *mut xdg_toplevel) {
+    wl_proxy_marshal_flags(
+        toplevel as *mut wl_proxy,
+        XDG_TOPLEVEL_SET_FULLSCREEN,
+        std::ptr::null(),
+        wl_proxy_get_version(toplevel as *mut wl_proxy),
+        0,
+        std::ptr::null_mut::<wl_output>(),
+    );
+}
+
+pub unsafe fn xdg_toplevel_unset_fullscreen(toplevel: *mut xdg_toplevel) {
+    xdg_toplevel_request(toplevel, XDG_TOPLEVEL_UNSET_FULLSCREEN);
+}
+
+pub unsafe fn xdg_toplevel_set_minimized(toplevel: *mut xdg_toplevel) {
+    xdg_toplevel_request(toplevel, XDG_TOPLEVEL_SET_MINIMIZED);
+}
+
+pub unsafe fn wp_viewporter_destroy(viewporter: *mut wp_viewporter) {
+    marshal_destructor(viewporter, WP_VIEWPORTER_DESTROY);
+}
+
+pub unsafe fn wp_viewporter_get_viewport(
+    viewporter: *mut wp_viewporter,
+    surface: *mut wl_surface,
+) -> *mut wp_viewport {
+    marshal_constructor_for(
+        viewporter,
+        WP_VIEWPORTER_GET_VIEWPORT,
+        &wp_viewport_interface,
+        surface,
+    ) as *mut _
+}
+
+pub unsafe fn wp_viewport_destroy(viewport: *mut wp_viewport) {
+    marshal_destructor(viewport, WP_VIEWPORT_DESTROY);
+}
+
+pub unsafe fn wp_viewport_set_destination(viewport: *mut wp_viewport, width: i32, height: i32) {
+    wl_proxy_marshal_flags(
+        viewport as *mut wl_proxy,
+        WP_VIEWPORT_SET_DESTINATION,
+        std::ptr::null(),
+        wl_proxy_get_version(viewport as *mut wl_proxy),
+        0,
+        width,
+        height,
+    );
+}
+
+pub unsafe fn wp_fractional_scale_manager_v1_destroy(manager: *mut wp_fractional_scale_manager_v1) {
+    marshal_destructor(manager, WP_FRACTIONAL_SCALE_MANAGER_V1_DESTROY);
+}
+
+pub unsafe fn wp_fractional_scale_manager_v1_get_fractional_scale(
+    manager: *mut wp_fractional_scale_manager_v1,
+    surface: *mut wl_surface,
+) -> *mut wp_fractional_scale_v1 {
+    marshal_constructor_for(
+        manager,
+        WP_FRACTIONAL_SCALE_MANAGER_V1_GET_FRACTIONAL_SCALE,
+        &wp_fractional_scale_v1_interface,
+        surface,
+    ) as *mut _
+}
+
+pub unsafe fn wp_fractional_scale_v1_destroy(scale: *mut wp_fractional_scale_v1) {
+    marshal_destructor(scale, WP_FRACTIONAL_SCALE_V1_DESTROY);
+}
+
+pub unsafe fn zxdg_decoration_manager_v1_destroy(manager: *mut zxdg_decoration_manager_v1) {
+    marshal_destructor(manager, ZXDG_DECORATION_MANAGER_V1_DESTROY);
+}
+
+pub unsafe fn zxdg_decoration_manager_v1_get_toplevel_decoration(
+    manager: *mut zxdg_decoration_manager_v1,
+    toplevel: *mut xdg_toplevel,
+) -> *mut zxdg_toplevel_decoration_v1 {
+    marshal_constructor_for(
+        manager,
+        ZXDG_DECORATION_MANAGER_V1_GET_TOPLEVEL_DECORATION,
+        &zxdg_toplevel_decoration_v1_interface,
+        toplevel,
+    ) as *mut _
+}
+
+pub unsafe fn zxdg_toplevel_decoration_v1_destroy(decoration: *mut zxdg_toplevel_decoration_v1) {
+    marshal_destructor(decoration, ZXDG_TOPLEVEL_DECORATION_V1_DESTROY);
+}
+
+pub unsafe fn zxdg_toplevel_decoration_v1_set_mode(
+    decoration: *mut zxdg_toplevel_decoration_v1,
+    mode: u32,
+) {
+    wl_proxy_marshal_flags(
+        decoration as *mut wl_proxy,
+        ZXDG_TOPLEVEL_DECORATION_V1_SET_MODE,
+        std::ptr::null(),
+        wl_proxy_get_version(decoration as *mut wl_proxy),
+        0,
+        mode,
+    );
+}
+
+pub unsafe fn zwp_relative_pointer_manager_v1_destroy(
+    manager: *mut zwp_relative_pointer_manager_v1,
+) {
+    marshal_destructor(manager, ZWP_RELATIVE_POINTER_MANAGER_V1_DESTROY);
+}
+
+pub unsafe fn zwp_relative_pointer_manager_v1_get_relative_pointer(
+    manager: *mut zwp_relative_pointer_manager_v1,
+    pointer: *mut wl_pointer,
+) -> *mut zwp_relative_pointer_v1 {
+    marshal_constructor_for(
+        manager,
+        ZWP_RELATIVE_POINTER_MANAGER_V1_GET_RELATIVE_POINTER,
+        &zwp_relative_pointer_v1_interface,
+        pointer,
+    ) as *mut _
+}
+
+pub unsafe fn zwp_relative_pointer_v1_destroy(pointer: *mut zwp_relative_pointer_v1) {
+    marshal_destructor(pointer, ZWP_RELATIVE_POINTER_V1_DESTROY);
+}
+
+pub unsafe fn zwp_pointer_constraints_v1_destroy(constraints: *mut zwp_pointer_constraints_v1) {
+    marshal_destructor(constraints, ZWP_POINTER_CONSTRAINTS_V1_DESTROY);
+}
+
+/// Locks the pointer in place while it is over `surface`. The region is left
+/// null, which means the whole surface.
+pub unsafe fn zwp_pointer_constraints_v1_lock_pointer(
+    constraints: *mut zwp_pointer_constraints_v1,
+    surface: *mut wl_surface,
+    pointer: *mut wl_pointer,
+    lifetime: u32,
+) -> *mut zwp_locked_pointer_v1 {
+    wl_proxy_marshal_flags(
+        constraints as *mut wl_proxy,
+        ZWP_POINTER_CONSTRAINTS_V1_LOCK_POINTER,
+        &zwp_locked_pointer_v1_interface,
+        wl_proxy_get_version(constraints as *mut wl_proxy),
+        0,
+        std::ptr::null_mut::<c_void>(),
+        surface,
+        pointer,
+        std::ptr::null_mut::<wl_region>(),
+        lifetime,
+    ) as *mut _
+}
+
+/// Keeps the pointer inside of `surface`. The region is left null, which me-
+/// ans the whole surface.
+pub unsafe fn zwp_pointer_constraints_v1_confine_pointer(
+    constraints: *mut zwp_pointer_constraints_v1,
+    surface: *mut wl_surface,
+    pointer: *mut wl_pointer,
+    lifetime: u32,
+) -> *mut zwp_confined_pointer_v1 {
+    wl_proxy_marshal_flags(
+        constraints as *mut wl_proxy,
+        ZWP_POINTER_CONSTRAINTS_V1_CONFINE_POINTER,
+        &zwp_confined_pointer_v1_interface,
+        wl_proxy_get_version(constraints as *mut wl_proxy),
+        0,
+        std::ptr::null_mut::<c_void>(),
+        surface,
+        pointer,
+        std::ptr::null_mut::<wl_region>(),
+        lifetime,
+    ) as *mut _
+}
+
+pub unsafe fn zwp_locked_pointer_v1_destroy(pointer: *mut zwp_locked_pointer_v1) {
+    marshal_destructor(pointer, ZWP_LOCKED_POINTER_V1_DESTROY);
+}
+
+pub unsafe fn zwp_confined_pointer_v1_destroy(pointer: *mut zwp_confined_pointer_v1) {
+    marshal_destructor(pointer, ZWP_CONFINED_POINTER_V1_DESTROY);
+}
+
+pub unsafe fn zwp_text_input_manager_v3_destroy(manager: *mut zwp_text_input_manager_v3) {
+    marshal_destructor(manager, ZWP_TEXT_INPUT_MANAGER_V3_DESTROY);
+}
+
+pub unsafe fn zwp_text_input_manager_v3_get_text_input(
+    manager: *mut zwp_text_input_manager_v3,
+    seat: *mut wl_seat,
+) -> *mut zwp_text_input_v3 {
+    marshal_constructor_for(
+        manager,
+        ZWP_TEXT_INPUT_MANAGER_V3_GET_TEXT_INPUT,
+        &zwp_text_input_v3_interface,
+        seat,
+    ) as *mut _
+}
+
+pub unsafe fn zwp_text_input_v3_destroy(text_input: *mut zwp_text_input_v3) {
+    marshal_destructor(text_input, ZWP_TEXT_INPUT_V3_DESTROY);
+}
+
+/// Sends one of the `zwp_text_input_v3` requests that take no arguments.
+unsafe fn zwp_text_input_v3_request(text_input: *mut zwp_text_input_v3, opcode: u32) {
+    wl_proxy_marshal_flags(
+        text_input as *mut wl_proxy,
+        opcode,
+        std::ptr::null(),
+        wl_proxy_get_version(text_input as *mut wl_proxy),
+        0,
+    );
+}
+
+pub unsafe fn zwp_text_input_v3_enable(text_input: *mut zwp_text_input_v3) {
+    zwp_text_input_v3_request(text_input, ZWP_TEXT_INPUT_V3_ENABLE);
+}
+
+pub unsafe fn zwp_text_input_v3_disable(text_input: *mut zwp_text_input_v3) {
+    zwp_text_input_v3_request(text_input, ZWP_TEXT_INPUT_V3_DISABLE);
+}
+
+pub unsafe fn zwp_text_input_v3_commit(text_input: *mut zwp_text_input_v3) {
+    zwp_text_input_v3_request(text_input, ZWP_TEXT_INPUT_V3_COMMIT);
+}
+
+pub unsafe fn zwp_text_input_v3_set_cursor_rectangle(
+    text_input: *mut zwp_text_input_v3,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+) {
+    wl_proxy_marshal_flags(
+        text_input as *mut wl_proxy,
+        ZWP_TEXT_INPUT_V3_SET_CURSOR_RECTANGLE,
+        std::ptr::null(),
+        wl_proxy_get_version(text_input as *mut wl_proxy),
+        0,
+        x,
+        y,
+        width,
+        height,
+    );
+}
@@ -1,8 +1,24 @@
 #ifndef C40AE0B5_750F_4DAF_9097_754429831D10
 #define C40AE0B5_750F_4DAF_9097_754429831D10
 
+// For `free`, which releases the events and replies that XCB hands out.
+#include <stdlib.h>
+// For `poll`, which waits on the connection for the owner of a selection.
+#include <poll.h>
+
 #include <xcb/xcb.h>
+#include <xcb/xkb.h>
+#include <xcb/xinput.h>
+#include <xcb/render.h>
+#include <xcb/randr.h>
 #include <xkbcommon/xkbcommon.h>
+#include <xkbcommon/xkbcommon-compose.h>
 #include <xkbcommon/xkbcommon-x11.h>
+// XIM over XCB, which input methods are reached through. It is only there
+// with the `xim` feature, since few distributions ship xcb-imdkit.
+#ifdef NENGINE_XIM
+#include <xcb-imdkit/imclient.h>
+#include <xcb-imdkit/encoding.h>
+#endif
 
 #endif /* C40AE0B5_750F_4DAF_9097_754429831D10 */
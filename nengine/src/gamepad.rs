@@ -0,0 +1,123 @@
+// The gamepad module reads gamepads and joysticks. Unlike the keyboard and
+// the mouse, they do not belong to a window, so they have their own event
+// source in `Gamepads`. Devices are mapped to the layout of an Xbox controll-
+// er, either with the mappings of SDL's `gamecontrollerdb.txt` or, for gamep-
+// ads that follow the kernel's layout, on their own.
+
+// Only the Linux backend reads devices, so far.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod mapping;
+
+// Linux stuff
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use self::linux::*;
+
+// Everything else, which has no gamepads yet
+#[cfg(not(target_os = "linux"))]
+mod unsupported;
+#[cfg(not(target_os = "linux"))]
+pub use self::unsupported::*;
+
+use std::fmt;
+
+/// Identifies a gamepad for as long as it is connected. A gamepad that is
+/// plugged in again gets a new id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GamepadId(pub(crate) u64);
+
+/// The buttons of a gamepad, named after where they are on an Xbox control-
+/// ler. `A` is the bottom face button on every gamepad, even if it has a
+/// different label.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    Back,
+    Guide,
+    Start,
+    LeftStick,
+    RightStick,
+    LeftShoulder,
+    RightShoulder,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    /// The extra button in the middle, e.g. Share or Capture.
+    Misc,
+    /// A button of a joystick that has no mapping, by its number.
+    Other(u16),
+}
+
+/// The axes of a gamepad. Sticks go from -1.0 to 1.0, with up and right be-
+/// ing positive, and triggers from 0.0 to 1.0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+    /// An axis of a joystick that has no mapping, by its number.
+    Other(u16),
+}
+
+impl GamepadAxis {
+    fn is_trigger(self) -> bool {
+        self == GamepadAxis::LeftTrigger || self == GamepadAxis::RightTrigger
+    }
+}
+
+/// The ways setting up gamepads can fail.
+#[derive(Debug)]
+pub enum GamepadError {
+    /// The operating system reported an error.
+    Os(String),
+    /// Gamepads are not supported on this platform yet.
+    Unsupported,
+}
+
+impl fmt::Display for GamepadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GamepadError::Os(message) => write!(f, "{}", message),
+            GamepadError::Unsupported => write!(f, "gamepads are not supported on this platform"),
+        }
+    }
+}
+
+impl std::error::Error for GamepadError {}
+
+/// How far an axis has to move before it counts, unless `set_deadzone` says
+/// otherwise. Worn sticks rarely rest further out than this.
+pub const DEFAULT_DEADZONE: f64 = 0.1;
+
+/// Drops the part of an axis that is within the deadzone, and stretches the
+/// rest so the axis still reaches its ends. Without this a stick at rest
+/// keeps sending small values.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn apply_deadzone(value: f64, deadzone: f64) -> f64 {
+    if value.abs() <= deadzone {
+        return 0.0;
+    }
+
+    value.signum() * ((value.abs() - deadzone) / (1.0 - deadzone)).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadzone_is_cut_out_and_the_rest_stretched() {
+        assert_eq!(apply_deadzone(0.05, 0.1), 0.0);
+        assert_eq!(apply_deadzone(-0.1, 0.1), 0.0);
+        assert_eq!(apply_deadzone(1.0, 0.1), 1.0);
+        assert_eq!(apply_deadzone(-0.75, 0.5), -0.5);
+    }
+}
@@ -0,0 +1,818 @@
+// Gamepads on Linux are read straight from their evdev nodes in `/dev/input`,
+// which udev makes readable for the user at the seat. New nodes are found by
+// watching the directory with inotify, and a node that goes away reads back
+// an error.
+
+use super::mapping::{make_guid, Change, Guid, Half, Input, Mapping, Mappings, Output, Value};
+use super::{
+    apply_deadzone, GamepadAxis, GamepadButton, GamepadError, GamepadId, DEFAULT_DEADZONE,
+};
+use crate::ffi::evdev;
+use crate::platform::Events;
+use crate::Event;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::CString;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const INPUT_DIR: &str = "/dev/input";
+
+// How many bytes it takes to hold one bit per code, for `EVIOCGBIT`.
+const KEY_BYTES: usize = evdev::KEY_MAX as usize / 8 + 1;
+const ABS_BYTES: usize = evdev::ABS_MAX as usize / 8 + 1;
+const FF_BYTES: usize = evdev::FF_MAX as usize / 8 + 1;
+
+// The directions of a hat, as SDL numbers them.
+const HAT_UP: u8 = 1;
+const HAT_RIGHT: u8 = 2;
+const HAT_DOWN: u8 = 4;
+const HAT_LEFT: u8 = 8;
+
+/// Gamepads that follow the layout of the kernel, which most drivers do, need
+/// no mapping of their own. This is that layout.
+const KERNEL_BUTTONS: &[(u32, Output)] = &[
+    (evdev::BTN_SOUTH, Output::Button(GamepadButton::A)),
+    (evdev::BTN_EAST, Output::Button(GamepadButton::B)),
+    (evdev::BTN_WEST, Output::Button(GamepadButton::X)),
+    (evdev::BTN_NORTH, Output::Button(GamepadButton::Y)),
+    (evdev::BTN_SELECT, Output::Button(GamepadButton::Back)),
+    (evdev::BTN_MODE, Output::Button(GamepadButton::Guide)),
+    (evdev::BTN_START, Output::Button(GamepadButton::Start)),
+    (evdev::BTN_THUMBL, Output::Button(GamepadButton::LeftStick)),
+    (evdev::BTN_THUMBR, Output::Button(GamepadButton::RightStick)),
+    (evdev::BTN_TL, Output::Button(GamepadButton::LeftShoulder)),
+    (evdev::BTN_TR, Output::Button(GamepadButton::RightShoulder)),
+    (evdev::BTN_DPAD_UP, Output::Button(GamepadButton::DPadUp)),
+    (
+        evdev::BTN_DPAD_DOWN,
+        Output::Button(GamepadButton::DPadDown),
+    ),
+    (
+        evdev::BTN_DPAD_LEFT,
+        Output::Button(GamepadButton::DPadLeft),
+    ),
+    (
+        evdev::BTN_DPAD_RIGHT,
+        Output::Button(GamepadButton::DPadRight),
+    ),
+    // Triggers without an axis of their own.
+    (evdev::BTN_TL2, full_axis(GamepadAxis::LeftTrigger)),
+    (evdev::BTN_TR2, full_axis(GamepadAxis::RightTrigger)),
+];
+const KERNEL_AXES: &[(u32, Output)] = &[
+    (evdev::ABS_X, full_axis(GamepadAxis::LeftX)),
+    (evdev::ABS_Y, full_axis(GamepadAxis::LeftY)),
+    (evdev::ABS_RX, full_axis(GamepadAxis::RightX)),
+    (evdev::ABS_RY, full_axis(GamepadAxis::RightY)),
+    (evdev::ABS_Z, full_axis(GamepadAxis::LeftTrigger)),
+    (evdev::ABS_RZ, full_axis(GamepadAxis::RightTrigger)),
+];
+
+const fn full_axis(axis: GamepadAxis) -> Output {
+    Output::Axis {
+        axis,
+        half: Half::Full,
+    }
+}
+
+fn is_bit_set(bits: &[u8], bit: u32) -> bool {
+    bits.get(bit as usize / 8)
+        .map_or(false, |byte| byte & (1 << (bit % 8)) != 0)
+}
+
+/// Turns the value of an absolute axis into -1.0 to 1.0.
+fn normalize_axis(value: i32, info: &evdev::input_absinfo) -> f64 {
+    if info.maximum <= info.minimum {
+        return 0.0;
+    }
+
+    let range = f64::from(info.maximum) - f64::from(info.minimum);
+    ((f64::from(value) - f64::from(info.minimum)) / range * 2.0 - 1.0).clamp(-1.0, 1.0)
+}
+
+/// Lets the caller know about gamepads being connected, disconnected and used.
+/// Gamepads that are already connected are reported by the first call to
+/// `poll_events`.
+pub struct Gamepads {
+    /// Watches `/dev/input` for new devices. It is None if the directory
+    /// does not exist, like in most containers.
+    inotify: Option<File>,
+    devices: HashMap<GamepadId, Device>,
+    next_id: u64,
+
+    mappings: Mappings,
+    deadzone: f64,
+
+    events: VecDeque<Event>,
+}
+
+/// An open gamepad.
+struct Device {
+    id: GamepadId,
+    file: File,
+    path: PathBuf,
+    name: String,
+    guid: Guid,
+    mapping: Mapping,
+
+    /// The key codes of the buttons, in the order SDL numbers them.
+    buttons: Vec<u32>,
+    /// The codes of the axes, in the order SDL numbers them, with their rang-
+    /// es. Hats are not axes.
+    axes: Vec<(u32, evdev::input_absinfo)>,
+    /// The numbers of the hats the device has. SDL numbers them in order.
+    hats: Vec<u32>,
+    /// The directions every hat holds, by its number.
+    hat_states: [u8; 4],
+
+    /// What was last sent for every button and axis, so that only changes are
+    /// sent.
+    button_states: HashMap<GamepadButton, bool>,
+    axis_values: HashMap<GamepadAxis, f64>,
+
+    /// The kernel threw events away, the state is read again with the next
+    /// `SYN_REPORT`.
+    is_dropping: bool,
+
+    can_rumble: bool,
+    /// The id of the uploaded rumble effect, or -1 before the first one.
+    rumble_effect: i16,
+}
+
+impl Device {
+    /// Opens an evdev node. Returns None if it cannot be opened or is not a
+    /// gamepad, like a keyboard or the motion sensor of a gamepad.
+    unsafe fn open(path: &Path, mappings: &Mappings) -> Option<Device> {
+        // Rumbling needs write access, but reading is enough for the rest.
+        let open = |write| {
+            OpenOptions::new()
+                .read(true)
+                .write(write)
+                .custom_flags(evdev::O_NONBLOCK as i32)
+                .open(path)
+        };
+        let (file, is_writable) = match open(true) {
+            Ok(file) => (file, true),
+            Err(_) => (open(false).ok()?, false),
+        };
+        let fd = file.as_raw_fd();
+
+        let mut id: evdev::input_id = std::mem::zeroed();
+        if evdev::ioctl(fd, evdev::EVIOCGID, &mut id as *mut evdev::input_id) < 0 {
+            return None;
+        }
+        let guid = make_guid(id.bustype, id.vendor, id.product, id.version);
+
+        let mut key_bits = [0u8; KEY_BYTES];
+        let mut abs_bits = [0u8; ABS_BYTES];
+        let mut ff_bits = [0u8; FF_BYTES];
+        evdev::ioctl(
+            fd,
+            evdev::EVIOCGBIT(evdev::EV_KEY, KEY_BYTES),
+            key_bits.as_mut_ptr(),
+        );
+        evdev::ioctl(
+            fd,
+            evdev::EVIOCGBIT(evdev::EV_ABS, ABS_BYTES),
+            abs_bits.as_mut_ptr(),
+        );
+        evdev::ioctl(
+            fd,
+            evdev::EVIOCGBIT(evdev::EV_FF, FF_BYTES),
+            ff_bits.as_mut_ptr(),
+        );
+
+        // Joysticks and gamepads have buttons in their own range. A device
+        // without any of them is only read if a mapping asks for it.
+        let mapping = mappings.get(guid);
+        let has_joystick_buttons =
+            (evdev::BTN_JOYSTICK..evdev::BTN_DIGI).any(|code| is_bit_set(&key_bits, code));
+        if mapping.is_none() && !has_joystick_buttons {
+            return None;
+        }
+
+        let mut name = [0u8; 256];
+        evdev::ioctl(fd, evdev::EVIOCGNAME(name.len()), name.as_mut_ptr());
+        let length = name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(name.len());
+        let name = String::from_utf8_lossy(&name[..length]).into_owned();
+
+        // SDL numbers the joystick and gamepad buttons first and the keys be-
+        // low them after, and leaves the hats out of the axes.
+        let buttons = (evdev::BTN_JOYSTICK..evdev::KEY_MAX)
+            .chain(0..evdev::BTN_JOYSTICK)
+            .filter(|&code| is_bit_set(&key_bits, code))
+            .collect();
+        let axes = (0..evdev::ABS_MAX)
+            .filter(|&code| !(evdev::ABS_HAT0X..=evdev::ABS_HAT3Y).contains(&code))
+            .filter(|&code| is_bit_set(&abs_bits, code))
+            .map(|code| (code, std::mem::zeroed()))
+            .collect();
+        let hats = (0..4)
+            .filter(|hat| {
+                is_bit_set(&abs_bits, evdev::ABS_HAT0X + hat * 2)
+                    || is_bit_set(&abs_bits, evdev::ABS_HAT0Y + hat * 2)
+            })
+            .collect();
+
+        let mut device = Device {
+            id: GamepadId(0),
+            file,
+            path: path.to_owned(),
+            name,
+            guid,
+            mapping: Mapping::default(),
+            buttons,
+            axes,
+            hats,
+            hat_states: [0; 4],
+            button_states: HashMap::new(),
+            axis_values: HashMap::new(),
+            is_dropping: false,
+            can_rumble: is_writable && is_bit_set(&ff_bits, evdev::FF_RUMBLE),
+            rumble_effect: -1,
+        };
+        device.mapping = match mapping {
+            Some(mapping) => mapping.clone(),
+            None => device.default_mapping(),
+        };
+
+        Some(device)
+    }
+
+    /// Makes a mapping for a device that is not in the mappings. Gamepads are
+    /// expected to follow the layout of the kernel, joysticks just get their
+    /// buttons and axes by number.
+    fn default_mapping(&self) -> Mapping {
+        let mut mapping = Mapping::default();
+        let button = |code| self.buttons.iter().position(|&button| button == code);
+        let axis = |code| self.axes.iter().position(|&(axis, _)| axis == code);
+        let first_hat = if self.hats.first() == Some(&0) {
+            Some(0)
+        } else {
+            None
+        };
+
+        if button(evdev::BTN_GAMEPAD).is_some() {
+            for &(code, output) in KERNEL_BUTTONS {
+                if let Some(index) = button(code) {
+                    mapping.bindings.push((Input::Button(index as u16), output));
+                }
+            }
+            for &(code, output) in KERNEL_AXES {
+                if let Some(index) = axis(code) {
+                    let input = Input::Axis {
+                        index: index as u16,
+                        half: Half::Full,
+                        is_inverted: false,
+                    };
+                    mapping.bindings.push((input, output));
+                }
+            }
+        } else {
+            for index in 0..self.buttons.len() as u16 {
+                let output = Output::Button(GamepadButton::Other(index));
+                mapping.bindings.push((Input::Button(index), output));
+            }
+            for index in 0..self.axes.len() as u16 {
+                let input = Input::Axis {
+                    index,
+                    half: Half::Full,
+                    is_inverted: false,
+                };
+                mapping
+                    .bindings
+                    .push((input, full_axis(GamepadAxis::Other(index))));
+            }
+        }
+
+        // Gamepads without buttons for the D-pad have it as the first hat.
+        if let Some(index) = first_hat {
+            for (mask, button) in [
+                (HAT_UP, GamepadButton::DPadUp),
+                (HAT_RIGHT, GamepadButton::DPadRight),
+                (HAT_DOWN, GamepadButton::DPadDown),
+                (HAT_LEFT, GamepadButton::DPadLeft),
+            ] {
+                mapping
+                    .bindings
+                    .push((Input::Hat { index, mask }, Output::Button(button)));
+            }
+        }
+
+        mapping
+    }
+
+    /// Reads the whole state of the device, as changes to everything on it.
+    /// Used when the device was opened and when the kernel dropped events.
+    unsafe fn read_state(&mut self) -> Vec<Change> {
+        let fd = self.file.as_raw_fd();
+        let mut changes = Vec::new();
+
+        let mut key_bits = [0u8; KEY_BYTES];
+        if evdev::ioctl(fd, evdev::EVIOCGKEY(KEY_BYTES), key_bits.as_mut_ptr()) >= 0 {
+            for (index, &code) in self.buttons.iter().enumerate() {
+                changes.push(Change::Button(index as u16, is_bit_set(&key_bits, code)));
+            }
+        }
+
+        for (index, (code, info)) in self.axes.iter_mut().enumerate() {
+            if evdev::ioctl(
+                fd,
+                evdev::EVIOCGABS(*code),
+                info as *mut evdev::input_absinfo,
+            ) >= 0
+            {
+                changes.push(Change::Axis(index as u16, normalize_axis(info.value, info)));
+            }
+        }
+
+        for (index, hat) in self.hats.clone().into_iter().enumerate() {
+            for code in [evdev::ABS_HAT0X + hat * 2, evdev::ABS_HAT0Y + hat * 2] {
+                let mut info: evdev::input_absinfo = std::mem::zeroed();
+                if evdev::ioctl(fd, evdev::EVIOCGABS(code), &mut info as *mut _) >= 0 {
+                    self.update_hat(code, info.value);
+                }
+            }
+            changes.push(Change::Hat(index as u16, self.hat_states[hat as usize]));
+        }
+
+        changes
+    }
+
+    /// Sets the directions of a hat from one of its axes, and returns its
+    /// number.
+    fn update_hat(&mut self, code: u32, value: i32) -> u32 {
+        let hat = (code - evdev::ABS_HAT0X) / 2;
+        let (negative, positive) = if (code - evdev::ABS_HAT0X) & 1 == 0 {
+            (HAT_LEFT, HAT_RIGHT)
+        } else {
+            (HAT_UP, HAT_DOWN)
+        };
+
+        let state = &mut self.hat_states[hat as usize];
+        *state &= !(negative | positive);
+        if value < 0 {
+            *state |= negative;
+        } else if value > 0 {
+            *state |= positive;
+        }
+
+        hat
+    }
+
+    /// Turns a single evdev event into a change on the device.
+    fn translate(&mut self, event: &evdev::input_event) -> Option<Change> {
+        let code = u32::from(event.code);
+
+        match u32::from(event.type_) {
+            // A value of 2 is auto-repeat, which gamepads have no use for.
+            evdev::EV_KEY if event.value != 2 => {
+                let index = self.buttons.iter().position(|&button| button == code)?;
+                Some(Change::Button(index as u16, event.value != 0))
+            }
+            evdev::EV_ABS if (evdev::ABS_HAT0X..=evdev::ABS_HAT3Y).contains(&code) => {
+                let hat = self.update_hat(code, event.value);
+                let index = self.hats.iter().position(|&other| other == hat)?;
+                Some(Change::Hat(index as u16, self.hat_states[hat as usize]))
+            }
+            evdev::EV_ABS => {
+                let index = self.axes.iter().position(|&(axis, _)| axis == code)?;
+                let value = normalize_axis(event.value, &self.axes[index].1);
+                Some(Change::Axis(index as u16, value))
+            }
+            _ => None,
+        }
+    }
+
+    /// Sends the events for a change on the device, if it changed anything on
+    /// the gamepad.
+    fn apply(&mut self, change: Change, deadzone: f64, events: &mut VecDeque<Event>) {
+        for value in self.mapping.apply(change) {
+            match value {
+                Value::Button(button, is_press) => {
+                    let was_pressed = self.button_states.insert(button, is_press);
+                    if was_pressed.unwrap_or(false) != is_press {
+                        events.push_back(Event::GamepadButton {
+                            id: self.id,
+                            button,
+                            is_press,
+                        });
+                    }
+                }
+                Value::Axis(axis, value) => {
+                    let value = apply_deadzone(value, deadzone);
+                    let old_value = self.axis_values.insert(axis, value);
+                    if old_value.unwrap_or(0.0) != value {
+                        events.push_back(Event::GamepadAxis {
+                            id: self.id,
+                            axis,
+                            value,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles every event that is waiting on the device. Returns false once
+    /// the device is gone.
+    unsafe fn pump(&mut self, deadzone: f64, events: &mut VecDeque<Event>) -> bool {
+        let mut buffer: [evdev::input_event; 32] = std::mem::zeroed();
+
+        loop {
+            let bytes = std::slice::from_raw_parts_mut(
+                buffer.as_mut_ptr() as *mut u8,
+                std::mem::size_of_val(&buffer),
+            );
+            let count = match self.file.read(bytes) {
+                Ok(0) => return false,
+                Ok(length) => length / std::mem::size_of::<evdev::input_event>(),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return true,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                // Unplugged devices fail with `ENODEV`.
+                Err(_) => return false,
+            };
+
+            for event in &buffer[..count] {
+                if u32::from(event.type_) == evdev::EV_SYN {
+                    match u32::from(event.code) {
+                        evdev::SYN_DROPPED => self.is_dropping = true,
+                        evdev::SYN_REPORT if self.is_dropping => {
+                            self.is_dropping = false;
+                            for change in self.read_state() {
+                                self.apply(change, deadzone, events);
+                            }
+                        }
+                        _ => (),
+                    }
+                } else if !self.is_dropping {
+                    if let Some(change) = self.translate(event) {
+                        self.apply(change, deadzone, events);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Uploads a rumble effect and plays it. The effect is uploaded once and
+    /// then replaced, since a device only has room for a few.
+    unsafe fn rumble(&mut self, strong: f64, weak: f64, duration: Duration) {
+        let magnitude = |value: f64| (value.clamp(0.0, 1.0) * f64::from(u16::MAX)) as u16;
+
+        let mut effect: evdev::ff_effect = std::mem::zeroed();
+        effect.type_ = evdev::FF_RUMBLE as u16;
+        effect.id = self.rumble_effect;
+        effect.replay.length = duration.as_millis().min(u16::MAX.into()) as u16;
+        effect.u.rumble.strong_magnitude = magnitude(strong);
+        effect.u.rumble.weak_magnitude = magnitude(weak);
+
+        let fd = self.file.as_raw_fd();
+        if evdev::ioctl(fd, evdev::EVIOCSFF, &mut effect as *mut evdev::ff_effect) < 0 {
+            return;
+        }
+        self.rumble_effect = effect.id;
+
+        // Effects are played by writing an event for them.
+        let mut play: evdev::input_event = std::mem::zeroed();
+        play.type_ = evdev::EV_FF as u16;
+        play.code = effect.id as u16;
+        play.value = 1;
+        let bytes = std::slice::from_raw_parts(
+            &play as *const evdev::input_event as *const u8,
+            std::mem::size_of::<evdev::input_event>(),
+        );
+        let _ = self.file.write_all(bytes);
+    }
+}
+
+/// Starts watching `/dev/input` for new nodes. Nodes are made readable after
+/// they were created, so changes to their attributes count too.
+unsafe fn watch_input_dir() -> Result<Option<File>, GamepadError> {
+    let fd = evdev::inotify_init1((evdev::IN_NONBLOCK | evdev::IN_CLOEXEC) as i32);
+    if fd < 0 {
+        return Err(GamepadError::Os(format!(
+            "could not watch {}: {}",
+            INPUT_DIR,
+            std::io::Error::last_os_error()
+        )));
+    }
+    let inotify = File::from_raw_fd(fd);
+
+    let path = CString::new(INPUT_DIR).unwrap();
+    let mask = evdev::IN_CREATE | evdev::IN_ATTRIB | evdev::IN_DELETE;
+    if evdev::inotify_add_watch(fd, path.as_ptr(), mask) < 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(inotify))
+}
+
+/// Checks if a file in `/dev/input` is an evdev node. The others are the old
+/// joystick and mouse interfaces.
+fn is_event_node(name: &[u8]) -> bool {
+    name.starts_with(b"event")
+}
+
+impl Gamepads {
+    /// Starts looking for gamepads. The mappings in `SDL_GAMECONTROLLERCONFIG`
+    /// are added right away, like SDL does.
+    pub fn new() -> Result<Gamepads, GamepadError> {
+        let inotify = unsafe { watch_input_dir()? };
+
+        let mut gamepads = Gamepads {
+            inotify,
+            devices: HashMap::new(),
+            next_id: 1,
+            mappings: Mappings::default(),
+            deadzone: DEFAULT_DEADZONE,
+            events: VecDeque::new(),
+        };
+
+        if let Ok(mappings) = std::env::var("SDL_GAMECONTROLLERCONFIG") {
+            gamepads.mappings.add(&mappings);
+        }
+
+        // The nodes are sorted by number so the ids follow the order the de-
+        // vices were plugged in.
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(INPUT_DIR) {
+            Ok(entries) => entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| {
+                    path.file_name()
+                        .map_or(false, |name| is_event_node(name.as_bytes()))
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        paths.sort_by_key(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name["event".len()..].parse::<u32>().unwrap_or(u32::MAX)
+        });
+        for path in paths {
+            gamepads.add_device(&path);
+        }
+
+        Ok(gamepads)
+    }
+
+    /// Adds mappings in the format of SDL's `gamecontrollerdb.txt`, e.g. the
+    /// whole file. Mappings for other platforms are skipped. Returns how many
+    /// mappings were added. Gamepads that are already connected switch to
+    /// their new mapping.
+    pub fn add_mappings(&mut self, mappings: &str) -> usize {
+        let count = self.mappings.add(mappings);
+
+        for device in self.devices.values_mut() {
+            if let Some(mapping) = self.mappings.get(device.guid) {
+                if *mapping != device.mapping {
+                    device.mapping = mapping.clone();
+                    for change in unsafe { device.read_state() } {
+                        device.apply(change, self.deadzone, &mut self.events);
+                    }
+                }
+            }
+        }
+
+        count
+    }
+
+    /// Sets how far sticks and triggers have to move before they count, from
+    /// 0.0 to 1.0. The default is `DEFAULT_DEADZONE`.
+    pub fn set_deadzone(&mut self, deadzone: f64) {
+        self.deadzone = deadzone.clamp(0.0, 0.99);
+    }
+
+    /// Returns the name the gamepad gives itself, or None if it is not conne-
+    /// cted.
+    pub fn name(&self, id: GamepadId) -> Option<&str> {
+        Some(&self.devices.get(&id)?.name)
+    }
+
+    /// Makes the gamepad rumble for a while. `strong` is the heavy motor and
+    /// `weak` the light one, both from 0.0 to 1.0. A new rumble replaces the
+    /// last one, so rumbling with both at 0.0 stops it. Gamepads that cannot
+    /// rumble ignore this.
+    pub fn rumble(&mut self, id: GamepadId, strong: f64, weak: f64, duration: Duration) {
+        if let Some(device) = self.devices.get_mut(&id) {
+            if device.can_rumble {
+                unsafe { device.rumble(strong, weak, duration) };
+            }
+        }
+    }
+
+    /// Returns every event since the last call.
+    pub fn poll_events(&mut self) -> Events<'_> {
+        unsafe {
+            self.read_inotify();
+
+            let mut disconnected = Vec::new();
+            for (&id, device) in self.devices.iter_mut() {
+                if !device.pump(self.deadzone, &mut self.events) {
+                    disconnected.push(id);
+                }
+            }
+            for id in disconnected {
+                self.remove_device(id);
+            }
+        }
+
+        self.events.drain(..)
+    }
+
+    fn add_device(&mut self, path: &Path) {
+        if self.devices.values().any(|device| device.path == path) {
+            return;
+        }
+
+        let mut device = match unsafe { Device::open(path, &self.mappings) } {
+            Some(device) => device,
+            None => return,
+        };
+        device.id = GamepadId(self.next_id);
+        self.next_id += 1;
+
+        self.events.push_back(Event::GamepadConnected {
+            id: device.id,
+            name: device.name.clone(),
+        });
+
+        // Anything that is held already is sent right after.
+        for change in unsafe { device.read_state() } {
+            device.apply(change, self.deadzone, &mut self.events);
+        }
+
+        self.devices.insert(device.id, device);
+    }
+
+    fn remove_device(&mut self, id: GamepadId) {
+        if self.devices.remove(&id).is_some() {
+            self.events.push_back(Event::GamepadDisconnected { id });
+        }
+    }
+
+    /// Opens the nodes that showed up in `/dev/input` and drops the ones that
+    /// went away.
+    unsafe fn read_inotify(&mut self) {
+        let mut buffer = [0u8; 4096];
+        loop {
+            let length = match self
+                .inotify
+                .as_mut()
+                .map(|inotify| inotify.read(&mut buffer))
+            {
+                Some(Ok(length)) if length > 0 => length,
+                _ => return,
+            };
+
+            // Every event is a header of four 32-bit numbers, followed by the
+            // name of the file padded with null bytes.
+            let mut offset = 0;
+            while offset + 16 <= length {
+                let field = |index: usize| {
+                    let start = offset + index * 4;
+                    u32::from_ne_bytes(buffer[start..start + 4].try_into().unwrap())
+                };
+                let mask = field(1);
+                let name_length = field(3) as usize;
+
+                let name = &buffer[offset + 16..(offset + 16 + name_length).min(length)];
+                let name = &name[..name
+                    .iter()
+                    .position(|&byte| byte == 0)
+                    .unwrap_or(name.len())];
+                offset += 16 + name_length;
+
+                if !is_event_node(name) {
+                    continue;
+                }
+
+                let path = Path::new(INPUT_DIR).join(std::ffi::OsStr::from_bytes(name));
+                if mask & evdev::IN_DELETE != 0 {
+                    let id = self
+                        .devices
+                        .values()
+                        .find(|device| device.path == path)
+                        .map(|device| device.id);
+                    if let Some(id) = id {
+                        self.remove_device(id);
+                    }
+                } else {
+                    self.add_device(&path);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A gamepad made through uinput, which needs write access to `/dev/uinp-
+    /// ut`. The kernel takes it away when this is dropped.
+    struct VirtualGamepad(File);
+
+    impl VirtualGamepad {
+        unsafe fn new() -> VirtualGamepad {
+            let file = OpenOptions::new()
+                .write(true)
+                .open("/dev/uinput")
+                .expect("Could not open /dev/uinput");
+            let fd = file.as_raw_fd();
+
+            evdev::ioctl(fd, evdev::UI_SET_EVBIT, evdev::EV_KEY as i32);
+            evdev::ioctl(fd, evdev::UI_SET_KEYBIT, evdev::BTN_SOUTH as i32);
+            evdev::ioctl(fd, evdev::UI_SET_KEYBIT, evdev::BTN_EAST as i32);
+            evdev::ioctl(fd, evdev::UI_SET_EVBIT, evdev::EV_ABS as i32);
+            evdev::ioctl(fd, evdev::UI_SET_ABSBIT, evdev::ABS_X as i32);
+
+            let mut axis: evdev::uinput_abs_setup = std::mem::zeroed();
+            axis.code = evdev::ABS_X as u16;
+            axis.absinfo.minimum = -32768;
+            axis.absinfo.maximum = 32767;
+            evdev::ioctl(fd, evdev::UI_ABS_SETUP, &axis as *const _);
+
+            let mut setup: evdev::uinput_setup = std::mem::zeroed();
+            setup.id.bustype = evdev::BUS_VIRTUAL as u16;
+            setup.id.vendor = 0x1234;
+            setup.id.product = 0x5678;
+            for (to, &from) in setup.name.iter_mut().zip(b"Nengine Test Gamepad") {
+                *to = from as _;
+            }
+            evdev::ioctl(fd, evdev::UI_DEV_SETUP, &setup as *const _);
+            evdev::ioctl(fd, evdev::UI_DEV_CREATE);
+
+            VirtualGamepad(file)
+        }
+
+        fn send(&mut self, kind: u32, code: u32, value: i32) {
+            for (kind, code, value) in [(kind, code, value), (evdev::EV_SYN, evdev::SYN_REPORT, 0)]
+            {
+                unsafe {
+                    let mut event: evdev::input_event = std::mem::zeroed();
+                    event.type_ = kind as u16;
+                    event.code = code as u16;
+                    event.value = value;
+                    self.0
+                        .write_all(std::slice::from_raw_parts(
+                            &event as *const evdev::input_event as *const u8,
+                            std::mem::size_of::<evdev::input_event>(),
+                        ))
+                        .unwrap();
+                }
+            }
+        }
+    }
+
+    /// Waits for events to show up, since udev takes a moment with new nodes.
+    fn wait_for_events(gamepads: &mut Gamepads) -> Vec<Event> {
+        for _ in 0..200 {
+            let events: Vec<Event> = gamepads.poll_events().collect();
+            if !events.is_empty() {
+                return events;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        panic!("No events came in");
+    }
+
+    #[test]
+    #[ignore = "needs write access to /dev/uinput"]
+    fn virtual_gamepad_is_read() {
+        let mut gamepads = Gamepads::new().unwrap();
+        let mut gamepad = unsafe { VirtualGamepad::new() };
+
+        let id = match wait_for_events(&mut gamepads).as_slice() {
+            [Event::GamepadConnected { id, name }] if name == "Nengine Test Gamepad" => *id,
+            _ => panic!("The gamepad was not connected"),
+        };
+
+        gamepad.send(evdev::EV_KEY, evdev::BTN_SOUTH, 1);
+        assert!(matches!(
+            wait_for_events(&mut gamepads).as_slice(),
+            [Event::GamepadButton { id: button_id, button: GamepadButton::A, is_press: true }]
+                if *button_id == id
+        ));
+
+        gamepad.send(evdev::EV_ABS, evdev::ABS_X, 32767);
+        assert!(matches!(
+            wait_for_events(&mut gamepads).as_slice(),
+            [Event::GamepadAxis { axis: GamepadAxis::LeftX, value, .. }] if *value == 1.0
+        ));
+
+        drop(gamepad);
+        assert!(matches!(
+            wait_for_events(&mut gamepads).as_slice(),
+            [Event::GamepadDisconnected { id: disconnected_id }] if *disconnected_id == id
+        ));
+    }
+}
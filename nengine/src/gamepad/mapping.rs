@@ -0,0 +1,399 @@
+// SDL describes gamepads in `gamecontrollerdb.txt`, one device per line:
+//
+//     030000005e0400008e02000014010000,Xbox 360 Controller,a:b0,leftx:a0,...
+//
+// The first field is the GUID of the device, made from its bus, vendor, pro-
+// duct and version, and the second one its name. Every field after that bi-
+// nds a button or axis of the gamepad to a button, axis or hat of the device.
+
+use super::{GamepadAxis, GamepadButton};
+use std::collections::HashMap;
+
+/// Identifies a kind of device the way SDL does.
+pub(crate) type Guid = [u8; 16];
+
+/// The value of the `platform` field that mappings for this platform have.
+const PLATFORM: &str = if cfg!(target_os = "linux") {
+    "Linux"
+} else if cfg!(target_os = "windows") {
+    "Windows"
+} else {
+    "Mac OS X"
+};
+
+/// How much of an axis is used. An input can be half of an axis of the dev-
+/// ice, like `+a2`, and an output half of an axis of the gamepad, like `-le-
+/// ftx`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Half {
+    Full,
+    Positive,
+    Negative,
+}
+
+/// Something on the device, numbered the way SDL numbers them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Input {
+    Button(u16),
+    Axis {
+        index: u16,
+        half: Half,
+        is_inverted: bool,
+    },
+    /// A direction of a hat, as a mask of up 1, right 2, down 4 and left 8.
+    Hat {
+        index: u16,
+        mask: u8,
+    },
+}
+
+/// Something on the gamepad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Output {
+    Button(GamepadButton),
+    Axis { axis: GamepadAxis, half: Half },
+}
+
+/// A change on the device, as the backend reads it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Change {
+    Button(u16, bool),
+    /// An axis, from -1.0 to 1.0.
+    Axis(u16, f64),
+    /// A hat, with the mask of the directions that are held.
+    Hat(u16, u8),
+}
+
+/// The new state of something on the gamepad after a change.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Value {
+    Button(GamepadButton, bool),
+    Axis(GamepadAxis, f64),
+}
+
+/// How the inputs of one kind of device turn into gamepad buttons and axes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Mapping {
+    pub(crate) bindings: Vec<(Input, Output)>,
+}
+
+impl Mapping {
+    /// Parses one line of `gamecontrollerdb.txt`. Returns None for lines that
+    /// are not mappings and for mappings of other platforms. Bindings to
+    /// things the Nengine does not have, like paddles, are skipped.
+    pub(crate) fn parse(line: &str) -> Option<(Guid, Mapping)> {
+        let mut fields = line.trim().split(',');
+        let guid = parse_guid(fields.next()?)?;
+        let _name = fields.next()?;
+
+        let mut mapping = Mapping::default();
+        for field in fields {
+            // The line usually ends with a comma, which leaves an empty field.
+            let (output, input) = match field.split_once(':') {
+                Some(binding) => binding,
+                None => continue,
+            };
+
+            if output == "platform" {
+                if input != PLATFORM {
+                    return None;
+                }
+            } else if let (Some(output), Some(input)) = (parse_output(output), parse_input(input)) {
+                mapping.bindings.push((input, output));
+            }
+        }
+
+        Some((guid, mapping))
+    }
+
+    /// Works out what a change on the device does to the gamepad. Only the
+    /// outputs that are bound to the changed input are returned.
+    pub(crate) fn apply(&self, change: Change) -> Vec<Value> {
+        let values = self.bindings.iter().filter_map(|&(input, output)| {
+            // Buttons, hats and halves of axes go from 0.0 to 1.0, full axes
+            // from -1.0 to 1.0.
+            let (value, is_full) = match (input, change) {
+                (Input::Button(index), Change::Button(changed, is_pressed)) if index == changed => {
+                    (if is_pressed { 1.0 } else { 0.0 }, false)
+                }
+                (
+                    Input::Axis {
+                        index,
+                        half,
+                        is_inverted,
+                    },
+                    Change::Axis(changed, value),
+                ) if index == changed => {
+                    let value = if is_inverted { -value } else { value };
+                    match half {
+                        Half::Full => (value, true),
+                        Half::Positive => (value.max(0.0), false),
+                        Half::Negative => ((-value).max(0.0), false),
+                    }
+                }
+                (Input::Hat { index, mask }, Change::Hat(changed, held)) if index == changed => {
+                    (if held & mask != 0 { 1.0 } else { 0.0 }, false)
+                }
+                _ => return None,
+            };
+
+            // The same range as a half, for outputs that only have one.
+            let positive = if is_full { (value + 1.0) / 2.0 } else { value };
+
+            Some(match output {
+                Output::Button(button) => Value::Button(button, positive > 0.5),
+                Output::Axis { axis, half } => {
+                    let value = match half {
+                        Half::Positive => positive,
+                        Half::Negative => -positive,
+                        Half::Full if axis.is_trigger() => positive,
+                        Half::Full if is_full => value,
+                        Half::Full => value * 2.0 - 1.0,
+                    };
+
+                    // SDL has down as positive, the Nengine has up.
+                    match axis {
+                        GamepadAxis::LeftY | GamepadAxis::RightY => Value::Axis(axis, -value),
+                        _ => Value::Axis(axis, value),
+                    }
+                }
+            })
+        });
+
+        // Both halves of a gamepad axis can be bound to the same input, like
+        // `-leftx:-a0,+leftx:+a0`. The half that is not moved is at 0.0 and
+        // must not hide the other one.
+        let mut merged: Vec<Value> = Vec::new();
+        for value in values {
+            if let Value::Axis(axis, new) = value {
+                let earlier = merged.iter_mut().find_map(|value| match value {
+                    Value::Axis(other, old) if *other == axis => Some(old),
+                    _ => None,
+                });
+
+                if let Some(old) = earlier {
+                    if new.abs() > old.abs() {
+                        *old = new;
+                    }
+                    continue;
+                }
+            }
+
+            merged.push(value);
+        }
+
+        merged
+    }
+}
+
+/// The mappings of every kind of device that has one.
+#[derive(Default)]
+pub(crate) struct Mappings(HashMap<Guid, Mapping>);
+
+impl Mappings {
+    /// Adds every mapping in a text with the format of `gamecontrollerdb.txt`.
+    /// A mapping replaces an earlier one for the same GUID. Returns how many
+    /// mappings were added.
+    pub(crate) fn add(&mut self, text: &str) -> usize {
+        let mut count = 0;
+        for line in text.lines() {
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            if let Some((guid, mapping)) = Mapping::parse(line) {
+                self.0.insert(guid, mapping);
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    pub(crate) fn get(&self, guid: Guid) -> Option<&Mapping> {
+        self.0.get(&without_crc(guid))
+    }
+}
+
+/// Makes the GUID that SDL gives a device on Linux. The numbers are stored
+/// in little endian, each one followed by two bytes of zeros.
+pub(crate) fn make_guid(bus: u16, vendor: u16, product: u16, version: u16) -> Guid {
+    let mut guid = [0; 16];
+    for (i, number) in [bus, vendor, product, version].into_iter().enumerate() {
+        guid[i * 4..i * 4 + 2].copy_from_slice(&number.to_le_bytes());
+    }
+
+    guid
+}
+
+/// Newer versions of SDL put a checksum of the device name into the GUID,
+/// right after the bus. The Nengine does not, so it is cleared to make both
+/// kinds of GUIDs match.
+fn without_crc(mut guid: Guid) -> Guid {
+    guid[2] = 0;
+    guid[3] = 0;
+
+    guid
+}
+
+fn parse_guid(text: &str) -> Option<Guid> {
+    if text.len() != 32 {
+        return None;
+    }
+
+    let mut guid = [0; 16];
+    for (i, byte) in guid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(text.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+
+    Some(without_crc(guid))
+}
+
+/// Splits the `+` or `-` in front of a binding off.
+fn parse_half(text: &str) -> (Half, &str) {
+    if let Some(text) = text.strip_prefix('+') {
+        (Half::Positive, text)
+    } else if let Some(text) = text.strip_prefix('-') {
+        (Half::Negative, text)
+    } else {
+        (Half::Full, text)
+    }
+}
+
+fn parse_output(text: &str) -> Option<Output> {
+    let (half, name) = parse_half(text);
+
+    let button = match name {
+        "a" => GamepadButton::A,
+        "b" => GamepadButton::B,
+        "x" => GamepadButton::X,
+        "y" => GamepadButton::Y,
+        "back" => GamepadButton::Back,
+        "guide" => GamepadButton::Guide,
+        "start" => GamepadButton::Start,
+        "leftstick" => GamepadButton::LeftStick,
+        "rightstick" => GamepadButton::RightStick,
+        "leftshoulder" => GamepadButton::LeftShoulder,
+        "rightshoulder" => GamepadButton::RightShoulder,
+        "dpup" => GamepadButton::DPadUp,
+        "dpdown" => GamepadButton::DPadDown,
+        "dpleft" => GamepadButton::DPadLeft,
+        "dpright" => GamepadButton::DPadRight,
+        "misc1" => GamepadButton::Misc,
+        _ => {
+            let axis = match name {
+                "leftx" => GamepadAxis::LeftX,
+                "lefty" => GamepadAxis::LeftY,
+                "rightx" => GamepadAxis::RightX,
+                "righty" => GamepadAxis::RightY,
+                "lefttrigger" => GamepadAxis::LeftTrigger,
+                "righttrigger" => GamepadAxis::RightTrigger,
+                _ => return None,
+            };
+
+            return Some(Output::Axis { axis, half });
+        }
+    };
+
+    Some(Output::Button(button))
+}
+
+fn parse_input(text: &str) -> Option<Input> {
+    let (half, text) = parse_half(text);
+    let (text, is_inverted) = match text.strip_suffix('~') {
+        Some(text) => (text, true),
+        None => (text, false),
+    };
+
+    match text.as_bytes().first()? {
+        b'b' => Some(Input::Button(text[1..].parse().ok()?)),
+        b'a' => Some(Input::Axis {
+            index: text[1..].parse().ok()?,
+            half,
+            is_inverted,
+        }),
+        b'h' => {
+            let (index, mask) = text[1..].split_once('.')?;
+            Some(Input::Hat {
+                index: index.parse().ok()?,
+                mask: mask.parse().ok()?,
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XBOX_360: &str = "030000005e0400008e02000014010000,Xbox 360 Controller,a:b0,\
+        leftx:a0,lefty:a1,dpup:h0.1,-rightx:-a3,+rightx:+a3,lefttrigger:a2,\
+        righttrigger:b7,paddle1:b11,platform:Linux,";
+
+    #[test]
+    fn mappings_are_parsed() {
+        let (guid, mapping) = Mapping::parse(XBOX_360).unwrap();
+        assert_eq!(guid, make_guid(0x0003, 0x045e, 0x028e, 0x0114));
+        assert_eq!(mapping.bindings.len(), 8);
+        assert_eq!(
+            mapping.bindings[3],
+            (
+                Input::Hat { index: 0, mask: 1 },
+                Output::Button(GamepadButton::DPadUp)
+            )
+        );
+
+        // The paddle is skipped, mappings of other platforms are too.
+        assert_eq!(Mapping::parse(&XBOX_360.replace("Linux", "Windows")), None);
+        assert_eq!(Mapping::parse("# Xbox controllers"), None);
+    }
+
+    #[test]
+    fn guids_match_without_crc() {
+        let mut mappings = Mappings::default();
+        let with_crc = XBOX_360.replacen("0300000", "0300ab1", 1);
+        assert_eq!(mappings.add(&with_crc), 1);
+
+        assert!(mappings
+            .get(make_guid(0x0003, 0x045e, 0x028e, 0x0114))
+            .is_some());
+        assert!(mappings
+            .get(make_guid(0x0003, 0x045e, 0x028e, 0x0115))
+            .is_none());
+    }
+
+    #[test]
+    fn changes_are_mapped() {
+        let (_, mapping) = Mapping::parse(XBOX_360).unwrap();
+        let apply = |change| mapping.apply(change);
+
+        assert_eq!(
+            apply(Change::Button(0, true)),
+            [Value::Button(GamepadButton::A, true)]
+        );
+        // Up is positive, unlike on the device.
+        assert_eq!(
+            apply(Change::Axis(1, 0.5)),
+            [Value::Axis(GamepadAxis::LeftY, -0.5)]
+        );
+        // The half that moved wins over the one that is at rest.
+        assert_eq!(
+            apply(Change::Axis(3, -0.5)),
+            [Value::Axis(GamepadAxis::RightX, -0.5)]
+        );
+        // Full axes become triggers that start at 0.0.
+        assert_eq!(
+            apply(Change::Axis(2, -1.0)),
+            [Value::Axis(GamepadAxis::LeftTrigger, 0.0)]
+        );
+        assert_eq!(
+            apply(Change::Button(7, true)),
+            [Value::Axis(GamepadAxis::RightTrigger, 1.0)]
+        );
+        assert_eq!(
+            apply(Change::Hat(0, 1 | 2)),
+            [Value::Button(GamepadButton::DPadUp, true)]
+        );
+    }
+}
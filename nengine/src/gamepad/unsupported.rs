@@ -0,0 +1,35 @@
+// Gamepads are only read on Linux so far. Everywhere else `Gamepads::new`
+// fails, so a game can tell and go on without them.
+
+use super::{GamepadError, GamepadId};
+use crate::platform::Events;
+use crate::Event;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Lets the caller know about gamepads being connected, disconnected and used.
+pub struct Gamepads {
+    events: VecDeque<Event>,
+}
+
+impl Gamepads {
+    pub fn new() -> Result<Gamepads, GamepadError> {
+        Err(GamepadError::Unsupported)
+    }
+
+    pub fn add_mappings(&mut self, _mappings: &str) -> usize {
+        0
+    }
+
+    pub fn set_deadzone(&mut self, _deadzone: f64) {}
+
+    pub fn name(&self, _id: GamepadId) -> Option<&str> {
+        None
+    }
+
+    pub fn rumble(&mut self, _id: GamepadId, _strong: f64, _weak: f64, _duration: Duration) {}
+
+    pub fn poll_events(&mut self) -> Events<'_> {
+        self.events.drain(..)
+    }
+}
@@ -0,0 +1,26 @@
+// The input module sits between the events and gameplay code. Instead of ask-
+// ing about keys and buttons, gameplay asks about actions like "jump", which
+// the player can bind to whatever they like, or looks at what is held in the
+// `InputState` of the frame.
+
+mod actions;
+pub use self::actions::*;
+
+mod state;
+pub use self::state::*;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::{Event, Key};
+
+    /// A key event with the same keycode and scancode and no modifiers.
+    pub(crate) fn key(scancode: Key, is_press: bool) -> Event {
+        Event::Key {
+            keycode: scancode,
+            scancode,
+            is_press,
+            is_repeat: false,
+            modifiers: crate::Modifiers::empty(),
+        }
+    }
+}
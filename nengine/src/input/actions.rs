@@ -0,0 +1,1186 @@
+// Actions are named things the player can do, like "jump" or "move", bound to
+// the inputs that do them. Bindings live in contexts, such as "gameplay" and
+// "menu", which are pushed and popped as the game changes what it is doing.
+// They can be saved to and loaded from a config file, one line per action:
+//
+//     [gameplay]
+//     jump = key:Spacebar | button:A
+//     move = key:W/key:S/key:A/key:D | stick:Left
+//     sprint = key:LeftShift@hold:0.3
+//     save = key:LeftControl+key:S
+//     dodge = key:Spacebar@doubletap:0.25
+//     steer = axis:LeftX
+//     menu_down = axis:-LeftY
+//
+// Bindings are separated by `|`, inputs held together by `+`, the parts of a
+// composite by `/` and the trigger follows an `@`, with its time in seconds.
+
+use crate::gamepad::{GamepadAxis, GamepadButton, GamepadId};
+use crate::{Event, Key, MouseButton};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+/// How far a gamepad axis has to be pushed before it counts as held, and how
+/// far an axis action has to be before it counts as pressed.
+pub const PRESS_THRESHOLD: f64 = 0.5;
+
+/// One of the two directions of an axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AxisDirection {
+    Negative,
+    Positive,
+}
+
+/// Something on a device that can be held, from 0.0 to 1.0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Input {
+    /// A key by its scancode, so bindings stay in place on other layouts.
+    Key(Key),
+    MouseButton(MouseButton),
+    /// A button on any gamepad.
+    GamepadButton(GamepadButton),
+    /// One direction of an axis on any gamepad, e.g. the left stick pushed
+    /// up. It counts as held past `PRESS_THRESHOLD`.
+    GamepadAxis(GamepadAxis, AxisDirection),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+/// When a binding of buttons makes its action pressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trigger {
+    /// For as long as the inputs are held.
+    Press,
+    /// Once the inputs have been held for the duration, until they are rel-
+    /// eased.
+    Hold(Duration),
+    /// For one frame, when the inputs are released no later than the durat-
+    /// ion after being pressed.
+    Tap(Duration),
+    /// For one frame, when the inputs are pressed again no later than the du-
+    /// ration after the first press.
+    DoubleTap(Duration),
+}
+
+/// What makes an action pressed, and what its value is.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Binding {
+    /// Inputs that all have to be held at once, like `LeftControl` and `S`.
+    /// Most bindings have just one. The value is 1.0 while pressed.
+    Buttons {
+        inputs: Vec<Input>,
+        trigger: Trigger,
+    },
+    /// A whole gamepad axis, from -1.0 to 1.0.
+    Axis(GamepadAxis),
+    /// Two inputs that make an axis, like S and W for a throttle.
+    Composite1d { negative: Input, positive: Input },
+    /// Four inputs that make a 2D axis, like WASD. Up and right are posit-
+    /// ive, as on a stick.
+    Composite2d {
+        up: Input,
+        down: Input,
+        left: Input,
+        right: Input,
+    },
+    /// Both axes of a stick, on any gamepad.
+    Stick(Stick),
+}
+
+impl Binding {
+    /// Returns a binding to one input, which is pressed while it is held.
+    pub fn input(input: Input) -> Binding {
+        Binding::chord(&[input])
+    }
+
+    pub fn key(key: Key) -> Binding {
+        Binding::input(Input::Key(key))
+    }
+
+    /// Returns a binding to inputs that all have to be held at once.
+    pub fn chord(inputs: &[Input]) -> Binding {
+        Binding::Buttons {
+            inputs: inputs.to_vec(),
+            trigger: Trigger::Press,
+        }
+    }
+
+    /// Returns a 2D composite of four keys, in the order up, down, left and
+    /// right.
+    pub fn keys_2d(up: Key, down: Key, left: Key, right: Key) -> Binding {
+        Binding::Composite2d {
+            up: Input::Key(up),
+            down: Input::Key(down),
+            left: Input::Key(left),
+            right: Input::Key(right),
+        }
+    }
+
+    /// Changes when a binding of buttons is pressed. Other bindings have no
+    /// trigger and are returned as they are.
+    pub fn with_trigger(self, trigger: Trigger) -> Binding {
+        match self {
+            Binding::Buttons { inputs, .. } => Binding::Buttons { inputs, trigger },
+            other => other,
+        }
+    }
+}
+
+/// The ways loading and saving bindings can fail.
+#[derive(Debug)]
+pub enum ActionError {
+    Io(std::io::Error),
+    /// A line of a config could not be understood. Lines count from 1.
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionError::Io(error) => write!(f, "{}", error),
+            ActionError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ActionError {}
+
+impl From<std::io::Error> for ActionError {
+    fn from(error: std::io::Error) -> ActionError {
+        ActionError::Io(error)
+    }
+}
+
+/// Keeps track of what is held on every device, as far as the events tell.
+#[derive(Default)]
+struct Devices {
+    keys: HashSet<Key>,
+    mouse_buttons: HashSet<MouseButton>,
+    gamepad_buttons: HashSet<(GamepadId, GamepadButton)>,
+    gamepad_axes: HashMap<(GamepadId, GamepadAxis), f64>,
+    /// Inputs pressed since the last update, so a press and release between
+    /// two frames is not missed.
+    pressed: HashSet<Input>,
+}
+
+impl Devices {
+    fn set_held(&mut self, input: Input, is_held: bool) {
+        if is_held {
+            self.pressed.insert(input);
+        }
+
+        match input {
+            Input::Key(key) if is_held => {
+                self.keys.insert(key);
+            }
+            Input::Key(key) => {
+                self.keys.remove(&key);
+            }
+            Input::MouseButton(button) if is_held => {
+                self.mouse_buttons.insert(button);
+            }
+            Input::MouseButton(button) => {
+                self.mouse_buttons.remove(&button);
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the value of an axis on the gamepad that has it furthest out.
+    fn axis(&self, axis: GamepadAxis) -> f64 {
+        self.gamepad_axes
+            .iter()
+            .filter(|((_, other), _)| *other == axis)
+            .map(|(_, &value)| value)
+            .fold(0.0, |furthest: f64, value| {
+                if value.abs() > furthest.abs() {
+                    value
+                } else {
+                    furthest
+                }
+            })
+    }
+
+    fn value(&self, input: Input) -> f64 {
+        let is_held = match input {
+            Input::Key(key) => self.keys.contains(&key),
+            Input::MouseButton(button) => self.mouse_buttons.contains(&button),
+            Input::GamepadButton(button) => {
+                self.gamepad_buttons.iter().any(|&(_, held)| held == button)
+            }
+            Input::GamepadAxis(axis, AxisDirection::Negative) => {
+                return (-self.axis(axis)).max(0.0)
+            }
+            Input::GamepadAxis(axis, AxisDirection::Positive) => return self.axis(axis).max(0.0),
+        };
+
+        if is_held || self.pressed.contains(&input) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn is_held(&self, input: Input) -> bool {
+        self.value(input) > PRESS_THRESHOLD
+    }
+}
+
+/// What a binding remembers between frames for its trigger.
+#[derive(Clone, Copy, Default)]
+struct BindingState {
+    was_down: bool,
+    held_for: Option<Duration>,
+    since_press: Option<Duration>,
+}
+
+impl BindingState {
+    /// Returns if the binding is pressed this frame, given if its inputs are
+    /// held.
+    fn update(&mut self, is_down: bool, trigger: Trigger, dt: Duration) -> bool {
+        let was_down = self.was_down;
+        self.was_down = is_down;
+
+        let mut released_after = None;
+        if is_down {
+            self.held_for = Some(match self.held_for {
+                Some(held_for) if was_down => held_for + dt,
+                _ => Duration::ZERO,
+            });
+        } else if was_down {
+            released_after = self.held_for.take();
+        }
+
+        if let Some(since_press) = &mut self.since_press {
+            *since_press += dt;
+        }
+
+        match trigger {
+            Trigger::Press => is_down,
+            Trigger::Hold(duration) => {
+                is_down && self.held_for.map_or(false, |held| held >= duration)
+            }
+            Trigger::Tap(duration) => released_after.map_or(false, |held| held <= duration),
+            Trigger::DoubleTap(duration) => {
+                if !is_down || was_down {
+                    return false;
+                }
+
+                if self.since_press.map_or(false, |since| since <= duration) {
+                    self.since_press = None;
+                    true
+                } else {
+                    self.since_press = Some(Duration::ZERO);
+                    false
+                }
+            }
+        }
+    }
+}
+
+struct Action {
+    name: String,
+    bindings: Vec<Binding>,
+    states: Vec<BindingState>,
+}
+
+struct Context {
+    name: String,
+    is_blocking: bool,
+    actions: Vec<Action>,
+}
+
+impl Context {
+    fn action_mut(&mut self, name: &str) -> &mut Action {
+        match self.actions.iter().position(|action| action.name == name) {
+            Some(index) => &mut self.actions[index],
+            None => {
+                self.actions.push(Action {
+                    name: name.to_string(),
+                    bindings: Vec::new(),
+                    states: Vec::new(),
+                });
+                self.actions.last_mut().unwrap()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct ActionState {
+    value: (f64, f64),
+    is_pressed: bool,
+    was_pressed: bool,
+}
+
+/// Turns events into actions. Feed it every event with `handle_event`, call
+/// `update` once per frame, and then ask it about actions by name. Actions
+/// nobody bound are never pressed and have a value of zero.
+pub struct ActionMap {
+    contexts: Vec<Context>,
+    /// Indices into `contexts`, with the top of the stack last.
+    stack: Vec<usize>,
+    devices: Devices,
+    actions: HashMap<String, ActionState>,
+}
+
+impl ActionMap {
+    pub fn new() -> ActionMap {
+        ActionMap {
+            contexts: Vec::new(),
+            stack: Vec::new(),
+            devices: Devices::default(),
+            actions: HashMap::new(),
+        }
+    }
+
+    fn context_mut(&mut self, name: &str) -> (usize, &mut Context) {
+        let index = match self
+            .contexts
+            .iter()
+            .position(|context| context.name == name)
+        {
+            Some(index) => index,
+            None => {
+                self.contexts.push(Context {
+                    name: name.to_string(),
+                    is_blocking: true,
+                    actions: Vec::new(),
+                });
+                self.contexts.len() - 1
+            }
+        };
+
+        (index, &mut self.contexts[index])
+    }
+
+    /// Adds a binding to an action in a context, next to the ones it already
+    /// has. The context is created if it does not exist yet.
+    pub fn bind(&mut self, context: &str, action: &str, binding: Binding) {
+        let action = self.context_mut(context).1.action_mut(action);
+        action.bindings.push(binding);
+        action.states.push(BindingState::default());
+    }
+
+    /// Removes every binding of an action in a context, e.g. before the play-
+    /// er rebinds it.
+    pub fn unbind(&mut self, context: &str, action: &str) {
+        let action = self.context_mut(context).1.action_mut(action);
+        action.bindings.clear();
+        action.states.clear();
+    }
+
+    /// Returns the bindings of an action in a context.
+    pub fn bindings(&self, context: &str, action: &str) -> &[Binding] {
+        self.contexts
+            .iter()
+            .find(|other| other.name == context)
+            .and_then(|context| context.actions.iter().find(|other| other.name == action))
+            .map_or(&[], |action| &action.bindings[..])
+    }
+
+    /// Makes a context active on top of the others. While a blocking context
+    /// is on the stack, the contexts below it are not, which is how a menu
+    /// keeps the game from moving. Contexts block unless told otherwise.
+    pub fn push_context(&mut self, context: &str) {
+        let index = self.context_mut(context).0;
+        self.stack.push(index);
+    }
+
+    /// Removes the top context from the stack and returns its name.
+    pub fn pop_context(&mut self) -> Option<String> {
+        self.stack
+            .pop()
+            .map(|index| self.contexts[index].name.clone())
+    }
+
+    /// Sets if a context keeps the contexts below it on the stack from being
+    /// active. Something that works everywhere, like taking a screenshot,
+    /// goes in a context at the bottom, under contexts that do not block.
+    pub fn set_context_blocking(&mut self, context: &str, is_blocking: bool) {
+        self.context_mut(context).1.is_blocking = is_blocking;
+    }
+
+    /// Keeps track of what the event presses or releases. Key repeats are
+    /// ignored, and everything held on the keyboard and mouse is released
+    /// when the window loses focus, as their releases will not be sent.
+    pub fn handle_event(&mut self, event: &Event) {
+        let devices = &mut self.devices;
+        match *event {
+            Event::Key {
+                scancode,
+                is_press,
+                is_repeat: false,
+                ..
+            } => devices.set_held(Input::Key(scancode), is_press),
+            Event::MouseButton { button, is_press } => {
+                devices.set_held(Input::MouseButton(button), is_press)
+            }
+            Event::GamepadButton {
+                id,
+                button,
+                is_press,
+            } => {
+                if is_press {
+                    devices.gamepad_buttons.insert((id, button));
+                    devices.pressed.insert(Input::GamepadButton(button));
+                } else {
+                    devices.gamepad_buttons.remove(&(id, button));
+                }
+            }
+            Event::GamepadAxis { id, axis, value } => {
+                devices.gamepad_axes.insert((id, axis), value);
+            }
+            Event::GamepadDisconnected { id } => {
+                devices.gamepad_buttons.retain(|&(other, _)| other != id);
+                devices.gamepad_axes.retain(|&(other, _), _| other != id);
+            }
+            Event::FocusLost => {
+                devices.keys.clear();
+                devices.mouse_buttons.clear();
+            }
+            _ => {}
+        }
+    }
+
+    /// Works out the actions for this frame. `dt` is the time since the last
+    /// update, which the triggers measure time by.
+    pub fn update(&mut self, dt: Duration) {
+        for state in self.actions.values_mut() {
+            state.was_pressed = state.is_pressed;
+            state.is_pressed = false;
+            state.value = (0.0, 0.0);
+        }
+
+        let mut active = Vec::new();
+        for &index in self.stack.iter().rev() {
+            if !active.contains(&index) {
+                active.push(index);
+            }
+            if self.contexts[index].is_blocking {
+                break;
+            }
+        }
+
+        for (index, context) in self.contexts.iter_mut().enumerate() {
+            let is_active = active.contains(&index);
+            for action in &mut context.actions {
+                for (binding, state) in action.bindings.iter().zip(&mut action.states) {
+                    if !is_active {
+                        *state = BindingState::default();
+                        continue;
+                    }
+
+                    let (value, is_pressed) = evaluate(binding, state, &self.devices, dt);
+                    let action = self.actions.entry(action.name.clone()).or_default();
+                    if length(value) > length(action.value) {
+                        action.value = value;
+                    }
+                    action.is_pressed |= is_pressed;
+                }
+            }
+        }
+
+        self.devices.pressed.clear();
+    }
+
+    fn state(&self, action: &str) -> ActionState {
+        self.actions.get(action).copied().unwrap_or_default()
+    }
+
+    /// Returns if the action is pressed this frame.
+    pub fn is_action_pressed(&self, action: &str) -> bool {
+        self.state(action).is_pressed
+    }
+
+    /// Returns if the action became pressed this frame.
+    pub fn was_action_pressed(&self, action: &str) -> bool {
+        let state = self.state(action);
+        state.is_pressed && !state.was_pressed
+    }
+
+    /// Returns if the action stopped being pressed this frame.
+    pub fn was_action_released(&self, action: &str) -> bool {
+        let state = self.state(action);
+        !state.is_pressed && state.was_pressed
+    }
+
+    /// Returns the value of an action along one axis, e.g. how far a throttle
+    /// is pushed. For a 2D action it is the x axis.
+    pub fn action_value(&self, action: &str) -> f64 {
+        self.state(action).value.0
+    }
+
+    /// Returns the value of a 2D action, like where to move. Its length is
+    /// never more than 1.0, so diagonals are not faster.
+    pub fn action_axis(&self, action: &str) -> (f64, f64) {
+        self.state(action).value
+    }
+
+    /// Reads bindings from a config, see the top of this file for the format.
+    /// Every action in the config gets the bindings it lists, in place of
+    /// the ones it had, and others are left alone, so a player's config can
+    /// be loaded over the defaults. Nothing changes if the config has an er-
+    /// ror.
+    pub fn load_config(&mut self, config: &str) -> Result<(), ActionError> {
+        let mut parsed: Vec<(String, String, Vec<Binding>)> = Vec::new();
+        let mut context = None;
+
+        for (number, line) in config.lines().enumerate() {
+            let error = |message: String| ActionError::Parse {
+                line: number + 1,
+                message,
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+            {
+                context = Some(name.trim().to_string());
+                continue;
+            }
+
+            let (action, bindings) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected `action = bindings`, found `{}`", line)))?;
+            let context = context
+                .clone()
+                .ok_or_else(|| error("bindings have to be in a [context]".to_string()))?;
+
+            let bindings = bindings.trim();
+            let bindings = if bindings.is_empty() {
+                Vec::new()
+            } else {
+                bindings
+                    .split('|')
+                    .map(|binding| parse_binding(binding.trim()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?
+            };
+
+            parsed.push((context, action.trim().to_string(), bindings));
+        }
+
+        for (context, action, bindings) in parsed {
+            self.unbind(&context, &action);
+            for binding in bindings {
+                self.bind(&context, &action, binding);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes every binding as a config that `load_config` can read.
+    pub fn save_config(&self) -> String {
+        let mut config = String::new();
+
+        for context in &self.contexts {
+            if context.actions.is_empty() {
+                continue;
+            }
+            if !config.is_empty() {
+                config.push('\n');
+            }
+
+            config.push_str(&format!("[{}]\n", context.name));
+            for action in &context.actions {
+                let bindings: Vec<String> = action.bindings.iter().map(format_binding).collect();
+                config.push_str(&format!("{} = {}\n", action.name, bindings.join(" | ")));
+            }
+        }
+
+        config
+    }
+
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), ActionError> {
+        let config = std::fs::read_to_string(path)?;
+        self.load_config(&config)
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<(), ActionError> {
+        std::fs::write(path, self.save_config())?;
+        Ok(())
+    }
+}
+
+impl Default for ActionMap {
+    fn default() -> ActionMap {
+        ActionMap::new()
+    }
+}
+
+fn length((x, y): (f64, f64)) -> f64 {
+    (x * x + y * y).sqrt()
+}
+
+/// Returns the value of a binding and if it is pressed this frame.
+fn evaluate(
+    binding: &Binding,
+    state: &mut BindingState,
+    devices: &Devices,
+    dt: Duration,
+) -> ((f64, f64), bool) {
+    let value = match *binding {
+        Binding::Buttons {
+            ref inputs,
+            trigger,
+        } => {
+            let is_down = !inputs.is_empty() && inputs.iter().all(|&input| devices.is_held(input));
+            let is_pressed = state.update(is_down, trigger, dt);
+            return (if is_pressed { (1.0, 0.0) } else { (0.0, 0.0) }, is_pressed);
+        }
+        Binding::Axis(axis) => (devices.axis(axis), 0.0),
+        Binding::Composite1d { negative, positive } => {
+            (devices.value(positive) - devices.value(negative), 0.0)
+        }
+        Binding::Composite2d {
+            up,
+            down,
+            left,
+            right,
+        } => (
+            devices.value(right) - devices.value(left),
+            devices.value(up) - devices.value(down),
+        ),
+        Binding::Stick(Stick::Left) => (
+            devices.axis(GamepadAxis::LeftX),
+            devices.axis(GamepadAxis::LeftY),
+        ),
+        Binding::Stick(Stick::Right) => (
+            devices.axis(GamepadAxis::RightX),
+            devices.axis(GamepadAxis::RightY),
+        ),
+    };
+
+    let length = length(value);
+    let value = if length > 1.0 {
+        (value.0 / length, value.1 / length)
+    } else {
+        value
+    };
+
+    (value, length > PRESS_THRESHOLD)
+}
+
+const KEYS: &[Key] = &[
+    Key::Escape,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::F16,
+    Key::F17,
+    Key::F18,
+    Key::F19,
+    Key::F20,
+    Key::F21,
+    Key::F22,
+    Key::F23,
+    Key::F24,
+    Key::PrintScreen,
+    Key::ScrollLock,
+    Key::Pause,
+    Key::GraveAccent,
+    Key::One,
+    Key::Two,
+    Key::Three,
+    Key::Four,
+    Key::Five,
+    Key::Six,
+    Key::Seven,
+    Key::Eight,
+    Key::Nine,
+    Key::Zero,
+    Key::Hyphen,
+    Key::EqualSign,
+    Key::Backspace,
+    Key::Tab,
+    Key::Q,
+    Key::W,
+    Key::E,
+    Key::R,
+    Key::T,
+    Key::Y,
+    Key::U,
+    Key::I,
+    Key::O,
+    Key::P,
+    Key::LeftSquareBracket,
+    Key::RightSquareBracket,
+    Key::BackSlash,
+    Key::CapsLock,
+    Key::A,
+    Key::S,
+    Key::D,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::Semicolon,
+    Key::Quote,
+    Key::Enter,
+    Key::LeftShift,
+    Key::Z,
+    Key::X,
+    Key::C,
+    Key::V,
+    Key::B,
+    Key::N,
+    Key::M,
+    Key::Comma,
+    Key::Period,
+    Key::ForwardSlash,
+    Key::RightShift,
+    Key::LeftControl,
+    Key::LeftSuper,
+    Key::LeftAlt,
+    Key::Spacebar,
+    Key::RightAlt,
+    Key::RightSuper,
+    Key::Menu,
+    Key::RightControl,
+    Key::Insert,
+    Key::Delete,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::UpArrow,
+    Key::DownArrow,
+    Key::LeftArrow,
+    Key::RightArrow,
+    Key::NumLock,
+    Key::NumpadDivide,
+    Key::NumpadMultiply,
+    Key::NumpadSubtract,
+    Key::NumpadAdd,
+    Key::NumpadEnter,
+    Key::NumpadDecimal,
+    Key::NumpadComma,
+    Key::NumpadEqual,
+    Key::NumpadZero,
+    Key::NumpadOne,
+    Key::NumpadTwo,
+    Key::NumpadThree,
+    Key::NumpadFour,
+    Key::NumpadFive,
+    Key::NumpadSix,
+    Key::NumpadSeven,
+    Key::NumpadEight,
+    Key::NumpadNine,
+    Key::MediaPlayPause,
+    Key::MediaStop,
+    Key::MediaNextTrack,
+    Key::MediaPreviousTrack,
+    Key::VolumeMute,
+    Key::VolumeDown,
+    Key::VolumeUp,
+    Key::BrowserBack,
+    Key::BrowserForward,
+    Key::BrowserRefresh,
+    Key::BrowserStop,
+    Key::BrowserSearch,
+    Key::BrowserFavorites,
+    Key::BrowserHome,
+    Key::LaunchMail,
+    Key::LaunchCalculator,
+    Key::IntlBackslash,
+    Key::IntlRo,
+    Key::IntlYen,
+    Key::KanaMode,
+    Key::Convert,
+    Key::NonConvert,
+    Key::Lang1,
+    Key::Lang2,
+];
+
+const MOUSE_BUTTONS: &[MouseButton] = &[
+    MouseButton::Left,
+    MouseButton::Right,
+    MouseButton::Middle,
+    MouseButton::One,
+    MouseButton::Two,
+    MouseButton::Three,
+    MouseButton::Four,
+    MouseButton::Five,
+    MouseButton::Six,
+    MouseButton::Seven,
+    MouseButton::Eight,
+    MouseButton::Nine,
+    MouseButton::Ten,
+];
+
+const GAMEPAD_BUTTONS: &[GamepadButton] = &[
+    GamepadButton::A,
+    GamepadButton::B,
+    GamepadButton::X,
+    GamepadButton::Y,
+    GamepadButton::Back,
+    GamepadButton::Guide,
+    GamepadButton::Start,
+    GamepadButton::LeftStick,
+    GamepadButton::RightStick,
+    GamepadButton::LeftShoulder,
+    GamepadButton::RightShoulder,
+    GamepadButton::DPadUp,
+    GamepadButton::DPadDown,
+    GamepadButton::DPadLeft,
+    GamepadButton::DPadRight,
+    GamepadButton::Misc,
+];
+
+const GAMEPAD_AXES: &[GamepadAxis] = &[
+    GamepadAxis::LeftX,
+    GamepadAxis::LeftY,
+    GamepadAxis::RightX,
+    GamepadAxis::RightY,
+    GamepadAxis::LeftTrigger,
+    GamepadAxis::RightTrigger,
+];
+
+/// Finds a value by the name `Debug` gives it, or makes one with `numbered`
+/// for names like `Other(3)`.
+fn parse_name<T: fmt::Debug + Copy>(
+    name: &str,
+    values: &[T],
+    numbered: &str,
+    make: impl Fn(u32) -> Option<T>,
+) -> Result<T, String> {
+    if let Some(&value) = values.iter().find(|value| format!("{:?}", value) == name) {
+        return Ok(value);
+    }
+
+    name.strip_prefix(numbered)
+        .and_then(|rest| rest.strip_prefix('('))
+        .and_then(|rest| rest.strip_suffix(')'))
+        .and_then(|number| number.parse().ok())
+        .and_then(make)
+        .ok_or_else(|| format!("unknown name `{}`", name))
+}
+
+fn parse_axis(name: &str) -> Result<GamepadAxis, String> {
+    parse_name(name, GAMEPAD_AXES, "Other", |number| {
+        u16::try_from(number).ok().map(GamepadAxis::Other)
+    })
+}
+
+fn parse_input(text: &str) -> Result<Input, String> {
+    let (kind, name) = text
+        .split_once(':')
+        .ok_or_else(|| format!("expected `kind:name`, found `{}`", text))?;
+    let name = name.trim();
+
+    match kind.trim() {
+        "key" => {
+            parse_name(name, KEYS, "Unknown", |number| Some(Key::Unknown(number))).map(Input::Key)
+        }
+        "mouse" => parse_name(name, MOUSE_BUTTONS, "", |_| None).map(Input::MouseButton),
+        "button" => parse_name(name, GAMEPAD_BUTTONS, "Other", |number| {
+            u16::try_from(number).ok().map(GamepadButton::Other)
+        })
+        .map(Input::GamepadButton),
+        "axis" => {
+            if let Some(name) = name.strip_prefix('-') {
+                Ok(Input::GamepadAxis(
+                    parse_axis(name)?,
+                    AxisDirection::Negative,
+                ))
+            } else if let Some(name) = name.strip_prefix('+') {
+                Ok(Input::GamepadAxis(
+                    parse_axis(name)?,
+                    AxisDirection::Positive,
+                ))
+            } else {
+                Err(format!(
+                    "`axis:{}` needs a direction here, like `axis:+{}`",
+                    name, name
+                ))
+            }
+        }
+        kind => Err(format!("unknown kind of input `{}`", kind)),
+    }
+}
+
+fn parse_trigger(text: &str) -> Result<Trigger, String> {
+    let (kind, seconds) = text
+        .split_once(':')
+        .ok_or_else(|| format!("expected `trigger:seconds`, found `{}`", text))?;
+    let seconds: f64 = seconds
+        .trim()
+        .parse()
+        .map_err(|_| format!("`{}` is not a number of seconds", seconds.trim()))?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(format!("`{}` is not a number of seconds", seconds));
+    }
+
+    let duration = Duration::from_secs_f64(seconds);
+    match kind.trim() {
+        "hold" => Ok(Trigger::Hold(duration)),
+        "tap" => Ok(Trigger::Tap(duration)),
+        "doubletap" => Ok(Trigger::DoubleTap(duration)),
+        kind => Err(format!("unknown trigger `{}`", kind)),
+    }
+}
+
+fn parse_binding(text: &str) -> Result<Binding, String> {
+    let (body, trigger) = match text.split_once('@') {
+        Some((body, trigger)) => (body.trim(), Some(parse_trigger(trigger.trim())?)),
+        None => (text, None),
+    };
+
+    let binding = if let Some(name) = body.strip_prefix("stick:") {
+        match name.trim() {
+            "Left" => Binding::Stick(Stick::Left),
+            "Right" => Binding::Stick(Stick::Right),
+            name => return Err(format!("unknown stick `{}`", name)),
+        }
+    } else if body.contains('/') {
+        let parts = body
+            .split('/')
+            .map(|part| parse_input(part.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match parts[..] {
+            [negative, positive] => Binding::Composite1d { negative, positive },
+            [up, down, left, right] => Binding::Composite2d {
+                up,
+                down,
+                left,
+                right,
+            },
+            _ => {
+                return Err(format!(
+                    "a composite has two or four parts, not {}",
+                    parts.len()
+                ))
+            }
+        }
+    } else if let Some(axis) = body
+        .strip_prefix("axis:")
+        .filter(|name| !name.starts_with('+') && !name.starts_with('-'))
+    {
+        Binding::Axis(parse_axis(axis.trim())?)
+    } else {
+        // A `+` right after the `:` is the direction of an axis, not another
+        // input of the chord.
+        let mut inputs = Vec::new();
+        let mut input = String::new();
+        for part in body.split('+') {
+            if input.ends_with(':') {
+                input.push('+');
+                input.push_str(part);
+                continue;
+            }
+            if !input.is_empty() {
+                inputs.push(parse_input(input.trim())?);
+            }
+            input = part.to_string();
+        }
+        inputs.push(parse_input(input.trim())?);
+
+        return Ok(Binding::Buttons {
+            inputs,
+            trigger: trigger.unwrap_or(Trigger::Press),
+        });
+    };
+
+    match trigger {
+        Some(_) => Err(format!("only buttons can have a trigger, not `{}`", body)),
+        None => Ok(binding),
+    }
+}
+
+fn format_input(input: &Input) -> String {
+    match input {
+        Input::Key(key) => format!("key:{:?}", key),
+        Input::MouseButton(button) => format!("mouse:{:?}", button),
+        Input::GamepadButton(button) => format!("button:{:?}", button),
+        Input::GamepadAxis(axis, AxisDirection::Negative) => format!("axis:-{:?}", axis),
+        Input::GamepadAxis(axis, AxisDirection::Positive) => format!("axis:+{:?}", axis),
+    }
+}
+
+fn format_binding(binding: &Binding) -> String {
+    match binding {
+        Binding::Buttons { inputs, trigger } => {
+            let inputs: Vec<String> = inputs.iter().map(format_input).collect();
+            let inputs = inputs.join("+");
+            match trigger {
+                Trigger::Press => inputs,
+                Trigger::Hold(duration) => format!("{}@hold:{}", inputs, duration.as_secs_f64()),
+                Trigger::Tap(duration) => format!("{}@tap:{}", inputs, duration.as_secs_f64()),
+                Trigger::DoubleTap(duration) => {
+                    format!("{}@doubletap:{}", inputs, duration.as_secs_f64())
+                }
+            }
+        }
+        Binding::Axis(axis) => format!("axis:{:?}", axis),
+        Binding::Composite1d { negative, positive } => {
+            format!("{}/{}", format_input(negative), format_input(positive))
+        }
+        Binding::Composite2d {
+            up,
+            down,
+            left,
+            right,
+        } => format!(
+            "{}/{}/{}/{}",
+            format_input(up),
+            format_input(down),
+            format_input(left),
+            format_input(right)
+        ),
+        Binding::Stick(stick) => format!("stick:{:?}", stick),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::tests::key;
+
+    const FRAME: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn wasd_moves_diagonally_at_full_speed_only() {
+        let mut actions = ActionMap::new();
+        actions.bind(
+            "gameplay",
+            "move",
+            Binding::keys_2d(Key::W, Key::S, Key::A, Key::D),
+        );
+        actions.push_context("gameplay");
+
+        actions.handle_event(&key(Key::W, true));
+        actions.handle_event(&key(Key::D, true));
+        actions.update(FRAME);
+
+        let (x, y) = actions.action_axis("move");
+        assert!((x - y).abs() < 1e-9 && (length((x, y)) - 1.0).abs() < 1e-9);
+        assert!(actions.was_action_pressed("move"));
+    }
+
+    #[test]
+    fn hold_and_double_tap_wait_for_their_time() {
+        let mut actions = ActionMap::new();
+        let hold = Trigger::Hold(Duration::from_millis(200));
+        let double_tap = Trigger::DoubleTap(Duration::from_millis(300));
+        actions.bind(
+            "gameplay",
+            "sprint",
+            Binding::key(Key::LeftShift).with_trigger(hold),
+        );
+        actions.bind(
+            "gameplay",
+            "dodge",
+            Binding::key(Key::Spacebar).with_trigger(double_tap),
+        );
+        actions.push_context("gameplay");
+
+        actions.handle_event(&key(Key::LeftShift, true));
+        actions.update(FRAME);
+        actions.update(FRAME);
+        assert!(!actions.is_action_pressed("sprint"));
+        actions.update(FRAME);
+        assert!(actions.is_action_pressed("sprint"));
+
+        // A press and release between frames still counts.
+        actions.handle_event(&key(Key::Spacebar, true));
+        actions.handle_event(&key(Key::Spacebar, false));
+        actions.update(FRAME);
+        actions.update(FRAME);
+        assert!(!actions.is_action_pressed("dodge"));
+        actions.handle_event(&key(Key::Spacebar, true));
+        actions.update(FRAME);
+        assert!(actions.was_action_pressed("dodge"));
+    }
+
+    #[test]
+    fn blocking_context_hides_the_ones_below() {
+        let mut actions = ActionMap::new();
+        actions.bind("global", "screenshot", Binding::key(Key::F12));
+        actions.bind("gameplay", "jump", Binding::key(Key::Spacebar));
+        actions.bind("menu", "back", Binding::key(Key::Escape));
+        actions.set_context_blocking("global", false);
+        actions.push_context("gameplay");
+        actions.push_context("global");
+
+        actions.handle_event(&key(Key::Spacebar, true));
+        actions.handle_event(&key(Key::F12, true));
+        actions.update(FRAME);
+        assert!(actions.is_action_pressed("jump"));
+        assert!(actions.is_action_pressed("screenshot"));
+
+        actions.push_context("menu");
+        actions.update(FRAME);
+        assert!(!actions.is_action_pressed("jump"));
+        assert!(!actions.is_action_pressed("screenshot"));
+        assert!(actions.was_action_released("jump"));
+    }
+
+    #[test]
+    fn config_round_trips_and_overrides() {
+        let config = "\
+# Defaults
+[gameplay]
+jump = key:Spacebar | button:A
+move = key:W/key:S/key:A/key:D | stick:Left
+save = key:LeftControl+key:S@tap:0.25
+menu_down = axis:-LeftY+button:Other(4)
+steer = axis:LeftX
+";
+        let mut actions = ActionMap::new();
+        actions.load_config(config).unwrap();
+        assert_eq!(
+            actions.bindings("gameplay", "menu_down"),
+            &[Binding::chord(&[
+                Input::GamepadAxis(GamepadAxis::LeftY, AxisDirection::Negative),
+                Input::GamepadButton(GamepadButton::Other(4)),
+            ])]
+        );
+        assert_eq!(actions.save_config(), config.replace("# Defaults\n", ""));
+
+        actions.load_config("[gameplay]\njump = key:J\n").unwrap();
+        assert_eq!(
+            actions.bindings("gameplay", "jump"),
+            &[Binding::key(Key::J)]
+        );
+        assert_eq!(actions.bindings("gameplay", "steer").len(), 1);
+
+        let error = actions
+            .load_config("[gameplay]\njump = key:Nope\n")
+            .unwrap_err();
+        assert!(matches!(error, ActionError::Parse { line: 2, .. }));
+        assert_eq!(
+            actions.bindings("gameplay", "jump"),
+            &[Binding::key(Key::J)]
+        );
+    }
+}
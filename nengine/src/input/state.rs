@@ -0,0 +1,236 @@
+// The input state answers questions like "is W down right now?", for code
+// that would rather look than listen to every event. It is fed the same eve-
+// nts as everything else and forgets what happened once the frame is over.
+
+use crate::{Event, Key, MouseButton, ScrollUnit};
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// What the keyboard and the mouse are doing this frame. Feed it every event
+/// with `handle_event` and call `next_frame` once the frame is done with it.
+/// Keys are by scancode, so they are where they are on a US keyboard.
+#[derive(Default)]
+pub struct InputState {
+    keys_down: HashSet<Key>,
+    keys_pressed: HashSet<Key>,
+    keys_released: HashSet<Key>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    mouse_position: (f64, f64),
+    mouse_delta: (f64, f64),
+    scroll_lines: (f64, f64),
+    scroll_pixels: (f64, f64),
+}
+
+impl InputState {
+    pub fn new() -> InputState {
+        InputState::default()
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::Key {
+                scancode,
+                is_press,
+                is_repeat: false,
+                ..
+            } => update_held(
+                scancode,
+                is_press,
+                &mut self.keys_down,
+                &mut self.keys_pressed,
+                &mut self.keys_released,
+            ),
+            Event::MouseButton { button, is_press } => update_held(
+                button,
+                is_press,
+                &mut self.buttons_down,
+                &mut self.buttons_pressed,
+                &mut self.buttons_released,
+            ),
+            Event::MouseMove { x, y } => self.mouse_position = (x, y),
+            Event::MouseDelta { dx, dy } => {
+                self.mouse_delta.0 += dx;
+                self.mouse_delta.1 += dy;
+            }
+            Event::MouseScroll { x, y, unit } => {
+                let scroll = match unit {
+                    ScrollUnit::Lines => &mut self.scroll_lines,
+                    ScrollUnit::Pixels => &mut self.scroll_pixels,
+                };
+                scroll.0 += x;
+                scroll.1 += y;
+            }
+            // The releases of whatever is held now go to the window that gets
+            // the focus, so they are released here instead of sticking.
+            Event::FocusLost => {
+                self.keys_released.extend(self.keys_down.drain());
+                self.buttons_released.extend(self.buttons_down.drain());
+            }
+            _ => {}
+        }
+    }
+
+    /// Forgets what was pressed, released, moved and scrolled this frame.
+    /// What is held stays held.
+    pub fn next_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.scroll_lines = (0.0, 0.0);
+        self.scroll_pixels = (0.0, 0.0);
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    /// Returns if the key went down this frame. Key repeats do not count.
+    pub fn was_key_pressed(&self, key: Key) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn was_key_released(&self, key: Key) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn was_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn was_mouse_button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// Returns where the cursor was last seen in the window, in pixels from
+    /// its top-left corner.
+    pub fn mouse_position(&self) -> (f64, f64) {
+        self.mouse_position
+    }
+
+    /// Returns how far the mouse moved this frame, from `Event::MouseDelta`,
+    /// so it keeps counting while the cursor is locked.
+    pub fn mouse_delta(&self) -> (f64, f64) {
+        self.mouse_delta
+    }
+
+    /// Returns how far was scrolled this frame in one unit. Scrolls in the
+    /// other unit are not converted, as there is no right number of pixels
+    /// to a line.
+    pub fn scroll(&self, unit: ScrollUnit) -> (f64, f64) {
+        match unit {
+            ScrollUnit::Lines => self.scroll_lines,
+            ScrollUnit::Pixels => self.scroll_pixels,
+        }
+    }
+}
+
+/// Moves something in or out of the held set, and remembers that it was pre-
+/// ssed or released if it was not already.
+fn update_held<T: Copy + Eq + Hash>(
+    item: T,
+    is_press: bool,
+    down: &mut HashSet<T>,
+    pressed: &mut HashSet<T>,
+    released: &mut HashSet<T>,
+) {
+    if is_press && down.insert(item) {
+        pressed.insert(item);
+    } else if !is_press && down.remove(&item) {
+        released.insert(item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::tests::key;
+
+    #[test]
+    fn presses_last_a_frame_and_focus_loss_releases() {
+        let mut input = InputState::new();
+        input.handle_event(&key(Key::W, true));
+        input.handle_event(&Event::MouseDelta { dx: 2.0, dy: 1.0 });
+        input.handle_event(&Event::MouseDelta { dx: 3.0, dy: 0.0 });
+        assert!(input.is_key_down(Key::W) && input.was_key_pressed(Key::W));
+        assert_eq!(input.mouse_delta(), (5.0, 1.0));
+
+        input.next_frame();
+        assert!(input.is_key_down(Key::W) && !input.was_key_pressed(Key::W));
+        assert_eq!(input.mouse_delta(), (0.0, 0.0));
+
+        input.handle_event(&Event::FocusLost);
+        assert!(!input.is_key_down(Key::W) && input.was_key_released(Key::W));
+    }
+
+    #[test]
+    fn releases_last_a_frame_and_repeats_do_not_press() {
+        let mut input = InputState::new();
+        input.handle_event(&key(Key::A, true));
+        input.next_frame();
+        input.handle_event(&Event::Key {
+            keycode: Key::A,
+            scancode: Key::A,
+            is_press: true,
+            is_repeat: true,
+            modifiers: crate::Modifiers::empty(),
+        });
+        assert!(input.is_key_down(Key::A) && !input.was_key_pressed(Key::A));
+
+        input.handle_event(&key(Key::A, false));
+        assert!(!input.is_key_down(Key::A) && input.was_key_released(Key::A));
+
+        input.next_frame();
+        assert!(!input.was_key_released(Key::A));
+    }
+
+    #[test]
+    fn mouse_buttons_are_held_across_frames() {
+        let mut input = InputState::new();
+        let press = |is_press| Event::MouseButton {
+            button: MouseButton::Left,
+            is_press,
+        };
+
+        input.handle_event(&press(true));
+        input.handle_event(&Event::MouseMove { x: 10.0, y: 20.0 });
+        assert!(input.was_mouse_button_pressed(MouseButton::Left));
+        assert_eq!(input.mouse_position(), (10.0, 20.0));
+
+        input.next_frame();
+        assert!(input.is_mouse_button_down(MouseButton::Left));
+        assert!(!input.was_mouse_button_pressed(MouseButton::Left));
+        assert_eq!(input.mouse_position(), (10.0, 20.0));
+
+        input.handle_event(&press(false));
+        assert!(!input.is_mouse_button_down(MouseButton::Left));
+        assert!(input.was_mouse_button_released(MouseButton::Left));
+    }
+
+    #[test]
+    fn scrolls_are_kept_apart_by_unit() {
+        let mut input = InputState::new();
+        input.handle_event(&Event::MouseScroll {
+            x: 0.0,
+            y: 1.0,
+            unit: ScrollUnit::Lines,
+        });
+        input.handle_event(&Event::MouseScroll {
+            x: 4.0,
+            y: -2.0,
+            unit: ScrollUnit::Pixels,
+        });
+        assert_eq!(input.scroll(ScrollUnit::Lines), (0.0, 1.0));
+        assert_eq!(input.scroll(ScrollUnit::Pixels), (4.0, -2.0));
+
+        input.next_frame();
+        assert_eq!(input.scroll(ScrollUnit::Lines), (0.0, 0.0));
+    }
+}
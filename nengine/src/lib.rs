@@ -1,11 +1,22 @@
 pub mod ffi;
+pub mod gamepad;
+pub mod input;
 pub mod platform;
 
+use gamepad::{GamepadAxis, GamepadButton, GamepadId};
+use input::InputState;
+use platform::{CrossPlatformWindow, WindowId};
+use std::ops::{BitOr, BitOrAssign};
+use std::path::PathBuf;
+
 /// The struct that represents the entire Nengine. Rust doesn't let us use glo-
 /// bal variables so we have to use a struct to contain everything. So, it is  
-/// empty because we don't have any engine components yet, but soon stuff will
-/// show up.
-pub struct Nengine {}
+/// nearly empty because we don't have many engine components yet, but soon
+/// stuff will show up.
+pub struct Nengine {
+    input: InputState,
+    windows: Vec<Box<dyn CrossPlatformWindow>>,
+}
 
 impl Nengine {
     /// Initializes the Nengine. Because of how Rust works, this can only do s-
@@ -14,7 +25,10 @@ impl Nengine {
     pub fn new() -> Nengine {
         println!("[INFO]: Starting Phase 0 of Engine Initialization");
 
-        return Nengine {};
+        return Nengine {
+            input: InputState::new(),
+            windows: Vec::new(),
+        };
     }
 
     pub fn init(&mut self) {
@@ -26,8 +40,37 @@ impl Nengine {
         return true;
     }
 
+    /// Returns what the keyboard and the mouse are doing this frame.
+    pub fn input(&self) -> &InputState {
+        &self.input
+    }
+
+    /// Hands an event that did not come from one of the Nengine's windows to
+    /// the Nengine. It counts toward the frame the last `update` started.
+    pub fn handle_event(&mut self, event: &Event) {
+        self.input.handle_event(event);
+    }
+
+    /// Gives a window to the Nengine, which polls its events every frame. The
+    /// window is dropped once it is closed.
+    pub fn add_window(&mut self, window: Box<dyn CrossPlatformWindow>) -> WindowId {
+        let id = window.id();
+        self.windows.push(window);
+        id
+    }
+
+    /// Returns a window given to the Nengine, or None once `update` dropped it
+    /// because it was closed.
+    pub fn window_mut(&mut self, id: WindowId) -> Option<&mut dyn CrossPlatformWindow> {
+        let window = self.windows.iter_mut().find(|window| window.id() == id)?;
+        Some(window.as_mut())
+    }
+
     fn _on_event(&self, event: Event) {
-        if let Event::Key { keycode, is_press } = event {
+        if let Event::Key {
+            keycode, is_press, ..
+        } = event
+        {
             if let Key::W = keycode {
                 if is_press {
                     println!("The W key has been pressed!");
@@ -37,9 +80,20 @@ impl Nengine {
     }
 
     /// Executed every frame, this functions performs all of the per-frame ope-
-    /// rations.
+    /// rations. It starts the frame by forgetting what the last one pressed,
+    /// then feeds the events of every window to the input, so `input` des-
+    /// cribes the new frame until the next `update`.
     pub fn update(&mut self) {
         println!("[INFO]: Updating the Nengine.");
+
+        self.input.next_frame();
+        for window in &mut self.windows {
+            for event in window.poll_events() {
+                self.input.handle_event(&event);
+            }
+        }
+
+        self.windows.retain(|window| window.is_open());
     }
 }
 
@@ -50,7 +104,9 @@ impl Drop for Nengine {
 }
 
 /// A list of Keycodes (or rather not keycodes since Rust enums do not represe-
-/// nt integers). Incomplete for now and does not support Apple keyboards.
+/// nt integers). Keys that the Nengine does not know about are reported as `U-
+/// nknown` with the platform scancode, so they can still be told apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Key {
     Escape,
     F1,
@@ -65,6 +121,21 @@ pub enum Key {
     F10,
     F11,
     F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    PrintScreen,
+    ScrollLock,
+    Pause,
     GraveAccent,
     One,
     Two,
@@ -126,9 +197,119 @@ pub enum Key {
     RightSuper,
     Menu,
     RightControl,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    UpArrow,
+    DownArrow,
+    LeftArrow,
+    RightArrow,
+    NumLock,
+    NumpadDivide,
+    NumpadMultiply,
+    NumpadSubtract,
+    NumpadAdd,
+    NumpadEnter,
+    NumpadDecimal,
+    NumpadComma,
+    NumpadEqual,
+    NumpadZero,
+    NumpadOne,
+    NumpadTwo,
+    NumpadThree,
+    NumpadFour,
+    NumpadFive,
+    NumpadSix,
+    NumpadSeven,
+    NumpadEight,
+    NumpadNine,
+    MediaPlayPause,
+    MediaStop,
+    MediaNextTrack,
+    MediaPreviousTrack,
+    VolumeMute,
+    VolumeDown,
+    VolumeUp,
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserStop,
+    BrowserSearch,
+    BrowserFavorites,
+    BrowserHome,
+    LaunchMail,
+    LaunchCalculator,
+    /// The extra key next to the left Shift on ISO keyboards.
+    IntlBackslash,
+    /// The `ろ` key on JIS keyboards.
+    IntlRo,
+    /// The `¥` key on JIS keyboards.
+    IntlYen,
+    KanaMode,
+    Convert,
+    NonConvert,
+    /// The Hangul/English toggle on Korean keyboards.
+    Lang1,
+    /// The Hanja conversion key on Korean keyboards.
+    Lang2,
+    Unknown(u32),
+}
+
+/// A set of modifier keys that were held down or locked when a key event hap-
+/// pened. Combine them with `|` and test them with `contains`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const SHIFT: Modifiers = Modifiers(1 << 0);
+    pub const CONTROL: Modifiers = Modifiers(1 << 1);
+    pub const ALT: Modifiers = Modifiers(1 << 2);
+    pub const SUPER: Modifiers = Modifiers(1 << 3);
+    pub const CAPS_LOCK: Modifiers = Modifiers(1 << 4);
+    pub const NUM_LOCK: Modifiers = Modifiers(1 << 5);
+
+    /// Returns a set with no modifiers in it.
+    pub const fn empty() -> Modifiers {
+        Modifiers(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns if every modifier in `other` is also in this set.
+    pub fn contains(&self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn set(&mut self, other: Modifiers, value: bool) {
+        if value {
+            self.0 |= other.0;
+        } else {
+            self.0 &= !other.0;
+        }
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, other: Modifiers) -> Modifiers {
+        Modifiers(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self, other: Modifiers) {
+        self.0 |= other.0;
+    }
 }
 
 /// A list of Mouse buttons. Supports up to ten mouse buttons
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum MouseButton {
     Left,
     Right,
@@ -145,21 +326,160 @@ pub enum MouseButton {
     Ten,
 }
 
+/// What the deltas of a scroll are measured in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScrollUnit {
+    /// Notches of a wheel, which most programs scroll by three lines each.
+    /// Touchpads and high-resolution wheels send fractions of a notch.
+    Lines,
+    /// Pixels, as sent by touchpads that know how far the fingers moved.
+    Pixels,
+}
+
 /// A library-independent method of representing an external event that the Ne-
 /// ngine can receive from it's execution environment.
+#[derive(Clone)]
 pub enum Event {
-    Key { keycode: Key, is_press: bool },
-    MouseButton { button: MouseButton, is_press: bool },
-    MouseScroll { x: f64, y: f64 },
-    MouseMove { x: f64, y: f64 },
+    /// A key was pressed or released. `keycode` is the key according to the
+    /// active keyboard layout, so it is what the user sees printed on the key.
+    /// `scancode` is the physical key, named after the key at the same place
+    /// on a US QWERTY keyboard, so WASD bindings keep working on AZERTY.
+    Key {
+        keycode: Key,
+        scancode: Key,
+        is_press: bool,
+        is_repeat: bool,
+        modifiers: Modifiers,
+    },
+    TextInput {
+        text: String,
+    },
+    /// The text an input method is composing changed. It is not part of the
+    /// text yet and is usually shown underlined where the text cursor is. The
+    /// cursor is a byte offset into it, or None if it is hidden. An empty te-
+    /// xt means that the composition is over.
+    ImePreedit {
+        text: String,
+        cursor: Option<usize>,
+    },
+    /// An input method is done composing text, which goes where the text cur-
+    /// sor is. It comes instead of `TextInput`.
+    ImeCommit {
+        text: String,
+    },
+    MouseButton {
+        button: MouseButton,
+        is_press: bool,
+    },
+    /// The wheel was turned or the touchpad scrolled. Positive values mean
+    /// up and right.
+    MouseScroll {
+        x: f64,
+        y: f64,
+        unit: ScrollUnit,
+    },
+    MouseMove {
+        x: f64,
+        y: f64,
+    },
+    /// The mouse moved, straight from the device. Unlike `MouseMove` it comes
+    /// before pointer acceleration and does not stop at the edges of the win-
+    /// dow or the screen. It is sent to the focused window.
+    MouseDelta {
+        dx: f64,
+        dy: f64,
+    },
+    /// The inner size of the window changed, in pixels.
+    Resized {
+        width: u32,
+        height: u32,
+    },
+    /// The window was moved. The position is that of its top-left corner on
+    /// the desktop.
+    Moved {
+        x: i32,
+        y: i32,
+    },
+    /// The scale factor of the window changed, e.g. because it was moved to
+    /// a monitor with a different one. A `Resized` follows if the size in
+    /// pixels changes along with it.
+    ScaleFactorChanged {
+        scale_factor: f64,
+    },
+    FocusGained,
+    FocusLost,
+    CursorEntered,
+    CursorLeft,
+    /// The user asked for the window to be closed, e.g. with its close butt-
+    /// on. The window stays open until `close` is called on it, so ignoring
+    /// this event is how closing is cancelled.
+    CloseRequested,
+    /// The contents of the window were lost and have to be drawn again.
+    RedrawRequested,
+    /// Files are being dragged over the window. Which files only becomes kn-
+    /// own once they are dropped. On Windows this needs OLE, which a thread
+    /// that set up COM for another apartment does not have, and then only
+    /// `FileDropped` is sent.
+    FileHoverStarted,
+    /// The files that were dragged over the window left it, or the drag was
+    /// cancelled.
+    FileHoverCancelled,
+    /// Files were dropped onto the window. The position is where they were
+    /// dropped, in pixels from the top-left corner of the window.
+    FileDropped {
+        paths: Vec<PathBuf>,
+        position: (f64, f64),
+    },
+    /// A gamepad was plugged in, or was already when `Gamepads` was created.
+    GamepadConnected {
+        id: GamepadId,
+        name: String,
+    },
+    GamepadDisconnected {
+        id: GamepadId,
+    },
+    GamepadButton {
+        id: GamepadId,
+        button: GamepadButton,
+        is_press: bool,
+    },
+    /// An axis of a gamepad moved out of the deadzone, within it, or back in-
+    /// to it, which sends 0.0.
+    GamepadAxis {
+        id: GamepadId,
+        axis: GamepadAxis,
+        value: f64,
+    },
 }
 
-
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::input::tests::key;
+    use crate::platform::{headless, WindowBuilder};
+
     #[test]
     fn it_works() {
         let result = 2 + 2;
         assert_eq!(result, 4);
     }
+
+    #[test]
+    fn update_feeds_window_events_into_the_new_frame() {
+        let mut window = headless::Window::new(&WindowBuilder::new()).unwrap();
+        window.inject(key(Key::W, true));
+
+        let mut nengine = Nengine::new();
+        nengine.add_window(Box::new(window));
+        nengine.update();
+        assert!(nengine.input().was_key_pressed(Key::W));
+
+        nengine.handle_event(&key(Key::S, true));
+        assert!(nengine.input().was_key_pressed(Key::S));
+
+        nengine.update();
+        assert!(nengine.input().is_key_down(Key::W));
+        assert!(!nengine.input().was_key_pressed(Key::W));
+        assert!(!nengine.input().was_key_pressed(Key::S));
+    }
 }
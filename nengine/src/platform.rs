@@ -14,16 +14,684 @@ pub mod windows;
 #[cfg(target_os = "windows")]
 pub use self::windows::*;
 
+// Headless stuff, available everywhere
+pub mod headless;
+
+// The Win32 translation tables do not touch the windows crate, so they are
+// also built on other hosts to let their tests run there.
+#[cfg(all(test, not(target_os = "windows")))]
+#[path = "platform/windows/keyboard.rs"]
+mod windows_keyboard;
+#[cfg(all(test, not(target_os = "windows")))]
+#[path = "platform/windows/mouse.rs"]
+mod windows_mouse;
+
+use crate::Event;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::rc::Rc;
+
+/// The events returned by `CrossPlatformWindow::poll_events`. Any events left
+/// over when this is dropped are thrown away.
+pub type Events<'a> = std::collections::vec_deque::Drain<'a, Event>;
+
+/// The events returned by `CrossPlatformEventLoop::poll_events`, each one wi-
+/// th the window it belongs to.
+pub type WindowEvents<'a> = std::collections::vec_deque::Drain<'a, (WindowId, Event)>;
+
+/// A handler of the events of one window, as set by `set_event_callback`.
+type Callback = Box<dyn FnMut(&Event)>;
+
+/// The queue that the window implementations push translated events onto. It
+/// is shared by every window of an event loop, so events stay in the order
+/// they happened in. Events are kept until the caller takes them, and are sh-
+/// own to the callback of their window, if it has one, by `run_callbacks`.
+pub(crate) struct EventQueue {
+    events: VecDeque<(WindowId, Event)>,
+    /// Copies of the events that the callbacks have not seen yet.
+    unseen: VecDeque<(WindowId, Event)>,
+    callbacks: HashMap<WindowId, Rc<RefCell<Callback>>>,
+}
+
+impl EventQueue {
+    pub(crate) fn new() -> EventQueue {
+        EventQueue {
+            events: VecDeque::new(),
+            unseen: VecDeque::new(),
+            callbacks: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn set_callback(&mut self, window: WindowId, callback: Callback) {
+        self.callbacks
+            .insert(window, Rc::new(RefCell::new(callback)));
+    }
+
+    pub(crate) fn push(&mut self, window: WindowId, event: Event) {
+        if self.callbacks.contains_key(&window) {
+            self.unseen.push_back((window, event.clone()));
+        }
+
+        self.events.push_back((window, event));
+    }
+
+    /// Takes the next event that a callback has yet to see, along with that
+    /// callback.
+    fn next_unseen(&mut self) -> Option<(Rc<RefCell<Callback>>, Event)> {
+        while let Some((window, event)) = self.unseen.pop_front() {
+            if let Some(callback) = self.callbacks.get(&window) {
+                return Some((callback.clone(), event));
+            }
+        }
+
+        None
+    }
+
+    /// Takes every event out of the queue.
+    pub(crate) fn take_all(&mut self) -> VecDeque<(WindowId, Event)> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Moves the events of one window to `into`, leaving the ones of the oth-
+    /// er windows behind.
+    pub(crate) fn take_window(&mut self, window: WindowId, into: &mut VecDeque<Event>) {
+        for (id, event) in std::mem::take(&mut self.events) {
+            if id == window {
+                into.push_back(event);
+            } else {
+                self.events.push_back((id, event));
+            }
+        }
+    }
+
+    /// Forgets a window that is being dropped, along with its callback and
+    /// any events of it that were not taken yet.
+    pub(crate) fn remove_window(&mut self, window: WindowId) {
+        self.callbacks.remove(&window);
+        self.events.retain(|(id, _)| *id != window);
+        self.unseen.retain(|(id, _)| *id != window);
+    }
+}
+
+/// Shows the events that were pushed since the last time to the callbacks of
+/// their windows. `queue` finds the queue in whatever holds it, which is only
+/// borrowed in between the callbacks, so that they can use their windows.
+pub(crate) fn run_callbacks<T>(owner: &RefCell<T>, queue: fn(&mut T) -> &mut EventQueue) {
+    loop {
+        let next = queue(&mut owner.borrow_mut()).next_unseen();
+        let (callback, event) = match next {
+            Some(next) => next,
+            None => return,
+        };
+
+        // A callback that polls its own window from inside of itself is still
+        // running further up, so it misses the events that polling brings.
+        let mut callback = match callback.try_borrow_mut() {
+            Ok(callback) => callback,
+            Err(_) => continue,
+        };
+        callback(&event);
+    }
+}
+
+/// The ways creating a window can fail.
+#[derive(Debug)]
+pub enum WindowError {
+    /// There is no display to open a window on, e.g. `DISPLAY` is not set.
+    NoDisplay,
+    /// The display server could not be reached. Holds the XCB error code.
+    ConnectionFailed(i32),
+    /// The X server does not support the XKB extension, which is needed for
+    /// keyboard input.
+    MissingXkbExtension,
+    /// The X server could not look up the atom with this name.
+    AtomLookupFailed(String),
+    /// The operating system or one of the libraries it ships with reported an
+    /// error.
+    Os(String),
+    /// The pixels of an icon do not match its size. There have to be four by-
+    /// tes per pixel.
+    InvalidIcon,
+    /// The pixels of a custom cursor do not match its size, or its hotspot is
+    /// outside of it.
+    InvalidCursor,
+    /// The platform cannot do what was asked, e.g. a window attribute it has
+    /// no equivalent for. Holds the name of the feature.
+    Unsupported(&'static str),
+    /// The Wayland compositor does not offer a protocol that is needed. Holds
+    /// the name of its global, e.g. `xdg_wm_base`.
+    MissingProtocol(&'static str),
+}
+
+impl fmt::Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowError::NoDisplay => write!(f, "no display is available"),
+            WindowError::ConnectionFailed(code) => {
+                write!(
+                    f,
+                    "could not connect to the display server (error {})",
+                    code
+                )
+            }
+            WindowError::MissingXkbExtension => {
+                write!(f, "the X server does not support the XKB extension")
+            }
+            WindowError::AtomLookupFailed(name) => write!(f, "could not look up the {} atom", name),
+            WindowError::Os(message) => write!(f, "{}", message),
+            WindowError::InvalidIcon => write!(f, "the icon does not have 4 bytes per pixel"),
+            WindowError::InvalidCursor => {
+                write!(
+                    f,
+                    "the cursor does not have 4 bytes per pixel or its hotspot is outside of it"
+                )
+            }
+            WindowError::Unsupported(feature) => {
+                write!(f, "{} is not supported on this platform", feature)
+            }
+            WindowError::MissingProtocol(global) => {
+                write!(f, "the compositor does not support {}", global)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WindowError {}
+
+/// How a window covers the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// A normal window with decorations.
+    Windowed,
+    /// A window without decorations that covers the whole monitor it is on.
+    Borderless,
+    /// Like `Borderless`, but also switches the monitor to a video mode, one
+    /// of the `video_modes` of the monitor the window is on. The old mode is
+    /// switched back to once the window leaves fullscreen or is closed. Way-
+    /// land does not let clients switch modes, so it is `Borderless` there,
+    /// and X11 cannot switch to modes larger than the desktop.
+    Exclusive(VideoMode),
+}
+
+/// How a window holds on to the cursor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorGrab {
+    /// The cursor moves freely.
+    None,
+    /// The cursor cannot leave the window.
+    Confined,
+    /// The cursor stays where it is, which is what first-person cameras want.
+    /// Movement is only reported through `Event::MouseDelta` then, there are
+    /// no `MouseMove` events.
+    Locked,
+}
+
+/// The standard shapes of the cursor. Each platform draws them in its own st-
+/// yle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CursorIcon {
+    /// The arrow that windows start out with.
+    Default,
+    /// The I-beam shown over text that can be selected or typed into.
+    Text,
+    /// The hand shown over links and buttons.
+    Pointer,
+    Crosshair,
+    /// Shown while the program is busy.
+    Wait,
+    /// Shown over something that cannot be used, or dropped onto.
+    NotAllowed,
+    /// Arrows in all four directions, for moving something around.
+    Move,
+    /// Arrows to the left and the right, e.g. over a vertical splitter.
+    EwResize,
+    /// Arrows to the top and the bottom, e.g. over a horizontal splitter.
+    NsResize,
+    /// Arrows to the top left and the bottom right.
+    NwseResize,
+    /// Arrows to the top right and the bottom left.
+    NeswResize,
+}
+
+/// An image to use as the cursor, e.g. the crosshair of a game. `rgba` holds
+/// four bytes per pixel, row by row from the top, and the hotspot is the pix-
+/// el that does the pointing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomCursor {
+    pub(crate) rgba: Vec<u8>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) hotspot: (u32, u32),
+}
+
+impl CustomCursor {
+    pub fn new(
+        rgba: Vec<u8>,
+        width: u32,
+        height: u32,
+        hotspot: (u32, u32),
+    ) -> Result<CustomCursor, WindowError> {
+        if rgba.len() as u64 != u64::from(width) * u64::from(height) * 4
+            || hotspot.0 >= width
+            || hotspot.1 >= height
+        {
+            return Err(WindowError::InvalidCursor);
+        }
+
+        Ok(CustomCursor {
+            rgba,
+            width,
+            height,
+            hotspot,
+        })
+    }
+
+    /// Returns the pixels as BGRA with the colors multiplied by the alpha,
+    /// which is what the platforms want for cursors.
+    pub(crate) fn premultiplied_bgra(&self) -> Vec<u8> {
+        self.rgba
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                let alpha = u16::from(pixel[3]);
+                let premultiply = |color: u8| ((u16::from(color) * alpha + 127) / 255) as u8;
+                [
+                    premultiply(pixel[2]),
+                    premultiply(pixel[1]),
+                    premultiply(pixel[0]),
+                    pixel[3],
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Which clipboard to use. Besides the clipboard that copy and paste go thro-
+/// ugh, X11 has the primary selection, which holds whatever text was selected
+/// last and is pasted with the middle mouse button. Elsewhere the primary se-
+/// lection is always empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Selection {
+    Clipboard,
+    Primary,
+}
+
+/// How urgently a window wants the attention of the user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UserAttention {
+    /// Something needs the user, e.g. a prompt. On Windows the taskbar button
+    /// flashes until the window is focused.
+    Critical,
+    /// Something finished, e.g. a long import. On Windows the taskbar button
+    /// flashes once and then stays highlighted until the window is focused.
+    Informational,
+}
+
+/// A monitor, as it was when it was asked for. Positions and sizes are in
+/// pixels, on the desktop all monitors share.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Monitor {
+    /// The name the monitor goes by, e.g. `DP-1` on X11. It is empty if the
+    /// platform does not name it.
+    pub name: String,
+    pub position: (i32, i32),
+    /// The size of the current video mode.
+    pub size: (u32, u32),
+    /// In millimeters. It is (0, 0) if the monitor does not say, and some
+    /// projectors say nonsense.
+    pub physical_size: (u32, u32),
+    /// The refresh rate of the current video mode.
+    pub refresh_rate_millihertz: u32,
+    /// How much larger than usual things have to be drawn for them to have
+    /// the usual size, e.g. 2.0 on most laptops with 4K screens.
+    pub scale_factor: f64,
+    /// Whether this is the monitor that new windows and the taskbar go to.
+    /// Wayland has no such monitor.
+    pub is_primary: bool,
+    /// Every mode the monitor can be switched to, including the current one.
+    pub video_modes: Vec<VideoMode>,
+}
+
+/// A mode a monitor can display in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VideoMode {
+    pub size: (u32, u32),
+    /// Bits per pixel, across all color channels. It is 0 if the platform
+    /// does not know.
+    pub bit_depth: u16,
+    /// In millihertz, since rates like 59.94 Hz are common. It is 0 if the
+    /// platform does not know.
+    pub refresh_rate_millihertz: u32,
+}
+
+/// Identifies a window, e.g. to make it the parent of another one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WindowId(pub(crate) u64);
+
+/// An image in RGBA order, row by row from the top.
+#[derive(Clone, Debug)]
+pub(crate) struct Icon {
+    pub(crate) rgba: Vec<u8>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl Icon {
+    /// Checks that there are four bytes for every pixel.
+    pub(crate) fn validate(&self) -> Result<(), WindowError> {
+        if self.rgba.len() as u64 != u64::from(self.width) * u64::from(self.height) * 4 {
+            return Err(WindowError::InvalidIcon);
+        }
+
+        Ok(())
+    }
+}
+
+/// Describes a window before it is created. Every attribute has a default,
+/// so only the ones that matter have to be set.
+#[derive(Clone, Debug)]
+pub struct WindowBuilder {
+    pub(crate) size: (u32, u32),
+    pub(crate) position: Option<(i32, i32)>,
+    pub(crate) title: String,
+    pub(crate) fullscreen: FullscreenMode,
+    pub(crate) decorations: bool,
+    pub(crate) resizable: bool,
+    pub(crate) transparent: bool,
+    pub(crate) always_on_top: bool,
+    pub(crate) icon: Option<Icon>,
+    pub(crate) class: Option<(String, String)>,
+    pub(crate) parent: Option<WindowId>,
+}
+
+impl WindowBuilder {
+    pub fn new() -> WindowBuilder {
+        WindowBuilder {
+            size: (800, 600),
+            position: None,
+            title: "Nengine".to_owned(),
+            fullscreen: FullscreenMode::Windowed,
+            decorations: true,
+            resizable: true,
+            transparent: false,
+            always_on_top: false,
+            icon: None,
+            class: None,
+            parent: None,
+        }
+    }
+
+    /// Sets the inner size of the window, in pixels. It is only used once the
+    /// window leaves fullscreen, if it starts out fullscreen.
+    pub fn with_size(mut self, width: u32, height: u32) -> WindowBuilder {
+        self.size = (width, height);
+        self
+    }
+
+    /// Places the top-left corner of the window on the desktop. Without this
+    /// the window manager picks a spot.
+    pub fn with_position(mut self, x: i32, y: i32) -> WindowBuilder {
+        self.position = Some((x, y));
+        self
+    }
+
+    pub fn with_title(mut self, title: &str) -> WindowBuilder {
+        self.title = title.to_owned();
+        self
+    }
+
+    pub fn with_fullscreen(mut self, mode: FullscreenMode) -> WindowBuilder {
+        self.fullscreen = mode;
+        self
+    }
+
+    /// Sets whether the window has a title bar and borders.
+    pub fn with_decorations(mut self, decorations: bool) -> WindowBuilder {
+        self.decorations = decorations;
+        self
+    }
+
+    pub fn with_resizable(mut self, resizable: bool) -> WindowBuilder {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Lets the alpha channel of whatever is drawn into the window show the
+    /// desktop behind it.
+    pub fn with_transparent(mut self, transparent: bool) -> WindowBuilder {
+        self.transparent = transparent;
+        self
+    }
+
+    /// Keeps the window above every other window that is not on top.
+    pub fn with_always_on_top(mut self, always_on_top: bool) -> WindowBuilder {
+        self.always_on_top = always_on_top;
+        self
+    }
+
+    /// Sets the icon shown in the title bar and the taskbar. `rgba` holds four
+    /// bytes per pixel, row by row from the top.
+    pub fn with_icon(mut self, rgba: Vec<u8>, width: u32, height: u32) -> WindowBuilder {
+        self.icon = Some(Icon {
+            rgba,
+            width,
+            height,
+        });
+        self
+    }
+
+    /// Sets the instance and class names in `WM_CLASS`, which X11 desktops use
+    /// to group windows and find their desktop entry. On Wayland the class be-
+    /// comes the app id. Other platforms have neither, so building fails with
+    /// `WindowError::Unsupported` there.
+    pub fn with_class(mut self, instance: &str, class: &str) -> WindowBuilder {
+        self.class = Some((instance.to_owned(), class.to_owned()));
+        self
+    }
+
+    /// Makes the window belong to another one. It stays above its parent, is
+    /// minimized along with it and gets no taskbar entry of its own, which is
+    /// what dialogs and tool windows want.
+    pub fn with_parent(mut self, parent: WindowId) -> WindowBuilder {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// Creates the window on its own event loop. It is not shown until `show`
+    /// is called.
+    pub fn build(&self) -> Result<Window, WindowError> {
+        Window::new(self)
+    }
+
+    /// Creates the window on an existing event loop, next to the other win-
+    /// dows of that loop.
+    pub fn build_in(&self, event_loop: &EventLoop) -> Result<Window, WindowError> {
+        event_loop.create_window(self)
+    }
+
+    /// Checks the attributes that can be wrong regardless of the platform.
+    pub(crate) fn validate(&self) -> Result<(), WindowError> {
+        if let Some(icon) = &self.icon {
+            icon.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for WindowBuilder {
+    fn default() -> WindowBuilder {
+        WindowBuilder::new()
+    }
+}
+
+/// An event loop trait, kept consistent across platforms like the Window tr-
+/// ait. An event loop owns the connection to the display server and can dri-
+/// ve any number of windows.
+pub trait CrossPlatformEventLoop {
+    fn new() -> Result<Self, WindowError>
+    where
+        Self: Sized;
+
+    /// Creates a window that shares this loop. It keeps working after the loop
+    /// itself is dropped.
+    fn create_window(&self, builder: &WindowBuilder) -> Result<Window, WindowError>;
+
+    /// Lists the monitors that are connected and turned on.
+    fn monitors(&self) -> Vec<Monitor>;
+
+    /// Processes every event that is waiting and returns them, together with
+    /// the window each one belongs to.
+    fn poll_events(&mut self) -> WindowEvents<'_>;
+}
+
 /// A Window trait. This trait is used to maintain consistency across the diff-
 /// erent implmenentations of the Window class.
 pub trait CrossPlatformWindow {
-    fn new(width: u32, height: u32, title: &str, fullscreen: bool) -> Self;
+    /// Creates a window on an event loop of its own. `WindowBuilder::build` is
+    /// the nicer way to call this.
+    fn new(builder: &WindowBuilder) -> Result<Self, WindowError>
+    where
+        Self: Sized;
+
+    fn id(&self) -> WindowId;
     
-    fn set_event_callback(&mut self, callback: fn(crate::Event));
+    /// Sets a handler that sees every event of the window while events are
+    /// polled, before `poll_events` returns them. The handler may use any wi-
+    /// ndow of the event loop, its own included.
+    fn set_event_callback(&mut self, callback: Box<dyn FnMut(&Event)>);
     
-    fn show(&self);
+    fn show(&mut self);
+
+    fn set_title(&mut self, title: &str);
+
+    /// Sets the inner size of the window, in pixels.
+    fn set_size(&mut self, width: u32, height: u32);
+
+    /// Moves the top-left corner of the window to a position on the desktop.
+    fn set_position(&mut self, x: i32, y: i32);
+
+    fn set_fullscreen(&mut self, mode: FullscreenMode);
+
+    /// Lists the monitors, like `CrossPlatformEventLoop::monitors` does.
+    fn monitors(&self) -> Vec<Monitor>;
+
+    /// Returns the monitor that most of the window is on.
+    fn current_monitor(&self) -> Option<Monitor>;
+
+    /// Returns the scale factor of the window, which follows the monitor it
+    /// is on. `Event::ScaleFactorChanged` says when it changes.
+    fn scale_factor(&self) -> f64;
+
+    /// Sets whether the user can resize the window. `set_size` still works on
+    /// windows that are not resizable.
+    fn set_resizable(&mut self, resizable: bool);
+
+    /// Limits how small the user can make the window. `None` removes the lim-
+    /// it.
+    fn set_min_size(&mut self, size: Option<(u32, u32)>);
+
+    /// Limits how large the user can make the window. `None` removes the lim-
+    /// it.
+    fn set_max_size(&mut self, size: Option<(u32, u32)>);
+
+    /// Minimizes the window. If it is not shown yet, it will start out mini-
+    /// mized once it is.
+    fn minimize(&mut self);
+
+    /// Maximizes the window, or gives it back the size and position it had
+    /// before. If it is not shown yet, it will start out maximized once it
+    /// is.
+    fn set_maximized(&mut self, maximized: bool);
+
+    /// Replaces the icon shown in the title bar and the taskbar. `rgba` holds
+    /// four bytes per pixel, row by row from the top.
+    fn set_icon(&mut self, rgba: &[u8], width: u32, height: u32) -> Result<(), WindowError>;
+
+    /// Asks the user to look at the window, usually by highlighting it in the
+    /// taskbar. It stops once the window is focused. X11 has only one kind of
+    /// attention, and Wayland has none.
+    fn request_attention(&mut self, attention: UserAttention);
+
+    /// Hides or shows the cursor while it is over the window.
+    fn set_cursor_visible(&mut self, visible: bool);
+
+    /// Sets the shape of the cursor while it is over the window, in place of
+    /// any custom cursor. A hidden cursor stays hidden.
+    fn set_cursor_icon(&mut self, icon: CursorIcon);
+
+    /// Sets an image as the cursor while it is over the window. A hidden cur-
+    /// sor stays hidden. Fails if the platform cannot make a cursor out of
+    /// the image, in which case the old cursor stays.
+    fn set_custom_cursor(&mut self, cursor: &CustomCursor) -> Result<(), WindowError>;
+
+    /// Confines or locks the cursor to the window. The grab only holds while
+    /// the window has focus and comes back when it regains it.
+    fn set_cursor_grab(&mut self, grab: CursorGrab);
+
+    /// Returns the text on a clipboard, or None if it is empty or holds so-
+    /// mething that is not text. On X11 the text has to be asked for from the
+    /// window that copied it, so this waits for it, and gives up if nothing
+    /// arrives for a second.
+    fn get_clipboard_text(&mut self, selection: Selection) -> Option<String>;
+
+    /// Puts text on a clipboard. On X11 the window keeps the text and hands it
+    /// out when asked, so it is gone once the window is closed.
+    fn set_clipboard_text(&mut self, selection: Selection, text: &str);
+
+    /// Lets an input method compose text for the window, or stops it. It is
+    /// off at first so that games get every key as it is. While it is on, the
+    /// text comes as `ImePreedit` and `ImeCommit`. On X11 it is the XIM input
+    /// method that `XMODIFIERS` names, which needs the `xim` feature.
+    fn set_ime_allowed(&mut self, allowed: bool);
+
+    /// Places the candidate window of the input method next to a point, in
+    /// pixels from the top-left corner of the window. It is usually where the
+    /// text cursor is.
+    fn set_ime_position(&mut self, x: i32, y: i32);
     
     fn is_open(&self) -> bool;
     
-    fn poll_events(&mut self);
-}
\ No newline at end of file
+    /// Closes the window. Windows are never closed on their own, so this has
+    /// to be called in response to `Event::CloseRequested`.
+    fn close(&mut self);
+    
+    /// Processes every event that is waiting and returns the ones of this wi-
+    /// ndow. The events of other windows on the same event loop are kept for
+    /// them.
+    fn poll_events(&mut self) -> Events<'_>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The callback borrows the queue like window methods borrow what the
+    // windows of an event loop share.
+    #[test]
+    fn callbacks_can_borrow_the_queue() {
+        let queue = Rc::new(RefCell::new(EventQueue::new()));
+        let window = WindowId(1);
+
+        let shared = Rc::downgrade(&queue);
+        let callback = move |event: &Event| {
+            if let (Event::FocusGained, Some(queue)) = (event, shared.upgrade()) {
+                queue.borrow_mut().push(window, Event::FocusLost);
+            }
+        };
+        queue.borrow_mut().set_callback(window, Box::new(callback));
+        queue.borrow_mut().push(window, Event::FocusGained);
+        run_callbacks(&queue, |queue| queue);
+
+        let events: Vec<Event> = queue
+            .borrow_mut()
+            .take_all()
+            .into_iter()
+            .map(|(_, event)| event)
+            .collect();
+        assert!(matches!(
+            events.as_slice(),
+            [Event::FocusGained, Event::FocusLost]
+        ));
+    }
+}
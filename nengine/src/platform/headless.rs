@@ -0,0 +1,333 @@
+// The headless backend never talks to the operating system. Its windows only
+// exist in memory, so it works without a display, e.g. in tests and on dedi-
+// cated servers. Events come from whoever holds the window instead of a disp-
+// lay server.
+
+use crate::platform::{
+    run_callbacks, CrossPlatformWindow, CursorGrab, CursorIcon, CustomCursor, EventQueue, Events,
+    FullscreenMode, Icon, Monitor, Selection, UserAttention, WindowBuilder, WindowError, WindowId,
+};
+use crate::Event;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Gives every headless window an id of its own.
+static NEXT_WINDOW_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A call made on a headless window. Every call is recorded, even the ones a
+/// real window would ignore, like `show` on a window that is already shown.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call {
+    Show,
+    SetTitle(String),
+    SetSize(u32, u32),
+    SetPosition(i32, i32),
+    SetFullscreen(FullscreenMode),
+    SetResizable(bool),
+    SetMinSize(Option<(u32, u32)>),
+    SetMaxSize(Option<(u32, u32)>),
+    Minimize,
+    SetMaximized(bool),
+    /// Holds the size of the icon.
+    SetIcon(u32, u32),
+    RequestAttention(UserAttention),
+    SetCursorVisible(bool),
+    SetCursorIcon(CursorIcon),
+    SetCustomCursor(CustomCursor),
+    SetCursorGrab(CursorGrab),
+    SetClipboardText(Selection, String),
+    SetImeAllowed(bool),
+    SetImePosition(i32, i32),
+    Close,
+}
+
+/// A window that is not shown anywhere. Each one is on its own, there is no
+/// event loop to share.
+pub struct Window {
+    id: WindowId,
+    is_open: bool,
+    calls: Vec<Call>,
+    queue: RefCell<EventQueue>,
+
+    /// The clipboards of the window, which no other window can see.
+    clipboard: HashMap<Selection, String>,
+
+    /// The events that were taken from the queue by the last `poll_events`.
+    events: VecDeque<Event>,
+}
+
+impl Window {
+    /// Sends an event to the window as if the platform had. The callback sees
+    /// it right away, and the next `poll_events` returns it.
+    pub fn inject(&mut self, event: Event) {
+        self.queue.borrow_mut().push(self.id, event);
+        run_callbacks(&self.queue, |queue| queue);
+    }
+
+    /// Returns every call made on the window so far, oldest first.
+    pub fn calls(&self) -> &[Call] {
+        &self.calls
+    }
+}
+
+impl CrossPlatformWindow for Window {
+    fn new(builder: &WindowBuilder) -> Result<Window, WindowError> {
+        builder.validate()?;
+
+        Ok(Window {
+            id: WindowId(NEXT_WINDOW_ID.fetch_add(1, Ordering::Relaxed)),
+            is_open: true,
+            calls: Vec::new(),
+            queue: RefCell::new(EventQueue::new()),
+            clipboard: HashMap::new(),
+            events: VecDeque::new(),
+        })
+    }
+
+    fn id(&self) -> WindowId {
+        self.id
+    }
+
+    fn set_event_callback(&mut self, callback: Box<dyn FnMut(&Event)>) {
+        self.queue.borrow_mut().set_callback(self.id, callback);
+    }
+
+    fn show(&mut self) {
+        self.calls.push(Call::Show);
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.calls.push(Call::SetTitle(title.to_owned()));
+    }
+
+    fn set_size(&mut self, width: u32, height: u32) {
+        self.calls.push(Call::SetSize(width, height));
+    }
+
+    fn set_position(&mut self, x: i32, y: i32) {
+        self.calls.push(Call::SetPosition(x, y));
+    }
+
+    fn set_fullscreen(&mut self, mode: FullscreenMode) {
+        self.calls.push(Call::SetFullscreen(mode));
+    }
+
+    // There is no display, so there are no monitors either.
+    fn monitors(&self) -> Vec<Monitor> {
+        Vec::new()
+    }
+
+    fn current_monitor(&self) -> Option<Monitor> {
+        None
+    }
+
+    fn scale_factor(&self) -> f64 {
+        1.0
+    }
+
+    fn set_resizable(&mut self, resizable: bool) {
+        self.calls.push(Call::SetResizable(resizable));
+    }
+
+    fn set_min_size(&mut self, size: Option<(u32, u32)>) {
+        self.calls.push(Call::SetMinSize(size));
+    }
+
+    fn set_max_size(&mut self, size: Option<(u32, u32)>) {
+        self.calls.push(Call::SetMaxSize(size));
+    }
+
+    fn minimize(&mut self) {
+        self.calls.push(Call::Minimize);
+    }
+
+    fn set_maximized(&mut self, maximized: bool) {
+        self.calls.push(Call::SetMaximized(maximized));
+    }
+
+    fn set_icon(&mut self, rgba: &[u8], width: u32, height: u32) -> Result<(), WindowError> {
+        Icon {
+            rgba: rgba.to_vec(),
+            width,
+            height,
+        }
+        .validate()?;
+
+        self.calls.push(Call::SetIcon(width, height));
+        Ok(())
+    }
+
+    fn request_attention(&mut self, attention: UserAttention) {
+        self.calls.push(Call::RequestAttention(attention));
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        self.calls.push(Call::SetCursorVisible(visible));
+    }
+
+    fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        self.calls.push(Call::SetCursorIcon(icon));
+    }
+
+    fn set_custom_cursor(&mut self, cursor: &CustomCursor) -> Result<(), WindowError> {
+        self.calls.push(Call::SetCustomCursor(cursor.clone()));
+        Ok(())
+    }
+
+    fn set_cursor_grab(&mut self, grab: CursorGrab) {
+        self.calls.push(Call::SetCursorGrab(grab));
+    }
+
+    fn get_clipboard_text(&mut self, selection: Selection) -> Option<String> {
+        self.clipboard.get(&selection).cloned()
+    }
+
+    fn set_clipboard_text(&mut self, selection: Selection, text: &str) {
+        self.calls
+            .push(Call::SetClipboardText(selection, text.to_owned()));
+        self.clipboard.insert(selection, text.to_owned());
+    }
+
+    fn set_ime_allowed(&mut self, allowed: bool) {
+        self.calls.push(Call::SetImeAllowed(allowed));
+    }
+
+    fn set_ime_position(&mut self, x: i32, y: i32) {
+        self.calls.push(Call::SetImePosition(x, y));
+    }
+
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    fn close(&mut self) {
+        self.calls.push(Call::Close);
+        self.is_open = false;
+    }
+
+    fn poll_events(&mut self) -> Events<'_> {
+        self.queue
+            .borrow_mut()
+            .take_window(self.id, &mut self.events);
+
+        self.events.drain(..)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::VideoMode;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn injected_events_are_polled_in_order() {
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        window.inject(Event::Resized {
+            width: 640,
+            height: 480,
+        });
+        window.inject(Event::CloseRequested);
+
+        let events: Vec<Event> = window.poll_events().collect();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                Event::Resized {
+                    width: 640,
+                    height: 480
+                },
+                Event::CloseRequested
+            ]
+        ));
+        assert_eq!(window.poll_events().count(), 0);
+    }
+
+    #[test]
+    fn callback_sees_injected_events() {
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        let seen = Rc::new(Cell::new(0));
+
+        let counter = seen.clone();
+        window.set_event_callback(Box::new(move |_| counter.set(counter.get() + 1)));
+        window.inject(Event::FocusGained);
+        window.inject(Event::FocusLost);
+
+        assert_eq!(seen.get(), 2);
+    }
+
+    #[test]
+    fn calls_are_recorded() {
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        window.show();
+        window.set_title("Test");
+        window.close();
+
+        assert_eq!(
+            window.calls(),
+            &[Call::Show, Call::SetTitle("Test".to_owned()), Call::Close]
+        );
+        assert!(!window.is_open());
+    }
+
+    #[test]
+    fn clipboards_are_kept_apart() {
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        window.set_clipboard_text(Selection::Primary, "selected");
+
+        assert_eq!(window.get_clipboard_text(Selection::Clipboard), None);
+        assert_eq!(
+            window.get_clipboard_text(Selection::Primary).as_deref(),
+            Some("selected")
+        );
+    }
+
+    #[test]
+    fn icon_needs_four_bytes_per_pixel() {
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        assert!(matches!(
+            window.set_icon(&[0; 3 * 4], 2, 2),
+            Err(WindowError::InvalidIcon)
+        ));
+        assert!(window.set_icon(&[0; 2 * 2 * 4], 2, 2).is_ok());
+        assert_eq!(window.calls(), &[Call::SetIcon(2, 2)]);
+    }
+
+    #[test]
+    fn maximizing_and_fullscreen_can_be_undone() {
+        let video_mode = VideoMode {
+            size: (1280, 720),
+            bit_depth: 32,
+            refresh_rate_millihertz: 60000,
+        };
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        window.set_maximized(true);
+        window.set_maximized(false);
+        window.set_fullscreen(FullscreenMode::Exclusive(video_mode));
+        window.set_fullscreen(FullscreenMode::Windowed);
+
+        assert_eq!(
+            window.calls(),
+            &[
+                Call::SetMaximized(true),
+                Call::SetMaximized(false),
+                Call::SetFullscreen(FullscreenMode::Exclusive(video_mode)),
+                Call::SetFullscreen(FullscreenMode::Windowed),
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_cursor_hotspot_must_be_inside() {
+        let rgba = vec![255; 2 * 2 * 4];
+        assert!(CustomCursor::new(rgba.clone(), 2, 2, (2, 0)).is_err());
+        assert!(CustomCursor::new(rgba[..4].to_vec(), 2, 2, (0, 0)).is_err());
+
+        let cursor = CustomCursor::new(rgba, 2, 2, (1, 1)).unwrap();
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        window.set_custom_cursor(&cursor).unwrap();
+        assert_eq!(window.calls(), &[Call::SetCustomCursor(cursor)]);
+    }
+}
@@ -1,320 +1,333 @@
-use crate::{ffi::xcb, Event, MouseButton};
-use std::ffi::c_void;
-
-/// The keyboard mapping.
-struct KeyboardMapping {
-    context: *mut xcb::xkb_context,
-    keymap: *mut xcb::xkb_keymap,
-    state: *mut xcb::xkb_state,
+// Linux has two display servers, so there are two backends. Wayland is tried
+// first and X11 is the fallback, which also covers XWayland. Setting
+// `NENGINE_BACKEND` to `wayland` or `x11` skips the other one. The Wayland
+// backend is behind the `wayland` feature, since building it needs the pro-
+// tocol files and wayland-scanner.
+
+mod keyboard;
+#[cfg(feature = "wayland")]
+pub mod wayland;
+pub mod x11;
+
+use crate::platform::{
+    CrossPlatformEventLoop, CrossPlatformWindow, CursorGrab, CursorIcon, CustomCursor, Events,
+    FullscreenMode, Monitor, Selection, UserAttention, WindowBuilder, WindowError, WindowEvents,
+    WindowId,
+};
+use crate::Event;
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle,
+};
+
+/// The Linux event loop, on whichever display server was found.
+pub enum EventLoop {
+    #[cfg(feature = "wayland")]
+    Wayland(wayland::EventLoop),
+    X11(x11::EventLoop),
 }
 
-impl KeyboardMapping {
-    unsafe fn new(connection: *mut xcb::xcb_connection_t) -> KeyboardMapping {
-        let context = xcb::xkb_context_new(xcb::XKB_CONTEXT_NO_FLAGS);
-        if context == std::ptr::null_mut() {
-            // TODO: Error handling
+/// The Linux window. Which variant it is follows the event loop it was crea-
+/// ted on.
+pub enum Window {
+    #[cfg(feature = "wayland")]
+    Wayland(wayland::Window),
+    X11(x11::Window),
+}
+
+impl CrossPlatformEventLoop for EventLoop {
+    fn new() -> Result<EventLoop, WindowError> {
+        match std::env::var("NENGINE_BACKEND").as_deref() {
+            #[cfg(feature = "wayland")]
+            Ok("wayland") => return Ok(EventLoop::Wayland(wayland::EventLoop::new()?)),
+            #[cfg(not(feature = "wayland"))]
+            Ok("wayland") => return Err(WindowError::Unsupported("the Wayland backend")),
+            Ok("x11") => return Ok(EventLoop::X11(x11::EventLoop::new()?)),
+            _ => (),
         }
 
-        let core_keyboard_device = xcb::xkb_x11_get_core_keyboard_device_id(connection);
-        if core_keyboard_device == -1 {
-            // TODO: Error handling
+        // If neither works, the X11 error is the one reported, since that is
+        // the backend that works in the most places.
+        #[cfg(feature = "wayland")]
+        if let Ok(event_loop) = wayland::EventLoop::new() {
+            return Ok(EventLoop::Wayland(event_loop));
         }
 
-        let keymap = xcb::xkb_x11_keymap_new_from_device(
-            context,
-            connection,
-            core_keyboard_device,
-            xcb::XKB_KEYMAP_COMPILE_NO_FLAGS,
-        );
-        if keymap == std::ptr::null_mut() {
-            // TODO: Error handling
+        Ok(EventLoop::X11(x11::EventLoop::new()?))
+    }
+
+    fn create_window(&self, builder: &WindowBuilder) -> Result<Window, WindowError> {
+        match self {
+            #[cfg(feature = "wayland")]
+            EventLoop::Wayland(event_loop) => {
+                event_loop.create_window(builder).map(Window::Wayland)
+            }
+            EventLoop::X11(event_loop) => event_loop.create_window(builder).map(Window::X11),
         }
+    }
 
-        let state = xcb::xkb_x11_state_new_from_device(keymap, connection, core_keyboard_device);
-        if state == std::ptr::null_mut() {
-            // TODO: Error handling
+    fn monitors(&self) -> Vec<Monitor> {
+        match self {
+            #[cfg(feature = "wayland")]
+            EventLoop::Wayland(event_loop) => event_loop.monitors(),
+            EventLoop::X11(event_loop) => event_loop.monitors(),
         }
+    }
 
-        KeyboardMapping {
-            context,
-            keymap,
-            state,
+    fn poll_events(&mut self) -> WindowEvents<'_> {
+        match self {
+            #[cfg(feature = "wayland")]
+            EventLoop::Wayland(event_loop) => event_loop.poll_events(),
+            EventLoop::X11(event_loop) => event_loop.poll_events(),
         }
     }
+}
 
-    unsafe fn update_keymap(
-        &self,
-        depressed_mods: u32,
-        latched_mods: u32,
-        locked_mods: u32,
-        depressed_layout: u32,
-        latched_layout: u32,
-        locked_layout: u32,
-    ) {
-        xcb::xkb_state_update_mask(
-            self.state,
-            depressed_mods,
-            latched_mods,
-            locked_mods,
-            depressed_layout,
-            latched_layout,
-            locked_layout,
-        );
+impl CrossPlatformWindow for Window {
+    fn new(builder: &WindowBuilder) -> Result<Window, WindowError> {
+        EventLoop::new()?.create_window(builder)
     }
 
-    unsafe fn keycode_to_keysym(&self, keycode: xcb::xcb_keycode_t) -> xcb::xcb_keysym_t {
-        xcb::xkb_state_key_get_one_sym(self.state, keycode.into())
+    fn id(&self) -> WindowId {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.id(),
+            Window::X11(window) => window.id(),
+        }
     }
-}
 
-impl Drop for KeyboardMapping {
-    fn drop(&mut self) {
-        unsafe {
-            xcb::xkb_state_unref(self.state);
-            xcb::xkb_keymap_unref(self.keymap);
-            xcb::xkb_context_unref(self.context);
+    fn set_event_callback(&mut self, callback: Box<dyn FnMut(&Event)>) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_event_callback(callback),
+            Window::X11(window) => window.set_event_callback(callback),
         }
     }
-}
 
-/// The Linux window.
-pub struct Window {
-    connection: *mut xcb::xcb_connection_t,
-    raw_handle: xcb::xcb_window_t,
-    is_open: bool,
-    
-    event_callback: Option<fn(Event)>,
+    fn show(&mut self) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.show(),
+            Window::X11(window) => window.show(),
+        }
+    }
 
-    // Atoms
-    wm_delete_window_atom: xcb::xcb_atom_t,
-}
+    fn set_title(&mut self, title: &str) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_title(title),
+            Window::X11(window) => window.set_title(title),
+        }
+    }
 
-unsafe fn get_xcb_atom(connection: *mut xcb::xcb_connection_t, name: &str) -> xcb::xcb_atom_t {
-    let cookie = xcb::xcb_intern_atom(
-        connection,
-        0,
-        name.len().try_into().unwrap(),
-        name.as_ptr() as *const i8,
-    );
-    let reply = xcb::xcb_intern_atom_reply(connection, cookie, std::ptr::null_mut());
-    (*reply).atom
-}
+    fn set_size(&mut self, width: u32, height: u32) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_size(width, height),
+            Window::X11(window) => window.set_size(width, height),
+        }
+    }
 
-fn translate_xcb_buttons(xcb_button_code: u8) -> MouseButton {
-    match xcb_button_code {
-        1 => MouseButton::Left,
-        2 => MouseButton::Middle,
-        3 => MouseButton::Right,
-        // 4 and 5 are skipped because XCB uses them to send scroll events.
-        6 => MouseButton::Six,
-        7 => MouseButton::Seven,
-        8 => MouseButton::Eight,
-        9 => MouseButton::Nine,
-        10 => MouseButton::Ten,
-        _ => MouseButton::Left,
+    fn set_position(&mut self, x: i32, y: i32) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_position(x, y),
+            Window::X11(window) => window.set_position(x, y),
+        }
     }
-}
 
-impl super::CrossPlatformWindow for Window {
-    /// Creates a new window. Most of the parameters should be self-explanator-
-    /// y.
-    ///
-    /// One thing you need to note is that the `fullscreen` parameter currently
-    /// does not work, but fullscreen functionalities will be added later.
-    ///
-    /// Also note that `width` and `height` will be ignored if `fullscreen` is
-    /// set to true.
-    fn new(width: u32, height: u32, title: &str, fullscreen: bool) -> Window {
-        unsafe {
-            let connection = xcb::xcb_connect(std::ptr::null(), std::ptr::null_mut());
-            let screen = xcb::xcb_setup_roots_iterator(xcb::xcb_get_setup(connection)).data;
-
-            let events = [xcb::XCB_EVENT_MASK_EXPOSURE
-                | xcb::XCB_EVENT_MASK_BUTTON_PRESS
-                | xcb::XCB_EVENT_MASK_BUTTON_RELEASE
-                | xcb::XCB_EVENT_MASK_POINTER_MOTION
-                | xcb::XCB_EVENT_MASK_KEY_PRESS
-                | xcb::XCB_EVENT_MASK_KEY_RELEASE
-                | xcb::XCB_EVENT_MASK_KEYMAP_STATE];
-
-            let window = xcb::xcb_generate_id(connection);
-            xcb::xcb_create_window(
-                connection,
-                xcb::XCB_COPY_FROM_PARENT.try_into().unwrap(),
-                window,
-                (*screen).root,
-                0,
-                0,
-                width.try_into().unwrap(),
-                height.try_into().unwrap(),
-                0,
-                xcb::XCB_WINDOW_CLASS_INPUT_OUTPUT.try_into().unwrap(),
-                (*screen).root_visual,
-                xcb::XCB_CW_EVENT_MASK,
-                events.as_ptr() as *const c_void,
-            );
-
-            xcb::xcb_change_property(
-                connection,
-                xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
-                window,
-                xcb::XCB_ATOM_WM_NAME,
-                xcb::XCB_ATOM_STRING,
-                8,
-                title.len().try_into().unwrap(),
-                title.as_ptr() as *const c_void,
-            );
-
-            let wm_protocols_atom = get_xcb_atom(connection, "WM_PROTOCOLS");
-            let wm_delete_window_atom = get_xcb_atom(connection, "WM_DELETE_WINDOW");
-
-            xcb::xcb_change_property(
-                connection,
-                xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
-                window,
-                wm_protocols_atom,
-                4,
-                32,
-                1,
-                &wm_delete_window_atom as *const u32 as *const c_void,
-            );
-
-            if fullscreen {
-                let net_wm_state_atom = get_xcb_atom(connection, "_NET_WM_STATE");
-                let net_wm_state_fullscreen_atom =
-                    get_xcb_atom(connection, "_NET_WM_STATE_FULLSCREEN");
-
-                xcb::xcb_change_property(
-                    connection,
-                    xcb::XCB_PROP_MODE_APPEND.try_into().unwrap(),
-                    window,
-                    net_wm_state_atom,
-                    4,
-                    32,
-                    1,
-                    &net_wm_state_fullscreen_atom as *const u32 as *const c_void,
-                );
-            }
+    fn set_fullscreen(&mut self, mode: FullscreenMode) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_fullscreen(mode),
+            Window::X11(window) => window.set_fullscreen(mode),
+        }
+    }
 
-            xcb::xcb_flush(connection);
+    fn monitors(&self) -> Vec<Monitor> {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.monitors(),
+            Window::X11(window) => window.monitors(),
+        }
+    }
 
-            Window {
-                connection,
-                raw_handle: window,
-                is_open: true,
-                event_callback: None,
-                wm_delete_window_atom,
-            }
+    fn current_monitor(&self) -> Option<Monitor> {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.current_monitor(),
+            Window::X11(window) => window.current_monitor(),
         }
     }
 
-    fn set_event_callback(&mut self, callback: fn(Event)) {
-        self.event_callback = Some(callback);
+    fn scale_factor(&self) -> f64 {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.scale_factor(),
+            Window::X11(window) => window.scale_factor(),
+        }
     }
 
-    fn show(&self) {
-        unsafe {
-            xcb::xcb_map_window(self.connection, self.raw_handle);
-            xcb::xcb_flush(self.connection);
+    fn set_resizable(&mut self, resizable: bool) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_resizable(resizable),
+            Window::X11(window) => window.set_resizable(resizable),
         }
     }
 
-    fn is_open(&self) -> bool {
-        self.is_open
+    fn set_min_size(&mut self, size: Option<(u32, u32)>) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_min_size(size),
+            Window::X11(window) => window.set_min_size(size),
+        }
     }
 
-    fn poll_events(&mut self) {
-        unsafe {
-            let event = xcb::xcb_poll_for_event(self.connection);
+    fn set_max_size(&mut self, size: Option<(u32, u32)>) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_max_size(size),
+            Window::X11(window) => window.set_max_size(size),
+        }
+    }
 
-            if event == std::ptr::null_mut() {
-                return;
-            }
+    fn minimize(&mut self) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.minimize(),
+            Window::X11(window) => window.minimize(),
+        }
+    }
 
-            let response_type = (*event).response_type & !0x80;
-
-            // Internally handled events.
-            match response_type as u32 {
-                xcb::XCB_CLIENT_MESSAGE => {
-                    if (*(event as *mut xcb::xcb_client_message_event_t))
-                        .data
-                        .data32[0]
-                        == self.wm_delete_window_atom
-                    {
-                        self.is_open = false;
-                    }
-                }
-                _ => (),
-            }
+    fn set_maximized(&mut self, maximized: bool) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_maximized(maximized),
+            Window::X11(window) => window.set_maximized(maximized),
+        }
+    }
 
-            // Broadcasted events.
-            let translated_event = match response_type as u32 {
-                xcb::XCB_BUTTON_PRESS => {
-                    let event = event as *mut xcb::xcb_button_press_event_t;
-
-                    let button_code = (*event).detail;
-                    if button_code == 4 {
-                        Some(Event::MouseScroll { x: 0.0, y: 1.0 })
-                    } else if button_code == 5 {
-                        Some(Event::MouseScroll { x: 0.0, y: -1.0 })
-                    } else {
-                        Some(Event::MouseButton {
-                            button: translate_xcb_buttons((*event).detail),
-                            is_press: true,
-                        })
-                    }
-                }
-                xcb::XCB_BUTTON_RELEASE => {
-                    let event = event as *mut xcb::xcb_button_release_event_t;
-                    let button_code = (*event).detail;
-
-                    if button_code != 4 && button_code != 5 {
-                        Some(Event::MouseButton {
-                            button: translate_xcb_buttons(button_code),
-                            is_press: false,
-                        })
-                    } else {
-                        None
-                    }
-                }
-                xcb::XCB_MOTION_NOTIFY => {
-                    let event = event as *mut xcb::xcb_motion_notify_event_t;
-
-                    Some(Event::MouseMove {
-                        x: (*event).event_x.into(),
-                        y: (*event).event_y.into(),
-                    })
-                }
-                xcb::XCB_KEY_PRESS => {
-                    let event = event as *mut xcb::xcb_key_press_event_t;
-                    println!("[INFO]: Key {} pressed", (*event).detail);
-
-                    (*event).detail;
-
-                    None
-                }
-                xcb::XCB_KEY_RELEASE => {
-                    let event = event as *mut xcb::xcb_key_release_event_t;
-                    println!("[INFO]: Key {} released", (*event).detail);
-
-                    None
-                }
-                _ => None,
-            };
-
-            if let Some(callback) = self.event_callback {
-                if let Some(event) = translated_event {
-                    callback(event);
-                }
-            }
+    fn set_icon(&mut self, rgba: &[u8], width: u32, height: u32) -> Result<(), WindowError> {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_icon(rgba, width, height),
+            Window::X11(window) => window.set_icon(rgba, width, height),
+        }
+    }
+
+    fn request_attention(&mut self, attention: UserAttention) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.request_attention(attention),
+            Window::X11(window) => window.request_attention(attention),
+        }
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_cursor_visible(visible),
+            Window::X11(window) => window.set_cursor_visible(visible),
+        }
+    }
+
+    fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_cursor_icon(icon),
+            Window::X11(window) => window.set_cursor_icon(icon),
+        }
+    }
+
+    fn set_custom_cursor(&mut self, cursor: &CustomCursor) -> Result<(), WindowError> {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_custom_cursor(cursor),
+            Window::X11(window) => window.set_custom_cursor(cursor),
+        }
+    }
+
+    fn set_cursor_grab(&mut self, grab: CursorGrab) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_cursor_grab(grab),
+            Window::X11(window) => window.set_cursor_grab(grab),
+        }
+    }
+
+    fn get_clipboard_text(&mut self, selection: Selection) -> Option<String> {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.get_clipboard_text(selection),
+            Window::X11(window) => window.get_clipboard_text(selection),
+        }
+    }
+
+    fn set_clipboard_text(&mut self, selection: Selection, text: &str) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_clipboard_text(selection, text),
+            Window::X11(window) => window.set_clipboard_text(selection, text),
+        }
+    }
+
+    fn set_ime_allowed(&mut self, allowed: bool) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_ime_allowed(allowed),
+            Window::X11(window) => window.set_ime_allowed(allowed),
+        }
+    }
+
+    fn set_ime_position(&mut self, x: i32, y: i32) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.set_ime_position(x, y),
+            Window::X11(window) => window.set_ime_position(x, y),
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.is_open(),
+            Window::X11(window) => window.is_open(),
+        }
+    }
+
+    fn close(&mut self) {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.close(),
+            Window::X11(window) => window.close(),
+        }
+    }
+
+    fn poll_events(&mut self) -> Events<'_> {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.poll_events(),
+            Window::X11(window) => window.poll_events(),
+        }
+    }
+}
+
+impl HasWindowHandle for Window {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.window_handle(),
+            Window::X11(window) => window.window_handle(),
         }
     }
 }
 
-impl Drop for Window {
-    fn drop(&mut self) {
-        unsafe {
-            xcb::xcb_disconnect(self.connection);
+impl HasDisplayHandle for Window {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        match self {
+            #[cfg(feature = "wayland")]
+            Window::Wayland(window) => window.display_handle(),
+            Window::X11(window) => window.display_handle(),
         }
     }
 }
@@ -0,0 +1,795 @@
+use crate::{ffi::xcb, platform::WindowError, Key, Modifiers};
+use std::ffi::CString;
+
+/// The keyboard mapping. It wraps an xkbcommon keymap and state so that XKB
+/// keycodes can be turned into keysyms. On X11 these are those of the core k-
+/// eyboard, on Wayland the compositor sends the keymap of the seat.
+pub(super) struct KeyboardMapping {
+    context: *mut xcb::xkb_context,
+    keymap: *mut xcb::xkb_keymap,
+    state: *mut xcb::xkb_state,
+
+    // Dead keys and compose sequences. Both are null if there is no compose
+    // table for the current locale.
+    compose_table: *mut xcb::xkb_compose_table,
+    compose_state: *mut xcb::xkb_compose_state,
+
+    /// The modifiers of the current state, refreshed whenever it changes.
+    modifiers: Modifiers,
+
+    /// The XKB device id of the core keyboard, or -1 on Wayland.
+    device_id: i32,
+
+    /// The first event code of the XKB extension. Every XKB event is sent wi-
+    /// th this response type and told apart by its `xkbType` field. It is 0
+    /// on Wayland, which has no XKB extension.
+    first_event: u8,
+}
+
+impl KeyboardMapping {
+    pub(super) unsafe fn new(
+        connection: *mut xcb::xcb_connection_t,
+    ) -> Result<KeyboardMapping, WindowError> {
+        let mut first_event = 0;
+        if xcb::xkb_x11_setup_xkb_extension(
+            connection,
+            xcb::XKB_X11_MIN_MAJOR_XKB_VERSION.try_into().unwrap(),
+            xcb::XKB_X11_MIN_MINOR_XKB_VERSION.try_into().unwrap(),
+            xcb::XKB_X11_SETUP_XKB_EXTENSION_NO_FLAGS,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &mut first_event,
+            std::ptr::null_mut(),
+        ) == 0
+        {
+            return Err(WindowError::MissingXkbExtension);
+        }
+
+        let context = xcb::xkb_context_new(xcb::XKB_CONTEXT_NO_FLAGS);
+        if context == std::ptr::null_mut() {
+            return Err(WindowError::Os(
+                "could not create an xkbcommon context".to_owned(),
+            ));
+        }
+
+        let core_keyboard_device = xcb::xkb_x11_get_core_keyboard_device_id(connection);
+        if core_keyboard_device == -1 {
+            xcb::xkb_context_unref(context);
+            return Err(WindowError::Os(
+                "could not find the core keyboard".to_owned(),
+            ));
+        }
+
+        let keymap = xcb::xkb_x11_keymap_new_from_device(
+            context,
+            connection,
+            core_keyboard_device,
+            xcb::XKB_KEYMAP_COMPILE_NO_FLAGS,
+        );
+        if keymap == std::ptr::null_mut() {
+            xcb::xkb_context_unref(context);
+            return Err(WindowError::Os(
+                "could not fetch the keymap of the core keyboard".to_owned(),
+            ));
+        }
+
+        let state = xcb::xkb_x11_state_new_from_device(keymap, connection, core_keyboard_device);
+        if state == std::ptr::null_mut() {
+            xcb::xkb_keymap_unref(keymap);
+            xcb::xkb_context_unref(context);
+            return Err(WindowError::Os(
+                "could not fetch the state of the core keyboard".to_owned(),
+            ));
+        }
+
+        // Ask the server to tell us whenever the keymap or the modifier and l-
+        // ayout state changes, otherwise our copy of the state goes stale.
+        let events = xcb::XCB_XKB_EVENT_TYPE_NEW_KEYBOARD_NOTIFY
+            | xcb::XCB_XKB_EVENT_TYPE_MAP_NOTIFY
+            | xcb::XCB_XKB_EVENT_TYPE_STATE_NOTIFY;
+        let map_parts = xcb::XCB_XKB_MAP_PART_KEY_TYPES
+            | xcb::XCB_XKB_MAP_PART_KEY_SYMS
+            | xcb::XCB_XKB_MAP_PART_MODIFIER_MAP
+            | xcb::XCB_XKB_MAP_PART_EXPLICIT_COMPONENTS
+            | xcb::XCB_XKB_MAP_PART_KEY_ACTIONS
+            | xcb::XCB_XKB_MAP_PART_VIRTUAL_MODS
+            | xcb::XCB_XKB_MAP_PART_VIRTUAL_MOD_MAP;
+
+        xcb::xcb_xkb_select_events(
+            connection,
+            core_keyboard_device.try_into().unwrap(),
+            events.try_into().unwrap(),
+            0,
+            events.try_into().unwrap(),
+            map_parts.try_into().unwrap(),
+            map_parts.try_into().unwrap(),
+            std::ptr::null(),
+        );
+
+        Ok(KeyboardMapping::from_parts(
+            context,
+            keymap,
+            state,
+            core_keyboard_device,
+            first_event,
+        ))
+    }
+
+    /// Creates a keyboard mapping from a keymap in the text format, which is
+    /// what Wayland compositors send.
+    #[cfg(feature = "wayland")]
+    pub(super) unsafe fn from_keymap_text(text: &[u8]) -> Result<KeyboardMapping, WindowError> {
+        let context = xcb::xkb_context_new(xcb::XKB_CONTEXT_NO_FLAGS);
+        if context == std::ptr::null_mut() {
+            return Err(WindowError::Os(
+                "could not create an xkbcommon context".to_owned(),
+            ));
+        }
+
+        let keymap = xcb::xkb_keymap_new_from_buffer(
+            context,
+            text.as_ptr() as *const std::os::raw::c_char,
+            text.len(),
+            xcb::XKB_KEYMAP_FORMAT_TEXT_V1,
+            xcb::XKB_KEYMAP_COMPILE_NO_FLAGS,
+        );
+        if keymap == std::ptr::null_mut() {
+            xcb::xkb_context_unref(context);
+            return Err(WindowError::Os(
+                "could not compile the keymap of the seat".to_owned(),
+            ));
+        }
+
+        let state = xcb::xkb_state_new(keymap);
+        if state == std::ptr::null_mut() {
+            xcb::xkb_keymap_unref(keymap);
+            xcb::xkb_context_unref(context);
+            return Err(WindowError::Os(
+                "could not create a keyboard state".to_owned(),
+            ));
+        }
+
+        Ok(KeyboardMapping::from_parts(context, keymap, state, -1, 0))
+    }
+
+    /// Finishes a keyboard mapping by loading the compose table, which does
+    /// not depend on where the keymap came from.
+    unsafe fn from_parts(
+        context: *mut xcb::xkb_context,
+        keymap: *mut xcb::xkb_keymap,
+        state: *mut xcb::xkb_state,
+        device_id: i32,
+        first_event: u8,
+    ) -> KeyboardMapping {
+        let compose_table = xcb::xkb_compose_table_new_from_locale(
+            context,
+            get_locale().as_ptr(),
+            xcb::XKB_COMPOSE_COMPILE_NO_FLAGS,
+        );
+        let compose_state = if compose_table != std::ptr::null_mut() {
+            xcb::xkb_compose_state_new(compose_table, xcb::XKB_COMPOSE_STATE_NO_FLAGS)
+        } else {
+            std::ptr::null_mut()
+        };
+
+        let mut keyboard_mapping = KeyboardMapping {
+            context,
+            keymap,
+            state,
+            compose_table,
+            compose_state,
+            modifiers: Modifiers::empty(),
+            device_id,
+            first_event,
+        };
+        keyboard_mapping.update_modifiers();
+
+        keyboard_mapping
+    }
+
+    /// Throws away the current keymap and fetches a new one from the server.
+    /// This is needed when the keyboard itself or its layouts change.
+    pub(super) unsafe fn reload_keymap(&mut self, connection: *mut xcb::xcb_connection_t) {
+        let keymap = xcb::xkb_x11_keymap_new_from_device(
+            self.context,
+            connection,
+            self.device_id,
+            xcb::XKB_KEYMAP_COMPILE_NO_FLAGS,
+        );
+        if keymap == std::ptr::null_mut() {
+            return;
+        }
+
+        let state = xcb::xkb_x11_state_new_from_device(keymap, connection, self.device_id);
+        if state == std::ptr::null_mut() {
+            xcb::xkb_keymap_unref(keymap);
+            return;
+        }
+
+        xcb::xkb_state_unref(self.state);
+        xcb::xkb_keymap_unref(self.keymap);
+
+        self.keymap = keymap;
+        self.state = state;
+
+        self.update_modifiers();
+    }
+
+    pub(super) unsafe fn update_keymap(
+        &mut self,
+        depressed_mods: u32,
+        latched_mods: u32,
+        locked_mods: u32,
+        depressed_layout: u32,
+        latched_layout: u32,
+        locked_layout: u32,
+    ) {
+        xcb::xkb_state_update_mask(
+            self.state,
+            depressed_mods,
+            latched_mods,
+            locked_mods,
+            depressed_layout,
+            latched_layout,
+            locked_layout,
+        );
+
+        self.update_modifiers();
+    }
+
+    unsafe fn update_modifiers(&mut self) {
+        let modifiers = [
+            (Modifiers::SHIFT, xcb::XKB_MOD_NAME_SHIFT.as_ptr()),
+            (Modifiers::CONTROL, xcb::XKB_MOD_NAME_CTRL.as_ptr()),
+            (Modifiers::ALT, xcb::XKB_MOD_NAME_ALT.as_ptr()),
+            (Modifiers::SUPER, xcb::XKB_MOD_NAME_LOGO.as_ptr()),
+            (Modifiers::CAPS_LOCK, xcb::XKB_MOD_NAME_CAPS.as_ptr()),
+            (Modifiers::NUM_LOCK, xcb::XKB_MOD_NAME_NUM.as_ptr()),
+        ];
+
+        for (modifier, name) in modifiers {
+            let is_active = xcb::xkb_state_mod_name_is_active(
+                self.state,
+                name as *const std::os::raw::c_char,
+                xcb::XKB_STATE_MODS_EFFECTIVE,
+            ) > 0;
+
+            self.modifiers.set(modifier, is_active);
+        }
+    }
+
+    pub(super) fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Returns if holding the key down should repeat it. Only Wayland needs
+    /// this, since X11 does the repeating itself.
+    #[cfg(feature = "wayland")]
+    pub(super) unsafe fn key_repeats(&self, keycode: xcb::xkb_keycode_t) -> bool {
+        xcb::xkb_keymap_key_repeats(self.keymap, keycode) != 0
+    }
+
+    /// Handles an event coming from the XKB extension. Returns if the event w-
+    /// as one of ours.
+    pub(super) unsafe fn handle_xkb_event(
+        &mut self,
+        connection: *mut xcb::xcb_connection_t,
+        event: *mut xcb::xcb_generic_event_t,
+    ) -> bool {
+        if (*event).response_type & !0x80 != self.first_event {
+            return false;
+        }
+
+        // All XKB events share the same header, so any of them can be used to
+        // peek at the XKB event type.
+        let event = event as *mut xcb::xcb_xkb_state_notify_event_t;
+        if i32::from((*event).deviceID) != self.device_id {
+            return true;
+        }
+
+        match (*event).xkbType as u32 {
+            xcb::XCB_XKB_NEW_KEYBOARD_NOTIFY | xcb::XCB_XKB_MAP_NOTIFY => {
+                self.reload_keymap(connection);
+            }
+            xcb::XCB_XKB_STATE_NOTIFY => {
+                self.update_keymap(
+                    (*event).baseMods.into(),
+                    (*event).latchedMods.into(),
+                    (*event).lockedMods.into(),
+                    (*event).baseGroup as u32,
+                    (*event).latchedGroup as u32,
+                    (*event).lockedGroup.into(),
+                );
+            }
+            _ => (),
+        }
+
+        true
+    }
+
+    /// Returns the keysym of a key at the first shift level of the active la-
+    /// yout, so that `Shift + 1` is still reported as `1` and not `!`. Layouts
+    /// like AZERTY put the digits on the second level instead, and those keys
+    /// are still reported as the digits.
+    pub(super) unsafe fn keycode_to_keysym(
+        &self,
+        keycode: xcb::xkb_keycode_t,
+    ) -> xcb::xkb_keysym_t {
+        let layout = xcb::xkb_state_key_get_layout(self.state, keycode);
+        let keysym_at = |level| {
+            let mut keysyms = std::ptr::null();
+            let count = xcb::xkb_keymap_key_get_syms_by_level(
+                self.keymap,
+                keycode,
+                layout,
+                level,
+                &mut keysyms,
+            );
+            if count == 1 {
+                Some(*keysyms)
+            } else {
+                None
+            }
+        };
+        let is_digit = |keysym| (xcb::XKB_KEY_0..=xcb::XKB_KEY_9).contains(&keysym);
+
+        match keysym_at(0) {
+            Some(keysym) if is_digit(keysym) => keysym,
+            Some(keysym) => keysym_at(1)
+                .filter(|&shifted| is_digit(shifted))
+                .unwrap_or(keysym),
+            None => xcb::xkb_state_key_get_one_sym(self.state, keycode),
+        }
+    }
+
+    /// Returns the layout-dependent and the physical key for an XKB keycode.
+    /// Keys that have no keysym the Nengine knows about fall back to their p-
+    /// hysical key.
+    pub(super) unsafe fn translate_key(&self, keycode: xcb::xkb_keycode_t) -> (Key, Key) {
+        let scancode = translate_scancode(keycode);
+        let keysym = self.keycode_to_keysym(keycode);
+
+        (translate_keysym(keysym).unwrap_or(scancode), scancode)
+    }
+
+    /// Returns the text typed by pressing a key, taking the active modifiers,
+    /// dead keys and compose sequences into account. Returns `None` if the k-
+    /// ey does not produce any text, or is part of an unfinished sequence.
+    pub(super) unsafe fn keycode_to_text(&self, keycode: xcb::xkb_keycode_t) -> Option<String> {
+        if self.compose_state != std::ptr::null_mut() {
+            let keysym = xcb::xkb_state_key_get_one_sym(self.state, keycode);
+
+            if xcb::xkb_compose_state_feed(self.compose_state, keysym)
+                == xcb::XKB_COMPOSE_FEED_ACCEPTED
+            {
+                match xcb::xkb_compose_state_get_status(self.compose_state) {
+                    xcb::XKB_COMPOSE_COMPOSING => return None,
+                    xcb::XKB_COMPOSE_COMPOSED => {
+                        let text = read_utf8(|buffer, size| {
+                            xcb::xkb_compose_state_get_utf8(self.compose_state, buffer, size)
+                        });
+                        xcb::xkb_compose_state_reset(self.compose_state);
+
+                        return text;
+                    }
+                    xcb::XKB_COMPOSE_CANCELLED => {
+                        xcb::xkb_compose_state_reset(self.compose_state);
+
+                        return None;
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        read_utf8(|buffer, size| {
+            xcb::xkb_state_key_get_utf8(self.state, keycode, buffer, size)
+        })
+    }
+}
+
+/// Returns the locale that decides which compose table is loaded, following
+/// the usual `LC_ALL`, `LC_CTYPE`, `LANG` order.
+fn get_locale() -> CString {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|locale| !locale.is_empty())
+        .and_then(|locale| CString::new(locale).ok())
+        .unwrap_or_else(|| CString::new("C").unwrap())
+}
+
+/// Reads a UTF-8 string out of one of the xkbcommon functions that follow the
+/// `snprintf` convention. Control characters are dropped, since they are not
+/// text (think Backspace, Enter or Ctrl + A).
+unsafe fn read_utf8(
+    mut read: impl FnMut(*mut std::os::raw::c_char, usize) -> std::os::raw::c_int,
+) -> Option<String> {
+    let size = read(std::ptr::null_mut(), 0);
+    if size <= 0 {
+        return None;
+    }
+
+    let mut buffer = vec![0u8; size as usize + 1];
+    read(
+        buffer.as_mut_ptr() as *mut std::os::raw::c_char,
+        buffer.len(),
+    );
+    buffer.truncate(size as usize);
+
+    let text: String = String::from_utf8(buffer)
+        .ok()?
+        .chars()
+        .filter(|character| !character.is_control())
+        .collect();
+
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+impl Drop for KeyboardMapping {
+    fn drop(&mut self) {
+        unsafe {
+            xcb::xkb_compose_state_unref(self.compose_state);
+            xcb::xkb_compose_table_unref(self.compose_table);
+            xcb::xkb_state_unref(self.state);
+            xcb::xkb_keymap_unref(self.keymap);
+            xcb::xkb_context_unref(self.context);
+        }
+    }
+}
+
+/// Translates an XKB keysym into a `Key`. Returns `None` for keysyms the Nen-
+/// gine does not know about yet. Symbols only map to the key they are on wi-
+/// thout Shift, since other layouts put e.g. `&` on the 1 key.
+fn translate_keysym(keysym: xcb::xkb_keysym_t) -> Option<Key> {
+    let key = match keysym {
+        xcb::XKB_KEY_Escape => Key::Escape,
+        xcb::XKB_KEY_F1 => Key::F1,
+        xcb::XKB_KEY_F2 => Key::F2,
+        xcb::XKB_KEY_F3 => Key::F3,
+        xcb::XKB_KEY_F4 => Key::F4,
+        xcb::XKB_KEY_F5 => Key::F5,
+        xcb::XKB_KEY_F6 => Key::F6,
+        xcb::XKB_KEY_F7 => Key::F7,
+        xcb::XKB_KEY_F8 => Key::F8,
+        xcb::XKB_KEY_F9 => Key::F9,
+        xcb::XKB_KEY_F10 => Key::F10,
+        xcb::XKB_KEY_F11 => Key::F11,
+        xcb::XKB_KEY_F12 => Key::F12,
+        xcb::XKB_KEY_F13 => Key::F13,
+        xcb::XKB_KEY_F14 => Key::F14,
+        xcb::XKB_KEY_F15 => Key::F15,
+        xcb::XKB_KEY_F16 => Key::F16,
+        xcb::XKB_KEY_F17 => Key::F17,
+        xcb::XKB_KEY_F18 => Key::F18,
+        xcb::XKB_KEY_F19 => Key::F19,
+        xcb::XKB_KEY_F20 => Key::F20,
+        xcb::XKB_KEY_F21 => Key::F21,
+        xcb::XKB_KEY_F22 => Key::F22,
+        xcb::XKB_KEY_F23 => Key::F23,
+        xcb::XKB_KEY_F24 => Key::F24,
+        xcb::XKB_KEY_Print | xcb::XKB_KEY_Sys_Req => Key::PrintScreen,
+        xcb::XKB_KEY_Scroll_Lock => Key::ScrollLock,
+        xcb::XKB_KEY_Pause | xcb::XKB_KEY_Break => Key::Pause,
+        xcb::XKB_KEY_grave => Key::GraveAccent,
+        xcb::XKB_KEY_1 => Key::One,
+        xcb::XKB_KEY_2 => Key::Two,
+        xcb::XKB_KEY_3 => Key::Three,
+        xcb::XKB_KEY_4 => Key::Four,
+        xcb::XKB_KEY_5 => Key::Five,
+        xcb::XKB_KEY_6 => Key::Six,
+        xcb::XKB_KEY_7 => Key::Seven,
+        xcb::XKB_KEY_8 => Key::Eight,
+        xcb::XKB_KEY_9 => Key::Nine,
+        xcb::XKB_KEY_0 => Key::Zero,
+        xcb::XKB_KEY_minus => Key::Hyphen,
+        xcb::XKB_KEY_equal => Key::EqualSign,
+        xcb::XKB_KEY_BackSpace => Key::Backspace,
+        xcb::XKB_KEY_Tab | xcb::XKB_KEY_ISO_Left_Tab => Key::Tab,
+        xcb::XKB_KEY_q | xcb::XKB_KEY_Q => Key::Q,
+        xcb::XKB_KEY_w | xcb::XKB_KEY_W => Key::W,
+        xcb::XKB_KEY_e | xcb::XKB_KEY_E => Key::E,
+        xcb::XKB_KEY_r | xcb::XKB_KEY_R => Key::R,
+        xcb::XKB_KEY_t | xcb::XKB_KEY_T => Key::T,
+        xcb::XKB_KEY_y | xcb::XKB_KEY_Y => Key::Y,
+        xcb::XKB_KEY_u | xcb::XKB_KEY_U => Key::U,
+        xcb::XKB_KEY_i | xcb::XKB_KEY_I => Key::I,
+        xcb::XKB_KEY_o | xcb::XKB_KEY_O => Key::O,
+        xcb::XKB_KEY_p | xcb::XKB_KEY_P => Key::P,
+        xcb::XKB_KEY_bracketleft => Key::LeftSquareBracket,
+        xcb::XKB_KEY_bracketright => Key::RightSquareBracket,
+        xcb::XKB_KEY_backslash => Key::BackSlash,
+        xcb::XKB_KEY_Caps_Lock => Key::CapsLock,
+        xcb::XKB_KEY_a | xcb::XKB_KEY_A => Key::A,
+        xcb::XKB_KEY_s | xcb::XKB_KEY_S => Key::S,
+        xcb::XKB_KEY_d | xcb::XKB_KEY_D => Key::D,
+        xcb::XKB_KEY_f | xcb::XKB_KEY_F => Key::F,
+        xcb::XKB_KEY_g | xcb::XKB_KEY_G => Key::G,
+        xcb::XKB_KEY_h | xcb::XKB_KEY_H => Key::H,
+        xcb::XKB_KEY_j | xcb::XKB_KEY_J => Key::J,
+        xcb::XKB_KEY_k | xcb::XKB_KEY_K => Key::K,
+        xcb::XKB_KEY_l | xcb::XKB_KEY_L => Key::L,
+        xcb::XKB_KEY_semicolon => Key::Semicolon,
+        xcb::XKB_KEY_apostrophe => Key::Quote,
+        xcb::XKB_KEY_Return => Key::Enter,
+        xcb::XKB_KEY_Shift_L => Key::LeftShift,
+        xcb::XKB_KEY_z | xcb::XKB_KEY_Z => Key::Z,
+        xcb::XKB_KEY_x | xcb::XKB_KEY_X => Key::X,
+        xcb::XKB_KEY_c | xcb::XKB_KEY_C => Key::C,
+        xcb::XKB_KEY_v | xcb::XKB_KEY_V => Key::V,
+        xcb::XKB_KEY_b | xcb::XKB_KEY_B => Key::B,
+        xcb::XKB_KEY_n | xcb::XKB_KEY_N => Key::N,
+        xcb::XKB_KEY_m | xcb::XKB_KEY_M => Key::M,
+        xcb::XKB_KEY_comma => Key::Comma,
+        xcb::XKB_KEY_period => Key::Period,
+        xcb::XKB_KEY_slash => Key::ForwardSlash,
+        xcb::XKB_KEY_Shift_R => Key::RightShift,
+        xcb::XKB_KEY_Control_L => Key::LeftControl,
+        xcb::XKB_KEY_Super_L | xcb::XKB_KEY_Meta_L => Key::LeftSuper,
+        xcb::XKB_KEY_Alt_L => Key::LeftAlt,
+        xcb::XKB_KEY_space => Key::Spacebar,
+        xcb::XKB_KEY_Alt_R | xcb::XKB_KEY_ISO_Level3_Shift => Key::RightAlt,
+        xcb::XKB_KEY_Super_R | xcb::XKB_KEY_Meta_R => Key::RightSuper,
+        xcb::XKB_KEY_Menu => Key::Menu,
+        xcb::XKB_KEY_Control_R => Key::RightControl,
+        xcb::XKB_KEY_Insert => Key::Insert,
+        xcb::XKB_KEY_Delete => Key::Delete,
+        xcb::XKB_KEY_Home => Key::Home,
+        xcb::XKB_KEY_End => Key::End,
+        xcb::XKB_KEY_Prior => Key::PageUp,
+        xcb::XKB_KEY_Next => Key::PageDown,
+        xcb::XKB_KEY_Up => Key::UpArrow,
+        xcb::XKB_KEY_Down => Key::DownArrow,
+        xcb::XKB_KEY_Left => Key::LeftArrow,
+        xcb::XKB_KEY_Right => Key::RightArrow,
+        xcb::XKB_KEY_Num_Lock => Key::NumLock,
+        xcb::XKB_KEY_KP_Divide => Key::NumpadDivide,
+        xcb::XKB_KEY_KP_Multiply => Key::NumpadMultiply,
+        xcb::XKB_KEY_KP_Subtract => Key::NumpadSubtract,
+        xcb::XKB_KEY_KP_Add => Key::NumpadAdd,
+        xcb::XKB_KEY_KP_Enter => Key::NumpadEnter,
+        xcb::XKB_KEY_KP_Equal => Key::NumpadEqual,
+        xcb::XKB_KEY_KP_Separator => Key::NumpadComma,
+        // The first level of the keypad keys is their navigation meaning, so
+        // both levels are mapped to the same key here.
+        xcb::XKB_KEY_KP_Delete | xcb::XKB_KEY_KP_Decimal => Key::NumpadDecimal,
+        xcb::XKB_KEY_KP_Insert | xcb::XKB_KEY_KP_0 => Key::NumpadZero,
+        xcb::XKB_KEY_KP_End | xcb::XKB_KEY_KP_1 => Key::NumpadOne,
+        xcb::XKB_KEY_KP_Down | xcb::XKB_KEY_KP_2 => Key::NumpadTwo,
+        xcb::XKB_KEY_KP_Next | xcb::XKB_KEY_KP_3 => Key::NumpadThree,
+        xcb::XKB_KEY_KP_Left | xcb::XKB_KEY_KP_4 => Key::NumpadFour,
+        xcb::XKB_KEY_KP_Begin | xcb::XKB_KEY_KP_5 => Key::NumpadFive,
+        xcb::XKB_KEY_KP_Right | xcb::XKB_KEY_KP_6 => Key::NumpadSix,
+        xcb::XKB_KEY_KP_Home | xcb::XKB_KEY_KP_7 => Key::NumpadSeven,
+        xcb::XKB_KEY_KP_Up | xcb::XKB_KEY_KP_8 => Key::NumpadEight,
+        xcb::XKB_KEY_KP_Prior | xcb::XKB_KEY_KP_9 => Key::NumpadNine,
+        xcb::XKB_KEY_XF86AudioPlay | xcb::XKB_KEY_XF86AudioPause => Key::MediaPlayPause,
+        xcb::XKB_KEY_XF86AudioStop => Key::MediaStop,
+        xcb::XKB_KEY_XF86AudioNext => Key::MediaNextTrack,
+        xcb::XKB_KEY_XF86AudioPrev => Key::MediaPreviousTrack,
+        xcb::XKB_KEY_XF86AudioMute => Key::VolumeMute,
+        xcb::XKB_KEY_XF86AudioLowerVolume => Key::VolumeDown,
+        xcb::XKB_KEY_XF86AudioRaiseVolume => Key::VolumeUp,
+        xcb::XKB_KEY_XF86Back => Key::BrowserBack,
+        xcb::XKB_KEY_XF86Forward => Key::BrowserForward,
+        xcb::XKB_KEY_XF86Reload => Key::BrowserRefresh,
+        xcb::XKB_KEY_XF86Stop => Key::BrowserStop,
+        xcb::XKB_KEY_XF86Search => Key::BrowserSearch,
+        xcb::XKB_KEY_XF86Favorites => Key::BrowserFavorites,
+        xcb::XKB_KEY_XF86HomePage => Key::BrowserHome,
+        xcb::XKB_KEY_XF86Mail => Key::LaunchMail,
+        xcb::XKB_KEY_XF86Calculator => Key::LaunchCalculator,
+        xcb::XKB_KEY_less | xcb::XKB_KEY_greater => Key::IntlBackslash,
+        xcb::XKB_KEY_yen => Key::IntlYen,
+        xcb::XKB_KEY_Hiragana_Katakana => Key::KanaMode,
+        xcb::XKB_KEY_Henkan_Mode => Key::Convert,
+        xcb::XKB_KEY_Muhenkan => Key::NonConvert,
+        xcb::XKB_KEY_Hangul => Key::Lang1,
+        xcb::XKB_KEY_Hangul_Hanja => Key::Lang2,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+/// Translates an XKB keycode into the physical `Key` at that position, igno-
+/// ring the keyboard layout. XKB keycodes are Linux evdev scancodes offset by
+/// 8, which is what this table is written in terms of. Unknown keys carry the
+/// evdev scancode, without the offset.
+fn translate_scancode(keycode: xcb::xkb_keycode_t) -> Key {
+    let scancode = keycode.saturating_sub(8);
+
+    match scancode {
+        1 => Key::Escape,
+        2 => Key::One,
+        3 => Key::Two,
+        4 => Key::Three,
+        5 => Key::Four,
+        6 => Key::Five,
+        7 => Key::Six,
+        8 => Key::Seven,
+        9 => Key::Eight,
+        10 => Key::Nine,
+        11 => Key::Zero,
+        12 => Key::Hyphen,
+        13 => Key::EqualSign,
+        14 => Key::Backspace,
+        15 => Key::Tab,
+        16 => Key::Q,
+        17 => Key::W,
+        18 => Key::E,
+        19 => Key::R,
+        20 => Key::T,
+        21 => Key::Y,
+        22 => Key::U,
+        23 => Key::I,
+        24 => Key::O,
+        25 => Key::P,
+        26 => Key::LeftSquareBracket,
+        27 => Key::RightSquareBracket,
+        28 => Key::Enter,
+        29 => Key::LeftControl,
+        30 => Key::A,
+        31 => Key::S,
+        32 => Key::D,
+        33 => Key::F,
+        34 => Key::G,
+        35 => Key::H,
+        36 => Key::J,
+        37 => Key::K,
+        38 => Key::L,
+        39 => Key::Semicolon,
+        40 => Key::Quote,
+        41 => Key::GraveAccent,
+        42 => Key::LeftShift,
+        43 => Key::BackSlash,
+        44 => Key::Z,
+        45 => Key::X,
+        46 => Key::C,
+        47 => Key::V,
+        48 => Key::B,
+        49 => Key::N,
+        50 => Key::M,
+        51 => Key::Comma,
+        52 => Key::Period,
+        53 => Key::ForwardSlash,
+        54 => Key::RightShift,
+        55 => Key::NumpadMultiply,
+        56 => Key::LeftAlt,
+        57 => Key::Spacebar,
+        58 => Key::CapsLock,
+        59 => Key::F1,
+        60 => Key::F2,
+        61 => Key::F3,
+        62 => Key::F4,
+        63 => Key::F5,
+        64 => Key::F6,
+        65 => Key::F7,
+        66 => Key::F8,
+        67 => Key::F9,
+        68 => Key::F10,
+        69 => Key::NumLock,
+        70 => Key::ScrollLock,
+        71 => Key::NumpadSeven,
+        72 => Key::NumpadEight,
+        73 => Key::NumpadNine,
+        74 => Key::NumpadSubtract,
+        75 => Key::NumpadFour,
+        76 => Key::NumpadFive,
+        77 => Key::NumpadSix,
+        78 => Key::NumpadAdd,
+        79 => Key::NumpadOne,
+        80 => Key::NumpadTwo,
+        81 => Key::NumpadThree,
+        82 => Key::NumpadZero,
+        83 => Key::NumpadDecimal,
+        86 => Key::IntlBackslash,
+        87 => Key::F11,
+        88 => Key::F12,
+        89 => Key::IntlRo,
+        92 => Key::Convert,
+        93 => Key::KanaMode,
+        94 => Key::NonConvert,
+        96 => Key::NumpadEnter,
+        97 => Key::RightControl,
+        98 => Key::NumpadDivide,
+        99 => Key::PrintScreen,
+        100 => Key::RightAlt,
+        102 => Key::Home,
+        103 => Key::UpArrow,
+        104 => Key::PageUp,
+        105 => Key::LeftArrow,
+        106 => Key::RightArrow,
+        107 => Key::End,
+        108 => Key::DownArrow,
+        109 => Key::PageDown,
+        110 => Key::Insert,
+        111 => Key::Delete,
+        113 => Key::VolumeMute,
+        114 => Key::VolumeDown,
+        115 => Key::VolumeUp,
+        117 => Key::NumpadEqual,
+        119 => Key::Pause,
+        121 => Key::NumpadComma,
+        122 => Key::Lang1,
+        123 => Key::Lang2,
+        124 => Key::IntlYen,
+        125 => Key::LeftSuper,
+        126 => Key::RightSuper,
+        127 => Key::Menu,
+        128 => Key::BrowserStop,
+        140 => Key::LaunchCalculator,
+        155 => Key::LaunchMail,
+        156 => Key::BrowserFavorites,
+        158 => Key::BrowserBack,
+        159 => Key::BrowserForward,
+        163 => Key::MediaNextTrack,
+        164 => Key::MediaPlayPause,
+        165 => Key::MediaPreviousTrack,
+        166 => Key::MediaStop,
+        172 => Key::BrowserHome,
+        173 => Key::BrowserRefresh,
+        183 => Key::F13,
+        184 => Key::F14,
+        185 => Key::F15,
+        186 => Key::F16,
+        187 => Key::F17,
+        188 => Key::F18,
+        189 => Key::F19,
+        190 => Key::F20,
+        191 => Key::F21,
+        192 => Key::F22,
+        193 => Key::F23,
+        194 => Key::F24,
+        217 => Key::BrowserSearch,
+        _ => Key::Unknown(scancode),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_keys_carry_the_evdev_scancode() {
+        assert_eq!(translate_scancode(9), Key::Escape);
+        // KEY_PROG1, which the Nengine has no name for.
+        assert_eq!(translate_scancode(148 + 8), Key::Unknown(148));
+    }
+
+    // Needs the keymaps of xkeyboard-config, which every desktop has.
+    #[test]
+    fn french_symbols_do_not_move_digits() {
+        unsafe {
+            let context = xcb::xkb_context_new(xcb::XKB_CONTEXT_NO_FLAGS);
+            let names = xcb::xkb_rule_names {
+                rules: std::ptr::null(),
+                model: std::ptr::null(),
+                layout: b"fr\0".as_ptr() as *const std::os::raw::c_char,
+                variant: std::ptr::null(),
+                options: std::ptr::null(),
+            };
+            let keymap =
+                xcb::xkb_keymap_new_from_names(context, &names, xcb::XKB_KEYMAP_COMPILE_NO_FLAGS);
+            assert_ne!(keymap, std::ptr::null_mut());
+            let state = xcb::xkb_state_new(keymap);
+            let mapping = KeyboardMapping::from_parts(context, keymap, state, -1, 0);
+
+            // `&`, `"`, `'`, `(` and `-` are on the 1, 3, 4, 5 and 6 keys,
+            // with the digits on the second level.
+            assert_eq!(mapping.translate_key(10), (Key::One, Key::One));
+            assert_eq!(mapping.translate_key(12), (Key::Three, Key::Three));
+            assert_eq!(mapping.translate_key(13), (Key::Four, Key::Four));
+            assert_eq!(mapping.translate_key(14), (Key::Five, Key::Five));
+            assert_eq!(mapping.translate_key(15), (Key::Six, Key::Six));
+            // `)` is on the key where US layouts have `-`.
+            assert_eq!(mapping.translate_key(20), (Key::Hyphen, Key::Hyphen));
+            // Letters still follow the layout.
+            assert_eq!(mapping.translate_key(24), (Key::A, Key::Q));
+        }
+    }
+}
@@ -0,0 +1,2400 @@
+use super::keyboard::KeyboardMapping;
+use crate::platform::{
+    run_callbacks, CrossPlatformWindow, CursorGrab, CursorIcon, CustomCursor, EventQueue, Events,
+    FullscreenMode, Monitor, Selection, UserAttention, VideoMode, WindowBuilder, WindowError,
+    WindowEvents, WindowId,
+};
+use crate::{ffi::wayland, Event, MouseButton, ScrollUnit};
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WaylandDisplayHandle,
+    WaylandWindowHandle, WindowHandle,
+};
+use std::cell::{RefCell, RefMut};
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{c_void, CStr, CString};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::raw::c_char;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// The globals the windows need, bound once per connection. The optional ones
+/// are null if the compositor does not offer them.
+#[derive(Clone, Copy)]
+struct Globals {
+    compositor: *mut wayland::wl_compositor,
+    shm: *mut wayland::wl_shm,
+    seat: *mut wayland::wl_seat,
+    wm_base: *mut wayland::xdg_wm_base,
+    viewporter: *mut wayland::wp_viewporter,
+    fractional_scale_manager: *mut wayland::wp_fractional_scale_manager_v1,
+    decoration_manager: *mut wayland::zxdg_decoration_manager_v1,
+    relative_pointer_manager: *mut wayland::zwp_relative_pointer_manager_v1,
+    pointer_constraints: *mut wayland::zwp_pointer_constraints_v1,
+    text_input_manager: *mut wayland::zwp_text_input_manager_v3,
+}
+
+/// The listeners of every kind of object. libwayland only keeps a pointer to
+/// them, so they are boxed and live as long as the connection.
+struct Listeners {
+    registry: wayland::wl_registry_listener,
+    seat: wayland::wl_seat_listener,
+    pointer: wayland::wl_pointer_listener,
+    keyboard: wayland::wl_keyboard_listener,
+    wm_base: wayland::xdg_wm_base_listener,
+    xdg_surface: wayland::xdg_surface_listener,
+    xdg_toplevel: wayland::xdg_toplevel_listener,
+    fractional_scale: wayland::wp_fractional_scale_v1_listener,
+    relative_pointer: wayland::zwp_relative_pointer_v1_listener,
+    output: wayland::wl_output_listener,
+    surface: wayland::wl_surface_listener,
+    text_input: wayland::zwp_text_input_v3_listener,
+}
+
+/// The part of a window that the listeners update.
+struct Surface {
+    surface: *mut wayland::wl_surface,
+    xdg_surface: *mut wayland::xdg_surface,
+    xdg_toplevel: *mut wayland::xdg_toplevel,
+    viewport: *mut wayland::wp_viewport,
+    fractional_scale: *mut wayland::wp_fractional_scale_v1,
+    /// The outputs the window is on, in the order it entered them.
+    outputs: Vec<*mut wayland::wl_output>,
+    decoration: *mut wayland::zxdg_toplevel_decoration_v1,
+
+    /// The size in surface coordinates, which are logical pixels.
+    size: (u32, u32),
+    /// The size from the last toplevel configure. It only takes effect once
+    /// the surface configure that ends the sequence arrives. 0 means that the
+    /// window picks its own size.
+    pending_size: (u32, u32),
+    /// How many pixels there are per logical pixel.
+    scale: f64,
+
+    /// The buffer that is shown until something draws into the surface. Com-
+    /// positors do not show a surface without one.
+    buffer: *mut wayland::wl_buffer,
+    is_transparent: bool,
+    is_configured: bool,
+    is_cursor_visible: bool,
+    cursor_icon: CursorIcon,
+    /// The last custom cursor image, which is shown instead of the icon. Its
+    /// buffer belongs to the window.
+    custom_cursor: Option<CursorImage>,
+
+    cursor_grab: CursorGrab,
+    /// The constraint that holds the cursor grab, depending on its kind. Both
+    /// are null if there is none.
+    locked_pointer: *mut wayland::zwp_locked_pointer_v1,
+    confined_pointer: *mut wayland::zwp_confined_pointer_v1,
+
+    is_ime_allowed: bool,
+    /// Where the candidate window of the input method goes, in pixels.
+    ime_position: (i32, i32),
+}
+
+/// A monitor, which Wayland calls an output. What is known about it is upda-
+/// ted as the compositor sends it.
+struct Output {
+    /// The name of its global, which is how the compositor says it is gone.
+    name: u32,
+    output: *mut wayland::wl_output,
+    monitor: Monitor,
+}
+
+/// A buffer with a cursor in it, either from the cursor theme or a custom one.
+#[derive(Clone, Copy)]
+struct CursorImage {
+    buffer: *mut wayland::wl_buffer,
+    size: (i32, i32),
+    hotspot: (i32, i32),
+}
+
+/// A key that is held down. Wayland leaves repeating keys to the client.
+#[derive(Clone, Copy)]
+struct KeyRepeat {
+    keycode: u32,
+    next: Instant,
+}
+
+/// The connection to the compositor and everything that belongs to it rather
+/// than to a single window. It is shared by every window of an event loop.
+struct Connection {
+    display: *mut wayland::wl_display,
+    registry: *mut wayland::wl_registry,
+    globals: Globals,
+    listeners: Box<Listeners>,
+
+    pointer: *mut wayland::wl_pointer,
+    keyboard: *mut wayland::wl_keyboard,
+    /// Where `MouseDelta` comes from. It is null if the compositor does not
+    /// have the relative pointer protocol.
+    relative_pointer: *mut wayland::zwp_relative_pointer_v1,
+
+    /// The cursor theme, loaded the first time the pointer enters a window.
+    cursor_theme: *mut wayland::wl_cursor_theme,
+    /// The cursors of the theme that were looked up so far. Their buffers be-
+    /// long to the theme.
+    cursor_images: HashMap<CursorIcon, CursorImage>,
+    cursor_surface: *mut wayland::wl_surface,
+
+    /// None until the compositor sends a keymap.
+    keyboard_mapping: Option<KeyboardMapping>,
+    /// Repeated keys per second, 0 turns repeating off.
+    repeat_rate: i32,
+    repeat_delay: Duration,
+    key_repeat: Option<KeyRepeat>,
+
+    pointer_focus: Option<WindowId>,
+    /// The serial of the last pointer enter, which setting the cursor needs.
+    pointer_serial: u32,
+    /// The scroll of the current pointer frame, in logical pixels. A frame
+    /// can scroll on both axes and says what did the scrolling first, so the
+    /// scroll is only sent once the frame is done.
+    scroll: (f64, f64),
+    /// The notches of the current frame, if it came from a wheel.
+    scroll_notches: Option<(f64, f64)>,
+    keyboard_focus: Option<WindowId>,
+
+    /// Where input methods send their text. It is null if the compositor does
+    /// not have the text input protocol, then there is no `ImePreedit` or
+    /// `ImeCommit`.
+    text_input: *mut wayland::zwp_text_input_v3,
+    /// The window the text input is in, which the compositor says separately
+    /// from the keyboard focus.
+    text_input_focus: Option<WindowId>,
+    is_text_input_enabled: bool,
+    /// The text of the input method that takes effect with the next `done`.
+    pending_preedit: Option<(String, Option<usize>)>,
+    pending_commit: Option<String>,
+    /// Whether there is a preedit, so that an empty one can end it.
+    has_preedit: bool,
+
+    outputs: Vec<Output>,
+    surfaces: HashMap<WindowId, Surface>,
+    events: EventQueue,
+}
+
+/// The Wayland event loop.
+pub struct EventLoop {
+    connection: Rc<RefCell<Connection>>,
+    events: VecDeque<(WindowId, Event)>,
+}
+
+/// The Wayland window. Wayland does not let windows place themselves, so the
+/// position of the builder and `set_position` are ignored, and `Moved` is n-
+/// ever sent.
+pub struct Window {
+    shared: Rc<RefCell<Connection>>,
+    id: WindowId,
+
+    // Copies of what the shared connection holds for this window. They are
+    // only valid while the window is open.
+    surface: *mut wayland::wl_surface,
+    xdg_toplevel: *mut wayland::xdg_toplevel,
+
+    is_open: bool,
+    is_shown: bool,
+    is_resizable: bool,
+    is_minimized: bool,
+    min_size: Option<(u32, u32)>,
+    max_size: Option<(u32, u32)>,
+
+    /// The events of this window that were taken from the shared queue.
+    events: VecDeque<Event>,
+}
+
+/// Gets the connection back out of the data pointer the listeners are given.
+unsafe fn get_connection<'a>(data: *mut c_void) -> RefMut<'a, Connection> {
+    (*(data as *const RefCell<Connection>)).borrow_mut()
+}
+
+unsafe fn add_listener<T, L>(proxy: *mut T, listener: &L, data: *mut c_void) {
+    wayland::wl_proxy_add_listener(
+        proxy as *mut wayland::wl_proxy,
+        listener as *const L as *mut Option<unsafe extern "C" fn()>,
+        data,
+    );
+}
+
+fn to_physical(size: (u32, u32), scale: f64) -> (u32, u32) {
+    (
+        (f64::from(size.0) * scale).round() as u32,
+        (f64::from(size.1) * scale).round() as u32,
+    )
+}
+
+fn to_logical(size: (u32, u32), scale: f64) -> (u32, u32) {
+    (
+        (f64::from(size.0) / scale).round() as u32,
+        (f64::from(size.1) / scale).round() as u32,
+    )
+}
+
+/// Translates the evdev button codes Wayland sends, following what X11 does
+/// with the same buttons. Others, e.g. `BTN_TASK`, have nothing to translate
+/// to.
+fn translate_wayland_buttons(button: u32) -> Option<MouseButton> {
+    const BTN_LEFT: u32 = 0x110;
+    const BTN_RIGHT: u32 = 0x111;
+    const BTN_MIDDLE: u32 = 0x112;
+    const BTN_SIDE: u32 = 0x113;
+    const BTN_EXTRA: u32 = 0x114;
+    const BTN_FORWARD: u32 = 0x115;
+
+    match button {
+        BTN_LEFT => Some(MouseButton::Left),
+        BTN_RIGHT => Some(MouseButton::Right),
+        BTN_MIDDLE => Some(MouseButton::Middle),
+        BTN_SIDE => Some(MouseButton::Eight),
+        BTN_EXTRA => Some(MouseButton::Nine),
+        BTN_FORWARD => Some(MouseButton::Ten),
+        _ => None,
+    }
+}
+
+/// Creates a buffer that is all black, or all transparent if the window is.
+unsafe fn create_placeholder_buffer(
+    shm: *mut wayland::wl_shm,
+    id: WindowId,
+    size: (u32, u32),
+    is_transparent: bool,
+) -> *mut wayland::wl_buffer {
+    let format = if is_transparent {
+        wayland::WL_SHM_FORMAT_ARGB8888
+    } else {
+        wayland::WL_SHM_FORMAT_XRGB8888
+    };
+    let name = format!("nengine-{}-{}", std::process::id(), id.0);
+
+    create_shm_buffer(shm, &name, size, format, &[])
+}
+
+/// Creates a buffer with 4 bytes per pixel, filled with `pixels` or with ze-
+/// ros if there are none. The memory is a file in `XDG_RUNTIME_DIR` that is
+/// unlinked right away, so no shared memory functions are needed. Returns
+/// null if that fails.
+unsafe fn create_shm_buffer(
+    shm: *mut wayland::wl_shm,
+    name: &str,
+    (width, height): (u32, u32),
+    format: u32,
+    pixels: &[u8],
+) -> *mut wayland::wl_buffer {
+    let runtime_dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) => runtime_dir,
+        None => return std::ptr::null_mut(),
+    };
+    if width == 0 || height == 0 {
+        return std::ptr::null_mut();
+    }
+
+    let path = std::path::Path::new(&runtime_dir).join(name);
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path);
+    let _ = std::fs::remove_file(&path);
+
+    // A file that is only extended reads back as zeros, which is black, or
+    // transparent if there is an alpha channel.
+    let stride = width * 4;
+    let size = stride * height;
+    let mut file = match file {
+        Ok(file) if file.set_len(size.into()).is_ok() => file,
+        _ => return std::ptr::null_mut(),
+    };
+    if file.write_all(pixels).is_err() {
+        return std::ptr::null_mut();
+    }
+
+    // The file descriptor is duplicated when the request is sent, so the file
+    // can be closed right after.
+    let pool = wayland::wl_shm_create_pool(shm, file.as_raw_fd(), size as i32);
+    let buffer = wayland::wl_shm_pool_create_buffer(
+        pool,
+        0,
+        width as i32,
+        height as i32,
+        stride as i32,
+        format,
+    );
+    wayland::wl_shm_pool_destroy(pool);
+
+    buffer
+}
+
+impl Listeners {
+    unsafe fn new() -> Listeners {
+        // Newer versions of the headers add events at the end of some of the
+        // listeners. Those are never sent at the versions that are bound, so
+        // they are left null.
+        Listeners {
+            registry: wayland::wl_registry_listener {
+                global: Some(handle_global),
+                global_remove: Some(handle_global_remove),
+            },
+            seat: wayland::wl_seat_listener {
+                capabilities: Some(handle_seat_capabilities),
+                name: Some(handle_seat_name),
+            },
+            pointer: wayland::wl_pointer_listener {
+                enter: Some(handle_pointer_enter),
+                leave: Some(handle_pointer_leave),
+                motion: Some(handle_pointer_motion),
+                button: Some(handle_pointer_button),
+                axis: Some(handle_pointer_axis),
+                frame: Some(handle_pointer_frame),
+                axis_source: Some(handle_pointer_axis_source),
+                axis_stop: Some(handle_pointer_axis_stop),
+                axis_discrete: Some(handle_pointer_axis_discrete),
+                axis_value120: Some(handle_pointer_axis_value120),
+                ..std::mem::zeroed()
+            },
+            keyboard: wayland::wl_keyboard_listener {
+                keymap: Some(handle_keymap),
+                enter: Some(handle_keyboard_enter),
+                leave: Some(handle_keyboard_leave),
+                key: Some(handle_key),
+                modifiers: Some(handle_modifiers),
+                repeat_info: Some(handle_repeat_info),
+            },
+            wm_base: wayland::xdg_wm_base_listener {
+                ping: Some(handle_ping),
+            },
+            xdg_surface: wayland::xdg_surface_listener {
+                configure: Some(handle_surface_configure),
+            },
+            xdg_toplevel: wayland::xdg_toplevel_listener {
+                configure: Some(handle_toplevel_configure),
+                close: Some(handle_toplevel_close),
+                ..std::mem::zeroed()
+            },
+            fractional_scale: wayland::wp_fractional_scale_v1_listener {
+                preferred_scale: Some(handle_preferred_scale),
+            },
+            relative_pointer: wayland::zwp_relative_pointer_v1_listener {
+                relative_motion: Some(handle_relative_motion),
+            },
+            output: wayland::wl_output_listener {
+                geometry: Some(handle_output_geometry),
+                mode: Some(handle_output_mode),
+                done: Some(handle_output_done),
+                scale: Some(handle_output_scale),
+                name: Some(handle_output_name),
+                description: Some(handle_output_description),
+            },
+            surface: wayland::wl_surface_listener {
+                enter: Some(handle_surface_enter),
+                leave: Some(handle_surface_leave),
+                ..std::mem::zeroed()
+            },
+            text_input: wayland::zwp_text_input_v3_listener {
+                enter: Some(handle_text_input_enter),
+                leave: Some(handle_text_input_leave),
+                preedit_string: Some(handle_preedit_string),
+                commit_string: Some(handle_commit_string),
+                delete_surrounding_text: Some(handle_delete_surrounding_text),
+                done: Some(handle_text_input_done),
+            },
+        }
+    }
+}
+
+impl Connection {
+    fn open() -> Result<Rc<RefCell<Connection>>, WindowError> {
+        if std::env::var_os("WAYLAND_DISPLAY").is_none()
+            && std::env::var_os("WAYLAND_SOCKET").is_none()
+        {
+            return Err(WindowError::NoDisplay);
+        }
+
+        unsafe {
+            let display = wayland::wl_display_connect(std::ptr::null());
+            if display == std::ptr::null_mut() {
+                return Err(WindowError::Os(
+                    "could not connect to the Wayland compositor".to_owned(),
+                ));
+            }
+
+            // From here on, dropping the connection cleans up after it.
+            let connection = Rc::new(RefCell::new(Connection {
+                display,
+                registry: std::ptr::null_mut(),
+                globals: Globals {
+                    compositor: std::ptr::null_mut(),
+                    shm: std::ptr::null_mut(),
+                    seat: std::ptr::null_mut(),
+                    wm_base: std::ptr::null_mut(),
+                    viewporter: std::ptr::null_mut(),
+                    fractional_scale_manager: std::ptr::null_mut(),
+                    decoration_manager: std::ptr::null_mut(),
+                    relative_pointer_manager: std::ptr::null_mut(),
+                    pointer_constraints: std::ptr::null_mut(),
+                    text_input_manager: std::ptr::null_mut(),
+                },
+                listeners: Box::new(Listeners::new()),
+                pointer: std::ptr::null_mut(),
+                keyboard: std::ptr::null_mut(),
+                relative_pointer: std::ptr::null_mut(),
+                cursor_theme: std::ptr::null_mut(),
+                cursor_images: HashMap::new(),
+                cursor_surface: std::ptr::null_mut(),
+                keyboard_mapping: None,
+                repeat_rate: 25,
+                repeat_delay: Duration::from_millis(600),
+                key_repeat: None,
+                pointer_focus: None,
+                pointer_serial: 0,
+                scroll: (0.0, 0.0),
+                scroll_notches: None,
+                keyboard_focus: None,
+                text_input: std::ptr::null_mut(),
+                text_input_focus: None,
+                is_text_input_enabled: false,
+                pending_preedit: None,
+                pending_commit: None,
+                has_preedit: false,
+                outputs: Vec::new(),
+                surfaces: HashMap::new(),
+                events: EventQueue::new(),
+            }));
+
+            let data = Rc::as_ptr(&connection) as *mut c_void;
+            {
+                let mut connection = connection.borrow_mut();
+                connection.registry = wayland::wl_display_get_registry(display);
+                add_listener(connection.registry, &connection.listeners.registry, data);
+            }
+
+            // The first roundtrip brings in the globals, the second one the
+            // events of the objects bound during the first, like the seat
+            // capabilities.
+            for _ in 0..2 {
+                if wayland::wl_display_roundtrip(display) == -1 {
+                    return Err(WindowError::Os(
+                        "the connection to the Wayland compositor broke".to_owned(),
+                    ));
+                }
+            }
+
+            let globals = connection.borrow().globals;
+            if globals.compositor == std::ptr::null_mut() {
+                return Err(WindowError::MissingProtocol("wl_compositor"));
+            }
+            if globals.shm == std::ptr::null_mut() {
+                return Err(WindowError::MissingProtocol("wl_shm"));
+            }
+            if globals.wm_base == std::ptr::null_mut() {
+                return Err(WindowError::MissingProtocol("xdg_wm_base"));
+            }
+
+            if globals.text_input_manager != std::ptr::null_mut()
+                && globals.seat != std::ptr::null_mut()
+            {
+                let mut connection = connection.borrow_mut();
+                connection.text_input = wayland::zwp_text_input_manager_v3_get_text_input(
+                    globals.text_input_manager,
+                    globals.seat,
+                );
+                add_listener(
+                    connection.text_input,
+                    &connection.listeners.text_input,
+                    data,
+                );
+            }
+
+            Ok(connection)
+        }
+    }
+
+    fn find_window(&self, matches: impl Fn(&Surface) -> bool) -> Option<WindowId> {
+        self.surfaces
+            .iter()
+            .find(|(_, surface)| matches(surface))
+            .map(|(id, _)| *id)
+    }
+
+    /// Shows a black buffer at the current size and scale of the window. It
+    /// is what the compositor shows until something else draws into the su-
+    /// rface.
+    unsafe fn draw_placeholder(&mut self, id: WindowId) {
+        let shm = self.globals.shm;
+        let surface = match self.surfaces.get_mut(&id) {
+            Some(surface) => surface,
+            None => return,
+        };
+
+        // Without a viewport the buffer cannot be scaled, so the scale is al-
+        // ways 1 then.
+        let buffer_size = to_physical(surface.size, surface.scale);
+        let buffer = create_placeholder_buffer(shm, id, buffer_size, surface.is_transparent);
+        if buffer == std::ptr::null_mut() {
+            return;
+        }
+
+        wayland::wl_surface_attach(surface.surface, buffer, 0, 0);
+        wayland::wl_surface_damage(surface.surface, 0, 0, i32::MAX, i32::MAX);
+        if surface.viewport != std::ptr::null_mut() {
+            wayland::wp_viewport_set_destination(
+                surface.viewport,
+                surface.size.0 as i32,
+                surface.size.1 as i32,
+            );
+        }
+        wayland::wl_surface_commit(surface.surface);
+
+        if surface.buffer != std::ptr::null_mut() {
+            wayland::wl_buffer_destroy(surface.buffer);
+        }
+        surface.buffer = buffer;
+    }
+
+    fn monitors(&self) -> Vec<Monitor> {
+        self.outputs
+            .iter()
+            .map(|output| output.monitor.clone())
+            .collect()
+    }
+
+    unsafe fn push_resized(&mut self, id: WindowId) {
+        if let Some(surface) = self.surfaces.get(&id) {
+            let (width, height) = to_physical(surface.size, surface.scale);
+            self.events.push(id, Event::Resized { width, height });
+        }
+    }
+
+    fn push_mouse_move(&mut self, id: WindowId, x: wayland::wl_fixed_t, y: wayland::wl_fixed_t) {
+        // Events are in logical pixels, everything else in the Nengine is in
+        // actual pixels.
+        let scale = self.surfaces.get(&id).map_or(1.0, |surface| surface.scale);
+
+        self.events.push(
+            id,
+            Event::MouseMove {
+                x: wayland::wl_fixed_to_double(x) * scale,
+                y: wayland::wl_fixed_to_double(y) * scale,
+            },
+        );
+    }
+
+    /// Adds the notches a wheel turned to the current frame, flipped the same
+    /// way as the axis values.
+    fn add_scroll_notches(&mut self, axis: u32, notches: f64) {
+        let scroll = self.scroll_notches.get_or_insert((0.0, 0.0));
+        if axis == wayland::WL_POINTER_AXIS_VERTICAL_SCROLL {
+            scroll.1 -= notches;
+        } else {
+            scroll.0 += notches;
+        }
+    }
+
+    /// Sends the scroll of the pointer frame that just ended. Wheels are sent
+    /// in lines by the notches they turned, anything else in pixels.
+    fn push_scroll(&mut self) {
+        let (x, y) = std::mem::take(&mut self.scroll);
+        let notches = self.scroll_notches.take();
+        let id = match self.pointer_focus {
+            Some(id) => id,
+            None => return,
+        };
+
+        let (x, y, unit) = match notches {
+            Some((x, y)) => (x, y, ScrollUnit::Lines),
+            None => {
+                let scale = self.surfaces.get(&id).map_or(1.0, |surface| surface.scale);
+                (x * scale, y * scale, ScrollUnit::Pixels)
+            }
+        };
+
+        if x != 0.0 || y != 0.0 {
+            self.events.push(id, Event::MouseScroll { x, y, unit });
+        }
+    }
+
+    unsafe fn push_key(&mut self, id: WindowId, keycode: u32, is_press: bool, is_repeat: bool) {
+        let keyboard_mapping = match &self.keyboard_mapping {
+            Some(keyboard_mapping) => keyboard_mapping,
+            None => return,
+        };
+
+        let (key, scancode) = keyboard_mapping.translate_key(keycode);
+        self.events.push(
+            id,
+            Event::Key {
+                keycode: key,
+                scancode,
+                is_press,
+                is_repeat,
+                modifiers: keyboard_mapping.modifiers(),
+            },
+        );
+
+        // The text is sent after the key event itself.
+        if is_press {
+            if let Some(text) = keyboard_mapping.keycode_to_text(keycode) {
+                self.events.push(id, Event::TextInput { text });
+            }
+        }
+    }
+
+    /// Sends the repeats of the held key that are due.
+    unsafe fn repeat_keys(&mut self) {
+        let id = match self.keyboard_focus {
+            Some(id) => id,
+            None => return,
+        };
+
+        let now = Instant::now();
+        while let Some(key_repeat) = self.key_repeat {
+            if self.repeat_rate <= 0 || key_repeat.next > now {
+                break;
+            }
+
+            self.push_key(id, key_repeat.keycode, true, true);
+            self.key_repeat = Some(KeyRepeat {
+                keycode: key_repeat.keycode,
+                next: key_repeat.next + Duration::from_secs(1) / self.repeat_rate as u32,
+            });
+        }
+    }
+
+    /// Replaces the pointer constraint of a window with one that matches its
+    /// cursor grab. The compositor only applies it while the window has focus
+    /// and, since it is persistent, applies it again whenever focus returns.
+    unsafe fn apply_cursor_grab(&mut self, id: WindowId) {
+        let pointer = self.pointer;
+        let pointer_constraints = self.globals.pointer_constraints;
+        let surface = match self.surfaces.get_mut(&id) {
+            Some(surface) => surface,
+            None => return,
+        };
+
+        if surface.locked_pointer != std::ptr::null_mut() {
+            wayland::zwp_locked_pointer_v1_destroy(surface.locked_pointer);
+            surface.locked_pointer = std::ptr::null_mut();
+        }
+        if surface.confined_pointer != std::ptr::null_mut() {
+            wayland::zwp_confined_pointer_v1_destroy(surface.confined_pointer);
+            surface.confined_pointer = std::ptr::null_mut();
+        }
+
+        if pointer == std::ptr::null_mut() || pointer_constraints == std::ptr::null_mut() {
+            return;
+        }
+
+        let lifetime = wayland::ZWP_POINTER_CONSTRAINTS_V1_LIFETIME_PERSISTENT;
+        match surface.cursor_grab {
+            CursorGrab::None => (),
+            CursorGrab::Confined => {
+                surface.confined_pointer = wayland::zwp_pointer_constraints_v1_confine_pointer(
+                    pointer_constraints,
+                    surface.surface,
+                    pointer,
+                    lifetime,
+                );
+            }
+            CursorGrab::Locked => {
+                surface.locked_pointer = wayland::zwp_pointer_constraints_v1_lock_pointer(
+                    pointer_constraints,
+                    surface.surface,
+                    pointer,
+                    lifetime,
+                );
+            }
+        }
+    }
+
+    /// Turns the text input on or off to match what the window it is in asked
+    /// for, and tells the input method where the candidate window goes.
+    unsafe fn update_text_input(&mut self) {
+        let text_input = self.text_input;
+        let surface = match self.text_input_focus.and_then(|id| self.surfaces.get(&id)) {
+            Some(surface) => surface,
+            None => return,
+        };
+        if text_input == std::ptr::null_mut() {
+            return;
+        }
+
+        // Enabling the text input again would throw away what the input met-
+        // hod is composing.
+        if surface.is_ime_allowed {
+            if !self.is_text_input_enabled {
+                wayland::zwp_text_input_v3_enable(text_input);
+            }
+
+            // The rectangle is in surface coordinates, which are logical pix-
+            // els.
+            let (x, y) = surface.ime_position;
+            wayland::zwp_text_input_v3_set_cursor_rectangle(
+                text_input,
+                (f64::from(x) / surface.scale).round() as i32,
+                (f64::from(y) / surface.scale).round() as i32,
+                0,
+                0,
+            );
+        } else if self.is_text_input_enabled {
+            wayland::zwp_text_input_v3_disable(text_input);
+        }
+        wayland::zwp_text_input_v3_commit(text_input);
+        self.is_text_input_enabled = surface.is_ime_allowed;
+    }
+
+    /// Ends the preedit of the window the text input is in, if there is one.
+    fn end_preedit(&mut self) {
+        if !std::mem::take(&mut self.has_preedit) {
+            return;
+        }
+
+        if let Some(id) = self.text_input_focus {
+            self.events.push(
+                id,
+                Event::ImePreedit {
+                    text: String::new(),
+                    cursor: None,
+                },
+            );
+        }
+    }
+
+    /// Sets the cursor of the window the pointer is over, which Wayland wants
+    /// every time the pointer enters a window.
+    unsafe fn update_cursor(&mut self) {
+        let id = match self.pointer_focus {
+            Some(id) => id,
+            None => return,
+        };
+        let image = match self.surfaces.get(&id) {
+            Some(surface) if !surface.is_cursor_visible => None,
+            Some(surface) if surface.custom_cursor.is_some() => surface.custom_cursor,
+            Some(surface) => self.load_cursor(surface.cursor_icon),
+            None => self.load_cursor(CursorIcon::Default),
+        };
+
+        let image = match image {
+            Some(image) => image,
+            None => {
+                // A null surface hides the cursor.
+                wayland::wl_pointer_set_cursor(
+                    self.pointer,
+                    self.pointer_serial,
+                    std::ptr::null_mut(),
+                    0,
+                    0,
+                );
+                return;
+            }
+        };
+
+        if self.cursor_surface == std::ptr::null_mut() {
+            self.cursor_surface = wayland::wl_compositor_create_surface(self.globals.compositor);
+        }
+        wayland::wl_surface_attach(self.cursor_surface, image.buffer, 0, 0);
+        wayland::wl_surface_damage(self.cursor_surface, 0, 0, image.size.0, image.size.1);
+        wayland::wl_surface_commit(self.cursor_surface);
+
+        wayland::wl_pointer_set_cursor(
+            self.pointer,
+            self.pointer_serial,
+            self.cursor_surface,
+            image.hotspot.0,
+            image.hotspot.1,
+        );
+    }
+
+    /// Looks up a cursor in the cursor theme. The theme and its size come from
+    /// `XCURSOR_THEME` and `XCURSOR_SIZE`, like everywhere else. Returns None
+    /// if there is no cursor to show.
+    unsafe fn load_cursor(&mut self, icon: CursorIcon) -> Option<CursorImage> {
+        if let Some(&image) = self.cursor_images.get(&icon) {
+            return Some(image);
+        }
+
+        if self.cursor_theme == std::ptr::null_mut() {
+            let theme = std::env::var("XCURSOR_THEME")
+                .ok()
+                .and_then(|theme| CString::new(theme).ok());
+            let size = std::env::var("XCURSOR_SIZE")
+                .ok()
+                .and_then(|size| size.parse().ok())
+                .unwrap_or(24);
+
+            self.cursor_theme = wayland::wl_cursor_theme_load(
+                theme
+                    .as_ref()
+                    .map_or(std::ptr::null(), |theme| theme.as_ptr()),
+                size,
+                self.globals.shm,
+            );
+            if self.cursor_theme == std::ptr::null_mut() {
+                return None;
+            }
+        }
+
+        // Newer themes name their cursors like CSS does, older ones only have
+        // the names of the X cursor font.
+        let names: &[&[u8]] = match icon {
+            CursorIcon::Default => &[b"default\0", b"left_ptr\0"],
+            CursorIcon::Text => &[b"text\0", b"xterm\0"],
+            CursorIcon::Pointer => &[b"pointer\0", b"hand2\0"],
+            CursorIcon::Crosshair => &[b"crosshair\0", b"cross\0"],
+            CursorIcon::Wait => &[b"wait\0", b"watch\0"],
+            CursorIcon::NotAllowed => &[b"not-allowed\0", b"crossed_circle\0"],
+            CursorIcon::Move => &[b"move\0", b"fleur\0"],
+            CursorIcon::EwResize => &[b"ew-resize\0", b"sb_h_double_arrow\0"],
+            CursorIcon::NsResize => &[b"ns-resize\0", b"sb_v_double_arrow\0"],
+            CursorIcon::NwseResize => &[b"nwse-resize\0", b"bottom_right_corner\0"],
+            CursorIcon::NeswResize => &[b"nesw-resize\0", b"bottom_left_corner\0"],
+        };
+        let cursor = names
+            .iter()
+            .map(|name| {
+                wayland::wl_cursor_theme_get_cursor(
+                    self.cursor_theme,
+                    name.as_ptr() as *const c_char,
+                )
+            })
+            .find(|&cursor| cursor != std::ptr::null_mut() && (*cursor).image_count > 0)?;
+
+        // Animated cursors only show their first frame.
+        let image = *(*cursor).images;
+        let buffer = wayland::wl_cursor_image_get_buffer(image);
+        if buffer == std::ptr::null_mut() {
+            return None;
+        }
+
+        let image = CursorImage {
+            buffer,
+            size: ((*image).width as i32, (*image).height as i32),
+            hotspot: ((*image).hotspot_x as i32, (*image).hotspot_y as i32),
+        };
+        self.cursor_images.insert(icon, image);
+
+        Some(image)
+    }
+
+    /// Dispatches every event that is waiting on the connection. No borrow of
+    /// the connection may be held, since the listeners take their own.
+    unsafe fn pump(shared: &RefCell<Connection>) {
+        let display = shared.borrow().display;
+
+        // Reading fails until the events that were already read, e.g. by a
+        // roundtrip, are dispatched.
+        while wayland::wl_display_prepare_read(display) != 0 {
+            wayland::wl_display_dispatch_pending(display);
+        }
+        wayland::wl_display_flush(display);
+
+        let mut poll_fd = wayland::pollfd {
+            fd: wayland::wl_display_get_fd(display),
+            events: wayland::POLLIN as i16,
+            revents: 0,
+        };
+        if wayland::poll(&mut poll_fd, 1, 0) > 0 {
+            wayland::wl_display_read_events(display);
+        } else {
+            wayland::wl_display_cancel_read(display);
+        }
+        wayland::wl_display_dispatch_pending(display);
+
+        shared.borrow_mut().repeat_keys();
+        wayland::wl_display_flush(display);
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe {
+            if self.relative_pointer != std::ptr::null_mut() {
+                wayland::zwp_relative_pointer_v1_destroy(self.relative_pointer);
+            }
+            if self.text_input != std::ptr::null_mut() {
+                wayland::zwp_text_input_v3_destroy(self.text_input);
+            }
+            if self.pointer != std::ptr::null_mut() {
+                wayland::wl_pointer_release(self.pointer);
+            }
+            if self.keyboard != std::ptr::null_mut() {
+                wayland::wl_keyboard_release(self.keyboard);
+            }
+            if self.cursor_surface != std::ptr::null_mut() {
+                wayland::wl_surface_destroy(self.cursor_surface);
+            }
+            if self.cursor_theme != std::ptr::null_mut() {
+                wayland::wl_cursor_theme_destroy(self.cursor_theme);
+            }
+            for output in &self.outputs {
+                release_output(output.output);
+            }
+
+            let globals = self.globals;
+            if globals.text_input_manager != std::ptr::null_mut() {
+                wayland::zwp_text_input_manager_v3_destroy(globals.text_input_manager);
+            }
+            if globals.pointer_constraints != std::ptr::null_mut() {
+                wayland::zwp_pointer_constraints_v1_destroy(globals.pointer_constraints);
+            }
+            if globals.relative_pointer_manager != std::ptr::null_mut() {
+                wayland::zwp_relative_pointer_manager_v1_destroy(globals.relative_pointer_manager);
+            }
+            if globals.decoration_manager != std::ptr::null_mut() {
+                wayland::zxdg_decoration_manager_v1_destroy(globals.decoration_manager);
+            }
+            if globals.fractional_scale_manager != std::ptr::null_mut() {
+                wayland::wp_fractional_scale_manager_v1_destroy(globals.fractional_scale_manager);
+            }
+            if globals.viewporter != std::ptr::null_mut() {
+                wayland::wp_viewporter_destroy(globals.viewporter);
+            }
+            if globals.wm_base != std::ptr::null_mut() {
+                wayland::xdg_wm_base_destroy(globals.wm_base);
+            }
+
+            // These have no destructor request at the versions that are bo-
+            // und, so only the proxies go away.
+            for proxy in [
+                globals.seat as *mut wayland::wl_proxy,
+                globals.shm as *mut wayland::wl_proxy,
+                globals.compositor as *mut wayland::wl_proxy,
+                self.registry as *mut wayland::wl_proxy,
+            ] {
+                if proxy != std::ptr::null_mut() {
+                    wayland::wl_proxy_destroy(proxy);
+                }
+            }
+
+            self.keyboard_mapping = None;
+            wayland::wl_display_disconnect(self.display);
+        }
+    }
+}
+
+unsafe extern "C" fn handle_global(
+    data: *mut c_void,
+    registry: *mut wayland::wl_registry,
+    name: u32,
+    interface: *const c_char,
+    version: u32,
+) {
+    let mut connection = get_connection(data);
+    let bind = |interface: &wayland::wl_interface, max_version: u32| {
+        wayland::wl_registry_bind(registry, name, interface, version.min(max_version))
+    };
+
+    match CStr::from_ptr(interface).to_bytes() {
+        b"wl_compositor" => {
+            connection.globals.compositor = bind(&wayland::wl_compositor_interface, 4) as *mut _;
+        }
+        b"wl_shm" => {
+            connection.globals.shm = bind(&wayland::wl_shm_interface, 1) as *mut _;
+        }
+        // Only the first seat is used.
+        b"wl_seat" if connection.globals.seat == std::ptr::null_mut() => {
+            let seat = bind(&wayland::wl_seat_interface, 8) as *mut wayland::wl_seat;
+            connection.globals.seat = seat;
+            add_listener(seat, &connection.listeners.seat, data);
+        }
+        b"xdg_wm_base" => {
+            let wm_base = bind(&wayland::xdg_wm_base_interface, 1) as *mut wayland::xdg_wm_base;
+            connection.globals.wm_base = wm_base;
+            add_listener(wm_base, &connection.listeners.wm_base, data);
+        }
+        b"wp_viewporter" => {
+            connection.globals.viewporter = bind(&wayland::wp_viewporter_interface, 1) as *mut _;
+        }
+        b"wp_fractional_scale_manager_v1" => {
+            connection.globals.fractional_scale_manager =
+                bind(&wayland::wp_fractional_scale_manager_v1_interface, 1) as *mut _;
+        }
+        b"zxdg_decoration_manager_v1" => {
+            connection.globals.decoration_manager =
+                bind(&wayland::zxdg_decoration_manager_v1_interface, 1) as *mut _;
+        }
+        b"zwp_relative_pointer_manager_v1" => {
+            connection.globals.relative_pointer_manager =
+                bind(&wayland::zwp_relative_pointer_manager_v1_interface, 1) as *mut _;
+        }
+        b"zwp_pointer_constraints_v1" => {
+            connection.globals.pointer_constraints =
+                bind(&wayland::zwp_pointer_constraints_v1_interface, 1) as *mut _;
+        }
+        b"zwp_text_input_manager_v3" => {
+            connection.globals.text_input_manager =
+                bind(&wayland::zwp_text_input_manager_v3_interface, 1) as *mut _;
+        }
+        // Version 4 adds the names of the outputs.
+        b"wl_output" => {
+            let output = bind(&wayland::wl_output_interface, 4) as *mut wayland::wl_output;
+            add_listener(output, &connection.listeners.output, data);
+            connection.outputs.push(Output {
+                name,
+                output,
+                monitor: Monitor {
+                    name: String::new(),
+                    position: (0, 0),
+                    size: (0, 0),
+                    physical_size: (0, 0),
+                    refresh_rate_millihertz: 0,
+                    scale_factor: 1.0,
+                    is_primary: false,
+                    video_modes: Vec::new(),
+                },
+            });
+        }
+        _ => (),
+    }
+}
+
+unsafe extern "C" fn handle_global_remove(
+    data: *mut c_void,
+    _registry: *mut wayland::wl_registry,
+    name: u32,
+) {
+    // Outputs come and go with monitors. Nothing else the Nengine binds is
+    // expected to go away while it runs.
+    let mut connection = get_connection(data);
+    let index = match connection
+        .outputs
+        .iter()
+        .position(|output| output.name == name)
+    {
+        Some(index) => index,
+        None => return,
+    };
+
+    let output = connection.outputs.remove(index).output;
+    for surface in connection.surfaces.values_mut() {
+        surface.outputs.retain(|&entered| entered != output);
+    }
+    release_output(output);
+}
+
+/// Gets rid of an output, which only has a destructor request from version 3
+/// on.
+unsafe fn release_output(output: *mut wayland::wl_output) {
+    if wayland::wl_proxy_get_version(output as *mut wayland::wl_proxy) >= 3 {
+        wayland::wl_output_release(output);
+    } else {
+        wayland::wl_proxy_destroy(output as *mut wayland::wl_proxy);
+    }
+}
+
+/// Gets the monitor of an output to update it.
+fn find_monitor(
+    connection: &mut Connection,
+    output: *mut wayland::wl_output,
+) -> Option<&mut Monitor> {
+    connection
+        .outputs
+        .iter_mut()
+        .find(|known| known.output == output)
+        .map(|known| &mut known.monitor)
+}
+
+unsafe extern "C" fn handle_output_geometry(
+    data: *mut c_void,
+    output: *mut wayland::wl_output,
+    x: i32,
+    y: i32,
+    physical_width: i32,
+    physical_height: i32,
+    _subpixel: i32,
+    make: *const c_char,
+    model: *const c_char,
+    _transform: i32,
+) {
+    let mut connection = get_connection(data);
+    let monitor = match find_monitor(&mut connection, output) {
+        Some(monitor) => monitor,
+        None => return,
+    };
+
+    // The position is in logical pixels, which is all Wayland gives.
+    monitor.position = (x, y);
+    monitor.physical_size = (physical_width.max(0) as u32, physical_height.max(0) as u32);
+
+    // Before version 4 there is no name, the make and model are the closest
+    // thing to one.
+    if monitor.name.is_empty() {
+        monitor.name = format!(
+            "{} {}",
+            CStr::from_ptr(make).to_string_lossy(),
+            CStr::from_ptr(model).to_string_lossy()
+        );
+    }
+}
+
+unsafe extern "C" fn handle_output_mode(
+    data: *mut c_void,
+    output: *mut wayland::wl_output,
+    flags: u32,
+    width: i32,
+    height: i32,
+    refresh: i32,
+) {
+    let mut connection = get_connection(data);
+    let monitor = match find_monitor(&mut connection, output) {
+        Some(monitor) => monitor,
+        None => return,
+    };
+
+    // The refresh rate is already in millihertz. Wayland does not say how
+    // many bits the pixels have.
+    let mode = VideoMode {
+        size: (width.max(0) as u32, height.max(0) as u32),
+        bit_depth: 0,
+        refresh_rate_millihertz: refresh.max(0) as u32,
+    };
+    if !monitor.video_modes.contains(&mode) {
+        monitor.video_modes.push(mode);
+    }
+
+    if flags & wayland::WL_OUTPUT_MODE_CURRENT != 0 {
+        monitor.size = mode.size;
+        monitor.refresh_rate_millihertz = mode.refresh_rate_millihertz;
+    }
+}
+
+unsafe extern "C" fn handle_output_done(_data: *mut c_void, _output: *mut wayland::wl_output) {
+    // The monitors are updated as each event arrives, which is good enough
+    // for something that is only read every now and then.
+}
+
+unsafe extern "C" fn handle_output_scale(
+    data: *mut c_void,
+    output: *mut wayland::wl_output,
+    factor: i32,
+) {
+    let mut connection = get_connection(data);
+    if let Some(monitor) = find_monitor(&mut connection, output) {
+        monitor.scale_factor = factor.into();
+    }
+}
+
+unsafe extern "C" fn handle_output_name(
+    data: *mut c_void,
+    output: *mut wayland::wl_output,
+    name: *const c_char,
+) {
+    let mut connection = get_connection(data);
+    if let Some(monitor) = find_monitor(&mut connection, output) {
+        monitor.name = CStr::from_ptr(name).to_string_lossy().into_owned();
+    }
+}
+
+unsafe extern "C" fn handle_output_description(
+    _data: *mut c_void,
+    _output: *mut wayland::wl_output,
+    _description: *const c_char,
+) {
+}
+
+unsafe extern "C" fn handle_surface_enter(
+    data: *mut c_void,
+    surface: *mut wayland::wl_surface,
+    output: *mut wayland::wl_output,
+) {
+    let mut connection = get_connection(data);
+    if let Some(id) = connection.find_window(|window| window.surface == surface) {
+        let outputs = &mut connection.surfaces.get_mut(&id).unwrap().outputs;
+        if !outputs.contains(&output) {
+            outputs.push(output);
+        }
+    }
+}
+
+unsafe extern "C" fn handle_surface_leave(
+    data: *mut c_void,
+    surface: *mut wayland::wl_surface,
+    output: *mut wayland::wl_output,
+) {
+    let mut connection = get_connection(data);
+    if let Some(id) = connection.find_window(|window| window.surface == surface) {
+        let outputs = &mut connection.surfaces.get_mut(&id).unwrap().outputs;
+        outputs.retain(|&entered| entered != output);
+    }
+}
+
+unsafe extern "C" fn handle_seat_capabilities(
+    data: *mut c_void,
+    seat: *mut wayland::wl_seat,
+    capabilities: u32,
+) {
+    let mut connection = get_connection(data);
+
+    let has_pointer = capabilities & wayland::WL_SEAT_CAPABILITY_POINTER != 0;
+    if has_pointer && connection.pointer == std::ptr::null_mut() {
+        connection.pointer = wayland::wl_seat_get_pointer(seat);
+        add_listener(connection.pointer, &connection.listeners.pointer, data);
+
+        let manager = connection.globals.relative_pointer_manager;
+        if manager != std::ptr::null_mut() {
+            connection.relative_pointer =
+                wayland::zwp_relative_pointer_manager_v1_get_relative_pointer(
+                    manager,
+                    connection.pointer,
+                );
+            add_listener(
+                connection.relative_pointer,
+                &connection.listeners.relative_pointer,
+                data,
+            );
+        }
+    } else if !has_pointer && connection.pointer != std::ptr::null_mut() {
+        if connection.relative_pointer != std::ptr::null_mut() {
+            wayland::zwp_relative_pointer_v1_destroy(connection.relative_pointer);
+            connection.relative_pointer = std::ptr::null_mut();
+        }
+        wayland::wl_pointer_release(connection.pointer);
+        connection.pointer = std::ptr::null_mut();
+        connection.pointer_focus = None;
+    }
+
+    // Cursor grabs belong to a pointer, so they are made again for a new one
+    // and dropped along with an old one.
+    let ids: Vec<WindowId> = connection.surfaces.keys().copied().collect();
+    for id in ids {
+        connection.apply_cursor_grab(id);
+    }
+
+    let has_keyboard = capabilities & wayland::WL_SEAT_CAPABILITY_KEYBOARD != 0;
+    if has_keyboard && connection.keyboard == std::ptr::null_mut() {
+        connection.keyboard = wayland::wl_seat_get_keyboard(seat);
+        add_listener(connection.keyboard, &connection.listeners.keyboard, data);
+    } else if !has_keyboard && connection.keyboard != std::ptr::null_mut() {
+        wayland::wl_keyboard_release(connection.keyboard);
+        connection.keyboard = std::ptr::null_mut();
+        connection.keyboard_focus = None;
+        connection.key_repeat = None;
+    }
+}
+
+unsafe extern "C" fn handle_seat_name(
+    _data: *mut c_void,
+    _seat: *mut wayland::wl_seat,
+    _name: *const c_char,
+) {
+}
+
+unsafe extern "C" fn handle_pointer_enter(
+    data: *mut c_void,
+    _pointer: *mut wayland::wl_pointer,
+    serial: u32,
+    surface: *mut wayland::wl_surface,
+    x: wayland::wl_fixed_t,
+    y: wayland::wl_fixed_t,
+) {
+    let mut connection = get_connection(data);
+    let id = match connection.find_window(|window| window.surface == surface) {
+        Some(id) => id,
+        None => return,
+    };
+
+    connection.pointer_focus = Some(id);
+    connection.pointer_serial = serial;
+    connection.update_cursor();
+
+    connection.events.push(id, Event::CursorEntered);
+    connection.push_mouse_move(id, x, y);
+}
+
+unsafe extern "C" fn handle_pointer_leave(
+    data: *mut c_void,
+    _pointer: *mut wayland::wl_pointer,
+    _serial: u32,
+    _surface: *mut wayland::wl_surface,
+) {
+    let mut connection = get_connection(data);
+    if let Some(id) = connection.pointer_focus.take() {
+        connection.events.push(id, Event::CursorLeft);
+    }
+}
+
+unsafe extern "C" fn handle_pointer_motion(
+    data: *mut c_void,
+    _pointer: *mut wayland::wl_pointer,
+    _time: u32,
+    x: wayland::wl_fixed_t,
+    y: wayland::wl_fixed_t,
+) {
+    let mut connection = get_connection(data);
+    if let Some(id) = connection.pointer_focus {
+        connection.push_mouse_move(id, x, y);
+    }
+}
+
+unsafe extern "C" fn handle_pointer_button(
+    data: *mut c_void,
+    _pointer: *mut wayland::wl_pointer,
+    _serial: u32,
+    _time: u32,
+    button: u32,
+    state: u32,
+) {
+    let mut connection = get_connection(data);
+    let button = match translate_wayland_buttons(button) {
+        Some(button) => button,
+        None => return,
+    };
+    if let Some(id) = connection.pointer_focus {
+        connection.events.push(
+            id,
+            Event::MouseButton {
+                button,
+                is_press: state == wayland::WL_POINTER_BUTTON_STATE_PRESSED,
+            },
+        );
+    }
+}
+
+unsafe extern "C" fn handle_pointer_axis(
+    data: *mut c_void,
+    pointer: *mut wayland::wl_pointer,
+    _time: u32,
+    axis: u32,
+    value: wayland::wl_fixed_t,
+) {
+    let mut connection = get_connection(data);
+
+    // Positive values scroll down or right. Scrolling up is positive every-
+    // where else in the Nengine.
+    let value = wayland::wl_fixed_to_double(value);
+    if axis == wayland::WL_POINTER_AXIS_VERTICAL_SCROLL {
+        connection.scroll.1 -= value;
+    } else {
+        connection.scroll.0 += value;
+    }
+
+    // Frames came with version 5, before that every axis event stands alone.
+    if wayland::wl_proxy_get_version(pointer as *mut wayland::wl_proxy) < 5 {
+        connection.push_scroll();
+    }
+}
+
+unsafe extern "C" fn handle_pointer_frame(data: *mut c_void, _pointer: *mut wayland::wl_pointer) {
+    get_connection(data).push_scroll();
+}
+
+unsafe extern "C" fn handle_pointer_axis_source(
+    _data: *mut c_void,
+    _pointer: *mut wayland::wl_pointer,
+    _axis_source: u32,
+) {
+}
+
+unsafe extern "C" fn handle_pointer_axis_stop(
+    _data: *mut c_void,
+    _pointer: *mut wayland::wl_pointer,
+    _time: u32,
+    _axis: u32,
+) {
+}
+
+// Wheels send notches with `axis_discrete` up to version 7 and in 120ths of
+// a notch with `axis_value120` from version 8, never both.
+unsafe extern "C" fn handle_pointer_axis_discrete(
+    data: *mut c_void,
+    _pointer: *mut wayland::wl_pointer,
+    axis: u32,
+    discrete: i32,
+) {
+    get_connection(data).add_scroll_notches(axis, discrete as f64);
+}
+
+unsafe extern "C" fn handle_pointer_axis_value120(
+    data: *mut c_void,
+    _pointer: *mut wayland::wl_pointer,
+    axis: u32,
+    value120: i32,
+) {
+    get_connection(data).add_scroll_notches(axis, value120 as f64 / 120.0);
+}
+
+unsafe extern "C" fn handle_text_input_enter(
+    data: *mut c_void,
+    _text_input: *mut wayland::zwp_text_input_v3,
+    surface: *mut wayland::wl_surface,
+) {
+    let mut connection = get_connection(data);
+    connection.text_input_focus = connection.find_window(|window| window.surface == surface);
+    connection.update_text_input();
+}
+
+unsafe extern "C" fn handle_text_input_leave(
+    data: *mut c_void,
+    text_input: *mut wayland::zwp_text_input_v3,
+    _surface: *mut wayland::wl_surface,
+) {
+    let mut connection = get_connection(data);
+    connection.end_preedit();
+    if connection.is_text_input_enabled {
+        wayland::zwp_text_input_v3_disable(text_input);
+        wayland::zwp_text_input_v3_commit(text_input);
+        connection.is_text_input_enabled = false;
+    }
+    connection.text_input_focus = None;
+}
+
+/// The cursor is a byte range of the text, and both ends are -1 if it is hid-
+/// den. Only where it begins is kept.
+unsafe extern "C" fn handle_preedit_string(
+    data: *mut c_void,
+    _text_input: *mut wayland::zwp_text_input_v3,
+    text: *const c_char,
+    cursor_begin: i32,
+    _cursor_end: i32,
+) {
+    let text = if text == std::ptr::null() {
+        String::new()
+    } else {
+        CStr::from_ptr(text).to_string_lossy().into_owned()
+    };
+    let cursor = usize::try_from(cursor_begin).ok();
+    get_connection(data).pending_preedit = Some((text, cursor));
+}
+
+unsafe extern "C" fn handle_commit_string(
+    data: *mut c_void,
+    _text_input: *mut wayland::zwp_text_input_v3,
+    text: *const c_char,
+) {
+    if text != std::ptr::null() {
+        get_connection(data).pending_commit =
+            Some(CStr::from_ptr(text).to_string_lossy().into_owned());
+    }
+}
+
+// The Nengine does not send the text around the cursor, so there is nothing
+// for the input method to delete.
+unsafe extern "C" fn handle_delete_surrounding_text(
+    _data: *mut c_void,
+    _text_input: *mut wayland::zwp_text_input_v3,
+    _before_length: u32,
+    _after_length: u32,
+) {
+}
+
+/// Applies the text that came since the last `done`. A preedit that is not
+/// sent again is gone.
+unsafe extern "C" fn handle_text_input_done(
+    data: *mut c_void,
+    _text_input: *mut wayland::zwp_text_input_v3,
+    _serial: u32,
+) {
+    let mut connection = get_connection(data);
+    let preedit = connection.pending_preedit.take();
+    let commit = connection.pending_commit.take();
+    let id = match connection.text_input_focus {
+        Some(id) => id,
+        None => return,
+    };
+
+    if let Some(text) = commit {
+        connection.end_preedit();
+        connection.events.push(id, Event::ImeCommit { text });
+    }
+    match preedit {
+        Some((text, cursor)) if !text.is_empty() => {
+            connection.has_preedit = true;
+            connection
+                .events
+                .push(id, Event::ImePreedit { text, cursor });
+        }
+        _ => connection.end_preedit(),
+    }
+}
+
+unsafe extern "C" fn handle_relative_motion(
+    data: *mut c_void,
+    _relative_pointer: *mut wayland::zwp_relative_pointer_v1,
+    _time_high: u32,
+    _time_low: u32,
+    _dx: wayland::wl_fixed_t,
+    _dy: wayland::wl_fixed_t,
+    dx_unaccelerated: wayland::wl_fixed_t,
+    dy_unaccelerated: wayland::wl_fixed_t,
+) {
+    let mut connection = get_connection(data);
+    if let Some(id) = connection.pointer_focus {
+        connection.events.push(
+            id,
+            Event::MouseDelta {
+                dx: wayland::wl_fixed_to_double(dx_unaccelerated),
+                dy: wayland::wl_fixed_to_double(dy_unaccelerated),
+            },
+        );
+    }
+}
+
+unsafe extern "C" fn handle_keymap(
+    data: *mut c_void,
+    _keyboard: *mut wayland::wl_keyboard,
+    format: u32,
+    fd: i32,
+    size: u32,
+) {
+    // The file closes the descriptor when it is dropped.
+    let mut file = File::from_raw_fd(fd);
+    if format != wayland::WL_KEYBOARD_KEYMAP_FORMAT_XKB_V1 {
+        return;
+    }
+
+    let mut text = vec![0; size as usize];
+    if file.read_exact(&mut text).is_err() {
+        return;
+    }
+
+    // A keymap that does not compile leaves the old one in place.
+    if let Ok(keyboard_mapping) = KeyboardMapping::from_keymap_text(&text) {
+        get_connection(data).keyboard_mapping = Some(keyboard_mapping);
+    }
+}
+
+unsafe extern "C" fn handle_keyboard_enter(
+    data: *mut c_void,
+    _keyboard: *mut wayland::wl_keyboard,
+    _serial: u32,
+    surface: *mut wayland::wl_surface,
+    _keys: *mut wayland::wl_array,
+) {
+    let mut connection = get_connection(data);
+    if let Some(id) = connection.find_window(|window| window.surface == surface) {
+        connection.keyboard_focus = Some(id);
+        connection.events.push(id, Event::FocusGained);
+    }
+}
+
+unsafe extern "C" fn handle_keyboard_leave(
+    data: *mut c_void,
+    _keyboard: *mut wayland::wl_keyboard,
+    _serial: u32,
+    _surface: *mut wayland::wl_surface,
+) {
+    let mut connection = get_connection(data);
+    connection.key_repeat = None;
+    if let Some(id) = connection.keyboard_focus.take() {
+        connection.events.push(id, Event::FocusLost);
+    }
+}
+
+unsafe extern "C" fn handle_key(
+    data: *mut c_void,
+    _keyboard: *mut wayland::wl_keyboard,
+    _serial: u32,
+    _time: u32,
+    key: u32,
+    state: u32,
+) {
+    let mut connection = get_connection(data);
+    let id = match connection.keyboard_focus {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Wayland sends evdev scancodes, XKB keycodes are those offset by 8.
+    let keycode = key + 8;
+    let is_press = state == wayland::WL_KEYBOARD_KEY_STATE_PRESSED;
+    connection.push_key(id, keycode, is_press, false);
+
+    if is_press {
+        let repeats = connection
+            .keyboard_mapping
+            .as_ref()
+            .map_or(false, |keyboard_mapping| {
+                keyboard_mapping.key_repeats(keycode)
+            });
+
+        if repeats && connection.repeat_rate > 0 {
+            connection.key_repeat = Some(KeyRepeat {
+                keycode,
+                next: Instant::now() + connection.repeat_delay,
+            });
+        }
+    } else if connection.key_repeat.map(|key_repeat| key_repeat.keycode) == Some(keycode) {
+        connection.key_repeat = None;
+    }
+}
+
+unsafe extern "C" fn handle_modifiers(
+    data: *mut c_void,
+    _keyboard: *mut wayland::wl_keyboard,
+    _serial: u32,
+    depressed_mods: u32,
+    latched_mods: u32,
+    locked_mods: u32,
+    group: u32,
+) {
+    if let Some(keyboard_mapping) = &mut get_connection(data).keyboard_mapping {
+        keyboard_mapping.update_keymap(depressed_mods, latched_mods, locked_mods, 0, 0, group);
+    }
+}
+
+unsafe extern "C" fn handle_repeat_info(
+    data: *mut c_void,
+    _keyboard: *mut wayland::wl_keyboard,
+    rate: i32,
+    delay: i32,
+) {
+    let mut connection = get_connection(data);
+    connection.repeat_rate = rate;
+    connection.repeat_delay = Duration::from_millis(delay.max(0) as u64);
+}
+
+unsafe extern "C" fn handle_ping(
+    _data: *mut c_void,
+    wm_base: *mut wayland::xdg_wm_base,
+    serial: u32,
+) {
+    wayland::xdg_wm_base_pong(wm_base, serial);
+}
+
+unsafe extern "C" fn handle_surface_configure(
+    data: *mut c_void,
+    xdg_surface: *mut wayland::xdg_surface,
+    serial: u32,
+) {
+    let mut connection = get_connection(data);
+    wayland::xdg_surface_ack_configure(xdg_surface, serial);
+
+    let id = match connection.find_window(|window| window.xdg_surface == xdg_surface) {
+        Some(id) => id,
+        None => return,
+    };
+
+    let surface = connection.surfaces.get_mut(&id).unwrap();
+    let pending_size = surface.pending_size;
+    let is_resized = pending_size.0 != 0 && pending_size.1 != 0 && pending_size != surface.size;
+    if is_resized {
+        surface.size = pending_size;
+    }
+    surface.is_configured = true;
+
+    connection.draw_placeholder(id);
+    if is_resized {
+        connection.push_resized(id);
+    }
+    connection.events.push(id, Event::RedrawRequested);
+}
+
+unsafe extern "C" fn handle_toplevel_configure(
+    data: *mut c_void,
+    xdg_toplevel: *mut wayland::xdg_toplevel,
+    width: i32,
+    height: i32,
+    _states: *mut wayland::wl_array,
+) {
+    let mut connection = get_connection(data);
+    if let Some(id) = connection.find_window(|window| window.xdg_toplevel == xdg_toplevel) {
+        let surface = connection.surfaces.get_mut(&id).unwrap();
+        surface.pending_size = (width.max(0) as u32, height.max(0) as u32);
+    }
+}
+
+unsafe extern "C" fn handle_toplevel_close(
+    data: *mut c_void,
+    xdg_toplevel: *mut wayland::xdg_toplevel,
+) {
+    let mut connection = get_connection(data);
+    if let Some(id) = connection.find_window(|window| window.xdg_toplevel == xdg_toplevel) {
+        connection.events.push(id, Event::CloseRequested);
+    }
+}
+
+unsafe extern "C" fn handle_preferred_scale(
+    data: *mut c_void,
+    fractional_scale: *mut wayland::wp_fractional_scale_v1,
+    scale: u32,
+) {
+    let mut connection = get_connection(data);
+    let id = match connection.find_window(|window| window.fractional_scale == fractional_scale) {
+        Some(id) => id,
+        None => return,
+    };
+
+    // The scale is sent in 120ths.
+    let scale = f64::from(scale) / 120.0;
+    let surface = connection.surfaces.get_mut(&id).unwrap();
+    if scale == surface.scale {
+        return;
+    }
+    surface.scale = scale;
+    let is_configured = surface.is_configured;
+
+    connection.events.push(
+        id,
+        Event::ScaleFactorChanged {
+            scale_factor: scale,
+        },
+    );
+    if is_configured {
+        connection.draw_placeholder(id);
+        connection.push_resized(id);
+    }
+}
+
+// The `CrossPlatformEventLoop` trait is implemented on the Linux event loop,
+// which hands out either these windows or X11 ones.
+impl EventLoop {
+    pub(super) fn new() -> Result<EventLoop, WindowError> {
+        Ok(EventLoop {
+            connection: Connection::open()?,
+            events: VecDeque::new(),
+        })
+    }
+
+    pub(super) fn create_window(&self, builder: &WindowBuilder) -> Result<Window, WindowError> {
+        builder.validate()?;
+        unsafe { Window::create(self.connection.clone(), builder) }
+    }
+
+    pub(super) fn monitors(&self) -> Vec<Monitor> {
+        self.connection.borrow().monitors()
+    }
+
+    pub(super) fn poll_events(&mut self) -> WindowEvents<'_> {
+        unsafe {
+            Connection::pump(&self.connection);
+        }
+        run_callbacks(&self.connection, |connection| &mut connection.events);
+        self.events
+            .extend(self.connection.borrow_mut().events.take_all());
+
+        self.events.drain(..)
+    }
+}
+
+impl Window {
+    unsafe fn create(
+        shared: Rc<RefCell<Connection>>,
+        builder: &WindowBuilder,
+    ) -> Result<Window, WindowError> {
+        if builder.icon.is_some() {
+            return Err(WindowError::Unsupported("window icons"));
+        }
+        if builder.always_on_top {
+            return Err(WindowError::Unsupported("always on top"));
+        }
+
+        let data = Rc::as_ptr(&shared) as *mut c_void;
+        let mut connection = shared.borrow_mut();
+        let globals = connection.globals;
+
+        let surface = wayland::wl_compositor_create_surface(globals.compositor);
+        let id = WindowId(wayland::wl_proxy_get_id(surface as *mut wayland::wl_proxy).into());
+        add_listener(surface, &connection.listeners.surface, data);
+
+        let xdg_surface = wayland::xdg_wm_base_get_xdg_surface(globals.wm_base, surface);
+        add_listener(xdg_surface, &connection.listeners.xdg_surface, data);
+        let xdg_toplevel = wayland::xdg_surface_get_toplevel(xdg_surface);
+        add_listener(xdg_toplevel, &connection.listeners.xdg_toplevel, data);
+
+        // Fractional scaling works by drawing a bigger buffer and having the
+        // compositor scale it down to the size of the surface, which is what
+        // the viewport is for.
+        let (viewport, fractional_scale) = if globals.viewporter != std::ptr::null_mut()
+            && globals.fractional_scale_manager != std::ptr::null_mut()
+        {
+            let viewport = wayland::wp_viewporter_get_viewport(globals.viewporter, surface);
+            let fractional_scale = wayland::wp_fractional_scale_manager_v1_get_fractional_scale(
+                globals.fractional_scale_manager,
+                surface,
+            );
+            add_listener(
+                fractional_scale,
+                &connection.listeners.fractional_scale,
+                data,
+            );
+
+            (viewport, fractional_scale)
+        } else {
+            (std::ptr::null_mut(), std::ptr::null_mut())
+        };
+
+        // The Nengine does not draw decorations of its own, so asking for cl-
+        // ient-side ones means getting none. Compositors without the decora-
+        // tion protocol always leave them to the client.
+        let decoration = if globals.decoration_manager != std::ptr::null_mut() {
+            let decoration = wayland::zxdg_decoration_manager_v1_get_toplevel_decoration(
+                globals.decoration_manager,
+                xdg_toplevel,
+            );
+            wayland::zxdg_toplevel_decoration_v1_set_mode(
+                decoration,
+                if builder.decorations {
+                    wayland::ZXDG_TOPLEVEL_DECORATION_V1_MODE_SERVER_SIDE
+                } else {
+                    wayland::ZXDG_TOPLEVEL_DECORATION_V1_MODE_CLIENT_SIDE
+                },
+            );
+
+            decoration
+        } else {
+            std::ptr::null_mut()
+        };
+
+        // The app id is what the class is on X11.
+        if let Some((_, class)) = &builder.class {
+            if let Ok(class) = CString::new(class.as_str()) {
+                wayland::xdg_toplevel_set_app_id(xdg_toplevel, class.as_ptr());
+            }
+        }
+
+        if let Some(parent) = builder
+            .parent
+            .and_then(|parent| connection.surfaces.get(&parent))
+        {
+            wayland::xdg_toplevel_set_parent(xdg_toplevel, parent.xdg_toplevel);
+        }
+
+        // The scale is not known until the compositor sends it, so the size
+        // starts out as if it was 1.
+        connection.surfaces.insert(
+            id,
+            Surface {
+                surface,
+                xdg_surface,
+                xdg_toplevel,
+                viewport,
+                fractional_scale,
+                outputs: Vec::new(),
+                decoration,
+                size: builder.size,
+                pending_size: (0, 0),
+                scale: 1.0,
+                buffer: std::ptr::null_mut(),
+                is_transparent: builder.transparent,
+                is_configured: false,
+                is_cursor_visible: true,
+                cursor_icon: CursorIcon::Default,
+                custom_cursor: None,
+                cursor_grab: CursorGrab::None,
+                locked_pointer: std::ptr::null_mut(),
+                confined_pointer: std::ptr::null_mut(),
+                is_ime_allowed: false,
+                ime_position: (0, 0),
+            },
+        );
+        drop(connection);
+
+        let mut window = Window {
+            shared,
+            id,
+            surface,
+            xdg_toplevel,
+            is_open: true,
+            is_shown: false,
+            is_resizable: builder.resizable,
+            is_minimized: false,
+            min_size: None,
+            max_size: None,
+            events: VecDeque::new(),
+        };
+
+        window.set_title(&builder.title);
+        window.write_size_limits();
+
+        if builder.fullscreen != FullscreenMode::Windowed {
+            window.set_fullscreen(builder.fullscreen);
+        }
+
+        Ok(window)
+    }
+
+    fn flush(&self) {
+        unsafe {
+            wayland::wl_display_flush(self.shared.borrow().display);
+        }
+    }
+
+    /// Sends the minimum and maximum size, which are in logical pixels. A wi-
+    /// ndow that is not resizable gets its current size as both.
+    unsafe fn write_size_limits(&self) {
+        let (size, scale) = match self.shared.borrow().surfaces.get(&self.id) {
+            Some(surface) => (surface.size, surface.scale),
+            None => return,
+        };
+
+        // 0 means that there is no limit.
+        let (min_size, max_size) = if self.is_resizable {
+            (
+                self.min_size.map_or((0, 0), |size| to_logical(size, scale)),
+                self.max_size.map_or((0, 0), |size| to_logical(size, scale)),
+            )
+        } else {
+            (size, size)
+        };
+
+        wayland::xdg_toplevel_set_min_size(self.xdg_toplevel, min_size.0 as i32, min_size.1 as i32);
+        wayland::xdg_toplevel_set_max_size(self.xdg_toplevel, max_size.0 as i32, max_size.1 as i32);
+
+        // The limits are double-buffered, but the first commit is left to
+        // `show`.
+        if self.is_shown {
+            wayland::wl_surface_commit(self.surface);
+        }
+    }
+}
+
+impl CrossPlatformWindow for Window {
+    fn new(builder: &WindowBuilder) -> Result<Window, WindowError> {
+        EventLoop::new()?.create_window(builder)
+    }
+
+    fn id(&self) -> WindowId {
+        self.id
+    }
+
+    fn set_event_callback(&mut self, callback: Box<dyn FnMut(&Event)>) {
+        self.shared
+            .borrow_mut()
+            .events
+            .set_callback(self.id, callback);
+    }
+
+    fn show(&mut self) {
+        if !self.is_open || self.is_shown {
+            return;
+        }
+
+        unsafe {
+            // The first commit has no buffer. The compositor answers it with
+            // a configure, which is when the window gets drawn.
+            wayland::wl_surface_commit(self.surface);
+            if self.is_minimized {
+                wayland::xdg_toplevel_set_minimized(self.xdg_toplevel);
+            }
+        }
+        self.flush();
+
+        self.is_shown = true;
+    }
+
+    fn set_title(&mut self, title: &str) {
+        if !self.is_open {
+            return;
+        }
+
+        let title = CString::new(title.replace('\0', "")).unwrap();
+        unsafe {
+            wayland::xdg_toplevel_set_title(self.xdg_toplevel, title.as_ptr());
+        }
+        self.flush();
+    }
+
+    fn set_size(&mut self, width: u32, height: u32) {
+        if !self.is_open {
+            return;
+        }
+
+        unsafe {
+            let mut connection = self.shared.borrow_mut();
+            let surface = match connection.surfaces.get_mut(&self.id) {
+                Some(surface) => surface,
+                None => return,
+            };
+            surface.size = to_logical((width, height), surface.scale);
+
+            // Wayland windows pick their own size, so there is no one else to
+            // send `Resized`.
+            if surface.is_configured {
+                connection.draw_placeholder(self.id);
+                connection.push_resized(self.id);
+            }
+            drop(connection);
+
+            if !self.is_resizable {
+                self.write_size_limits();
+            }
+        }
+        self.flush();
+    }
+
+    fn set_position(&mut self, _x: i32, _y: i32) {
+        // Wayland does not let windows place themselves.
+    }
+
+    fn set_fullscreen(&mut self, mode: FullscreenMode) {
+        if !self.is_open {
+            return;
+        }
+
+        // The compositor decides how a fullscreen window is presented and
+        // which mode the output is in, so the fullscreen modes are all the
+        // same here.
+        unsafe {
+            if mode == FullscreenMode::Windowed {
+                wayland::xdg_toplevel_unset_fullscreen(self.xdg_toplevel);
+            } else {
+                wayland::xdg_toplevel_set_fullscreen(self.xdg_toplevel);
+            }
+        }
+        self.flush();
+    }
+
+    fn monitors(&self) -> Vec<Monitor> {
+        self.shared.borrow().monitors()
+    }
+
+    fn current_monitor(&self) -> Option<Monitor> {
+        let connection = self.shared.borrow();
+        let entered = *connection.surfaces.get(&self.id)?.outputs.first()?;
+
+        connection
+            .outputs
+            .iter()
+            .find(|output| output.output == entered)
+            .map(|output| output.monitor.clone())
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.shared
+            .borrow()
+            .surfaces
+            .get(&self.id)
+            .map_or(1.0, |surface| surface.scale)
+    }
+
+    fn set_resizable(&mut self, resizable: bool) {
+        if !self.is_open {
+            return;
+        }
+
+        self.is_resizable = resizable;
+        unsafe {
+            self.write_size_limits();
+        }
+        self.flush();
+    }
+
+    fn set_min_size(&mut self, size: Option<(u32, u32)>) {
+        if !self.is_open {
+            return;
+        }
+
+        self.min_size = size;
+        unsafe {
+            self.write_size_limits();
+        }
+        self.flush();
+    }
+
+    fn set_max_size(&mut self, size: Option<(u32, u32)>) {
+        if !self.is_open {
+            return;
+        }
+
+        self.max_size = size;
+        unsafe {
+            self.write_size_limits();
+        }
+        self.flush();
+    }
+
+    fn minimize(&mut self) {
+        if !self.is_open {
+            return;
+        }
+
+        // Compositors ignore this before the window is mapped, so it waits
+        // for `show`.
+        if !self.is_shown {
+            self.is_minimized = true;
+            return;
+        }
+
+        unsafe {
+            wayland::xdg_toplevel_set_minimized(self.xdg_toplevel);
+        }
+        self.flush();
+    }
+
+    // The compositor remembers the size from before, and sends a configure
+    // without one on unmaximizing so that the window picks it.
+    fn set_maximized(&mut self, maximized: bool) {
+        if !self.is_open {
+            return;
+        }
+
+        unsafe {
+            if maximized {
+                wayland::xdg_toplevel_set_maximized(self.xdg_toplevel);
+            } else {
+                wayland::xdg_toplevel_unset_maximized(self.xdg_toplevel);
+            }
+        }
+        self.flush();
+    }
+
+    // There is no protocol for window icons yet, the compositor takes them from
+    // the desktop file that matches the app id.
+    fn set_icon(&mut self, _rgba: &[u8], _width: u32, _height: u32) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported("window icons"))
+    }
+
+    fn request_attention(&mut self, _attention: UserAttention) {}
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        if !self.is_open {
+            return;
+        }
+
+        let mut connection = self.shared.borrow_mut();
+        if let Some(surface) = connection.surfaces.get_mut(&self.id) {
+            surface.is_cursor_visible = visible;
+        }
+
+        if connection.pointer_focus == Some(self.id) {
+            unsafe {
+                connection.update_cursor();
+            }
+        }
+        drop(connection);
+
+        self.flush();
+    }
+
+    fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        if !self.is_open {
+            return;
+        }
+
+        let mut connection = self.shared.borrow_mut();
+        if let Some(surface) = connection.surfaces.get_mut(&self.id) {
+            surface.cursor_icon = icon;
+            if let Some(custom_cursor) = surface.custom_cursor.take() {
+                unsafe {
+                    wayland::wl_buffer_destroy(custom_cursor.buffer);
+                }
+            }
+        }
+
+        if connection.pointer_focus == Some(self.id) {
+            unsafe {
+                connection.update_cursor();
+            }
+        }
+        drop(connection);
+
+        self.flush();
+    }
+
+    fn set_custom_cursor(&mut self, cursor: &CustomCursor) -> Result<(), WindowError> {
+        if !self.is_open {
+            return Ok(());
+        }
+
+        let mut connection = self.shared.borrow_mut();
+        unsafe {
+            let name = format!("nengine-{}-cursor-{}", std::process::id(), self.id.0);
+            let buffer = create_shm_buffer(
+                connection.globals.shm,
+                &name,
+                (cursor.width, cursor.height),
+                wayland::WL_SHM_FORMAT_ARGB8888,
+                &cursor.premultiplied_bgra(),
+            );
+            if buffer == std::ptr::null_mut() {
+                return Err(WindowError::Os(
+                    "could not create a shared memory buffer for the cursor".to_owned(),
+                ));
+            }
+
+            let image = CursorImage {
+                buffer,
+                size: (cursor.width as i32, cursor.height as i32),
+                hotspot: (cursor.hotspot.0 as i32, cursor.hotspot.1 as i32),
+            };
+            if let Some(surface) = connection.surfaces.get_mut(&self.id) {
+                // The cursor surface may still show the old buffer, which is
+                // only until `update_cursor` attaches another one.
+                if let Some(custom_cursor) = surface.custom_cursor.replace(image) {
+                    wayland::wl_buffer_destroy(custom_cursor.buffer);
+                }
+            }
+
+            if connection.pointer_focus == Some(self.id) {
+                connection.update_cursor();
+            }
+        }
+        drop(connection);
+
+        self.flush();
+        Ok(())
+    }
+
+    fn set_cursor_grab(&mut self, grab: CursorGrab) {
+        if !self.is_open {
+            return;
+        }
+
+        let mut connection = self.shared.borrow_mut();
+        if let Some(surface) = connection.surfaces.get_mut(&self.id) {
+            surface.cursor_grab = grab;
+        }
+        unsafe {
+            connection.apply_cursor_grab(self.id);
+        }
+        drop(connection);
+
+        self.flush();
+    }
+
+    fn set_ime_allowed(&mut self, allowed: bool) {
+        if !self.is_open {
+            return;
+        }
+
+        let mut connection = self.shared.borrow_mut();
+        if let Some(surface) = connection.surfaces.get_mut(&self.id) {
+            surface.is_ime_allowed = allowed;
+        }
+        if connection.text_input_focus == Some(self.id) {
+            if !allowed {
+                connection.end_preedit();
+            }
+            unsafe {
+                connection.update_text_input();
+            }
+        }
+        drop(connection);
+
+        self.flush();
+    }
+
+    fn set_ime_position(&mut self, x: i32, y: i32) {
+        if !self.is_open {
+            return;
+        }
+
+        let mut connection = self.shared.borrow_mut();
+        if let Some(surface) = connection.surfaces.get_mut(&self.id) {
+            surface.ime_position = (x, y);
+        }
+        if connection.text_input_focus == Some(self.id) {
+            unsafe {
+                connection.update_text_input();
+            }
+        }
+        drop(connection);
+
+        self.flush();
+    }
+
+    // The clipboard goes through `wl_data_device`, which is not bound yet, so
+    // it is always empty here.
+    fn get_clipboard_text(&mut self, _selection: Selection) -> Option<String> {
+        None
+    }
+
+    fn set_clipboard_text(&mut self, _selection: Selection, _text: &str) {}
+
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    fn close(&mut self) {
+        if !self.is_open {
+            return;
+        }
+
+        let mut connection = self.shared.borrow_mut();
+        if let Some(surface) = connection.surfaces.remove(&self.id) {
+            unsafe {
+                if surface.locked_pointer != std::ptr::null_mut() {
+                    wayland::zwp_locked_pointer_v1_destroy(surface.locked_pointer);
+                }
+                if surface.confined_pointer != std::ptr::null_mut() {
+                    wayland::zwp_confined_pointer_v1_destroy(surface.confined_pointer);
+                }
+                if surface.decoration != std::ptr::null_mut() {
+                    wayland::zxdg_toplevel_decoration_v1_destroy(surface.decoration);
+                }
+                if surface.fractional_scale != std::ptr::null_mut() {
+                    wayland::wp_fractional_scale_v1_destroy(surface.fractional_scale);
+                }
+                if surface.viewport != std::ptr::null_mut() {
+                    wayland::wp_viewport_destroy(surface.viewport);
+                }
+                wayland::xdg_toplevel_destroy(surface.xdg_toplevel);
+                wayland::xdg_surface_destroy(surface.xdg_surface);
+                wayland::wl_surface_destroy(surface.surface);
+                if surface.buffer != std::ptr::null_mut() {
+                    wayland::wl_buffer_destroy(surface.buffer);
+                }
+                if let Some(custom_cursor) = surface.custom_cursor {
+                    wayland::wl_buffer_destroy(custom_cursor.buffer);
+                }
+            }
+        }
+
+        if connection.pointer_focus == Some(self.id) {
+            connection.pointer_focus = None;
+        }
+        if connection.keyboard_focus == Some(self.id) {
+            connection.keyboard_focus = None;
+            connection.key_repeat = None;
+        }
+        if connection.text_input_focus == Some(self.id) {
+            connection.text_input_focus = None;
+            connection.has_preedit = false;
+        }
+        drop(connection);
+
+        self.flush();
+        self.is_open = false;
+    }
+
+    fn poll_events(&mut self) -> Events<'_> {
+        unsafe {
+            Connection::pump(&self.shared);
+        }
+        run_callbacks(&self.shared, |connection| &mut connection.events);
+        self.shared
+            .borrow_mut()
+            .events
+            .take_window(self.id, &mut self.events);
+
+        self.events.drain(..)
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        self.close();
+        self.shared.borrow_mut().events.remove_window(self.id);
+    }
+}
+
+// The surface lives until `close`, which cannot be called while a handle still
+// borrows the window.
+impl HasWindowHandle for Window {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        if !self.is_open {
+            return Err(HandleError::Unavailable);
+        }
+
+        let surface = NonNull::new(self.surface as *mut c_void).ok_or(HandleError::Unavailable)?;
+        unsafe {
+            Ok(WindowHandle::borrow_raw(
+                WaylandWindowHandle::new(surface).into(),
+            ))
+        }
+    }
+}
+
+impl HasDisplayHandle for Window {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        let display = self.shared.borrow().display;
+        let display = NonNull::new(display as *mut c_void).ok_or(HandleError::Unavailable)?;
+        unsafe {
+            Ok(DisplayHandle::borrow_raw(
+                WaylandDisplayHandle::new(display).into(),
+            ))
+        }
+    }
+}
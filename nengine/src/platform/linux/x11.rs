@@ -0,0 +1,3717 @@
+#[cfg(feature = "xim")]
+use self::ime::InputMethod;
+use super::keyboard::KeyboardMapping;
+use crate::platform::{
+    run_callbacks, CrossPlatformWindow, CursorGrab, CursorIcon, CustomCursor, EventQueue, Events,
+    FullscreenMode, Icon, Monitor, Selection, UserAttention, VideoMode, WindowBuilder, WindowError,
+    WindowEvents, WindowId,
+};
+use crate::{ffi::xcb, Event, MouseButton, ScrollUnit};
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle, XcbDisplayHandle,
+    XcbWindowHandle,
+};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{c_void, OsString};
+use std::num::NonZeroU32;
+use std::os::unix::ffi::OsStringExt;
+use std::path::PathBuf;
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "xim")]
+mod ime;
+
+/// Selections larger than this are sent in chunks of this size, with `INCR`.
+/// It is well below the smallest maximum request size an X server can have.
+const INCR_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How long to wait for the owner of a selection to send the next part of it
+/// before giving up.
+const SELECTION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The atoms the windows need. They are looked up once per connection.
+#[derive(Clone, Copy)]
+struct Atoms {
+    wm_protocols: xcb::xcb_atom_t,
+    wm_delete_window: xcb::xcb_atom_t,
+    wm_change_state: xcb::xcb_atom_t,
+    net_wm_name: xcb::xcb_atom_t,
+    net_wm_state: xcb::xcb_atom_t,
+    net_wm_state_fullscreen: xcb::xcb_atom_t,
+    net_wm_state_maximized_vert: xcb::xcb_atom_t,
+    net_wm_state_maximized_horz: xcb::xcb_atom_t,
+    net_wm_state_above: xcb::xcb_atom_t,
+    net_wm_state_demands_attention: xcb::xcb_atom_t,
+    net_wm_bypass_compositor: xcb::xcb_atom_t,
+    net_moveresize_window: xcb::xcb_atom_t,
+    net_wm_icon: xcb::xcb_atom_t,
+    motif_wm_hints: xcb::xcb_atom_t,
+    utf8_string: xcb::xcb_atom_t,
+    clipboard: xcb::xcb_atom_t,
+    targets: xcb::xcb_atom_t,
+    incr: xcb::xcb_atom_t,
+    /// The property on our windows that the selections of other clients are
+    /// written to.
+    nengine_selection: xcb::xcb_atom_t,
+    xdnd_aware: xcb::xcb_atom_t,
+    xdnd_enter: xcb::xcb_atom_t,
+    xdnd_position: xcb::xcb_atom_t,
+    xdnd_status: xcb::xcb_atom_t,
+    xdnd_leave: xcb::xcb_atom_t,
+    xdnd_drop: xcb::xcb_atom_t,
+    xdnd_finished: xcb::xcb_atom_t,
+    xdnd_selection: xcb::xcb_atom_t,
+    xdnd_action_copy: xcb::xcb_atom_t,
+    xdnd_type_list: xcb::xcb_atom_t,
+    text_uri_list: xcb::xcb_atom_t,
+}
+
+/// The connection to the X server and everything that belongs to it rather
+/// than to a single window. It is shared by every window of an event loop,
+/// since they all get their events through it.
+struct Connection {
+    raw: *mut xcb::xcb_connection_t,
+    screen: *mut xcb::xcb_screen_t,
+    atoms: Atoms,
+
+    keyboard_mapping: KeyboardMapping,
+
+    /// An event that was read ahead of time while looking for auto-repeat, t-
+    /// o be handled on the next call to `poll_events`.
+    pending_event: *mut xcb::xcb_generic_event_t,
+    /// The keycode of a key press that is known to be an auto-repeat.
+    repeated_keycode: Option<xcb::xcb_keycode_t>,
+
+    /// The last known size and position of every window, so that `Resized`
+    /// and `Moved` are only sent when something actually changed.
+    geometry: HashMap<xcb::xcb_window_t, ((u32, u32), (i32, i32))>,
+
+    /// The major opcode of XInput2, which raw motion comes from. It is None
+    /// if the X server does not have XInput2, then there is no `MouseDelta`.
+    xinput_opcode: Option<u8>,
+    /// The scroll valuators of every device, by device and valuator number.
+    scroll_axes: HashMap<(u16, u16), ScrollAxis>,
+    /// The time of the last smooth scroll. The X server also sends the scroll
+    /// as presses of buttons 4 to 7 for older clients, with the same time.
+    smooth_scroll_time: Option<xcb::xcb_timestamp_t>,
+    /// The window with the keyboard focus, which gets the raw motion and is
+    /// the only one that can hold a pointer grab.
+    focused_window: Option<xcb::xcb_window_t>,
+    /// The cursor grab every window asked for.
+    cursor_grabs: HashMap<xcb::xcb_window_t, CursorGrab>,
+    is_pointer_grabbed: bool,
+
+    /// The text of every selection a window of this connection owns, along
+    /// with that window.
+    selections: HashMap<xcb::xcb_atom_t, (xcb::xcb_window_t, String)>,
+    /// The selection of another client that is being read, if any.
+    incoming_selection: Option<IncomingSelection>,
+    /// Selections that are being sent to other clients in chunks.
+    outgoing_selections: Vec<OutgoingSelection>,
+    /// The time of the last input or property event. ICCCM wants selections
+    /// claimed with the time of the event that caused it rather than `Curre-
+    /// ntTime`, which is what 0 means until the first event comes.
+    last_timestamp: xcb::xcb_timestamp_t,
+
+    /// The cursor font, which the standard cursors are glyphs of. It is 0
+    /// until the first one is needed.
+    cursor_font: xcb::xcb_font_t,
+    /// The standard cursors that were created so far. They are shared by the
+    /// windows and live as long as the connection.
+    icon_cursors: HashMap<CursorIcon, xcb::xcb_cursor_t>,
+
+    /// The first event number of RandR, which says when the monitors change.
+    /// It is None if the X server does not have RandR 1.3, then the screen
+    /// is treated as a single monitor.
+    randr_first_event: Option<u8>,
+    /// The monitors as they were last looked up. They are looked up again
+    /// once RandR says they changed.
+    monitors: Option<Vec<Monitor>>,
+    /// The last scale factor of every window, so that `ScaleFactorChanged` is
+    /// only sent when it actually changed.
+    scale_factors: HashMap<xcb::xcb_window_t, f64>,
+
+    /// The files that are being dragged over one of the windows, if any.
+    drag: Option<Drag>,
+
+    #[cfg(feature = "xim")]
+    input_method: InputMethod,
+
+    events: EventQueue,
+}
+
+/// A drag of another client over one of our windows, as XDND tells about it.
+struct Drag {
+    /// The window of the client the drag comes from.
+    source: xcb::xcb_window_t,
+    window: xcb::xcb_window_t,
+    /// The version of XDND the source speaks.
+    version: u32,
+    /// Whether the source offers `text/uri-list`. Drags of anything else are
+    /// refused.
+    has_files: bool,
+    /// Where the pointer was last, in window coordinates.
+    position: (f64, f64),
+}
+
+/// A CRTC whose mode was switched by an exclusive fullscreen window, with
+/// what it has to be put back to.
+struct SwitchedCrtc {
+    crtc: xcb::xcb_randr_crtc_t,
+    mode: xcb::xcb_randr_mode_t,
+    position: (i16, i16),
+    rotation: u16,
+    outputs: Vec<xcb::xcb_randr_output_t>,
+}
+
+/// A selection that is being read from its owner. The owner writes it to a
+/// property of the window that asked, all at once or, if it is large, in
+/// chunks.
+struct IncomingSelection {
+    window: xcb::xcb_window_t,
+    selection: xcb::xcb_atom_t,
+    target: xcb::xcb_atom_t,
+    is_incremental: bool,
+    data: Vec<u8>,
+    /// When the owner last sent something, which the timeout counts from.
+    last_progress: Instant,
+    /// The text once the transfer is over. It holds None if the owner had no
+    /// text to give.
+    result: Option<Option<String>>,
+}
+
+/// A selection that is being sent to a client in chunks. The next chunk is
+/// written each time the client deletes the property it read the last one
+/// from, and an empty one ends the transfer.
+struct OutgoingSelection {
+    requestor: xcb::xcb_window_t,
+    property: xcb::xcb_atom_t,
+    target: xcb::xcb_atom_t,
+    data: Vec<u8>,
+    offset: usize,
+}
+
+/// A valuator that XInput2 scrolls with. Its value only ever adds up, so the
+/// scroll is the difference to the last one.
+struct ScrollAxis {
+    is_vertical: bool,
+    /// How much the value changes for one notch of a wheel.
+    increment: f64,
+    /// The last value seen, or None if it has to be read again first, since
+    /// the value changes while the pointer is over other windows too.
+    value: Option<f64>,
+}
+
+/// The X11 event loop.
+pub struct EventLoop {
+    connection: Rc<RefCell<Connection>>,
+    events: VecDeque<(WindowId, Event)>,
+}
+
+/// The X11 window.
+pub struct Window {
+    shared: Rc<RefCell<Connection>>,
+
+    // Copies of what the shared connection holds, so that the window does
+    // not have to borrow it for every request.
+    connection: *mut xcb::xcb_connection_t,
+    root: xcb::xcb_window_t,
+    atoms: Atoms,
+
+    raw_handle: xcb::xcb_window_t,
+    is_open: bool,
+    is_mapped: bool,
+
+    is_resizable: bool,
+    min_size: Option<(u32, u32)>,
+    max_size: Option<(u32, u32)>,
+    /// Where the window was asked to be created, which only matters until the
+    /// window manager places it.
+    initial_position: Option<(i32, i32)>,
+
+    // What the window manager is asked for. Until the window is mapped these
+    // are only written to the window properties by `show`.
+    fullscreen: FullscreenMode,
+    is_maximized: bool,
+    is_minimized: bool,
+    is_always_on_top: bool,
+    demands_attention: bool,
+    /// The size and position from before the window was maximized or made
+    /// fullscreen, which it goes back to afterwards.
+    windowed_geometry: Option<((u32, u32), (i32, i32))>,
+    /// The CRTC that was switched to another mode for exclusive fullscreen,
+    /// if any.
+    switched_crtc: Option<SwitchedCrtc>,
+
+    /// The colormap of a transparent window, which needs one to go with its
+    /// 32-bit visual. It is 0 for other windows.
+    colormap: xcb::xcb_colormap_t,
+
+    /// An empty cursor, created the first time the cursor is hidden.
+    hidden_cursor: xcb::xcb_cursor_t,
+    /// The cursor shown while the cursor is visible. `XCB_CURSOR_NONE` means
+    /// the cursor of the parent window, which is the desktop's arrow.
+    cursor: xcb::xcb_cursor_t,
+    /// The cursor made from the last custom cursor image, which belongs to
+    /// the window. It is 0 if there is none.
+    custom_cursor: xcb::xcb_cursor_t,
+    is_cursor_visible: bool,
+
+    /// The events of this window that were taken from the shared queue.
+    events: VecDeque<Event>,
+}
+
+unsafe fn get_xcb_atom(
+    connection: *mut xcb::xcb_connection_t,
+    name: &str,
+) -> Result<xcb::xcb_atom_t, WindowError> {
+    let cookie = xcb::xcb_intern_atom(
+        connection,
+        0,
+        name.len().try_into().unwrap(),
+        name.as_ptr() as *const i8,
+    );
+    let reply = xcb::xcb_intern_atom_reply(connection, cookie, std::ptr::null_mut());
+    if reply == std::ptr::null_mut() {
+        return Err(WindowError::AtomLookupFailed(name.to_owned()));
+    }
+
+    let atom = (*reply).atom;
+    xcb::free(reply as *mut c_void);
+
+    Ok(atom)
+}
+
+/// Asks XInput2 to send the given events of every master device that happen
+/// in a window.
+unsafe fn select_xinput_events(
+    connection: *mut xcb::xcb_connection_t,
+    window: xcb::xcb_window_t,
+    mask: u32,
+) {
+    // The mask itself directly follows its header.
+    #[repr(C)]
+    struct EventMask {
+        header: xcb::xcb_input_event_mask_t,
+        mask: u32,
+    }
+
+    let event_mask = EventMask {
+        header: xcb::xcb_input_event_mask_t {
+            deviceid: xcb::XCB_INPUT_DEVICE_ALL_MASTER.try_into().unwrap(),
+            mask_len: 1,
+        },
+        mask,
+    };
+    xcb::xcb_input_xi_select_events(connection, window, 1, &event_mask.header);
+}
+
+/// Asks XInput2 for the raw motion of every pointer. Raw events are only sent
+/// to the root window, so this is done once per connection, along with the
+/// changes of devices. Returns the major opcode of XInput2, or None if the X
+/// server does not have version 2.0.
+unsafe fn select_raw_motion(
+    connection: *mut xcb::xcb_connection_t,
+    root: xcb::xcb_window_t,
+) -> Option<u8> {
+    let extension =
+        xcb::xcb_get_extension_data(connection, std::ptr::addr_of_mut!(xcb::xcb_input_id));
+    if extension == std::ptr::null() || (*extension).present == 0 {
+        return None;
+    }
+
+    // Smooth scrolling needs 2.1, the server answers with a lower version if
+    // that is all it has.
+    let cookie = xcb::xcb_input_xi_query_version(connection, 2, 1);
+    let reply = xcb::xcb_input_xi_query_version_reply(connection, cookie, std::ptr::null_mut());
+    if reply == std::ptr::null_mut() {
+        return None;
+    }
+
+    let major_version = (*reply).major_version;
+    xcb::free(reply as *mut c_void);
+    if major_version < 2 {
+        return None;
+    }
+
+    select_xinput_events(
+        connection,
+        root,
+        xcb::XCB_INPUT_XI_EVENT_MASK_RAW_MOTION | xcb::XCB_INPUT_XI_EVENT_MASK_DEVICE_CHANGED,
+    );
+
+    Some((*extension).major_opcode)
+}
+
+/// Turns a 32.32 fixed-point number, which XInput2 sends valuators as, into a
+/// float.
+fn fp3232_to_f64(value: xcb::xcb_input_fp3232_t) -> f64 {
+    f64::from(value.integral) + f64::from(value.frac) / 4294967296.0
+}
+
+/// Checks if a valuator is set in the valuator mask of an XInput2 event. Only
+/// those come with a value.
+fn is_valuator_set(mask: &[u32], number: usize) -> bool {
+    mask.get(number / 32)
+        .map_or(false, |bits| bits & (1 << (number % 32)) != 0)
+}
+
+/// Reads the motion out of an XInput2 raw motion event. Values are only sent
+/// for the axes set in the valuator mask, and the first two axes of a pointer
+/// are x and y.
+unsafe fn read_raw_motion(event: *const xcb::xcb_input_raw_motion_event_t) -> (f64, f64) {
+    let mask = std::slice::from_raw_parts(
+        xcb::xcb_input_raw_button_press_valuator_mask(event),
+        xcb::xcb_input_raw_button_press_valuator_mask_length(event) as usize,
+    );
+    let mut values = xcb::xcb_input_raw_button_press_axisvalues_raw(event);
+
+    let mut delta = [0.0; 2];
+    for (axis, value) in delta.iter_mut().enumerate() {
+        if is_valuator_set(mask, axis) {
+            *value = fp3232_to_f64(*values);
+            values = values.add(1);
+        }
+    }
+
+    (delta[0], delta[1])
+}
+
+/// Looks for a 32-bit visual on the screen, which is what a window needs for
+/// its alpha channel to reach the compositor.
+unsafe fn find_argb_visual(screen: *mut xcb::xcb_screen_t) -> Option<xcb::xcb_visualid_t> {
+    let mut depths = xcb::xcb_screen_allowed_depths_iterator(screen);
+    while depths.rem > 0 {
+        if (*depths.data).depth == 32 {
+            let visuals = xcb::xcb_depth_visuals_iterator(depths.data);
+            if visuals.rem > 0 {
+                return Some((*visuals.data).visual_id);
+            }
+        }
+
+        xcb::xcb_depth_next(&mut depths);
+    }
+
+    None
+}
+
+/// Asks RandR to say when the monitors change. Returns the first event number
+/// of RandR, or None if the X server does not have version 1.3, which is the
+/// first one that can look up the monitors without probing them.
+unsafe fn select_screen_changes(
+    connection: *mut xcb::xcb_connection_t,
+    root: xcb::xcb_window_t,
+) -> Option<u8> {
+    let extension =
+        xcb::xcb_get_extension_data(connection, std::ptr::addr_of_mut!(xcb::xcb_randr_id));
+    if extension == std::ptr::null() || (*extension).present == 0 {
+        return None;
+    }
+
+    let cookie = xcb::xcb_randr_query_version(connection, 1, 3);
+    let reply = xcb::xcb_randr_query_version_reply(connection, cookie, std::ptr::null_mut());
+    if reply == std::ptr::null_mut() {
+        return None;
+    }
+
+    let version = ((*reply).major_version, (*reply).minor_version);
+    xcb::free(reply as *mut c_void);
+    if version < (1, 3) {
+        return None;
+    }
+
+    xcb::xcb_randr_select_input(
+        connection,
+        root,
+        xcb::XCB_RANDR_NOTIFY_MASK_SCREEN_CHANGE.try_into().unwrap(),
+    );
+
+    Some((*extension).first_event)
+}
+
+/// Reads `Xft.dpi` from the resources on the root window, which is where de-
+/// sktops put the DPI the user picked.
+unsafe fn read_xft_dpi(
+    connection: *mut xcb::xcb_connection_t,
+    root: xcb::xcb_window_t,
+) -> Option<f64> {
+    let cookie = xcb::xcb_get_property(
+        connection,
+        0,
+        root,
+        xcb::XCB_ATOM_RESOURCE_MANAGER,
+        xcb::XCB_ATOM_STRING,
+        0,
+        u32::MAX / 4,
+    );
+    let reply = xcb::xcb_get_property_reply(connection, cookie, std::ptr::null_mut());
+    if reply == std::ptr::null_mut() {
+        return None;
+    }
+
+    let resources = std::slice::from_raw_parts(
+        xcb::xcb_get_property_value(reply) as *const u8,
+        xcb::xcb_get_property_value_length(reply) as usize,
+    );
+    let dpi = parse_xft_dpi(&String::from_utf8_lossy(resources));
+    xcb::free(reply as *mut c_void);
+
+    dpi
+}
+
+/// Looks for `Xft.dpi` in resources in the format of `xrdb`, one `name: val-
+/// ue` per line.
+fn parse_xft_dpi(resources: &str) -> Option<f64> {
+    resources.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if name.trim() != "Xft.dpi" {
+            return None;
+        }
+
+        value.trim().parse().ok().filter(|&dpi: &f64| dpi > 0.0)
+    })
+}
+
+/// Works out a scale factor from the size of a monitor, rounded to a half so
+/// that pixels line up. Monitors that say they are smaller than a centimeter
+/// are lying, then there is nothing to go on.
+fn scale_factor_from_size(size: (u32, u32), physical_size: (u32, u32)) -> Option<f64> {
+    if physical_size.0 < 10 || physical_size.1 < 10 {
+        return None;
+    }
+
+    let dpi = f64::from(size.0) * 25.4 / f64::from(physical_size.0);
+    Some(((dpi / 96.0) * 2.0).round().max(2.0) / 2.0)
+}
+
+/// Turns a RandR mode into a video mode. The refresh rate is the pixel clock
+/// over the pixels in a frame, including the blanking around it.
+fn to_video_mode(mode: &xcb::xcb_randr_mode_info_t, bit_depth: u16) -> VideoMode {
+    let mut lines = u64::from(mode.vtotal);
+    if mode.mode_flags & xcb::XCB_RANDR_MODE_FLAG_DOUBLE_SCAN != 0 {
+        lines *= 2;
+    }
+    if mode.mode_flags & xcb::XCB_RANDR_MODE_FLAG_INTERLACE != 0 {
+        lines /= 2;
+    }
+
+    let pixels = u64::from(mode.htotal) * lines;
+    let refresh_rate_millihertz = if pixels == 0 {
+        0
+    } else {
+        (u64::from(mode.dot_clock) * 1000 / pixels) as u32
+    };
+
+    VideoMode {
+        size: (mode.width.into(), mode.height.into()),
+        bit_depth,
+        refresh_rate_millihertz,
+    }
+}
+
+/// Clamps a size to the 16 bits the protocol has for it, which is plenty for
+/// any screen. Sent as they are, larger values would wrap.
+fn clamp_size((width, height): (u32, u32)) -> (u32, u32) {
+    (width.min(u16::MAX.into()), height.min(u16::MAX.into()))
+}
+
+/// Clamps a position to the 16 bits the protocol has for it, like sizes.
+fn clamp_position((x, y): (i32, i32)) -> (i32, i32) {
+    let clamp_to_i16 = |value: i32| value.clamp(i16::MIN.into(), i16::MAX.into());
+    (clamp_to_i16(x), clamp_to_i16(y))
+}
+
+/// Returns the monitor that most of a rectangle on the desktop is on, or the
+/// first one if the rectangle is on none of them.
+fn monitor_at(monitors: &[Monitor], position: (i32, i32), size: (u32, u32)) -> Option<&Monitor> {
+    let overlap = |monitor: &Monitor| {
+        let overlap_on = |start: i32, length: u32, monitor_start: i32, monitor_length: u32| {
+            let end = i64::from(start) + i64::from(length);
+            let monitor_end = i64::from(monitor_start) + i64::from(monitor_length);
+            (end.min(monitor_end) - i64::from(start.max(monitor_start))).max(0)
+        };
+
+        overlap_on(position.0, size.0, monitor.position.0, monitor.size.0)
+            * overlap_on(position.1, size.1, monitor.position.1, monitor.size.1)
+    };
+
+    monitors
+        .iter()
+        .max_by_key(|monitor| overlap(monitor))
+        .filter(|monitor| overlap(monitor) > 0)
+        .or_else(|| monitors.first())
+}
+
+/// Returns the glyph of a standard cursor in the cursor font, as numbered in
+/// `X11/cursorfont.h`. The glyph after it is its mask.
+fn cursor_font_glyph(icon: CursorIcon) -> u16 {
+    match icon {
+        CursorIcon::Default => 68,    // left_ptr
+        CursorIcon::Text => 152,      // xterm
+        CursorIcon::Pointer => 60,    // hand2
+        CursorIcon::Crosshair => 34,  // crosshair
+        CursorIcon::Wait => 150,      // watch
+        CursorIcon::NotAllowed => 24, // circle
+        CursorIcon::Move => 52,       // fleur
+        CursorIcon::EwResize => 108,  // sb_h_double_arrow
+        CursorIcon::NsResize => 116,  // sb_v_double_arrow
+        CursorIcon::NwseResize => 14, // bottom_right_corner
+        CursorIcon::NeswResize => 12, // bottom_left_corner
+    }
+}
+
+/// Looks for the picture format that holds 32-bit ARGB pixels, which is what
+/// the RENDER extension makes cursors with alpha from.
+unsafe fn find_argb_format(
+    connection: *mut xcb::xcb_connection_t,
+) -> Option<xcb::xcb_render_pictformat_t> {
+    let cookie = xcb::xcb_render_query_pict_formats(connection);
+    let reply = xcb::xcb_render_query_pict_formats_reply(connection, cookie, std::ptr::null_mut());
+    if reply == std::ptr::null_mut() {
+        return None;
+    }
+
+    let mut format = None;
+    let mut formats = xcb::xcb_render_query_pict_formats_formats_iterator(reply);
+    while formats.rem > 0 {
+        let info = *formats.data;
+        let direct = info.direct;
+        if u32::from(info.type_) == xcb::XCB_RENDER_PICT_TYPE_DIRECT
+            && info.depth == 32
+            && direct.alpha_shift == 24
+            && direct.alpha_mask == 0xFF
+            && direct.red_shift == 16
+            && direct.red_mask == 0xFF
+            && direct.green_shift == 8
+            && direct.green_mask == 0xFF
+            && direct.blue_shift == 0
+            && direct.blue_mask == 0xFF
+        {
+            format = Some(info.id);
+            break;
+        }
+
+        xcb::xcb_render_pictforminfo_next(&mut formats);
+    }
+    xcb::free(reply as *mut c_void);
+
+    format
+}
+
+/// Makes a cursor out of an image with the RENDER extension. The core proto-
+/// col only has cursors with two colors, so there are no custom cursors wi-
+/// thout RENDER.
+unsafe fn create_render_cursor(
+    connection: *mut xcb::xcb_connection_t,
+    root: xcb::xcb_window_t,
+    cursor: &CustomCursor,
+) -> Result<xcb::xcb_cursor_t, WindowError> {
+    let extension =
+        xcb::xcb_get_extension_data(connection, std::ptr::addr_of_mut!(xcb::xcb_render_id));
+    if extension == std::ptr::null() || (*extension).present == 0 {
+        return Err(WindowError::Unsupported("the RENDER extension"));
+    }
+    let format = find_argb_format(connection).ok_or(WindowError::Unsupported("ARGB cursors"))?;
+
+    // Rows are placed with 16-bit signed positions.
+    let too_large = |_| WindowError::Unsupported("cursors larger than 32767 pixels");
+    let width: i16 = cursor.width.try_into().map_err(too_large)?;
+    let height: i16 = cursor.height.try_into().map_err(too_large)?;
+
+    // Images larger than the largest request the X server takes are sent in
+    // strips of rows. Its length is in units of 4 bytes and includes the 24
+    // bytes of the request itself.
+    let row_size = cursor.width as usize * 4;
+    let max_length = xcb::xcb_get_maximum_request_length(connection) as usize;
+    let max_size = (max_length * 4).saturating_sub(24);
+    let rows_per_request = max_size / row_size;
+    if rows_per_request == 0 {
+        return Err(WindowError::Unsupported("cursors this wide"));
+    }
+
+    // ARGB pixels in the byte order of pretty much every X server are BGRA.
+    let pixels = cursor.premultiplied_bgra();
+    let pixmap = xcb::xcb_generate_id(connection);
+    xcb::xcb_create_pixmap(connection, 32, pixmap, root, width as u16, height as u16);
+    let gc = xcb::xcb_generate_id(connection);
+    xcb::xcb_create_gc(connection, gc, pixmap, 0, std::ptr::null());
+    for (index, rows) in pixels.chunks(row_size * rows_per_request).enumerate() {
+        xcb::xcb_put_image(
+            connection,
+            xcb::XCB_IMAGE_FORMAT_Z_PIXMAP.try_into().unwrap(),
+            pixmap,
+            gc,
+            width as u16,
+            (rows.len() / row_size) as u16,
+            0,
+            (index * rows_per_request) as i16,
+            0,
+            32,
+            rows.len().try_into().unwrap(),
+            rows.as_ptr(),
+        );
+    }
+
+    let picture = xcb::xcb_generate_id(connection);
+    xcb::xcb_render_create_picture(connection, picture, pixmap, format, 0, std::ptr::null());
+
+    let created = xcb::xcb_generate_id(connection);
+    xcb::xcb_render_create_cursor(
+        connection,
+        created,
+        picture,
+        cursor.hotspot.0.try_into().unwrap(),
+        cursor.hotspot.1.try_into().unwrap(),
+    );
+
+    // The cursor keeps its own copy of the image.
+    xcb::xcb_render_free_picture(connection, picture);
+    xcb::xcb_free_gc(connection, gc);
+    xcb::xcb_free_pixmap(connection, pixmap);
+
+    Ok(created)
+}
+
+/// Translates an X11 button. 4 to 7 are skipped because X11 uses them to send
+/// scroll events, and there is nothing to translate buttons past 10 to.
+fn translate_xcb_buttons(xcb_button_code: u8) -> Option<MouseButton> {
+    match xcb_button_code {
+        1 => Some(MouseButton::Left),
+        2 => Some(MouseButton::Middle),
+        3 => Some(MouseButton::Right),
+        8 => Some(MouseButton::Eight),
+        9 => Some(MouseButton::Nine),
+        10 => Some(MouseButton::Ten),
+        _ => None,
+    }
+}
+
+/// Turns a press of one of the scroll buttons into notches of the wheel. 6
+/// and 7 are the horizontal wheel, or a wheel that is tilted.
+fn xcb_button_to_scroll(xcb_button_code: u8) -> Option<(f64, f64)> {
+    match xcb_button_code {
+        4 => Some((0.0, 1.0)),
+        5 => Some((0.0, -1.0)),
+        6 => Some((-1.0, 0.0)),
+        7 => Some((1.0, 0.0)),
+        _ => None,
+    }
+}
+
+impl Atoms {
+    unsafe fn new(connection: *mut xcb::xcb_connection_t) -> Result<Atoms, WindowError> {
+        Ok(Atoms {
+            wm_protocols: get_xcb_atom(connection, "WM_PROTOCOLS")?,
+            wm_delete_window: get_xcb_atom(connection, "WM_DELETE_WINDOW")?,
+            wm_change_state: get_xcb_atom(connection, "WM_CHANGE_STATE")?,
+            net_wm_name: get_xcb_atom(connection, "_NET_WM_NAME")?,
+            net_wm_state: get_xcb_atom(connection, "_NET_WM_STATE")?,
+            net_wm_state_fullscreen: get_xcb_atom(connection, "_NET_WM_STATE_FULLSCREEN")?,
+            net_wm_state_maximized_vert: get_xcb_atom(connection, "_NET_WM_STATE_MAXIMIZED_VERT")?,
+            net_wm_state_maximized_horz: get_xcb_atom(connection, "_NET_WM_STATE_MAXIMIZED_HORZ")?,
+            net_wm_state_above: get_xcb_atom(connection, "_NET_WM_STATE_ABOVE")?,
+            net_wm_state_demands_attention: get_xcb_atom(
+                connection,
+                "_NET_WM_STATE_DEMANDS_ATTENTION",
+            )?,
+            net_wm_bypass_compositor: get_xcb_atom(connection, "_NET_WM_BYPASS_COMPOSITOR")?,
+            net_moveresize_window: get_xcb_atom(connection, "_NET_MOVERESIZE_WINDOW")?,
+            net_wm_icon: get_xcb_atom(connection, "_NET_WM_ICON")?,
+            motif_wm_hints: get_xcb_atom(connection, "_MOTIF_WM_HINTS")?,
+            utf8_string: get_xcb_atom(connection, "UTF8_STRING")?,
+            clipboard: get_xcb_atom(connection, "CLIPBOARD")?,
+            targets: get_xcb_atom(connection, "TARGETS")?,
+            incr: get_xcb_atom(connection, "INCR")?,
+            nengine_selection: get_xcb_atom(connection, "NENGINE_SELECTION")?,
+            xdnd_aware: get_xcb_atom(connection, "XdndAware")?,
+            xdnd_enter: get_xcb_atom(connection, "XdndEnter")?,
+            xdnd_position: get_xcb_atom(connection, "XdndPosition")?,
+            xdnd_status: get_xcb_atom(connection, "XdndStatus")?,
+            xdnd_leave: get_xcb_atom(connection, "XdndLeave")?,
+            xdnd_drop: get_xcb_atom(connection, "XdndDrop")?,
+            xdnd_finished: get_xcb_atom(connection, "XdndFinished")?,
+            xdnd_selection: get_xcb_atom(connection, "XdndSelection")?,
+            xdnd_action_copy: get_xcb_atom(connection, "XdndActionCopy")?,
+            xdnd_type_list: get_xcb_atom(connection, "XdndTypeList")?,
+            text_uri_list: get_xcb_atom(connection, "text/uri-list")?,
+        })
+    }
+
+    fn selection(&self, selection: Selection) -> xcb::xcb_atom_t {
+        match selection {
+            Selection::Clipboard => self.clipboard,
+            Selection::Primary => xcb::XCB_ATOM_PRIMARY,
+        }
+    }
+}
+
+/// Waits until the X server sends something or the timeout is over. Events
+/// that were already read have to be handled first, the wait does not see
+/// them.
+unsafe fn wait_for_events(connection: *mut xcb::xcb_connection_t, timeout: Duration) {
+    xcb::xcb_flush(connection);
+
+    let mut poll_fd = xcb::pollfd {
+        fd: xcb::xcb_get_file_descriptor(connection),
+        events: xcb::POLLIN as i16,
+        revents: 0,
+    };
+    xcb::poll(
+        &mut poll_fd,
+        1,
+        timeout.as_millis().try_into().unwrap_or(i32::MAX),
+    );
+}
+
+/// Turns the contents of a selection into text. `STRING` is Latin-1, whose
+/// bytes are the first 256 code points.
+fn decode_selection(target: xcb::xcb_atom_t, data: &[u8]) -> String {
+    if target == xcb::XCB_ATOM_STRING {
+        data.iter().map(|&byte| char::from(byte)).collect()
+    } else {
+        String::from_utf8_lossy(data).into_owned()
+    }
+}
+
+/// Turns a `text/uri-list` into paths. Only `file` URIs are paths, the other
+/// ones are left out along with the comments.
+fn parse_uri_list(data: &[u8]) -> Vec<PathBuf> {
+    data.split(|&byte| byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.starts_with(b"#"))
+        .filter_map(|uri| uri.strip_prefix(b"file://"))
+        .filter_map(|uri| {
+            // The host comes before the path, and is usually empty.
+            let path = &uri[uri.iter().position(|&byte| byte == b'/')?..];
+            Some(PathBuf::from(OsString::from_vec(decode_percent(path))))
+        })
+        .collect()
+}
+
+/// Replaces every `%` and two hex digits with the byte they stand for.
+fn decode_percent(text: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(text.len());
+    let mut index = 0;
+    while index < text.len() {
+        let byte = match text.get(index + 1..index + 3) {
+            Some(hex) if text[index] == b'%' && hex.iter().all(u8::is_ascii_hexdigit) => {
+                index += 3;
+                u8::from_str_radix(std::str::from_utf8(hex).unwrap(), 16).unwrap()
+            }
+            _ => {
+                index += 1;
+                text[index - 1]
+            }
+        };
+        decoded.push(byte);
+    }
+
+    decoded
+}
+
+/// Turns text into `STRING`, which is Latin-1. Characters it does not have
+/// become question marks.
+fn encode_latin1(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|character| u8::try_from(u32::from(character)).unwrap_or(b'?'))
+        .collect()
+}
+
+impl Connection {
+    fn open() -> Result<Connection, WindowError> {
+        // Without `DISPLAY`, `xcb_connect` fails with a parse error that does
+        // not say much.
+        if std::env::var_os("DISPLAY").is_none() {
+            return Err(WindowError::NoDisplay);
+        }
+
+        unsafe {
+            let connection = xcb::xcb_connect(std::ptr::null(), std::ptr::null_mut());
+
+            // A connection is returned even if it failed and still has to be
+            // freed.
+            let error = xcb::xcb_connection_has_error(connection);
+            if error != 0 {
+                xcb::xcb_disconnect(connection);
+                return Err(WindowError::ConnectionFailed(error));
+            }
+
+            let setup = || -> Result<(Atoms, KeyboardMapping), WindowError> {
+                Ok((Atoms::new(connection)?, KeyboardMapping::new(connection)?))
+            };
+
+            let screen = xcb::xcb_setup_roots_iterator(xcb::xcb_get_setup(connection)).data;
+
+            match setup() {
+                Ok((atoms, keyboard_mapping)) => {
+                    let mut opened = Connection {
+                        raw: connection,
+                        screen,
+                        atoms,
+                        keyboard_mapping,
+                        pending_event: std::ptr::null_mut(),
+                        repeated_keycode: None,
+                        geometry: HashMap::new(),
+                        xinput_opcode: select_raw_motion(connection, (*screen).root),
+                        scroll_axes: HashMap::new(),
+                        smooth_scroll_time: None,
+                        focused_window: None,
+                        cursor_grabs: HashMap::new(),
+                        is_pointer_grabbed: false,
+                        selections: HashMap::new(),
+                        incoming_selection: None,
+                        outgoing_selections: Vec::new(),
+                        last_timestamp: xcb::XCB_CURRENT_TIME,
+                        cursor_font: 0,
+                        icon_cursors: HashMap::new(),
+                        randr_first_event: select_screen_changes(connection, (*screen).root),
+                        monitors: None,
+                        scale_factors: HashMap::new(),
+                        drag: None,
+                        #[cfg(feature = "xim")]
+                        input_method: InputMethod::new(),
+                        events: EventQueue::new(),
+                    };
+                    opened.update_scroll_axes();
+
+                    Ok(opened)
+                }
+                Err(error) => {
+                    xcb::xcb_disconnect(connection);
+                    Err(error)
+                }
+            }
+        }
+    }
+
+    /// Returns the monitors, looking them up if they changed since the last
+    /// time.
+    unsafe fn monitors(&mut self) -> &[Monitor] {
+        if self.monitors.is_none() {
+            self.monitors = Some(self.query_monitors());
+        }
+
+        self.monitors.as_deref().unwrap_or_default()
+    }
+
+    /// Looks up the monitors with RandR. Without it the whole screen counts
+    /// as one monitor.
+    unsafe fn query_monitors(&self) -> Vec<Monitor> {
+        let root = (*self.screen).root;
+        let bit_depth = (*self.screen).root_depth.into();
+        // The DPI the user picked goes for every monitor. Only without one is
+        // the scale factor guessed from the size the monitor says it has.
+        let xft_dpi = read_xft_dpi(self.raw, root);
+        let scale_factor = |size, physical_size| {
+            xft_dpi
+                .map(|dpi| dpi / 96.0)
+                .or_else(|| scale_factor_from_size(size, physical_size))
+                .unwrap_or(1.0)
+        };
+
+        if self.randr_first_event.is_none() {
+            let size = (
+                (*self.screen).width_in_pixels.into(),
+                (*self.screen).height_in_pixels.into(),
+            );
+            let physical_size = (
+                (*self.screen).width_in_millimeters.into(),
+                (*self.screen).height_in_millimeters.into(),
+            );
+
+            return vec![Monitor {
+                name: String::new(),
+                position: (0, 0),
+                size,
+                physical_size,
+                refresh_rate_millihertz: 0,
+                scale_factor: scale_factor(size, physical_size),
+                is_primary: true,
+                video_modes: vec![VideoMode {
+                    size,
+                    bit_depth,
+                    refresh_rate_millihertz: 0,
+                }],
+            }];
+        }
+
+        let cookie = xcb::xcb_randr_get_screen_resources_current(self.raw, root);
+        let resources = xcb::xcb_randr_get_screen_resources_current_reply(
+            self.raw,
+            cookie,
+            std::ptr::null_mut(),
+        );
+        if resources == std::ptr::null_mut() {
+            return Vec::new();
+        }
+
+        let cookie = xcb::xcb_randr_get_output_primary(self.raw, root);
+        let reply = xcb::xcb_randr_get_output_primary_reply(self.raw, cookie, std::ptr::null_mut());
+        let primary_output = if reply == std::ptr::null_mut() {
+            0
+        } else {
+            let output = (*reply).output;
+            xcb::free(reply as *mut c_void);
+            output
+        };
+
+        let modes = std::slice::from_raw_parts(
+            xcb::xcb_randr_get_screen_resources_current_modes(resources),
+            xcb::xcb_randr_get_screen_resources_current_modes_length(resources) as usize,
+        );
+        let outputs = std::slice::from_raw_parts(
+            xcb::xcb_randr_get_screen_resources_current_outputs(resources),
+            xcb::xcb_randr_get_screen_resources_current_outputs_length(resources) as usize,
+        );
+        let timestamp = (*resources).config_timestamp;
+
+        let mut monitors = Vec::new();
+        for &output in outputs {
+            let cookie = xcb::xcb_randr_get_output_info(self.raw, output, timestamp);
+            let info = xcb::xcb_randr_get_output_info_reply(self.raw, cookie, std::ptr::null_mut());
+            if info == std::ptr::null_mut() {
+                continue;
+            }
+
+            // Outputs without a CRTC are connected but turned off.
+            let crtc = (*info).crtc;
+            if u32::from((*info).connection) != xcb::XCB_RANDR_CONNECTION_CONNECTED || crtc == 0 {
+                xcb::free(info as *mut c_void);
+                continue;
+            }
+
+            let cookie = xcb::xcb_randr_get_crtc_info(self.raw, crtc, timestamp);
+            let crtc_info =
+                xcb::xcb_randr_get_crtc_info_reply(self.raw, cookie, std::ptr::null_mut());
+            if crtc_info == std::ptr::null_mut() {
+                xcb::free(info as *mut c_void);
+                continue;
+            }
+
+            let name = String::from_utf8_lossy(std::slice::from_raw_parts(
+                xcb::xcb_randr_get_output_info_name(info),
+                xcb::xcb_randr_get_output_info_name_length(info) as usize,
+            ))
+            .into_owned();
+            let output_modes = std::slice::from_raw_parts(
+                xcb::xcb_randr_get_output_info_modes(info),
+                xcb::xcb_randr_get_output_info_modes_length(info) as usize,
+            );
+            let video_modes = output_modes
+                .iter()
+                .filter_map(|&id| modes.iter().find(|mode| mode.id == id))
+                .map(|mode| to_video_mode(mode, bit_depth))
+                .collect();
+            let refresh_rate_millihertz = modes
+                .iter()
+                .find(|mode| mode.id == (*crtc_info).mode)
+                .map_or(0, |mode| {
+                    to_video_mode(mode, bit_depth).refresh_rate_millihertz
+                });
+
+            let size = ((*crtc_info).width.into(), (*crtc_info).height.into());
+            let physical_size = ((*info).mm_width, (*info).mm_height);
+            monitors.push(Monitor {
+                name,
+                position: ((*crtc_info).x.into(), (*crtc_info).y.into()),
+                size,
+                physical_size,
+                refresh_rate_millihertz,
+                scale_factor: scale_factor(size, physical_size),
+                is_primary: output == primary_output,
+                video_modes,
+            });
+
+            xcb::free(crtc_info as *mut c_void);
+            xcb::free(info as *mut c_void);
+        }
+        xcb::free(resources as *mut c_void);
+
+        monitors
+    }
+
+    /// Switches the CRTC that shows a point of the desktop to the RandR mode
+    /// that matches a video mode. Returns the CRTC as it was before, or None
+    /// if no CRTC shows the point, none of the modes of its outputs match or
+    /// the mode would not fit into the screen.
+    unsafe fn switch_crtc_mode(
+        &self,
+        point: (i32, i32),
+        video_mode: VideoMode,
+    ) -> Option<SwitchedCrtc> {
+        self.randr_first_event?;
+
+        let root = (*self.screen).root;
+        let bit_depth = (*self.screen).root_depth.into();
+        let cookie = xcb::xcb_randr_get_screen_resources_current(self.raw, root);
+        let resources = xcb::xcb_randr_get_screen_resources_current_reply(
+            self.raw,
+            cookie,
+            std::ptr::null_mut(),
+        );
+        if resources == std::ptr::null_mut() {
+            return None;
+        }
+
+        let modes = std::slice::from_raw_parts(
+            xcb::xcb_randr_get_screen_resources_current_modes(resources),
+            xcb::xcb_randr_get_screen_resources_current_modes_length(resources) as usize,
+        );
+        let crtcs = std::slice::from_raw_parts(
+            xcb::xcb_randr_get_screen_resources_current_crtcs(resources),
+            xcb::xcb_randr_get_screen_resources_current_crtcs_length(resources) as usize,
+        );
+        let timestamp = (*resources).config_timestamp;
+
+        let mut switched = None;
+        for &crtc in crtcs {
+            let cookie = xcb::xcb_randr_get_crtc_info(self.raw, crtc, timestamp);
+            let info = xcb::xcb_randr_get_crtc_info_reply(self.raw, cookie, std::ptr::null_mut());
+            if info == std::ptr::null_mut() {
+                continue;
+            }
+
+            let (x, y) = (i32::from((*info).x), i32::from((*info).y));
+            let (width, height) = (i32::from((*info).width), i32::from((*info).height));
+            let shows_point = (*info).mode != 0
+                && (x..x + width).contains(&point.0)
+                && (y..y + height).contains(&point.1);
+            if !shows_point {
+                xcb::free(info as *mut c_void);
+                continue;
+            }
+
+            let crtc = SwitchedCrtc {
+                crtc,
+                mode: (*info).mode,
+                position: ((*info).x, (*info).y),
+                rotation: (*info).rotation,
+                outputs: std::slice::from_raw_parts(
+                    xcb::xcb_randr_get_crtc_info_outputs(info),
+                    xcb::xcb_randr_get_crtc_info_outputs_length(info) as usize,
+                )
+                .to_vec(),
+            };
+            xcb::free(info as *mut c_void);
+
+            let fits = |mode: &xcb::xcb_randr_mode_info_t| {
+                x + i32::from(mode.width) <= i32::from((*self.screen).width_in_pixels)
+                    && y + i32::from(mode.height) <= i32::from((*self.screen).height_in_pixels)
+            };
+            let mode = modes.iter().find(|&mode| {
+                to_video_mode(mode, bit_depth) == video_mode
+                    && fits(mode)
+                    && crtc
+                        .outputs
+                        .iter()
+                        .all(|&output| self.output_has_mode(output, mode.id, timestamp))
+            });
+
+            if let Some(mode) = mode {
+                if self.set_crtc_mode(&crtc, mode.id) {
+                    switched = Some(crtc);
+                }
+            }
+            break;
+        }
+        xcb::free(resources as *mut c_void);
+
+        switched
+    }
+
+    /// Checks if an output can show a RandR mode.
+    unsafe fn output_has_mode(
+        &self,
+        output: xcb::xcb_randr_output_t,
+        mode: xcb::xcb_randr_mode_t,
+        timestamp: xcb::xcb_timestamp_t,
+    ) -> bool {
+        let cookie = xcb::xcb_randr_get_output_info(self.raw, output, timestamp);
+        let info = xcb::xcb_randr_get_output_info_reply(self.raw, cookie, std::ptr::null_mut());
+        if info == std::ptr::null_mut() {
+            return false;
+        }
+
+        let has_mode = std::slice::from_raw_parts(
+            xcb::xcb_randr_get_output_info_modes(info),
+            xcb::xcb_randr_get_output_info_modes_length(info) as usize,
+        )
+        .contains(&mode);
+        xcb::free(info as *mut c_void);
+
+        has_mode
+    }
+
+    /// Puts a CRTC into a mode, keeping where it is and what it shows. Retu-
+    /// rns whether the X server did it.
+    unsafe fn set_crtc_mode(&self, crtc: &SwitchedCrtc, mode: xcb::xcb_randr_mode_t) -> bool {
+        // The request is refused unless it knows about the latest configura-
+        // tion, whatever happened since the CRTC was looked at.
+        let cookie = xcb::xcb_randr_get_screen_resources_current(self.raw, (*self.screen).root);
+        let resources = xcb::xcb_randr_get_screen_resources_current_reply(
+            self.raw,
+            cookie,
+            std::ptr::null_mut(),
+        );
+        if resources == std::ptr::null_mut() {
+            return false;
+        }
+        let timestamp = (*resources).config_timestamp;
+        xcb::free(resources as *mut c_void);
+
+        let cookie = xcb::xcb_randr_set_crtc_config(
+            self.raw,
+            crtc.crtc,
+            xcb::XCB_CURRENT_TIME,
+            timestamp,
+            crtc.position.0,
+            crtc.position.1,
+            mode,
+            crtc.rotation,
+            crtc.outputs.len().try_into().unwrap(),
+            crtc.outputs.as_ptr(),
+        );
+        let reply = xcb::xcb_randr_set_crtc_config_reply(self.raw, cookie, std::ptr::null_mut());
+        if reply == std::ptr::null_mut() {
+            return false;
+        }
+
+        let status = u32::from((*reply).status);
+        xcb::free(reply as *mut c_void);
+
+        status == xcb::XCB_RANDR_SET_CONFIG_SUCCESS
+    }
+
+    /// Returns the monitor that most of a window is on.
+    unsafe fn window_monitor(&mut self, window: xcb::xcb_window_t) -> Option<Monitor> {
+        let (size, position) = *self.geometry.get(&window)?;
+
+        monitor_at(self.monitors(), position, size).cloned()
+    }
+
+    /// Sends `ScaleFactorChanged` if the window is on a monitor with another
+    /// scale factor than before.
+    unsafe fn update_scale_factor(&mut self, window: xcb::xcb_window_t) {
+        let scale_factor = self
+            .window_monitor(window)
+            .map_or(1.0, |monitor| monitor.scale_factor);
+
+        let old_scale_factor = self.scale_factors.insert(window, scale_factor);
+        if old_scale_factor.map_or(false, |old| old != scale_factor) {
+            self.events.push(
+                WindowId(window.into()),
+                Event::ScaleFactorChanged { scale_factor },
+            );
+        }
+    }
+
+    /// Asks the X server where a window is on the root window.
+    unsafe fn get_position(&self, window: xcb::xcb_window_t) -> Option<(i32, i32)> {
+        let cookie = xcb::xcb_translate_coordinates(self.raw, window, (*self.screen).root, 0, 0);
+        let reply = xcb::xcb_translate_coordinates_reply(self.raw, cookie, std::ptr::null_mut());
+        if reply == std::ptr::null_mut() {
+            return None;
+        }
+
+        let position = ((*reply).dst_x.into(), (*reply).dst_y.into());
+        xcb::free(reply as *mut c_void);
+
+        Some(position)
+    }
+
+    /// Returns the middle of a window, which is where a locked cursor is kept.
+    fn get_center(&self, window: xcb::xcb_window_t) -> Option<(i16, i16)> {
+        let ((width, height), _) = self.geometry.get(&window)?;
+
+        Some(((width / 2) as i16, (height / 2) as i16))
+    }
+
+    /// Grabs the pointer for the focused window if it asked for a cursor grab,
+    /// and lets go of it otherwise. The grab is a global one, so it is only
+    /// held while one of our windows has focus.
+    unsafe fn update_pointer_grab(&mut self) {
+        let grab = self
+            .focused_window
+            .and_then(|window| Some((window, *self.cursor_grabs.get(&window)?)));
+
+        match grab {
+            Some((window, grab)) if grab != CursorGrab::None => {
+                // Confining the pointer to the window is what keeps it in. The
+                // cursor is left alone, so the one of the window still shows.
+                let event_mask = xcb::XCB_EVENT_MASK_BUTTON_PRESS
+                    | xcb::XCB_EVENT_MASK_BUTTON_RELEASE
+                    | xcb::XCB_EVENT_MASK_POINTER_MOTION;
+                let cookie = xcb::xcb_grab_pointer(
+                    self.raw,
+                    1,
+                    window,
+                    event_mask.try_into().unwrap(),
+                    xcb::XCB_GRAB_MODE_ASYNC.try_into().unwrap(),
+                    xcb::XCB_GRAB_MODE_ASYNC.try_into().unwrap(),
+                    window,
+                    xcb::XCB_CURSOR_NONE,
+                    xcb::XCB_CURRENT_TIME,
+                );
+
+                // Another client can hold a grab already, which leaves the cu-
+                // rsor free. There is nothing better to do than to try again
+                // on the next focus change.
+                let reply = xcb::xcb_grab_pointer_reply(self.raw, cookie, std::ptr::null_mut());
+                if reply != std::ptr::null_mut() {
+                    self.is_pointer_grabbed =
+                        (*reply).status as u32 == xcb::XCB_GRAB_STATUS_SUCCESS;
+                    xcb::free(reply as *mut c_void);
+                }
+
+                if grab == CursorGrab::Locked {
+                    if let Some((x, y)) = self.get_center(window) {
+                        xcb::xcb_warp_pointer(self.raw, xcb::XCB_NONE, window, 0, 0, 0, 0, x, y);
+                    }
+                }
+            }
+            _ => {
+                if self.is_pointer_grabbed {
+                    xcb::xcb_ungrab_pointer(self.raw, xcb::XCB_CURRENT_TIME);
+                    self.is_pointer_grabbed = false;
+                }
+            }
+        }
+
+        xcb::xcb_flush(self.raw);
+    }
+
+    /// Reads the scroll valuators of every device again, with their current
+    /// values. Without XInput 2.1 there are none, and scrolling falls back to
+    /// buttons 4 to 7.
+    unsafe fn update_scroll_axes(&mut self) {
+        self.scroll_axes.clear();
+        if self.xinput_opcode.is_none() {
+            return;
+        }
+
+        let cookie =
+            xcb::xcb_input_xi_query_device(self.raw, xcb::XCB_INPUT_DEVICE_ALL.try_into().unwrap());
+        let reply = xcb::xcb_input_xi_query_device_reply(self.raw, cookie, std::ptr::null_mut());
+        if reply == std::ptr::null_mut() {
+            return;
+        }
+
+        let mut devices = xcb::xcb_input_xi_query_device_infos_iterator(reply);
+        while devices.rem > 0 {
+            let device = devices.data;
+
+            // The scroll class only says which valuator scrolls, the value is
+            // in the valuator class of the same number.
+            let mut values = HashMap::new();
+            let mut classes = xcb::xcb_input_xi_device_info_classes_iterator(device);
+            while classes.rem > 0 {
+                let class = classes.data;
+
+                match u32::from((*class).type_) {
+                    xcb::XCB_INPUT_DEVICE_CLASS_TYPE_SCROLL => {
+                        let class = class as *const xcb::xcb_input_scroll_class_t;
+                        let increment = fp3232_to_f64((*class).increment);
+
+                        if increment != 0.0 {
+                            self.scroll_axes.insert(
+                                ((*device).deviceid, (*class).number),
+                                ScrollAxis {
+                                    is_vertical: u32::from((*class).scroll_type)
+                                        == xcb::XCB_INPUT_SCROLL_TYPE_VERTICAL,
+                                    increment,
+                                    value: None,
+                                },
+                            );
+                        }
+                    }
+                    xcb::XCB_INPUT_DEVICE_CLASS_TYPE_VALUATOR => {
+                        let class = class as *const xcb::xcb_input_valuator_class_t;
+                        values.insert((*class).number, fp3232_to_f64((*class).value));
+                    }
+                    _ => (),
+                }
+
+                xcb::xcb_input_device_class_next(&mut classes);
+            }
+
+            for (&(deviceid, number), axis) in self.scroll_axes.iter_mut() {
+                if deviceid == (*device).deviceid {
+                    axis.value = values.get(&number).copied();
+                }
+            }
+
+            xcb::xcb_input_xi_device_info_next(&mut devices);
+        }
+
+        xcb::free(reply as *mut c_void);
+    }
+
+    /// Handles the pointer moving within a window, which comes from the core
+    /// protocol or from XInput2.
+    unsafe fn handle_motion(&mut self, window: xcb::xcb_window_t, x: f64, y: f64) {
+        // A locked cursor is put back in the middle of the window whenever it
+        // moves, so its position means nothing.
+        if self.is_pointer_grabbed && self.cursor_grabs.get(&window) == Some(&CursorGrab::Locked) {
+            if let Some(center) = self.get_center(window) {
+                if (x as i16, y as i16) != center {
+                    xcb::xcb_warp_pointer(
+                        self.raw,
+                        xcb::XCB_NONE,
+                        window,
+                        0,
+                        0,
+                        0,
+                        0,
+                        center.0,
+                        center.1,
+                    );
+                    xcb::xcb_flush(self.raw);
+                }
+            }
+
+            return;
+        }
+
+        self.events
+            .push(WindowId(window.into()), Event::MouseMove { x, y });
+    }
+
+    /// Handles an XInput2 motion event. Once XInput2 motion is selected on a
+    /// window, the X server stops sending core motion events for it, so this
+    /// reports the position too. The scroll valuators move along with it.
+    unsafe fn handle_xinput_motion(&mut self, event: *const xcb::xcb_input_motion_event_t) {
+        let window = (*event).event;
+
+        // The position is a 16.16 fixed-point number.
+        self.handle_motion(
+            window,
+            f64::from((*event).event_x) / 65536.0,
+            f64::from((*event).event_y) / 65536.0,
+        );
+
+        let mask = std::slice::from_raw_parts(
+            xcb::xcb_input_button_press_valuator_mask(event),
+            (*event).valuators_len.into(),
+        );
+        let mut values = xcb::xcb_input_button_press_axisvalues(event);
+
+        // Events of a master device have the valuators of the device that
+        // caused them, which is the source.
+        let (mut x, mut y) = (0.0, 0.0);
+        for number in 0..mask.len() * 32 {
+            if !is_valuator_set(mask, number) {
+                continue;
+            }
+
+            let value = fp3232_to_f64(*values);
+            values = values.add(1);
+
+            let axis = match self
+                .scroll_axes
+                .get_mut(&((*event).sourceid, number as u16))
+            {
+                Some(axis) => axis,
+                None => continue,
+            };
+
+            // Positive values scroll down or right.
+            if let Some(last_value) = axis.value.replace(value) {
+                let delta = (value - last_value) / axis.increment;
+                if axis.is_vertical {
+                    y -= delta;
+                } else {
+                    x += delta;
+                }
+            }
+        }
+
+        if x != 0.0 || y != 0.0 {
+            self.smooth_scroll_time = Some((*event).time);
+            self.events.push(
+                WindowId(window.into()),
+                Event::MouseScroll {
+                    x,
+                    y,
+                    unit: ScrollUnit::Lines,
+                },
+            );
+        }
+    }
+
+    /// Returns the cursor for a standard shape, creating it from the cursor
+    /// font the first time.
+    unsafe fn icon_cursor(&mut self, icon: CursorIcon) -> xcb::xcb_cursor_t {
+        if let Some(&cursor) = self.icon_cursors.get(&icon) {
+            return cursor;
+        }
+
+        if self.cursor_font == 0 {
+            let name = "cursor";
+            self.cursor_font = xcb::xcb_generate_id(self.raw);
+            xcb::xcb_open_font(
+                self.raw,
+                self.cursor_font,
+                name.len().try_into().unwrap(),
+                name.as_ptr() as *const i8,
+            );
+        }
+
+        // The glyphs are black on white, like the cursors of the desktop.
+        let glyph = cursor_font_glyph(icon);
+        let cursor = xcb::xcb_generate_id(self.raw);
+        xcb::xcb_create_glyph_cursor(
+            self.raw,
+            cursor,
+            self.cursor_font,
+            self.cursor_font,
+            glyph,
+            glyph + 1,
+            0,
+            0,
+            0,
+            0xFFFF,
+            0xFFFF,
+            0xFFFF,
+        );
+        self.icon_cursors.insert(icon, cursor);
+
+        cursor
+    }
+
+    /// Asks the owner of a selection to write it to a property of `window`, as
+    /// `target`. The answer comes in a `SELECTION_NOTIFY` event.
+    unsafe fn request_selection(
+        &mut self,
+        window: xcb::xcb_window_t,
+        selection: xcb::xcb_atom_t,
+        target: xcb::xcb_atom_t,
+    ) {
+        xcb::xcb_convert_selection(
+            self.raw,
+            window,
+            selection,
+            target,
+            self.atoms.nengine_selection,
+            xcb::XCB_CURRENT_TIME,
+        );
+        xcb::xcb_flush(self.raw);
+
+        self.incoming_selection = Some(IncomingSelection {
+            window,
+            selection,
+            target,
+            is_incremental: false,
+            data: Vec::new(),
+            last_progress: Instant::now(),
+            result: None,
+        });
+    }
+
+    /// Reads and deletes the property a selection is written to. Deleting it
+    /// is also what asks the owner for the next chunk. Returns the type of
+    /// the property and its contents.
+    unsafe fn take_selection_property(
+        &self,
+        window: xcb::xcb_window_t,
+        property: xcb::xcb_atom_t,
+    ) -> Option<(xcb::xcb_atom_t, Vec<u8>)> {
+        let cookie = xcb::xcb_get_property(
+            self.raw,
+            1,
+            window,
+            property,
+            xcb::XCB_GET_PROPERTY_TYPE_ANY,
+            0,
+            u32::MAX / 4,
+        );
+        let reply = xcb::xcb_get_property_reply(self.raw, cookie, std::ptr::null_mut());
+        if reply == std::ptr::null_mut() {
+            return None;
+        }
+
+        let data = std::slice::from_raw_parts(
+            xcb::xcb_get_property_value(reply) as *const u8,
+            xcb::xcb_get_property_value_length(reply) as usize,
+        )
+        .to_vec();
+        let property_type = (*reply).type_;
+        xcb::free(reply as *mut c_void);
+
+        Some((property_type, data))
+    }
+
+    /// Handles the answer of the owner of a selection we asked for.
+    unsafe fn handle_selection_notify(&mut self, event: *const xcb::xcb_selection_notify_event_t) {
+        let (window, selection, target) = match &self.incoming_selection {
+            Some(incoming)
+                if incoming.window == (*event).requestor && incoming.result.is_none() =>
+            {
+                (incoming.window, incoming.selection, incoming.target)
+            }
+            _ => return,
+        };
+
+        // The owner could not turn the selection into the target. Owners th-
+        // at predate `UTF8_STRING` can still give Latin-1.
+        if (*event).property == xcb::XCB_ATOM_NONE {
+            if target == self.atoms.utf8_string {
+                self.request_selection(window, selection, xcb::XCB_ATOM_STRING);
+            } else if let Some(incoming) = &mut self.incoming_selection {
+                incoming.result = Some(None);
+            }
+            return;
+        }
+
+        let property = self.take_selection_property(window, self.atoms.nengine_selection);
+        let incoming = match &mut self.incoming_selection {
+            Some(incoming) => incoming,
+            None => return,
+        };
+
+        incoming.last_progress = Instant::now();
+        match property {
+            // The selection is too large for one property. Its size came in-
+            // stead, and the chunks follow in `PROPERTY_NOTIFY` events.
+            Some((property_type, _)) if property_type == self.atoms.incr => {
+                incoming.is_incremental = true;
+            }
+            Some((_, data)) => incoming.result = Some(Some(decode_selection(target, &data))),
+            None => incoming.result = Some(None),
+        }
+    }
+
+    /// Reads the next chunk of a selection that is sent with `INCR`.
+    unsafe fn continue_incoming_selection(&mut self, window: xcb::xcb_window_t) {
+        match &self.incoming_selection {
+            Some(incoming)
+                if incoming.window == window
+                    && incoming.is_incremental
+                    && incoming.result.is_none() => {}
+            _ => return,
+        }
+
+        let property = self.take_selection_property(window, self.atoms.nengine_selection);
+        let incoming = match &mut self.incoming_selection {
+            Some(incoming) => incoming,
+            None => return,
+        };
+
+        incoming.last_progress = Instant::now();
+        match property {
+            // An empty chunk is the end.
+            Some((_, data)) if data.is_empty() => {
+                incoming.result = Some(Some(decode_selection(incoming.target, &incoming.data)));
+            }
+            Some((_, data)) => incoming.data.extend_from_slice(&data),
+            None => incoming.result = Some(None),
+        }
+    }
+
+    /// Writes the next chunk of a selection that is sent with `INCR`, once the
+    /// client took the last one.
+    unsafe fn continue_outgoing_selection(
+        &mut self,
+        window: xcb::xcb_window_t,
+        property: xcb::xcb_atom_t,
+    ) {
+        let index = match self
+            .outgoing_selections
+            .iter()
+            .position(|outgoing| outgoing.requestor == window && outgoing.property == property)
+        {
+            Some(index) => index,
+            None => return,
+        };
+
+        let outgoing = &mut self.outgoing_selections[index];
+        let end = (outgoing.offset + INCR_CHUNK_SIZE).min(outgoing.data.len());
+        let chunk = &outgoing.data[outgoing.offset..end];
+        xcb::xcb_change_property(
+            self.raw,
+            xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+            outgoing.requestor,
+            outgoing.property,
+            outgoing.target,
+            8,
+            chunk.len().try_into().unwrap(),
+            chunk.as_ptr() as *const c_void,
+        );
+        outgoing.offset = end;
+
+        if chunk.is_empty() {
+            // The window belongs to the client, so its events are no longer
+            // of interest.
+            let event_mask = xcb::XCB_EVENT_MASK_NO_EVENT;
+            xcb::xcb_change_window_attributes(
+                self.raw,
+                outgoing.requestor,
+                xcb::XCB_CW_EVENT_MASK,
+                &event_mask as *const u32 as *const c_void,
+            );
+            self.outgoing_selections.remove(index);
+        }
+
+        xcb::xcb_flush(self.raw);
+    }
+
+    /// Hands the text of a selection that one of our windows owns to another
+    /// client, or tells it that there is nothing it can have.
+    unsafe fn answer_selection_request(
+        &mut self,
+        request: *const xcb::xcb_selection_request_event_t,
+    ) {
+        let request = *request;
+
+        // Clients from before ICCCM 2.0 leave out the property, and expect the
+        // target to be used instead.
+        let property = if request.property == xcb::XCB_ATOM_NONE {
+            request.target
+        } else {
+            request.property
+        };
+
+        let text = self
+            .selections
+            .get(&request.selection)
+            .map(|(_, text)| text.as_str());
+        let mut answer = property;
+        match text {
+            Some(_) if request.target == self.atoms.targets => {
+                let targets = [
+                    self.atoms.targets,
+                    self.atoms.utf8_string,
+                    xcb::XCB_ATOM_STRING,
+                ];
+                xcb::xcb_change_property(
+                    self.raw,
+                    xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+                    request.requestor,
+                    property,
+                    xcb::XCB_ATOM_ATOM,
+                    32,
+                    targets.len().try_into().unwrap(),
+                    targets.as_ptr() as *const c_void,
+                );
+            }
+            Some(text)
+                if request.target == self.atoms.utf8_string
+                    || request.target == xcb::XCB_ATOM_STRING =>
+            {
+                let data = if request.target == xcb::XCB_ATOM_STRING {
+                    encode_latin1(text)
+                } else {
+                    text.as_bytes().to_vec()
+                };
+
+                if data.len() > INCR_CHUNK_SIZE {
+                    // The size goes first, as a lower bound. The chunks follow
+                    // as the client deletes the property, which it can only be
+                    // seen doing by listening to its window.
+                    let size = u32::try_from(data.len()).unwrap_or(u32::MAX);
+                    let event_mask = xcb::XCB_EVENT_MASK_PROPERTY_CHANGE;
+                    xcb::xcb_change_window_attributes(
+                        self.raw,
+                        request.requestor,
+                        xcb::XCB_CW_EVENT_MASK,
+                        &event_mask as *const u32 as *const c_void,
+                    );
+                    xcb::xcb_change_property(
+                        self.raw,
+                        xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+                        request.requestor,
+                        property,
+                        self.atoms.incr,
+                        32,
+                        1,
+                        &size as *const u32 as *const c_void,
+                    );
+
+                    self.outgoing_selections.push(OutgoingSelection {
+                        requestor: request.requestor,
+                        property,
+                        target: request.target,
+                        data,
+                        offset: 0,
+                    });
+                } else {
+                    xcb::xcb_change_property(
+                        self.raw,
+                        xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+                        request.requestor,
+                        property,
+                        request.target,
+                        8,
+                        data.len().try_into().unwrap(),
+                        data.as_ptr() as *const c_void,
+                    );
+                }
+            }
+            _ => answer = xcb::XCB_ATOM_NONE,
+        }
+
+        // `xcb_send_event` always sends 32 bytes, which is more than this ev-
+        // ent has.
+        #[repr(C)]
+        struct PaddedEvent {
+            event: xcb::xcb_selection_notify_event_t,
+            padding: [u8; 8],
+        }
+
+        let notify = PaddedEvent {
+            event: xcb::xcb_selection_notify_event_t {
+                response_type: xcb::XCB_SELECTION_NOTIFY as u8,
+                pad0: 0,
+                sequence: 0,
+                time: request.time,
+                requestor: request.requestor,
+                selection: request.selection,
+                target: request.target,
+                property: answer,
+            },
+            padding: [0; 8],
+        };
+        xcb::xcb_send_event(
+            self.raw,
+            0,
+            request.requestor,
+            xcb::XCB_EVENT_MASK_NO_EVENT,
+            &notify as *const PaddedEvent as *const i8,
+        );
+        xcb::xcb_flush(self.raw);
+    }
+
+    /// Sends an XDND message to the window of the client a drag comes from.
+    unsafe fn send_xdnd_message(
+        &self,
+        source: xcb::xcb_window_t,
+        message_type: xcb::xcb_atom_t,
+        data: [u32; 5],
+    ) {
+        let mut event: xcb::xcb_client_message_event_t = std::mem::zeroed();
+        event.response_type = xcb::XCB_CLIENT_MESSAGE as u8;
+        event.format = 32;
+        event.window = source;
+        event.type_ = message_type;
+        event.data.data32 = data;
+
+        xcb::xcb_send_event(
+            self.raw,
+            0,
+            source,
+            xcb::XCB_EVENT_MASK_NO_EVENT,
+            &event as *const xcb::xcb_client_message_event_t as *const i8,
+        );
+        xcb::xcb_flush(self.raw);
+    }
+
+    /// Handles a drag that entered `window`. The source lists the types it
+    /// offers in the message if there are at most three, and in `XdndTypeL-
+    /// ist` on its window otherwise.
+    unsafe fn handle_xdnd_enter(&mut self, window: xcb::xcb_window_t, data: [u32; 5]) {
+        let source = data[0];
+        let types = if data[1] & 1 != 0 {
+            let cookie = xcb::xcb_get_property(
+                self.raw,
+                0,
+                source,
+                self.atoms.xdnd_type_list,
+                xcb::XCB_ATOM_ATOM,
+                0,
+                u32::MAX / 4,
+            );
+            let reply = xcb::xcb_get_property_reply(self.raw, cookie, std::ptr::null_mut());
+            if reply == std::ptr::null_mut() {
+                Vec::new()
+            } else {
+                let types = std::slice::from_raw_parts(
+                    xcb::xcb_get_property_value(reply) as *const xcb::xcb_atom_t,
+                    xcb::xcb_get_property_value_length(reply) as usize / 4,
+                )
+                .to_vec();
+                xcb::free(reply as *mut c_void);
+                types
+            }
+        } else {
+            data[2..5].to_vec()
+        };
+
+        // The source went away in the middle of the last drag without leav-
+        // ing.
+        self.cancel_drag(None);
+
+        let has_files = types.contains(&self.atoms.text_uri_list);
+        self.drag = Some(Drag {
+            source,
+            window,
+            version: data[1] >> 24,
+            has_files,
+            position: (0.0, 0.0),
+        });
+        if has_files {
+            self.events
+                .push(WindowId(window.into()), Event::FileHoverStarted);
+        }
+    }
+
+    /// Handles the pointer moving during a drag, and tells the source whether
+    /// the files would be taken if they were dropped there.
+    unsafe fn handle_xdnd_position(&mut self, window: xcb::xcb_window_t, data: [u32; 5]) {
+        let origin = self
+            .geometry
+            .get(&window)
+            .map_or((0, 0), |(_, position)| *position);
+        let drag = match &mut self.drag {
+            Some(drag) if drag.window == window && drag.source == data[0] => drag,
+            _ => return,
+        };
+
+        // The position is relative to the root window, with x in the upper
+        // and y in the lower 16 bits.
+        let x = i32::from((data[2] >> 16) as i16) - origin.0;
+        let y = i32::from(data[2] as i16) - origin.1;
+        drag.position = (x.into(), y.into());
+
+        // Bit 1 asks for a position message on every move, since there is no
+        // rectangle where the answer stays the same.
+        let (accept, action) = if drag.has_files {
+            (1, self.atoms.xdnd_action_copy)
+        } else {
+            (0, xcb::XCB_ATOM_NONE)
+        };
+        let source = drag.source;
+        self.send_xdnd_message(
+            source,
+            self.atoms.xdnd_status,
+            [window, accept | 2, 0, 0, action],
+        );
+    }
+
+    /// Handles the files being dropped. They are read from `XdndSelection`,
+    /// and the drop is over once it is answered.
+    unsafe fn handle_xdnd_drop(&mut self, window: xcb::xcb_window_t, data: [u32; 5]) {
+        let drag = match &self.drag {
+            Some(drag) if drag.window == window && drag.source == data[0] => drag,
+            _ => return,
+        };
+
+        if !drag.has_files {
+            let source = drag.source;
+            self.drag = None;
+            self.send_xdnd_message(
+                source,
+                self.atoms.xdnd_finished,
+                [window, 0, xcb::XCB_ATOM_NONE, 0, 0],
+            );
+            return;
+        }
+
+        // The time of the drop only comes with version 1 and later.
+        let time = if drag.version >= 1 {
+            data[2]
+        } else {
+            xcb::XCB_CURRENT_TIME
+        };
+        xcb::xcb_convert_selection(
+            self.raw,
+            window,
+            self.atoms.xdnd_selection,
+            self.atoms.text_uri_list,
+            self.atoms.xdnd_selection,
+            time,
+        );
+        xcb::xcb_flush(self.raw);
+    }
+
+    /// Handles the answer of the source of a drag to a drop, which has the
+    /// files in it.
+    unsafe fn handle_xdnd_selection(&mut self, event: *const xcb::xcb_selection_notify_event_t) {
+        let window = (*event).requestor;
+        let drag = match self.drag.take() {
+            Some(drag) if drag.window == window => drag,
+            drag => {
+                self.drag = drag;
+                return;
+            }
+        };
+
+        let property = if (*event).property == xcb::XCB_ATOM_NONE {
+            None
+        } else {
+            self.take_selection_property(window, self.atoms.xdnd_selection)
+        };
+        let paths = property.map_or(Vec::new(), |(_, data)| parse_uri_list(&data));
+
+        let id = WindowId(window.into());
+        let is_accepted = !paths.is_empty();
+        if is_accepted {
+            self.events.push(
+                id,
+                Event::FileDropped {
+                    paths,
+                    position: drag.position,
+                },
+            );
+        } else {
+            self.events.push(id, Event::FileHoverCancelled);
+        }
+
+        let action = if is_accepted {
+            self.atoms.xdnd_action_copy
+        } else {
+            xcb::XCB_ATOM_NONE
+        };
+        self.send_xdnd_message(
+            drag.source,
+            self.atoms.xdnd_finished,
+            [window, is_accepted.into(), action, 0, 0],
+        );
+    }
+
+    /// Forgets the current drag, if it is over `window` or `window` is None,
+    /// and sends `FileHoverCancelled` if it was one of files.
+    fn cancel_drag(&mut self, window: Option<xcb::xcb_window_t>) {
+        match &self.drag {
+            Some(drag) if window.map_or(true, |window| window == drag.window) => {
+                if drag.has_files {
+                    self.events
+                        .push(WindowId(drag.window.into()), Event::FileHoverCancelled);
+                }
+                self.drag = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Pushes the key event of a key press, followed by its text. Keys come
+    /// here straight from the X server, or back from the input method.
+    unsafe fn handle_key_press(&mut self, event: *mut xcb::xcb_key_press_event_t, is_repeat: bool) {
+        let id = WindowId((*event).event.into());
+        let (keycode, scancode) = self.keyboard_mapping.translate_key((*event).detail.into());
+
+        self.events.push(
+            id,
+            Event::Key {
+                keycode,
+                scancode,
+                is_press: true,
+                is_repeat,
+                modifiers: self.keyboard_mapping.modifiers(),
+            },
+        );
+
+        // The text is sent after the key event itself.
+        if let Some(text) = self
+            .keyboard_mapping
+            .keycode_to_text((*event).detail.into())
+        {
+            self.events.push(id, Event::TextInput { text });
+        }
+    }
+
+    unsafe fn handle_key_release(&mut self, event: *mut xcb::xcb_key_release_event_t) {
+        let (keycode, scancode) = self.keyboard_mapping.translate_key((*event).detail.into());
+        self.events.push(
+            WindowId((*event).event.into()),
+            Event::Key {
+                keycode,
+                scancode,
+                is_press: false,
+                is_repeat: false,
+                modifiers: self.keyboard_mapping.modifiers(),
+            },
+        );
+    }
+
+    /// Translates a single XCB event and pushes the result onto the event qu-
+    /// eue. Some events are handled internally and never reach the queue.
+    unsafe fn handle_event(&mut self, event: *mut xcb::xcb_generic_event_t) {
+        #[cfg(feature = "xim")]
+        if self.filter_ime_event(event) {
+            return;
+        }
+        if self.keyboard_mapping.handle_xkb_event(self.raw, event) {
+            return;
+        }
+
+        // A monitor was plugged in, turned off or changed its mode, which can
+        // change the scale factor of every window.
+        let response_type = ((*event).response_type & !0x80) as u32;
+        let screen_change_notify = self
+            .randr_first_event
+            .map(|first_event| u32::from(first_event) + xcb::XCB_RANDR_SCREEN_CHANGE_NOTIFY);
+        if Some(response_type) == screen_change_notify {
+            self.monitors = None;
+            let windows: Vec<xcb::xcb_window_t> = self.geometry.keys().copied().collect();
+            for window in windows {
+                self.update_scale_factor(window);
+            }
+            return;
+        }
+
+        match response_type {
+            xcb::XCB_CLIENT_MESSAGE => {
+                let event = event as *mut xcb::xcb_client_message_event_t;
+                let window = (*event).window;
+                let message_type = (*event).type_;
+                let data = (*event).data.data32;
+
+                if message_type == self.atoms.xdnd_enter {
+                    self.handle_xdnd_enter(window, data);
+                } else if message_type == self.atoms.xdnd_position {
+                    self.handle_xdnd_position(window, data);
+                } else if message_type == self.atoms.xdnd_leave {
+                    if self
+                        .drag
+                        .as_ref()
+                        .map_or(false, |drag| drag.source == data[0])
+                    {
+                        self.cancel_drag(Some(window));
+                    }
+                } else if message_type == self.atoms.xdnd_drop {
+                    self.handle_xdnd_drop(window, data);
+                } else if message_type == self.atoms.wm_protocols
+                    && data[0] == self.atoms.wm_delete_window
+                {
+                    self.events
+                        .push(WindowId(window.into()), Event::CloseRequested);
+                }
+            }
+            xcb::XCB_CONFIGURE_NOTIFY => {
+                let event = event as *mut xcb::xcb_configure_notify_event_t;
+                let window = (*event).window;
+                let id = WindowId(window.into());
+
+                // The coordinates in the event are relative to the parent, wh-
+                // ich is usually a frame made by the window manager, so they
+                // have to be translated to the root window.
+                let position = self.get_position(window);
+
+                let (size, old_position) = match self.geometry.get_mut(&window) {
+                    Some(geometry) => geometry,
+                    None => return,
+                };
+
+                let new_size = ((*event).width.into(), (*event).height.into());
+                if new_size != *size {
+                    *size = new_size;
+                    self.events.push(
+                        id,
+                        Event::Resized {
+                            width: new_size.0,
+                            height: new_size.1,
+                        },
+                    );
+                }
+
+                if let Some(position) = position {
+                    if position != *old_position {
+                        *old_position = position;
+                        self.events.push(
+                            id,
+                            Event::Moved {
+                                x: position.0,
+                                y: position.1,
+                            },
+                        );
+                    }
+                }
+
+                self.update_scale_factor(window);
+            }
+            xcb::XCB_FOCUS_IN | xcb::XCB_FOCUS_OUT => {
+                let event = event as *mut xcb::xcb_focus_in_event_t;
+                let id = WindowId((*event).event.into());
+
+                // Keyboard grabs also send focus events, but the window never
+                // really lost focus during those.
+                let mode = (*event).mode as u32;
+                if mode != xcb::XCB_NOTIFY_MODE_GRAB && mode != xcb::XCB_NOTIFY_MODE_UNGRAB {
+                    if ((*event).response_type & !0x80) as u32 == xcb::XCB_FOCUS_IN {
+                        self.focused_window = Some((*event).event);
+                        #[cfg(feature = "xim")]
+                        self.update_ime_focus((*event).event, true);
+                        self.events.push(id, Event::FocusGained);
+                    } else {
+                        if self.focused_window == Some((*event).event) {
+                            self.focused_window = None;
+                        }
+                        #[cfg(feature = "xim")]
+                        self.update_ime_focus((*event).event, false);
+                        self.events.push(id, Event::FocusLost);
+                    }
+
+                    self.update_pointer_grab();
+                }
+            }
+            xcb::XCB_ENTER_NOTIFY => {
+                let event = event as *mut xcb::xcb_enter_notify_event_t;
+
+                // The scroll valuators kept counting while the pointer was
+                // somewhere else.
+                self.update_scroll_axes();
+
+                self.events
+                    .push(WindowId((*event).event.into()), Event::CursorEntered);
+            }
+            xcb::XCB_LEAVE_NOTIFY => {
+                let event = event as *mut xcb::xcb_leave_notify_event_t;
+                self.events
+                    .push(WindowId((*event).event.into()), Event::CursorLeft);
+            }
+            xcb::XCB_EXPOSE => {
+                let event = event as *mut xcb::xcb_expose_event_t;
+
+                // One expose event is sent per damaged rectangle, `count` says
+                // how many more are coming.
+                if (*event).count == 0 {
+                    self.events
+                        .push(WindowId((*event).window.into()), Event::RedrawRequested);
+                }
+            }
+            xcb::XCB_BUTTON_PRESS | xcb::XCB_BUTTON_RELEASE => {
+                let event = event as *mut xcb::xcb_button_press_event_t;
+                let id = WindowId((*event).event.into());
+                self.last_timestamp = (*event).time;
+                let is_press = ((*event).response_type & !0x80) as u32 == xcb::XCB_BUTTON_PRESS;
+
+                // A notch of the wheel is a press and a release of one of the
+                // scroll buttons, so only the press counts. If smooth scroll-
+                // ing sent the same scroll already, the press is only there
+                // for older clients.
+                if let Some((x, y)) = xcb_button_to_scroll((*event).detail) {
+                    if is_press && self.smooth_scroll_time != Some((*event).time) {
+                        self.events.push(
+                            id,
+                            Event::MouseScroll {
+                                x,
+                                y,
+                                unit: ScrollUnit::Lines,
+                            },
+                        );
+                    }
+                } else if let Some(button) = translate_xcb_buttons((*event).detail) {
+                    self.events
+                        .push(id, Event::MouseButton { button, is_press });
+                }
+            }
+            xcb::XCB_MOTION_NOTIFY => {
+                let event = event as *mut xcb::xcb_motion_notify_event_t;
+                self.last_timestamp = (*event).time;
+                self.handle_motion(
+                    (*event).event,
+                    (*event).event_x.into(),
+                    (*event).event_y.into(),
+                );
+            }
+            xcb::XCB_KEY_PRESS => {
+                let event = event as *mut xcb::xcb_key_press_event_t;
+                self.last_timestamp = (*event).time;
+                let is_repeat = self.repeated_keycode.take() == Some((*event).detail);
+
+                // The input method sends the press back if it has no use for
+                // it.
+                #[cfg(feature = "xim")]
+                if self.forward_key_to_ime(event, is_repeat) {
+                    return;
+                }
+                self.handle_key_press(event, is_repeat);
+            }
+            xcb::XCB_KEY_RELEASE => {
+                let event = event as *mut xcb::xcb_key_release_event_t;
+                self.last_timestamp = (*event).time;
+
+                // X11 reports auto-repeat as a release immediately followed by
+                // a press of the same key with the same timestamp. If that is
+                // the case, the release is dropped and the press is reported
+                // as a repeat.
+                self.pending_event = xcb::xcb_poll_for_event(self.raw);
+                if self.pending_event != std::ptr::null_mut()
+                    && ((*self.pending_event).response_type & !0x80) as u32 == xcb::XCB_KEY_PRESS
+                {
+                    let next_event = self.pending_event as *mut xcb::xcb_key_press_event_t;
+
+                    if (*next_event).detail == (*event).detail
+                        && (*next_event).time == (*event).time
+                    {
+                        self.repeated_keycode = Some((*event).detail);
+                    }
+                }
+
+                if self.repeated_keycode != Some((*event).detail) {
+                    // Releases go through the input method as well, since they
+                    // would otherwise overtake the presses it sends back.
+                    #[cfg(feature = "xim")]
+                    if self.forward_key_to_ime(event, false) {
+                        return;
+                    }
+                    self.handle_key_release(event);
+                }
+            }
+            xcb::XCB_SELECTION_REQUEST => {
+                self.answer_selection_request(event as *const xcb::xcb_selection_request_event_t);
+            }
+            xcb::XCB_SELECTION_NOTIFY => {
+                let event = event as *const xcb::xcb_selection_notify_event_t;
+                if (*event).selection == self.atoms.xdnd_selection {
+                    self.handle_xdnd_selection(event);
+                } else {
+                    self.handle_selection_notify(event);
+                }
+            }
+            xcb::XCB_SELECTION_CLEAR => {
+                // Another client owns the selection now.
+                let event = event as *mut xcb::xcb_selection_clear_event_t;
+                if let Some((owner, _)) = self.selections.get(&(*event).selection) {
+                    if *owner == (*event).owner {
+                        self.selections.remove(&(*event).selection);
+                    }
+                }
+            }
+            xcb::XCB_PROPERTY_NOTIFY => {
+                let event = event as *mut xcb::xcb_property_notify_event_t;
+                self.last_timestamp = (*event).time;
+                match u32::from((*event).state) {
+                    xcb::XCB_PROPERTY_NEW_VALUE
+                        if (*event).atom == self.atoms.nengine_selection =>
+                    {
+                        self.continue_incoming_selection((*event).window);
+                    }
+                    xcb::XCB_PROPERTY_DELETE => {
+                        self.continue_outgoing_selection((*event).window, (*event).atom);
+                    }
+                    _ => (),
+                }
+            }
+            xcb::XCB_GE_GENERIC => {
+                let event = event as *mut xcb::xcb_ge_generic_event_t;
+                if Some((*event).extension) != self.xinput_opcode {
+                    return;
+                }
+
+                match u32::from((*event).event_type) {
+                    xcb::XCB_INPUT_RAW_MOTION => {
+                        // Raw motion is not tied to a window, it goes to the
+                        // focused one. Motion while another client has focus
+                        // is dropped.
+                        if let Some(window) = self.focused_window {
+                            let (dx, dy) =
+                                read_raw_motion(event as *const xcb::xcb_input_raw_motion_event_t);
+                            if dx != 0.0 || dy != 0.0 {
+                                self.events
+                                    .push(WindowId(window.into()), Event::MouseDelta { dx, dy });
+                            }
+                        }
+                    }
+                    xcb::XCB_INPUT_MOTION => {
+                        self.handle_xinput_motion(event as *const xcb::xcb_input_motion_event_t);
+                    }
+                    // A device was plugged in, or a master device took over the
+                    // valuators of another one.
+                    xcb::XCB_INPUT_DEVICE_CHANGED => self.update_scroll_axes(),
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Handles every event that is waiting on the connection.
+    unsafe fn pump(&mut self) {
+        loop {
+            let event = if self.pending_event != std::ptr::null_mut() {
+                std::mem::replace(&mut self.pending_event, std::ptr::null_mut())
+            } else {
+                xcb::xcb_poll_for_event(self.raw)
+            };
+
+            if event == std::ptr::null_mut() {
+                break;
+            }
+
+            self.handle_event(event);
+            xcb::free(event as *mut c_void);
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe {
+            #[cfg(feature = "xim")]
+            self.close_input_method();
+            xcb::free(self.pending_event as *mut c_void);
+            xcb::xcb_disconnect(self.raw);
+        }
+    }
+}
+
+// The `CrossPlatformEventLoop` trait is implemented on the Linux event loop,
+// which hands out either these windows or Wayland ones.
+impl EventLoop {
+    pub(super) fn new() -> Result<EventLoop, WindowError> {
+        Ok(EventLoop {
+            connection: Rc::new(RefCell::new(Connection::open()?)),
+            events: VecDeque::new(),
+        })
+    }
+
+    pub(super) fn create_window(&self, builder: &WindowBuilder) -> Result<Window, WindowError> {
+        builder.validate()?;
+        unsafe { Window::create(self.connection.clone(), builder) }
+    }
+
+    pub(super) fn monitors(&self) -> Vec<Monitor> {
+        unsafe { self.connection.borrow_mut().monitors().to_vec() }
+    }
+
+    pub(super) fn poll_events(&mut self) -> WindowEvents<'_> {
+        unsafe {
+            self.connection.borrow_mut().pump();
+        }
+        run_callbacks(&self.connection, |connection| &mut connection.events);
+        self.events
+            .extend(self.connection.borrow_mut().events.take_all());
+
+        self.events.drain(..)
+    }
+}
+
+impl Window {
+    /// Creates a window on a connection that is shared with the other windo-
+    /// ws of the same event loop.
+    unsafe fn create(
+        shared: Rc<RefCell<Connection>>,
+        builder: &WindowBuilder,
+    ) -> Result<Window, WindowError> {
+        let (connection, screen, atoms) = {
+            let shared = shared.borrow();
+            (shared.raw, shared.screen, shared.atoms)
+        };
+        let (width, height) = clamp_size(builder.size);
+        let (x, y) = clamp_position(builder.position.unwrap_or((0, 0)));
+
+        let event_mask = xcb::XCB_EVENT_MASK_EXPOSURE
+            | xcb::XCB_EVENT_MASK_BUTTON_PRESS
+            | xcb::XCB_EVENT_MASK_BUTTON_RELEASE
+            | xcb::XCB_EVENT_MASK_POINTER_MOTION
+            | xcb::XCB_EVENT_MASK_KEY_PRESS
+            | xcb::XCB_EVENT_MASK_KEY_RELEASE
+            | xcb::XCB_EVENT_MASK_KEYMAP_STATE
+            | xcb::XCB_EVENT_MASK_STRUCTURE_NOTIFY
+            | xcb::XCB_EVENT_MASK_FOCUS_CHANGE
+            | xcb::XCB_EVENT_MASK_ENTER_WINDOW
+            | xcb::XCB_EVENT_MASK_LEAVE_WINDOW
+            | xcb::XCB_EVENT_MASK_PROPERTY_CHANGE;
+
+        // A transparent window needs a 32-bit visual, and a window with a vi-
+        // sual other than its parent's needs its own colormap and border pixel.
+        let mut depth = xcb::XCB_COPY_FROM_PARENT.try_into().unwrap();
+        let mut visual = (*screen).root_visual;
+        let mut colormap = 0;
+        let mut value_mask = xcb::XCB_CW_EVENT_MASK;
+        let mut values = vec![event_mask];
+        if builder.transparent {
+            visual = find_argb_visual(screen).ok_or(WindowError::Unsupported("transparency"))?;
+            depth = 32;
+
+            colormap = xcb::xcb_generate_id(connection);
+            xcb::xcb_create_colormap(
+                connection,
+                xcb::XCB_COLORMAP_ALLOC_NONE.try_into().unwrap(),
+                colormap,
+                (*screen).root,
+                visual,
+            );
+
+            // The values have to be in the same order as their bits.
+            value_mask = xcb::XCB_CW_BACK_PIXEL
+                | xcb::XCB_CW_BORDER_PIXEL
+                | xcb::XCB_CW_EVENT_MASK
+                | xcb::XCB_CW_COLORMAP;
+            values = vec![0, 0, event_mask, colormap];
+        }
+
+        let window = xcb::xcb_generate_id(connection);
+        let cookie = xcb::xcb_create_window_checked(
+            connection,
+            depth,
+            window,
+            (*screen).root,
+            x as i16,
+            y as i16,
+            width as u16,
+            height as u16,
+            0,
+            xcb::XCB_WINDOW_CLASS_INPUT_OUTPUT.try_into().unwrap(),
+            visual,
+            value_mask,
+            values.as_ptr() as *const c_void,
+        );
+
+        let error = xcb::xcb_request_check(connection, cookie);
+        if error != std::ptr::null_mut() {
+            let error_code = (*error).error_code;
+            xcb::free(error as *mut c_void);
+
+            return Err(WindowError::Os(format!(
+                "could not create the window (X11 error {})",
+                error_code
+            )));
+        }
+
+        xcb::xcb_change_property(
+            connection,
+            xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+            window,
+            atoms.wm_protocols,
+            4,
+            32,
+            1,
+            &atoms.wm_delete_window as *const u32 as *const c_void,
+        );
+
+        // Says that the window takes drops, and which version of XDND it spe-
+        // aks.
+        let xdnd_version: u32 = 5;
+        xcb::xcb_change_property(
+            connection,
+            xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+            window,
+            atoms.xdnd_aware,
+            xcb::XCB_ATOM_ATOM,
+            32,
+            1,
+            &xdnd_version as *const u32 as *const c_void,
+        );
+
+        {
+            let mut shared = shared.borrow_mut();
+            shared.geometry.insert(window, ((width, height), (x, y)));
+            shared.update_scale_factor(window);
+        }
+
+        // Smooth scrolling only comes with XInput2 motion events.
+        if shared.borrow().xinput_opcode.is_some() {
+            select_xinput_events(connection, window, xcb::XCB_INPUT_XI_EVENT_MASK_MOTION);
+        }
+
+        let mut window = Window {
+            shared,
+            connection,
+            root: (*screen).root,
+            atoms,
+            raw_handle: window,
+            is_open: true,
+            is_mapped: false,
+            is_resizable: builder.resizable,
+            min_size: None,
+            max_size: None,
+            initial_position: builder.position,
+            fullscreen: FullscreenMode::Windowed,
+            is_maximized: false,
+            is_minimized: false,
+            is_always_on_top: builder.always_on_top,
+            demands_attention: false,
+            windowed_geometry: None,
+            switched_crtc: None,
+            colormap,
+            hidden_cursor: 0,
+            cursor: xcb::XCB_CURSOR_NONE,
+            custom_cursor: 0,
+            is_cursor_visible: true,
+            events: VecDeque::new(),
+        };
+
+        window.set_title(&builder.title);
+        window.write_size_hints(builder.size);
+
+        if builder.fullscreen != FullscreenMode::Windowed {
+            window.set_fullscreen(builder.fullscreen);
+        }
+
+        if !builder.decorations {
+            // There is no standard way to turn off decorations, but every wi-
+            // ndow manager understands the Motif hints. Only the decorations
+            // flag is set, and the decorations themselves are all off.
+            const MWM_HINTS_DECORATIONS: u32 = 1 << 1;
+            let hints = [MWM_HINTS_DECORATIONS, 0, 0, 0, 0];
+
+            xcb::xcb_change_property(
+                connection,
+                xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+                window.raw_handle,
+                atoms.motif_wm_hints,
+                atoms.motif_wm_hints,
+                32,
+                hints.len().try_into().unwrap(),
+                hints.as_ptr() as *const c_void,
+            );
+        }
+
+        if let Some(icon) = &builder.icon {
+            window.write_icon(icon);
+        }
+
+        if let Some((instance, class)) = &builder.class {
+            // Two strings, each one ending in a null byte.
+            let class = format!("{}\0{}\0", instance, class);
+
+            xcb::xcb_change_property(
+                connection,
+                xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+                window.raw_handle,
+                xcb::XCB_ATOM_WM_CLASS,
+                xcb::XCB_ATOM_STRING,
+                8,
+                class.len().try_into().unwrap(),
+                class.as_ptr() as *const c_void,
+            );
+        }
+
+        if let Some(parent) = builder.parent {
+            let parent = parent.0 as xcb::xcb_window_t;
+
+            xcb::xcb_change_property(
+                connection,
+                xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+                window.raw_handle,
+                xcb::XCB_ATOM_WM_TRANSIENT_FOR,
+                xcb::XCB_ATOM_WINDOW,
+                32,
+                1,
+                &parent as *const u32 as *const c_void,
+            );
+        }
+
+        xcb::xcb_flush(connection);
+
+        Ok(window)
+    }
+
+    /// Returns the last size the X server reported for the window.
+    fn get_size(&self) -> (u32, u32) {
+        self.shared
+            .borrow()
+            .geometry
+            .get(&self.raw_handle)
+            .map_or((0, 0), |(size, _)| *size)
+    }
+
+    /// Shows the cursor of the window, or the empty one while it is hidden.
+    unsafe fn write_cursor(&self) {
+        let cursor = if self.is_cursor_visible {
+            self.cursor
+        } else {
+            self.hidden_cursor
+        };
+        xcb::xcb_change_window_attributes(
+            self.connection,
+            self.raw_handle,
+            xcb::XCB_CW_CURSOR,
+            &cursor as *const u32 as *const c_void,
+        );
+        xcb::xcb_flush(self.connection);
+    }
+
+    /// Frees the cursor made from a custom cursor image, if there is one.
+    unsafe fn free_custom_cursor(&mut self) {
+        if self.custom_cursor != 0 {
+            xcb::xcb_free_cursor(self.connection, self.custom_cursor);
+            self.custom_cursor = 0;
+        }
+    }
+
+    /// Sends a client message about this window to the window manager.
+    unsafe fn send_client_message(&self, message_type: xcb::xcb_atom_t, data: [u32; 5]) {
+        let mut event: xcb::xcb_client_message_event_t = std::mem::zeroed();
+        event.response_type = xcb::XCB_CLIENT_MESSAGE as u8;
+        event.format = 32;
+        event.window = self.raw_handle;
+        event.type_ = message_type;
+        event.data.data32 = data;
+
+        xcb::xcb_send_event(
+            self.connection,
+            0,
+            self.root,
+            xcb::XCB_EVENT_MASK_SUBSTRUCTURE_NOTIFY | xcb::XCB_EVENT_MASK_SUBSTRUCTURE_REDIRECT,
+            &event as *const xcb::xcb_client_message_event_t as *const i8,
+        );
+    }
+
+    /// Adds or removes up to two `_NET_WM_STATE` atoms on a mapped window. P-
+    /// ass 0 as `second` to only change one.
+    unsafe fn change_net_wm_state(
+        &self,
+        add: bool,
+        first: xcb::xcb_atom_t,
+        second: xcb::xcb_atom_t,
+    ) {
+        // The last value says that the request comes from a normal applicat-
+        // ion rather than a pager.
+        self.send_client_message(self.atoms.net_wm_state, [add as u32, first, second, 1, 0]);
+    }
+
+    /// Remembers where the window is before it is maximized or made fullscr-
+    /// een, unless it already is one of those.
+    fn save_windowed_geometry(&mut self) {
+        if self.windowed_geometry.is_none()
+            && !self.is_maximized
+            && self.fullscreen == FullscreenMode::Windowed
+        {
+            self.windowed_geometry = self.shared.borrow().geometry.get(&self.raw_handle).copied();
+        }
+    }
+
+    /// Puts the window back where it was before it was maximized or made ful-
+    /// lscreen, once it is neither. Window managers usually do this on their
+    /// own, but not all of them, and not for windows that were mapped in one
+    /// of those states.
+    unsafe fn restore_windowed_geometry(&mut self) {
+        if self.is_maximized || self.fullscreen != FullscreenMode::Windowed {
+            return;
+        }
+        let ((width, height), (x, y)) = match self.windowed_geometry.take() {
+            Some(geometry) => geometry,
+            None => return,
+        };
+
+        // The first value holds the gravity and flags for which of the other
+        // values are set. The static gravity says that the position is that
+        // of the window itself rather than of its frame, which is how it was
+        // saved.
+        const STATIC_GRAVITY: u32 = 10;
+        const HAS_GEOMETRY: u32 = 0xF << 8;
+        const FROM_APPLICATION: u32 = 1 << 12;
+        self.send_client_message(
+            self.atoms.net_moveresize_window,
+            [
+                STATIC_GRAVITY | HAS_GEOMETRY | FROM_APPLICATION,
+                x as u32,
+                y as u32,
+                width,
+                height,
+            ],
+        );
+    }
+
+    /// Switches the monitor in the middle of the window to a video mode. Any
+    /// mode that was switched to before is switched back first.
+    unsafe fn switch_video_mode(&mut self, video_mode: VideoMode) {
+        self.restore_video_mode();
+
+        let shared = self.shared.borrow();
+        let ((width, height), (x, y)) = match shared.geometry.get(&self.raw_handle) {
+            Some(&geometry) => geometry,
+            None => return,
+        };
+        let center = (x + (width / 2) as i32, y + (height / 2) as i32);
+        let switched_crtc = shared.switch_crtc_mode(center, video_mode);
+        drop(shared);
+
+        self.switched_crtc = switched_crtc;
+    }
+
+    /// Switches the monitor back to the mode it was in before exclusive full-
+    /// screen, if it was switched.
+    unsafe fn restore_video_mode(&mut self) {
+        if let Some(crtc) = self.switched_crtc.take() {
+            self.shared.borrow().set_crtc_mode(&crtc, crtc.mode);
+        }
+    }
+
+    /// Writes the requested state into the properties the window manager re-
+    /// ads when the window is mapped. Window managers ignore changes to these
+    /// after that, which is what `change_net_wm_state` is for.
+    unsafe fn write_initial_state(&self) {
+        let mut atoms = Vec::new();
+        if self.fullscreen != FullscreenMode::Windowed {
+            atoms.push(self.atoms.net_wm_state_fullscreen);
+        }
+        if self.is_maximized {
+            atoms.push(self.atoms.net_wm_state_maximized_vert);
+            atoms.push(self.atoms.net_wm_state_maximized_horz);
+        }
+        if self.is_always_on_top {
+            atoms.push(self.atoms.net_wm_state_above);
+        }
+        if self.demands_attention {
+            atoms.push(self.atoms.net_wm_state_demands_attention);
+        }
+
+        xcb::xcb_change_property(
+            self.connection,
+            xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+            self.raw_handle,
+            self.atoms.net_wm_state,
+            xcb::XCB_ATOM_ATOM,
+            32,
+            atoms.len().try_into().unwrap(),
+            atoms.as_ptr() as *const c_void,
+        );
+
+        // `WM_HINTS` is nine values long. The flags say that only the input
+        // model and the initial state are set.
+        const INPUT_HINT: u32 = 1 << 0;
+        const STATE_HINT: u32 = 1 << 1;
+        const NORMAL_STATE: u32 = 1;
+        const ICONIC_STATE: u32 = 3;
+
+        let mut hints = [0u32; 9];
+        hints[0] = INPUT_HINT | STATE_HINT;
+        hints[1] = 1;
+        hints[2] = if self.is_minimized {
+            ICONIC_STATE
+        } else {
+            NORMAL_STATE
+        };
+
+        xcb::xcb_change_property(
+            self.connection,
+            xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+            self.raw_handle,
+            xcb::XCB_ATOM_WM_HINTS,
+            xcb::XCB_ATOM_WM_HINTS,
+            32,
+            hints.len().try_into().unwrap(),
+            hints.as_ptr() as *const c_void,
+        );
+    }
+
+    /// Writes `WM_NORMAL_HINTS` by hand, which is not worth pulling in xcb-i-
+    /// ccm for. A window that is not resizable gets `size` as both its mini-
+    /// mum and its maximum size.
+    unsafe fn write_size_hints(&self, size: (u32, u32)) {
+        const US_POSITION: u32 = 1 << 0;
+        const P_MIN_SIZE: u32 = 1 << 4;
+        const P_MAX_SIZE: u32 = 1 << 5;
+
+        let (min_size, max_size) = if self.is_resizable {
+            (self.min_size, self.max_size)
+        } else {
+            (Some(size), Some(size))
+        };
+
+        // The flags come first, followed by the position, two unused values
+        // and then the minimum and maximum sizes.
+        let mut hints = [0u32; 18];
+        if let Some((x, y)) = self.initial_position.map(clamp_position) {
+            hints[0] |= US_POSITION;
+            hints[1] = x as u32;
+            hints[2] = y as u32;
+        }
+        if let Some((width, height)) = min_size.map(clamp_size) {
+            hints[0] |= P_MIN_SIZE;
+            hints[5] = width;
+            hints[6] = height;
+        }
+        if let Some((width, height)) = max_size.map(clamp_size) {
+            hints[0] |= P_MAX_SIZE;
+            hints[7] = width;
+            hints[8] = height;
+        }
+
+        xcb::xcb_change_property(
+            self.connection,
+            xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+            self.raw_handle,
+            xcb::XCB_ATOM_WM_NORMAL_HINTS,
+            xcb::XCB_ATOM_WM_SIZE_HINTS,
+            32,
+            hints.len().try_into().unwrap(),
+            hints.as_ptr() as *const c_void,
+        );
+        xcb::xcb_flush(self.connection);
+    }
+
+    /// Writes `_NET_WM_ICON`, which holds the size followed by the pixels as
+    /// ARGB values.
+    unsafe fn write_icon(&self, icon: &Icon) {
+        let mut data = vec![icon.width, icon.height];
+        data.extend(icon.rgba.chunks_exact(4).map(|pixel| {
+            u32::from(pixel[3]) << 24
+                | u32::from(pixel[0]) << 16
+                | u32::from(pixel[1]) << 8
+                | u32::from(pixel[2])
+        }));
+
+        xcb::xcb_change_property(
+            self.connection,
+            xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+            self.raw_handle,
+            self.atoms.net_wm_icon,
+            xcb::XCB_ATOM_CARDINAL,
+            32,
+            data.len().try_into().unwrap(),
+            data.as_ptr() as *const c_void,
+        );
+    }
+}
+
+impl CrossPlatformWindow for Window {
+    fn new(builder: &WindowBuilder) -> Result<Window, WindowError> {
+        EventLoop::new()?.create_window(builder)
+    }
+
+    fn id(&self) -> WindowId {
+        WindowId(self.raw_handle.into())
+    }
+
+    fn set_event_callback(&mut self, callback: Box<dyn FnMut(&Event)>) {
+        self.shared
+            .borrow_mut()
+            .events
+            .set_callback(self.id(), callback);
+    }
+
+    fn show(&mut self) {
+        if self.is_mapped {
+            return;
+        }
+
+        unsafe {
+            self.write_initial_state();
+            xcb::xcb_map_window(self.connection, self.raw_handle);
+            if let FullscreenMode::Exclusive(video_mode) = self.fullscreen {
+                self.switch_video_mode(video_mode);
+            }
+            xcb::xcb_flush(self.connection);
+        }
+
+        self.is_mapped = true;
+    }
+
+    fn set_title(&mut self, title: &str) {
+        unsafe {
+            // `WM_NAME` is Latin-1, which is only there for old window mana-
+            // gers. Everything else reads the UTF-8 `_NET_WM_NAME`.
+            xcb::xcb_change_property(
+                self.connection,
+                xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+                self.raw_handle,
+                xcb::XCB_ATOM_WM_NAME,
+                xcb::XCB_ATOM_STRING,
+                8,
+                title.len().try_into().unwrap(),
+                title.as_ptr() as *const c_void,
+            );
+            xcb::xcb_change_property(
+                self.connection,
+                xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+                self.raw_handle,
+                self.atoms.net_wm_name,
+                self.atoms.utf8_string,
+                8,
+                title.len().try_into().unwrap(),
+                title.as_ptr() as *const c_void,
+            );
+            xcb::xcb_flush(self.connection);
+        }
+    }
+
+    fn set_size(&mut self, width: u32, height: u32) {
+        unsafe {
+            if !self.is_resizable {
+                self.write_size_hints((width, height));
+            }
+
+            let (width, height) = clamp_size((width, height));
+            let values = [width, height];
+            xcb::xcb_configure_window(
+                self.connection,
+                self.raw_handle,
+                (xcb::XCB_CONFIG_WINDOW_WIDTH | xcb::XCB_CONFIG_WINDOW_HEIGHT)
+                    .try_into()
+                    .unwrap(),
+                values.as_ptr() as *const c_void,
+            );
+            xcb::xcb_flush(self.connection);
+        }
+    }
+
+    fn set_position(&mut self, x: i32, y: i32) {
+        unsafe {
+            // The values are sent as 32-bit words, negative ones included.
+            let (x, y) = clamp_position((x, y));
+            let values = [x as u32, y as u32];
+            xcb::xcb_configure_window(
+                self.connection,
+                self.raw_handle,
+                (xcb::XCB_CONFIG_WINDOW_X | xcb::XCB_CONFIG_WINDOW_Y)
+                    .try_into()
+                    .unwrap(),
+                values.as_ptr() as *const c_void,
+            );
+            xcb::xcb_flush(self.connection);
+        }
+    }
+
+    fn set_fullscreen(&mut self, mode: FullscreenMode) {
+        let was_fullscreen = self.fullscreen != FullscreenMode::Windowed;
+        let is_fullscreen = mode != FullscreenMode::Windowed;
+        if self.is_mapped && is_fullscreen {
+            self.save_windowed_geometry();
+        }
+        self.fullscreen = mode;
+
+        unsafe {
+            // 1 asks the compositor to unredirect the window, 0 leaves it up
+            // to the compositor.
+            let bypass_compositor = matches!(mode, FullscreenMode::Exclusive(_)) as u32;
+            xcb::xcb_change_property(
+                self.connection,
+                xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+                self.raw_handle,
+                self.atoms.net_wm_bypass_compositor,
+                xcb::XCB_ATOM_CARDINAL,
+                32,
+                1,
+                &bypass_compositor as *const u32 as *const c_void,
+            );
+
+            if self.is_mapped {
+                match mode {
+                    FullscreenMode::Exclusive(video_mode) => self.switch_video_mode(video_mode),
+                    _ => self.restore_video_mode(),
+                }
+            }
+
+            if self.is_mapped && was_fullscreen != is_fullscreen {
+                self.change_net_wm_state(is_fullscreen, self.atoms.net_wm_state_fullscreen, 0);
+                self.restore_windowed_geometry();
+            }
+
+            xcb::xcb_flush(self.connection);
+        }
+    }
+
+    fn monitors(&self) -> Vec<Monitor> {
+        unsafe { self.shared.borrow_mut().monitors().to_vec() }
+    }
+
+    fn current_monitor(&self) -> Option<Monitor> {
+        unsafe { self.shared.borrow_mut().window_monitor(self.raw_handle) }
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.shared
+            .borrow()
+            .scale_factors
+            .get(&self.raw_handle)
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    fn set_resizable(&mut self, resizable: bool) {
+        self.is_resizable = resizable;
+        unsafe {
+            self.write_size_hints(self.get_size());
+        }
+    }
+
+    fn set_min_size(&mut self, size: Option<(u32, u32)>) {
+        self.min_size = size;
+        unsafe {
+            self.write_size_hints(self.get_size());
+        }
+    }
+
+    fn set_max_size(&mut self, size: Option<(u32, u32)>) {
+        self.max_size = size;
+        unsafe {
+            self.write_size_hints(self.get_size());
+        }
+    }
+
+    fn minimize(&mut self) {
+        if !self.is_mapped {
+            self.is_minimized = true;
+            return;
+        }
+
+        unsafe {
+            // ICCCM's way of asking for the iconic state.
+            const ICONIC_STATE: u32 = 3;
+            self.send_client_message(self.atoms.wm_change_state, [ICONIC_STATE, 0, 0, 0, 0]);
+            xcb::xcb_flush(self.connection);
+        }
+    }
+
+    fn set_maximized(&mut self, maximized: bool) {
+        if !self.is_mapped {
+            self.is_maximized = maximized;
+            return;
+        }
+
+        if maximized {
+            self.save_windowed_geometry();
+        }
+        self.is_maximized = maximized;
+
+        unsafe {
+            self.change_net_wm_state(
+                maximized,
+                self.atoms.net_wm_state_maximized_vert,
+                self.atoms.net_wm_state_maximized_horz,
+            );
+            self.restore_windowed_geometry();
+            xcb::xcb_flush(self.connection);
+        }
+    }
+
+    fn set_icon(&mut self, rgba: &[u8], width: u32, height: u32) -> Result<(), WindowError> {
+        let icon = Icon {
+            rgba: rgba.to_vec(),
+            width,
+            height,
+        };
+        icon.validate()?;
+
+        unsafe {
+            self.write_icon(&icon);
+            xcb::xcb_flush(self.connection);
+        }
+        Ok(())
+    }
+
+    // The window manager drops the state once the window is focused.
+    fn request_attention(&mut self, _attention: UserAttention) {
+        if !self.is_mapped {
+            self.demands_attention = true;
+            return;
+        }
+
+        unsafe {
+            self.change_net_wm_state(true, self.atoms.net_wm_state_demands_attention, 0);
+            xcb::xcb_flush(self.connection);
+        }
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        unsafe {
+            // X11 has no way to hide the cursor, so it is swapped for one made
+            // from an empty 1x1 bitmap.
+            if !visible && self.hidden_cursor == 0 {
+                let pixmap = xcb::xcb_generate_id(self.connection);
+                xcb::xcb_create_pixmap(self.connection, 1, pixmap, self.raw_handle, 1, 1);
+
+                // The contents of a new pixmap are undefined, so it has to be
+                // cleared or the cursor might end up as a stray dot.
+                let gc = xcb::xcb_generate_id(self.connection);
+                let foreground = 0u32;
+                xcb::xcb_create_gc(
+                    self.connection,
+                    gc,
+                    pixmap,
+                    xcb::XCB_GC_FOREGROUND,
+                    &foreground as *const u32 as *const c_void,
+                );
+                let rectangle = xcb::xcb_rectangle_t {
+                    x: 0,
+                    y: 0,
+                    width: 1,
+                    height: 1,
+                };
+                xcb::xcb_poly_fill_rectangle(self.connection, pixmap, gc, 1, &rectangle);
+                xcb::xcb_free_gc(self.connection, gc);
+
+                self.hidden_cursor = xcb::xcb_generate_id(self.connection);
+                xcb::xcb_create_cursor(
+                    self.connection,
+                    self.hidden_cursor,
+                    pixmap,
+                    pixmap,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                );
+                xcb::xcb_free_pixmap(self.connection, pixmap);
+            }
+
+            self.is_cursor_visible = visible;
+            self.write_cursor();
+        }
+    }
+
+    fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        unsafe {
+            // The default is left to the desktop, which may have a themed ar-
+            // row that looks nicer than the one in the cursor font.
+            self.cursor = if icon == CursorIcon::Default {
+                xcb::XCB_CURSOR_NONE
+            } else {
+                self.shared.borrow_mut().icon_cursor(icon)
+            };
+            self.write_cursor();
+
+            // The window is no longer using it, so the X server lets go of it
+            // once it is freed.
+            self.free_custom_cursor();
+            xcb::xcb_flush(self.connection);
+        }
+    }
+
+    fn set_custom_cursor(&mut self, cursor: &CustomCursor) -> Result<(), WindowError> {
+        unsafe {
+            let created = create_render_cursor(self.connection, self.root, cursor)?;
+
+            self.cursor = created;
+            self.write_cursor();
+
+            self.free_custom_cursor();
+            self.custom_cursor = created;
+            xcb::xcb_flush(self.connection);
+        }
+        Ok(())
+    }
+
+    fn set_cursor_grab(&mut self, grab: CursorGrab) {
+        let mut shared = self.shared.borrow_mut();
+        shared.cursor_grabs.insert(self.raw_handle, grab);
+
+        // Otherwise the grab waits for the window to get focus.
+        if shared.focused_window == Some(self.raw_handle) {
+            unsafe {
+                shared.update_pointer_grab();
+            }
+        }
+    }
+
+    fn get_clipboard_text(&mut self, selection: Selection) -> Option<String> {
+        let selection = self.atoms.selection(selection);
+        {
+            let mut shared = self.shared.borrow_mut();
+
+            // Asking one of our own windows would wait for an answer that is
+            // only sent once the waiting is over.
+            if let Some((_, text)) = shared.selections.get(&selection) {
+                return Some(text.clone());
+            }
+
+            unsafe {
+                shared.request_selection(self.raw_handle, selection, self.atoms.utf8_string);
+            }
+        }
+
+        // The answer comes as events, and the events of the other windows
+        // that arrive in the meantime are queued as usual. The connection is
+        // only borrowed while they are handled, not while waiting for them.
+        loop {
+            let timeout = {
+                let mut shared = self.shared.borrow_mut();
+                unsafe {
+                    shared.pump();
+                }
+
+                let incoming = shared.incoming_selection.as_mut()?;
+                if let Some(result) = incoming.result.take() {
+                    shared.incoming_selection = None;
+                    return result;
+                }
+                match SELECTION_TIMEOUT.checked_sub(incoming.last_progress.elapsed()) {
+                    Some(timeout) => timeout,
+                    None => {
+                        shared.incoming_selection = None;
+                        return None;
+                    }
+                }
+            };
+
+            unsafe {
+                wait_for_events(self.connection, timeout);
+            }
+        }
+    }
+
+    fn set_clipboard_text(&mut self, selection: Selection, text: &str) {
+        let selection = self.atoms.selection(selection);
+        let mut shared = self.shared.borrow_mut();
+
+        // Claiming the selection fails without an error if another client
+        // claimed it at a later time, so the owner is checked afterwards.
+        let owner = unsafe {
+            xcb::xcb_set_selection_owner(
+                self.connection,
+                self.raw_handle,
+                selection,
+                shared.last_timestamp,
+            );
+
+            let cookie = xcb::xcb_get_selection_owner(self.connection, selection);
+            let reply =
+                xcb::xcb_get_selection_owner_reply(self.connection, cookie, std::ptr::null_mut());
+            if reply == std::ptr::null_mut() {
+                return;
+            }
+            let owner = (*reply).owner;
+            xcb::free(reply as *mut c_void);
+            owner
+        };
+
+        if owner == self.raw_handle {
+            shared
+                .selections
+                .insert(selection, (self.raw_handle, text.to_owned()));
+        }
+    }
+
+    #[cfg(feature = "xim")]
+    fn set_ime_allowed(&mut self, allowed: bool) {
+        unsafe {
+            self.shared
+                .borrow_mut()
+                .set_ime_allowed(self.raw_handle, allowed);
+        }
+    }
+
+    #[cfg(feature = "xim")]
+    fn set_ime_position(&mut self, x: i32, y: i32) {
+        unsafe {
+            self.shared
+                .borrow_mut()
+                .set_ime_position(self.raw_handle, x, y);
+        }
+    }
+
+    // Without the `xim` feature there is no input method to talk to.
+    #[cfg(not(feature = "xim"))]
+    fn set_ime_allowed(&mut self, _allowed: bool) {}
+
+    #[cfg(not(feature = "xim"))]
+    fn set_ime_position(&mut self, _x: i32, _y: i32) {}
+
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    fn close(&mut self) {
+        if !self.is_open {
+            return;
+        }
+
+        unsafe {
+            self.restore_video_mode();
+            xcb::xcb_destroy_window(self.connection, self.raw_handle);
+            xcb::xcb_flush(self.connection);
+        }
+
+        // The X server drops the selections of a window along with it.
+        let mut shared = self.shared.borrow_mut();
+        shared
+            .selections
+            .retain(|_, (owner, _)| *owner != self.raw_handle);
+        if shared
+            .drag
+            .as_ref()
+            .map_or(false, |drag| drag.window == self.raw_handle)
+        {
+            shared.drag = None;
+        }
+        drop(shared);
+
+        self.is_open = false;
+    }
+
+    fn poll_events(&mut self) -> Events<'_> {
+        unsafe {
+            self.shared.borrow_mut().pump();
+        }
+        run_callbacks(&self.shared, |connection| &mut connection.events);
+        self.shared
+            .borrow_mut()
+            .events
+            .take_window(self.id(), &mut self.events);
+
+        self.events.drain(..)
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        self.close();
+
+        let mut shared = self.shared.borrow_mut();
+        shared.events.remove_window(self.id());
+        shared.geometry.remove(&self.raw_handle);
+        shared.scale_factors.remove(&self.raw_handle);
+        shared.cursor_grabs.remove(&self.raw_handle);
+        #[cfg(feature = "xim")]
+        unsafe {
+            shared.remove_ime_window(self.raw_handle);
+        }
+        if shared.focused_window == Some(self.raw_handle) {
+            shared.focused_window = None;
+            unsafe {
+                shared.update_pointer_grab();
+            }
+        }
+
+        unsafe {
+            if self.hidden_cursor != 0 {
+                xcb::xcb_free_cursor(self.connection, self.hidden_cursor);
+            }
+            self.free_custom_cursor();
+            if self.colormap != 0 {
+                xcb::xcb_free_colormap(self.connection, self.colormap);
+            }
+            xcb::xcb_flush(self.connection);
+        }
+    }
+}
+
+// The handles borrow the window, and only `close` and dropping it destroy the
+// X11 window, so they cannot outlive it.
+impl HasWindowHandle for Window {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        if !self.is_open {
+            return Err(HandleError::Unavailable);
+        }
+
+        let window = NonZeroU32::new(self.raw_handle).ok_or(HandleError::Unavailable)?;
+        unsafe {
+            Ok(WindowHandle::borrow_raw(
+                XcbWindowHandle::new(window).into(),
+            ))
+        }
+    }
+}
+
+impl HasDisplayHandle for Window {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        // The windows are always on the first screen.
+        let connection = NonNull::new(self.connection as *mut c_void);
+        unsafe {
+            Ok(DisplayHandle::borrow_raw(
+                XcbDisplayHandle::new(connection, 0).into(),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+
+    #[test]
+    fn xft_dpi_is_found_among_other_resources() {
+        let resources = "Xcursor.size:\t24\nXft.dpi:\t144\nXft.hinting:\t1\n";
+        assert_eq!(parse_xft_dpi(resources), Some(144.0));
+        assert_eq!(parse_xft_dpi("Xft.hinting:\t1\n"), None);
+    }
+
+    #[test]
+    fn scale_factor_is_rounded_to_a_half() {
+        // A 27" 4K monitor and a 24" 1080p one.
+        assert_eq!(scale_factor_from_size((3840, 2160), (597, 336)), Some(1.5));
+        assert_eq!(scale_factor_from_size((1920, 1080), (531, 299)), Some(1.0));
+        assert_eq!(scale_factor_from_size((1920, 1080), (0, 0)), None);
+    }
+
+    #[test]
+    fn geometry_is_clamped_to_16_bits() {
+        assert_eq!(clamp_size((100_000, 600)), (65535, 600));
+        assert_eq!(clamp_position((100_000, -100_000)), (32767, -32768));
+        assert_eq!(clamp_position((-5, 5)), (-5, 5));
+    }
+
+    #[test]
+    fn only_file_uris_are_paths() {
+        let uri_list = b"# dragged from a file manager\r\n\
+            file:///home/user/My%20Notes.txt\r\n\
+            https://example.com/image.png\r\n\
+            file://localhost/tmp/%C3%A9t%C3%A9\r\n\
+            file:///100%\r\n";
+
+        assert_eq!(
+            parse_uri_list(uri_list),
+            [
+                PathBuf::from("/home/user/My Notes.txt"),
+                PathBuf::from("/tmp/\u{e9}t\u{e9}"),
+                PathBuf::from("/100%"),
+            ]
+        );
+    }
+
+    // Needs an X server, like the other ignored tests. The window methods
+    // borrow the connection, which must not be borrowed by the event loop
+    // while the callbacks run.
+    #[test]
+    #[ignore]
+    fn callbacks_can_use_their_window() {
+        let mut event_loop = EventLoop::new().unwrap();
+        let window = event_loop.create_window(&WindowBuilder::new()).unwrap();
+        let window = Rc::new(RefCell::new(window));
+        let was_called = Rc::new(Cell::new(false));
+
+        let (handle, called) = (Rc::downgrade(&window), was_called.clone());
+        window.borrow_mut().set_event_callback(Box::new(move |_| {
+            let window = handle.upgrade().unwrap();
+            let mut window = window.borrow_mut();
+            window.set_cursor_grab(CursorGrab::Confined);
+            window.get_clipboard_text(Selection::Clipboard);
+            called.set(true);
+        }));
+        window.borrow_mut().show();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !was_called.get() && Instant::now() < deadline {
+            for _ in event_loop.poll_events() {}
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(was_called.get());
+    }
+
+    // Needs an X server, like the other ignored tests.
+    #[test]
+    #[ignore]
+    fn positions_beyond_16_bits_are_clamped() {
+        let builder = WindowBuilder::new()
+            .with_position(100_000, -100_000)
+            .with_size(100_000, 1);
+        let window = Window::new(&builder).unwrap();
+        assert_eq!(
+            window.shared.borrow().geometry.get(&window.raw_handle),
+            Some(&((u32::from(u16::MAX), 1), (32767, -32768)))
+        );
+    }
+
+    // Needs an X server with RENDER. Even with BIG-REQUESTS the largest request
+    // is 16 MiB, which the pixels of this cursor are just over.
+    #[test]
+    #[ignore]
+    fn custom_cursors_larger_than_a_request_are_split() {
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        let cursor = CustomCursor::new(vec![255; 2048 * 2048 * 4], 2048, 2048, (0, 0)).unwrap();
+        window.set_custom_cursor(&cursor).unwrap();
+        assert_ne!(window.custom_cursor, 0);
+    }
+
+    // Needs an X server without a window manager, which would drop the state
+    // as soon as the window is focused.
+    #[test]
+    #[ignore]
+    fn attention_requested_before_show_is_kept() {
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        window.request_attention(UserAttention::Informational);
+        window.show();
+
+        let state = unsafe {
+            let cookie = xcb::xcb_get_property(
+                window.connection,
+                0,
+                window.raw_handle,
+                window.atoms.net_wm_state,
+                xcb::XCB_ATOM_ATOM,
+                0,
+                32,
+            );
+            let reply =
+                xcb::xcb_get_property_reply(window.connection, cookie, std::ptr::null_mut());
+            let state = std::slice::from_raw_parts(
+                xcb::xcb_get_property_value(reply) as *const xcb::xcb_atom_t,
+                (xcb::xcb_get_property_value_length(reply) / 4) as usize,
+            )
+            .to_vec();
+            xcb::free(reply as *mut c_void);
+            state
+        };
+        assert_eq!(state, [window.atoms.net_wm_state_demands_attention]);
+    }
+
+    // Needs an X server with RandR, which Xvfb has. Its monitor has a single
+    // mode, so that is the one switched to.
+    #[test]
+    #[ignore]
+    fn exclusive_fullscreen_switches_the_mode_back() {
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        let video_mode = window.current_monitor().unwrap().video_modes[0];
+        window.show();
+
+        window.set_fullscreen(FullscreenMode::Exclusive(video_mode));
+        assert!(window.switched_crtc.is_some());
+        window.set_fullscreen(FullscreenMode::Windowed);
+        assert!(window.switched_crtc.is_none());
+    }
+
+    // Needs an X server, like the test below. The source of the drag is a bare
+    // XCB client that sends the messages a file manager would, in order.
+    #[test]
+    #[ignore]
+    fn dropped_files_are_read_from_the_source() {
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        let target = window.raw_handle;
+
+        let source = std::thread::spawn(move || unsafe {
+            let connection = xcb::xcb_connect(std::ptr::null(), std::ptr::null_mut());
+            let atoms = Atoms::new(connection).unwrap();
+            let screen = xcb::xcb_setup_roots_iterator(xcb::xcb_get_setup(connection)).data;
+
+            let source = xcb::xcb_generate_id(connection);
+            xcb::xcb_create_window(
+                connection,
+                0,
+                source,
+                (*screen).root,
+                0,
+                0,
+                1,
+                1,
+                0,
+                xcb::XCB_WINDOW_CLASS_INPUT_ONLY.try_into().unwrap(),
+                0,
+                0,
+                std::ptr::null(),
+            );
+            xcb::xcb_set_selection_owner(
+                connection,
+                source,
+                atoms.xdnd_selection,
+                xcb::XCB_CURRENT_TIME,
+            );
+
+            let send = |message_type: xcb::xcb_atom_t, data: [u32; 5]| {
+                let mut event: xcb::xcb_client_message_event_t = std::mem::zeroed();
+                event.response_type = xcb::XCB_CLIENT_MESSAGE as u8;
+                event.format = 32;
+                event.window = target;
+                event.type_ = message_type;
+                event.data.data32 = data;
+                xcb::xcb_send_event(
+                    connection,
+                    0,
+                    target,
+                    xcb::XCB_EVENT_MASK_NO_EVENT,
+                    &event as *const xcb::xcb_client_message_event_t as *const i8,
+                );
+            };
+            send(
+                atoms.xdnd_enter,
+                [source, 5 << 24, atoms.text_uri_list, 0, 0],
+            );
+            send(
+                atoms.xdnd_position,
+                [source, 0, (10 << 16) | 20, 0, atoms.xdnd_action_copy],
+            );
+            send(atoms.xdnd_drop, [source, 0, 0, 0, 0]);
+            xcb::xcb_flush(connection);
+
+            // Answers the request for the files, then waits for the drop to
+            // be finished.
+            let uri_list = b"file:///tmp/dropped%20file\r\n";
+            let mut is_accepted = false;
+            loop {
+                let event = xcb::xcb_wait_for_event(connection);
+                match ((*event).response_type & !0x80) as u32 {
+                    xcb::XCB_SELECTION_REQUEST => {
+                        let request = event as *const xcb::xcb_selection_request_event_t;
+                        xcb::xcb_change_property(
+                            connection,
+                            xcb::XCB_PROP_MODE_REPLACE.try_into().unwrap(),
+                            (*request).requestor,
+                            (*request).property,
+                            atoms.text_uri_list,
+                            8,
+                            uri_list.len().try_into().unwrap(),
+                            uri_list.as_ptr() as *const c_void,
+                        );
+
+                        let mut notify: [u8; 32] = [0; 32];
+                        let event = notify.as_mut_ptr() as *mut xcb::xcb_selection_notify_event_t;
+                        (*event).response_type = xcb::XCB_SELECTION_NOTIFY as u8;
+                        (*event).time = (*request).time;
+                        (*event).requestor = (*request).requestor;
+                        (*event).selection = (*request).selection;
+                        (*event).target = (*request).target;
+                        (*event).property = (*request).property;
+                        xcb::xcb_send_event(
+                            connection,
+                            0,
+                            (*request).requestor,
+                            xcb::XCB_EVENT_MASK_NO_EVENT,
+                            notify.as_ptr() as *const i8,
+                        );
+                        xcb::xcb_flush(connection);
+                    }
+                    xcb::XCB_CLIENT_MESSAGE => {
+                        let message = event as *const xcb::xcb_client_message_event_t;
+                        if (*message).type_ == atoms.xdnd_finished {
+                            is_accepted = (*message).data.data32[1] & 1 != 0;
+                            xcb::free(event as *mut c_void);
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+                xcb::free(event as *mut c_void);
+            }
+
+            xcb::xcb_disconnect(connection);
+            is_accepted
+        });
+
+        let mut events = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline
+            && !events
+                .iter()
+                .any(|event| matches!(event, Event::FileDropped { .. }))
+        {
+            events.extend(window.poll_events());
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(source.join().unwrap());
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, Event::FileHoverStarted)));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            Event::FileDropped { paths, .. } if paths == &[PathBuf::from("/tmp/dropped file")]
+        )));
+    }
+
+    // Needs an X server, e.g. `xvfb-run cargo test -- --ignored`. The owner of
+    // the selection has to keep answering while it is read, so it runs on a
+    // thread of its own.
+    #[test]
+    #[ignore]
+    fn large_clipboard_text_is_sent_in_chunks() {
+        let text = "nengine ".repeat(INCR_CHUNK_SIZE / 4);
+        let is_done = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+
+        let owner = {
+            let text = text.clone();
+            let is_done = is_done.clone();
+            std::thread::spawn(move || {
+                let mut window = Window::new(&WindowBuilder::new()).unwrap();
+                window.set_clipboard_text(Selection::Clipboard, &text);
+                sender.send(()).unwrap();
+
+                while !is_done.load(Ordering::Relaxed) {
+                    for _ in window.poll_events() {}
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            })
+        };
+
+        receiver.recv().unwrap();
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        let pasted = window.get_clipboard_text(Selection::Clipboard);
+        is_done.store(true, Ordering::Relaxed);
+        owner.join().unwrap();
+
+        assert_eq!(pasted.as_deref(), Some(text.as_str()));
+    }
+}
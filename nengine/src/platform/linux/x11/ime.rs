@@ -0,0 +1,618 @@
+// Input methods on X11 speak XIM, which xcb-imdkit implements on top of XCB.
+// The composing happens in the input method: keys go to it first, and the
+// ones it does not use come back through `forward_event`. The text that
+// is being composed is sent to the window to draw, which is what `ImePreedit`
+// is for.
+
+use super::Connection;
+use crate::{ffi::xcb, platform::WindowId, Event};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::c_void;
+use std::os::raw::c_char;
+
+/// `XIMPreeditCallbacks | XIMStatusNothing`. The window draws the text that
+/// is being composed, and there is no status area.
+const INPUT_STYLE: u32 = 0x0002 | 0x0400;
+
+/// Set in the status of a preedit draw that has no text, e.g. when characters
+/// were only deleted.
+const PREEDIT_DRAW_NO_STRING: u32 = 0x1;
+
+/// The input context of a window that an input method was allowed for.
+struct InputContext {
+    /// 0 until the input method created it.
+    ic: xcb::xcb_xic_t,
+    is_allowed: bool,
+    /// Where the candidate window goes, in pixels from the top-left corner
+    /// of the window.
+    position: xcb::xcb_point_t,
+    /// The text that is being composed. XIM edits it by characters.
+    preedit: Vec<char>,
+}
+
+impl InputContext {
+    fn new() -> InputContext {
+        InputContext {
+            ic: 0,
+            is_allowed: false,
+            position: xcb::xcb_point_t { x: 0, y: 0 },
+            preedit: Vec::new(),
+        }
+    }
+}
+
+/// What XIM called back with. XIM only calls back from within its own fun-
+/// ctions, which the connection is borrowed for, so the callbacks queue these
+/// and the connection handles them once the function returns.
+enum Message {
+    Opened,
+    Disconnected,
+    ContextCreated(xcb::xcb_xic_t),
+    ForwardedKey(xcb::xcb_key_press_event_t),
+    Commit(xcb::xcb_xic_t, String),
+    PreeditStart(xcb::xcb_xic_t),
+    PreeditDraw {
+        ic: xcb::xcb_xic_t,
+        first: usize,
+        length: usize,
+        text: String,
+        caret: usize,
+    },
+    PreeditDone(xcb::xcb_xic_t),
+}
+
+type Messages = RefCell<VecDeque<Message>>;
+
+/// The XIM client of a connection. It only connects to the input method that
+/// `XMODIFIERS` names once a window allows one, so games that never do are
+/// left alone.
+pub(super) struct InputMethod {
+    /// Null until a window allows an input method.
+    xim: *mut xcb::xcb_xim_t,
+    is_open: bool,
+    contexts: HashMap<xcb::xcb_window_t, InputContext>,
+    /// The windows whose input context was asked for, oldest first. XIM cre-
+    /// ates them in the order they were asked for.
+    pending_contexts: VecDeque<xcb::xcb_window_t>,
+    /// The key and time of the last auto-repeat that was sent to the input
+    /// method, so that it is still a repeat if it comes back.
+    repeated_key: Option<(xcb::xcb_keycode_t, xcb::xcb_timestamp_t)>,
+    /// What the callbacks were called with. It is boxed because XIM holds on
+    /// to it.
+    messages: Box<Messages>,
+}
+
+impl InputMethod {
+    pub(super) fn new() -> InputMethod {
+        InputMethod {
+            xim: std::ptr::null_mut(),
+            is_open: false,
+            contexts: HashMap::new(),
+            pending_contexts: VecDeque::new(),
+            repeated_key: None,
+            messages: Box::new(RefCell::new(VecDeque::new())),
+        }
+    }
+
+    fn next_message(&self) -> Option<Message> {
+        self.messages.borrow_mut().pop_front()
+    }
+
+    /// What the callbacks get to queue their messages.
+    fn user_data(&self) -> *mut c_void {
+        &*self.messages as *const Messages as *mut c_void
+    }
+
+    fn find_context(
+        &mut self,
+        ic: xcb::xcb_xic_t,
+    ) -> Option<(xcb::xcb_window_t, &mut InputContext)> {
+        self.contexts
+            .iter_mut()
+            .find(|(_, context)| context.ic == ic)
+            .map(|(&window, context)| (window, context))
+    }
+}
+
+/// Replaces `length` characters of the preedit at `first` with `text`, which
+/// is how XIM sends changes to it. Returns the new text along with the caret
+/// as a byte offset into it.
+fn apply_preedit_draw(
+    preedit: &mut Vec<char>,
+    first: usize,
+    length: usize,
+    text: &str,
+    caret: usize,
+) -> (String, usize) {
+    let first = first.min(preedit.len());
+    let end = (first + length).min(preedit.len());
+    preedit.splice(first..end, text.chars());
+
+    let caret = caret.min(preedit.len());
+    let cursor = preedit[..caret].iter().map(|c| c.len_utf8()).sum();
+    (preedit.iter().collect(), cursor)
+}
+
+/// Turns text from the input method into a Rust string. It is UTF-8 if the
+/// input method agreed to that, and `COMPOUND_TEXT` otherwise.
+unsafe fn decode_text(xim: *mut xcb::xcb_xim_t, text: *const c_char, length: usize) -> String {
+    if text == std::ptr::null() || length == 0 {
+        return String::new();
+    }
+
+    if xcb::xcb_xim_encoding_is_utf8(xim) {
+        let bytes = std::slice::from_raw_parts(text as *const u8, length);
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+
+    let mut utf8_length = 0;
+    let utf8 = xcb::xcb_compound_text_to_utf8(text, length, &mut utf8_length);
+    if utf8 == std::ptr::null_mut() {
+        return String::new();
+    }
+    let bytes = std::slice::from_raw_parts(utf8 as *const u8, utf8_length);
+    let decoded = String::from_utf8_lossy(bytes).into_owned();
+    xcb::free(utf8 as *mut c_void);
+
+    decoded
+}
+
+unsafe fn push_message(user_data: *mut c_void, message: Message) {
+    (*(user_data as *const Messages))
+        .borrow_mut()
+        .push_back(message);
+}
+
+impl Connection {
+    /// Lets an input method compose text for a window, or stops it.
+    pub(super) unsafe fn set_ime_allowed(&mut self, window: xcb::xcb_window_t, allowed: bool) {
+        if !allowed {
+            if let Some(context) = self.input_method.contexts.get_mut(&window) {
+                context.is_allowed = false;
+                if context.ic != 0 {
+                    xcb::xcb_xim_unset_ic_focus(self.input_method.xim, context.ic);
+                }
+
+                // Whatever was being composed is dropped.
+                if !context.preedit.is_empty() {
+                    context.preedit.clear();
+                    self.events.push(
+                        WindowId(window.into()),
+                        Event::ImePreedit {
+                            text: String::new(),
+                            cursor: None,
+                        },
+                    );
+                }
+            }
+            self.handle_ime_messages();
+            return;
+        }
+
+        if self.input_method.xim == std::ptr::null_mut() && !self.open_input_method() {
+            return;
+        }
+
+        let context = self
+            .input_method
+            .contexts
+            .entry(window)
+            .or_insert_with(InputContext::new);
+        context.is_allowed = true;
+        if context.ic != 0 {
+            if self.focused_window == Some(window) {
+                xcb::xcb_xim_set_ic_focus(self.input_method.xim, context.ic);
+            }
+        } else if self.input_method.is_open {
+            self.create_input_context(window);
+        }
+        self.handle_ime_messages();
+    }
+
+    /// Moves the candidate window of the input method of a window.
+    pub(super) unsafe fn set_ime_position(&mut self, window: xcb::xcb_window_t, x: i32, y: i32) {
+        let clamp_to_i16 = |value: i32| value.clamp(i16::MIN.into(), i16::MAX.into()) as i16;
+        let context = self
+            .input_method
+            .contexts
+            .entry(window)
+            .or_insert_with(InputContext::new);
+        context.position = xcb::xcb_point_t {
+            x: clamp_to_i16(x),
+            y: clamp_to_i16(y),
+        };
+
+        if context.ic == 0 {
+            return;
+        }
+
+        let xim = self.input_method.xim;
+        let attributes = xcb::xcb_xim_create_nested_list(
+            xim,
+            xcb::XCB_XIM_XNSpotLocation.as_ptr() as *const c_char,
+            &context.position as *const xcb::xcb_point_t,
+            std::ptr::null::<c_void>(),
+        );
+        xcb::xcb_xim_set_ic_values(
+            xim,
+            context.ic,
+            None,
+            std::ptr::null_mut(),
+            xcb::XCB_XIM_XNPreeditAttributes.as_ptr() as *const c_char,
+            &attributes as *const xcb::xcb_xim_nested_list,
+            std::ptr::null::<c_void>(),
+        );
+        xcb::free(attributes.data as *mut c_void);
+        self.handle_ime_messages();
+    }
+
+    /// Tells the input method which window has the keyboard focus.
+    pub(super) unsafe fn update_ime_focus(&mut self, window: xcb::xcb_window_t, is_focused: bool) {
+        let ic = match self.input_method.contexts.get(&window) {
+            Some(context) if context.is_allowed && context.ic != 0 => context.ic,
+            _ => return,
+        };
+
+        if is_focused {
+            xcb::xcb_xim_set_ic_focus(self.input_method.xim, ic);
+        } else {
+            xcb::xcb_xim_unset_ic_focus(self.input_method.xim, ic);
+        }
+        self.handle_ime_messages();
+    }
+
+    /// Forgets the input context of a window that was destroyed.
+    pub(super) unsafe fn remove_ime_window(&mut self, window: xcb::xcb_window_t) {
+        if let Some(context) = self.input_method.contexts.remove(&window) {
+            if context.ic != 0 {
+                xcb::xcb_xim_destroy_ic(
+                    self.input_method.xim,
+                    context.ic,
+                    None,
+                    std::ptr::null_mut(),
+                );
+                self.handle_ime_messages();
+            }
+        }
+    }
+
+    /// Hands an event to XIM, which talks to the input method through client
+    /// messages and properties. Returns true if the event was for it.
+    pub(super) unsafe fn filter_ime_event(&mut self, event: *mut xcb::xcb_generic_event_t) -> bool {
+        if self.input_method.xim == std::ptr::null_mut() {
+            return false;
+        }
+
+        let is_filtered = xcb::xcb_xim_filter_event(self.input_method.xim, event);
+        self.handle_ime_messages();
+        is_filtered
+    }
+
+    /// Sends a key press or release to the input method of the window instead
+    /// of handling it. Returns false if the window has none.
+    pub(super) unsafe fn forward_key_to_ime(
+        &mut self,
+        event: *mut xcb::xcb_key_press_event_t,
+        is_repeat: bool,
+    ) -> bool {
+        let ic = match self.input_method.contexts.get(&(*event).event) {
+            Some(context) if context.is_allowed && context.ic != 0 => context.ic,
+            _ => return false,
+        };
+
+        if is_repeat {
+            self.input_method.repeated_key = Some(((*event).detail, (*event).time));
+        }
+        let is_forwarded = xcb::xcb_xim_forward_event(self.input_method.xim, ic, event);
+        self.handle_ime_messages();
+        is_forwarded
+    }
+
+    /// Disconnects from the input method. It has to happen before the conne-
+    /// ction to the X server is closed.
+    pub(super) unsafe fn close_input_method(&mut self) {
+        if self.input_method.xim != std::ptr::null_mut() {
+            xcb::xcb_xim_close(self.input_method.xim);
+            xcb::xcb_xim_destroy(self.input_method.xim);
+            self.input_method.xim = std::ptr::null_mut();
+        }
+    }
+
+    /// Sets up the XIM client and starts connecting to the input method.
+    unsafe fn open_input_method(&mut self) -> bool {
+        let xim = xcb::xcb_xim_create(self.raw, 0, std::ptr::null());
+        if xim == std::ptr::null_mut() {
+            return false;
+        }
+        self.input_method.xim = xim;
+
+        let callbacks = xcb::xcb_xim_im_callback {
+            forward_event: Some(handle_forward_event),
+            commit_string: Some(handle_commit_string),
+            preedit_start: Some(handle_preedit_start),
+            preedit_draw: Some(handle_preedit_draw),
+            preedit_done: Some(handle_preedit_done),
+            disconnected: Some(handle_disconnected),
+            ..std::mem::zeroed()
+        };
+        let user_data = self.input_method.user_data();
+        xcb::xcb_xim_set_im_callback(xim, &callbacks, user_data);
+        xcb::xcb_xim_set_use_utf8_string(xim, true);
+
+        // Connecting again once the input method is restarted is left to XIM.
+        xcb::xcb_xim_open(xim, Some(handle_open), true, user_data);
+        true
+    }
+
+    unsafe fn create_input_context(&mut self, window: xcb::xcb_window_t) {
+        if self.input_method.pending_contexts.contains(&window) {
+            return;
+        }
+        let position = match self.input_method.contexts.get(&window) {
+            Some(context) => context.position,
+            None => return,
+        };
+
+        let xim = self.input_method.xim;
+        let attributes = xcb::xcb_xim_create_nested_list(
+            xim,
+            xcb::XCB_XIM_XNSpotLocation.as_ptr() as *const c_char,
+            &position as *const xcb::xcb_point_t,
+            std::ptr::null::<c_void>(),
+        );
+        self.input_method.pending_contexts.push_back(window);
+        let is_sent = xcb::xcb_xim_create_ic(
+            xim,
+            Some(handle_input_context_created),
+            self.input_method.user_data(),
+            xcb::XCB_XIM_XNInputStyle.as_ptr() as *const c_char,
+            &INPUT_STYLE as *const u32,
+            xcb::XCB_XIM_XNClientWindow.as_ptr() as *const c_char,
+            &window as *const xcb::xcb_window_t,
+            xcb::XCB_XIM_XNFocusWindow.as_ptr() as *const c_char,
+            &window as *const xcb::xcb_window_t,
+            xcb::XCB_XIM_XNPreeditAttributes.as_ptr() as *const c_char,
+            &attributes as *const xcb::xcb_xim_nested_list,
+            std::ptr::null::<c_void>(),
+        );
+        xcb::free(attributes.data as *mut c_void);
+
+        if !is_sent {
+            self.input_method.pending_contexts.pop_back();
+        }
+    }
+
+    /// Handles what XIM called back with. Handling it can call XIM again, so
+    /// this goes on until nothing is left.
+    unsafe fn handle_ime_messages(&mut self) {
+        while let Some(message) = self.input_method.next_message() {
+            match message {
+                Message::Opened => {
+                    self.input_method.is_open = true;
+                    let windows: Vec<xcb::xcb_window_t> = self
+                        .input_method
+                        .contexts
+                        .iter()
+                        .filter(|(_, context)| context.is_allowed && context.ic == 0)
+                        .map(|(&window, _)| window)
+                        .collect();
+                    for window in windows {
+                        self.create_input_context(window);
+                    }
+                }
+                Message::Disconnected => {
+                    self.input_method.is_open = false;
+                    self.input_method.pending_contexts.clear();
+                    for context in self.input_method.contexts.values_mut() {
+                        context.ic = 0;
+                    }
+                }
+                Message::ContextCreated(ic) => self.handle_input_context_created(ic),
+                Message::ForwardedKey(mut event) => match (event.response_type & !0x80) as u32 {
+                    xcb::XCB_KEY_PRESS => {
+                        let is_repeat = self.input_method.repeated_key.take()
+                            == Some((event.detail, event.time));
+                        self.handle_key_press(&mut event, is_repeat);
+                    }
+                    xcb::XCB_KEY_RELEASE => self.handle_key_release(&mut event),
+                    _ => {}
+                },
+                Message::Commit(ic, text) => {
+                    if let Some((window, _)) = self.input_method.find_context(ic) {
+                        self.events
+                            .push(WindowId(window.into()), Event::ImeCommit { text });
+                    }
+                }
+                Message::PreeditStart(ic) => {
+                    if let Some((_, context)) = self.input_method.find_context(ic) {
+                        context.preedit.clear();
+                    }
+                }
+                Message::PreeditDraw {
+                    ic,
+                    first,
+                    length,
+                    text,
+                    caret,
+                } => {
+                    if let Some((window, context)) = self.input_method.find_context(ic) {
+                        let (text, cursor) =
+                            apply_preedit_draw(&mut context.preedit, first, length, &text, caret);
+                        self.events.push(
+                            WindowId(window.into()),
+                            Event::ImePreedit {
+                                text,
+                                cursor: Some(cursor),
+                            },
+                        );
+                    }
+                }
+                Message::PreeditDone(ic) => {
+                    if let Some((window, context)) = self.input_method.find_context(ic) {
+                        context.preedit.clear();
+                        self.events.push(
+                            WindowId(window.into()),
+                            Event::ImePreedit {
+                                text: String::new(),
+                                cursor: None,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+
+        xcb::xcb_flush(self.raw);
+    }
+
+    unsafe fn handle_input_context_created(&mut self, ic: xcb::xcb_xic_t) {
+        let xim = self.input_method.xim;
+        let window = match self.input_method.pending_contexts.pop_front() {
+            Some(window) => window,
+            None => return,
+        };
+
+        // The window may have disallowed it in the meantime, then the context
+        // is only kept for later. If it was closed, nobody needs it anymore.
+        match self.input_method.contexts.get_mut(&window) {
+            Some(context) => {
+                context.ic = ic;
+                if context.is_allowed && self.focused_window == Some(window) {
+                    xcb::xcb_xim_set_ic_focus(xim, ic);
+                }
+            }
+            None => {
+                xcb::xcb_xim_destroy_ic(xim, ic, None, std::ptr::null_mut());
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn handle_open(_xim: *mut xcb::xcb_xim_t, user_data: *mut c_void) {
+    push_message(user_data, Message::Opened);
+}
+
+unsafe extern "C" fn handle_input_context_created(
+    _xim: *mut xcb::xcb_xim_t,
+    ic: xcb::xcb_xic_t,
+    user_data: *mut c_void,
+) {
+    push_message(user_data, Message::ContextCreated(ic));
+}
+
+unsafe extern "C" fn handle_disconnected(_xim: *mut xcb::xcb_xim_t, user_data: *mut c_void) {
+    push_message(user_data, Message::Disconnected);
+}
+
+unsafe extern "C" fn handle_forward_event(
+    _xim: *mut xcb::xcb_xim_t,
+    _ic: xcb::xcb_xic_t,
+    event: *mut xcb::xcb_key_press_event_t,
+    user_data: *mut c_void,
+) {
+    push_message(user_data, Message::ForwardedKey(*event));
+}
+
+unsafe extern "C" fn handle_commit_string(
+    xim: *mut xcb::xcb_xim_t,
+    ic: xcb::xcb_xic_t,
+    _flag: u32,
+    text: *mut c_char,
+    length: u32,
+    _keysyms: *mut u32,
+    _keysym_count: usize,
+    user_data: *mut c_void,
+) {
+    let text = decode_text(xim, text, length as usize);
+    if !text.is_empty() {
+        push_message(user_data, Message::Commit(ic, text));
+    }
+}
+
+unsafe extern "C" fn handle_preedit_start(
+    _xim: *mut xcb::xcb_xim_t,
+    ic: xcb::xcb_xic_t,
+    user_data: *mut c_void,
+) {
+    push_message(user_data, Message::PreeditStart(ic));
+}
+
+unsafe extern "C" fn handle_preedit_draw(
+    xim: *mut xcb::xcb_xim_t,
+    ic: xcb::xcb_xic_t,
+    frame: *mut xcb::xcb_im_preedit_draw_fr_t,
+    user_data: *mut c_void,
+) {
+    let frame = &*frame;
+    let text = if frame.status & PREEDIT_DRAW_NO_STRING != 0 {
+        String::new()
+    } else {
+        decode_text(
+            xim,
+            frame.preedit_string as *const c_char,
+            frame.length_of_preedit_string.into(),
+        )
+    };
+
+    push_message(
+        user_data,
+        Message::PreeditDraw {
+            ic,
+            first: frame.chg_first.max(0) as usize,
+            length: frame.chg_length.max(0) as usize,
+            text,
+            caret: frame.caret.max(0) as usize,
+        },
+    );
+}
+
+unsafe extern "C" fn handle_preedit_done(
+    _xim: *mut xcb::xcb_xim_t,
+    ic: xcb::xcb_xic_t,
+    user_data: *mut c_void,
+) {
+    push_message(user_data, Message::PreeditDone(ic));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Window;
+    use super::*;
+    use crate::platform::{CrossPlatformWindow, WindowBuilder};
+
+    #[test]
+    fn preedit_draws_replace_characters() {
+        let mut preedit = Vec::new();
+        assert_eq!(
+            apply_preedit_draw(&mut preedit, 0, 0, "にほ", 2),
+            ("にほ".to_owned(), 6)
+        );
+        assert_eq!(
+            apply_preedit_draw(&mut preedit, 1, 1, "ほん", 3),
+            ("にほん".to_owned(), 9)
+        );
+        // Deleting past the end only deletes what is there.
+        assert_eq!(
+            apply_preedit_draw(&mut preedit, 2, 5, "", 2),
+            ("にほ".to_owned(), 6)
+        );
+    }
+
+    // Needs an X server without an input method, which is what Xvfb is on its
+    // own. The window has to wait for one until it shows up.
+    #[test]
+    #[ignore]
+    fn ime_waits_for_an_input_method() {
+        let mut window = Window::new(&WindowBuilder::new()).unwrap();
+        window.set_ime_allowed(true);
+        window.set_ime_position(10, 100_000);
+        window.poll_events().for_each(drop);
+
+        let shared = window.shared.borrow();
+        let context = &shared.input_method.contexts[&window.raw_handle];
+        assert!(context.is_allowed);
+        assert_eq!(context.ic, 0);
+        assert_eq!((context.position.x, context.position.y), (10, i16::MAX));
+    }
+}
@@ -2,23 +2,483 @@ use windows::{
     core::PCWSTR,
     w,
     Win32::{
-        Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM},
+        Foundation::{
+            GetLastError, BOOL, ERROR_CLASS_ALREADY_EXISTS, HANDLE, HINSTANCE, HWND, LPARAM,
+            LRESULT, POINT, RECT, WPARAM,
+        },
+        Graphics::Dwm::{
+            DwmEnableBlurBehindWindow, DWM_BB_BLURREGION, DWM_BB_ENABLE, DWM_BLURBEHIND,
+        },
+        Graphics::Gdi::{
+            ChangeDisplaySettingsExW, ClientToScreen, CreateBitmap, CreateDCW, CreateRectRgn,
+            DeleteDC, DeleteObject, EnumDisplayDevicesW, EnumDisplayMonitors, EnumDisplaySettingsW,
+            GetDeviceCaps, GetMonitorInfoW, MonitorFromWindow, ValidateRect, CDS_FULLSCREEN,
+            CDS_TYPE, DEVMODEW, DISPLAY_DEVICEW, DISP_CHANGE_SUCCESSFUL, ENUM_CURRENT_SETTINGS,
+            ENUM_DISPLAY_SETTINGS_MODE, HDC, HMONITOR, HORZSIZE, MONITORINFO, MONITORINFOEXW,
+            MONITORINFOF_PRIMARY, MONITOR_DEFAULTTONEAREST, VERTSIZE,
+        },
+        System::DataExchange::{
+            CloseClipboard, EmptyClipboard, GetClipboardData, OpenClipboard, SetClipboardData,
+        },
         System::LibraryLoader::GetModuleHandleW,
+        System::Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE},
+        UI::HiDpi::{
+            GetDpiForMonitor, GetDpiForWindow, SetProcessDpiAwarenessContext,
+            DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, MDT_EFFECTIVE_DPI,
+        },
+        UI::Input::KeyboardAndMouse::{
+            GetFocus, GetKeyState, TrackMouseEvent, TME_LEAVE, TRACKMOUSEEVENT, VIRTUAL_KEY,
+            VK_CAPITAL, VK_CONTROL, VK_LWIN, VK_MENU, VK_NUMLOCK, VK_RWIN, VK_SHIFT,
+        },
+        UI::Input::{
+            GetRawInputData, RegisterRawInputDevices, HRAWINPUT, RAWINPUT, RAWINPUTDEVICE,
+            RAWINPUTDEVICE_FLAGS, RAWINPUTHEADER, RID_INPUT, RIM_TYPEMOUSE,
+        },
         UI::WindowsAndMessaging::{
-            CreateWindowExW, DefWindowProcW, DispatchMessageW, LoadCursorW, PeekMessageW,
-            PostQuitMessage, RegisterClassW, ShowWindow, TranslateMessage, CW_USEDEFAULT, HMENU,
-            IDC_ARROW, MSG, PM_REMOVE, SW_SHOWNORMAL, WINDOW_EX_STYLE, WM_CLOSE, WM_QUIT,
-            WNDCLASSW, WS_OVERLAPPEDWINDOW,
+            AdjustWindowRectEx, ClipCursor, CreateIcon, CreateIconIndirect, CreateWindowExW,
+            DefWindowProcW, DestroyCursor, DestroyIcon, DestroyWindow, DispatchMessageW,
+            FlashWindowEx, GetClientRect, GetWindowLongPtrW, GetWindowRect, LoadCursorW,
+            PeekMessageW, RegisterClassW, SendMessageW, SetCursor, SetWindowLongPtrW, SetWindowPos,
+            SetWindowTextW, ShowWindow, TranslateMessage, CREATESTRUCTW, CW_USEDEFAULT, FLASHWINFO,
+            FLASHW_ALL, FLASHW_TIMERNOFG, FLASHW_TRAY, GWLP_USERDATA, GWL_EXSTYLE, GWL_STYLE,
+            HCURSOR, HICON, HMENU, HTCLIENT, HWND_NOTOPMOST, HWND_TOPMOST, ICONINFO, ICON_BIG,
+            ICON_SMALL, IDC_ARROW, IDC_CROSS, IDC_HAND, IDC_IBEAM, IDC_NO, IDC_SIZEALL,
+            IDC_SIZENESW, IDC_SIZENS, IDC_SIZENWSE, IDC_SIZEWE, IDC_WAIT, MINMAXINFO, MSG,
+            PM_REMOVE, SHOW_WINDOW_CMD, SIZE_MINIMIZED, SWP_FRAMECHANGED, SWP_NOACTIVATE,
+            SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SW_MAXIMIZE, SW_MINIMIZE, SW_RESTORE,
+            SW_SHOWMAXIMIZED, SW_SHOWMINIMIZED, SW_SHOWNORMAL, UNICODE_NOCHAR, WINDOW_EX_STYLE,
+            WINDOW_STYLE, WM_CHAR, WM_CLOSE, WM_DPICHANGED, WM_DROPFILES, WM_GETMINMAXINFO,
+            WM_IME_COMPOSITION, WM_IME_ENDCOMPOSITION, WM_IME_SETCONTEXT, WM_IME_STARTCOMPOSITION,
+            WM_INPUT, WM_KEYDOWN, WM_KEYUP, WM_KILLFOCUS, WM_LBUTTONDOWN, WM_LBUTTONUP,
+            WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_MOVE,
+            WM_NCCREATE, WM_PAINT, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETCURSOR, WM_SETFOCUS,
+            WM_SETICON, WM_SIZE, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_UNICHAR, WM_XBUTTONDOWN,
+            WM_XBUTTONUP, WNDCLASSW, WS_EX_TOPMOST, WS_MAXIMIZEBOX, WS_OVERLAPPEDWINDOW, WS_POPUP,
+            WS_THICKFRAME,
         },
     },
 };
 
+use super::{
+    run_callbacks, CrossPlatformEventLoop, CrossPlatformWindow, CursorGrab, CursorIcon,
+    CustomCursor, EventQueue, Events, FullscreenMode, Icon, Monitor, Selection, UserAttention,
+    VideoMode, WindowBuilder, WindowError, WindowEvents, WindowId,
+};
+use crate::{Event, Modifiers, MouseButton, ScrollUnit};
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, Win32WindowHandle, WindowHandle,
+};
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::ffi::OsString;
-use std::{os::windows::ffi::OsStrExt, str::FromStr};
+use std::num::NonZeroIsize;
+use std::os::windows::ffi::OsStrExt;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
+
+mod drop_target;
+mod keyboard;
+mod mouse;
+
+// Lives in `UI::Controls` in the windows crate, which is not worth pulling in
+// for a single constant.
+const WM_MOUSELEAVE: u32 = 0x02A3;
+
+// The same goes for these, which live in `Devices::HumanInterfaceDevice`. The
+// first two pick the mouse out of the HID usage tables.
+const HID_USAGE_PAGE_GENERIC: u16 = 0x01;
+const HID_USAGE_GENERIC_MOUSE: u16 = 0x02;
+const MOUSE_MOVE_ABSOLUTE: u16 = 0x01;
+
+// And this one lives in `System::Ole`.
+const CF_UNICODETEXT: u32 = 13;
+
+// These live in `UI::Shell`, which is a large feature for four functions. The
+// handle they take is an `HDROP`.
+#[link(name = "shell32")]
+extern "system" {
+    fn DragAcceptFiles(window: HWND, accept: BOOL);
+    fn DragQueryFileW(drop: isize, index: u32, file: *mut u16, length: u32) -> u32;
+    fn DragQueryPoint(drop: isize, point: *mut POINT) -> BOOL;
+    fn DragFinish(drop: isize);
+}
+
+// And these live in `UI::Input::Ime`. The input context they take is an `HIMC`.
+const GCS_COMPSTR: u32 = 0x0008;
+const GCS_CURSORPOS: u32 = 0x0080;
+const GCS_RESULTSTR: u32 = 0x0800;
+const CFS_CANDIDATEPOS: u32 = 0x0040;
+const IACE_DEFAULT: u32 = 0x0010;
+const ISC_SHOWUICOMPOSITIONWINDOW: u32 = 0x8000_0000;
+
+/// `CANDIDATEFORM`, which says where the candidate window goes.
+#[repr(C)]
+struct CandidateForm {
+    index: u32,
+    style: u32,
+    current_position: POINT,
+    area: RECT,
+}
+
+#[link(name = "imm32")]
+extern "system" {
+    fn ImmGetContext(window: HWND) -> isize;
+    fn ImmReleaseContext(window: HWND, context: isize) -> BOOL;
+    fn ImmGetCompositionStringW(
+        context: isize,
+        index: u32,
+        buffer: *mut std::ffi::c_void,
+        length: u32,
+    ) -> i32;
+    fn ImmSetCandidateWindow(context: isize, form: *const CandidateForm) -> BOOL;
+    fn ImmAssociateContextEx(window: HWND, context: isize, flags: u32) -> BOOL;
+}
+
+/// The part of a window that `window_proc` needs to reach. A pointer to it is
+/// kept in the `GWLP_USERDATA` slot of the window, which is why it is boxed.
+struct WindowState {
+    /// The id of the window, which is only known once `WM_NCCREATE` arrives.
+    id: WindowId,
+    /// The queue shared by every window of the event loop.
+    queue: Rc<RefCell<EventQueue>>,
+
+    /// The first half of a UTF-16 surrogate pair sent through `WM_CHAR`.
+    high_surrogate: Option<u16>,
+
+    /// Whether `TrackMouseEvent` was asked to send `WM_MOUSELEAVE`. It has to
+    /// be asked again every time the cursor comes back.
+    is_tracking_mouse: bool,
+
+    min_size: Option<(u32, u32)>,
+    max_size: Option<(u32, u32)>,
+    is_cursor_visible: bool,
+    /// The cursor shown over the client area while the cursor is visible.
+    cursor: HCURSOR,
+    cursor_grab: CursorGrab,
+    /// Where the candidate window of the input method goes, in client coordi-
+    /// nates.
+    ime_position: (i32, i32),
+}
+
+/// The Win32 event loop. The message queue belongs to the thread rather than
+/// to a window, so this only has to keep the event queue the windows share.
+pub struct EventLoop {
+    queue: Rc<RefCell<EventQueue>>,
+    events: VecDeque<(WindowId, Event)>,
+}
 
 pub struct Window {
     raw_handle: HWND,
     is_open: bool,
+    is_shown: bool,
+
+    /// How `show` shows the window, so that it can be minimized or maximized
+    /// before it is shown.
+    show_command: SHOW_WINDOW_CMD,
+
+    /// The style and the outer rectangle from before going fullscreen.
+    windowed_placement: Option<(isize, RECT)>,
+    /// The display device that exclusive fullscreen switched to another mo-
+    /// de, if any.
+    switched_device: Option<[u16; 32]>,
+
+    /// The icon set on the window, which is owned by it. It is 0 if there is
+    /// none.
+    icon: HICON,
+    /// The cursor made from the last custom cursor image, which is owned by
+    /// the window. It is 0 if there is none.
+    custom_cursor: HCURSOR,
+
+    /// Whether files come through the OLE drop target, otherwise they come
+    /// through `WM_DROPFILES` without any hover events.
+    has_drop_target: bool,
+
+    state: Box<WindowState>,
+
+    /// The events of this window that were taken from the shared queue.
+    events: VecDeque<Event>,
+}
+
+/// Turns a UTF-16 code unit from `WM_CHAR` into text. Characters outside of
+/// the BMP arrive as two messages, so the first half is held onto until the
+/// second one shows up.
+fn decode_utf16_char(high_surrogate: &mut Option<u16>, code_unit: u16) -> Option<String> {
+    let character = match code_unit {
+        0xD800..=0xDBFF => {
+            *high_surrogate = Some(code_unit);
+            return None;
+        }
+        0xDC00..=0xDFFF => {
+            let high_surrogate = high_surrogate.take()?;
+            char::decode_utf16([high_surrogate, code_unit])
+                .next()?
+                .ok()?
+        }
+        _ => {
+            *high_surrogate = None;
+            char::from_u32(code_unit.into())?
+        }
+    };
+
+    // Backspace, Enter, Escape and friends also come through `WM_CHAR`.
+    if character.is_control() {
+        None
+    } else {
+        Some(character.to_string())
+    }
+}
+
+/// Reads the current modifier state. This is only accurate while handling a
+/// keyboard message, which is the only place it is used.
+unsafe fn get_modifiers() -> Modifiers {
+    let is_down = |key: VIRTUAL_KEY| GetKeyState(key.0.into()) < 0;
+    let is_toggled = |key: VIRTUAL_KEY| GetKeyState(key.0.into()) & 1 != 0;
+
+    let mut modifiers = Modifiers::empty();
+    modifiers.set(Modifiers::SHIFT, is_down(VK_SHIFT));
+    modifiers.set(Modifiers::CONTROL, is_down(VK_CONTROL));
+    modifiers.set(Modifiers::ALT, is_down(VK_MENU));
+    modifiers.set(Modifiers::SUPER, is_down(VK_LWIN) || is_down(VK_RWIN));
+    modifiers.set(Modifiers::CAPS_LOCK, is_toggled(VK_CAPITAL));
+    modifiers.set(Modifiers::NUM_LOCK, is_toggled(VK_NUMLOCK));
+
+    modifiers
+}
+
+impl From<windows::core::Error> for WindowError {
+    fn from(error: windows::core::Error) -> WindowError {
+        WindowError::Os(error.message().to_string())
+    }
+}
+
+impl WindowState {
+    fn push(&self, event: Event) {
+        self.queue.borrow_mut().push(self.id, event);
+    }
+}
+
+/// Dispatches every message that is waiting on the thread, which covers all of
+/// its windows at once.
+unsafe fn pump_messages() {
+    let mut message: MSG = { Default::default() };
+    while PeekMessageW(&mut message, HWND::default(), 0, 0, PM_REMOVE).as_bool() {
+        TranslateMessage(&message);
+        DispatchMessageW(&message);
+    }
+}
+
+/// Opens the clipboard for a window. Another program can have it open for a
+/// moment, so this tries a few times before giving up.
+unsafe fn open_clipboard(window: HWND) -> bool {
+    for _ in 0..10 {
+        if OpenClipboard(window).as_bool() {
+            return true;
+        }
+
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    false
+}
+
+/// Keeps the cursor inside of the client area of a window, or on its middle
+/// pixel if it is locked. The clip applies to the whole desktop, so it is only
+/// set while the window has focus.
+unsafe fn clip_cursor(window: HWND, grab: CursorGrab) {
+    if grab == CursorGrab::None {
+        ClipCursor(std::ptr::null());
+        return;
+    }
+
+    // The clip rectangle is in screen coordinates.
+    let mut rect = RECT::default();
+    GetClientRect(window, &mut rect);
+    let mut top_left = POINT {
+        x: rect.left,
+        y: rect.top,
+    };
+    let mut bottom_right = POINT {
+        x: rect.right,
+        y: rect.bottom,
+    };
+    ClientToScreen(window, &mut top_left);
+    ClientToScreen(window, &mut bottom_right);
+
+    let mut clip = RECT {
+        left: top_left.x,
+        top: top_left.y,
+        right: bottom_right.x,
+        bottom: bottom_right.y,
+    };
+    if grab == CursorGrab::Locked {
+        let x = (clip.left + clip.right) / 2;
+        let y = (clip.top + clip.bottom) / 2;
+        clip = RECT {
+            left: x,
+            top: y,
+            right: x + 1,
+            bottom: y + 1,
+        };
+    }
+
+    ClipCursor(&clip);
+}
+
+/// Turns an inner size into the outer size of a window, which is what most of
+/// the Win32 functions want.
+unsafe fn get_outer_size(window: HWND, width: u32, height: u32) -> (i32, i32) {
+    let style = WINDOW_STYLE(GetWindowLongPtrW(window, GWL_STYLE) as u32);
+    let ex_style = WINDOW_EX_STYLE(GetWindowLongPtrW(window, GWL_EXSTYLE) as u32);
+    add_frame_size(width, height, style, ex_style)
+}
+
+/// Adds the size of the frame a window with these styles has to an inner
+/// size. Win32 sizes are signed, so larger ones are clamped to `i32::MAX`.
+unsafe fn add_frame_size(
+    width: u32,
+    height: u32,
+    style: WINDOW_STYLE,
+    ex_style: WINDOW_EX_STYLE,
+) -> (i32, i32) {
+    // The frame is measured around an empty rectangle, so that adding it
+    // cannot overflow.
+    let mut frame = RECT::default();
+    AdjustWindowRectEx(&mut frame, style, false, ex_style);
+
+    let clamp_to_i32 = |value: u32| i32::try_from(value).unwrap_or(i32::MAX);
+    (
+        clamp_to_i32(width).saturating_add(frame.right - frame.left),
+        clamp_to_i32(height).saturating_add(frame.bottom - frame.top),
+    )
+}
+
+/// Turns a string from Win32, which ends at the first null, into a Rust one.
+fn from_wide(wide: &[u16]) -> String {
+    let length = wide.iter().position(|&c| c == 0).unwrap_or(wide.len());
+    String::from_utf16_lossy(&wide[..length])
+}
+
+fn to_video_mode(mode: &DEVMODEW) -> VideoMode {
+    // 0 and 1 both mean the hardware default, whatever that is.
+    let refresh_rate_millihertz = if mode.dmDisplayFrequency > 1 {
+        mode.dmDisplayFrequency * 1000
+    } else {
+        0
+    };
+
+    VideoMode {
+        size: (mode.dmPelsWidth, mode.dmPelsHeight),
+        bit_depth: mode.dmBitsPerPel.try_into().unwrap_or(0),
+        refresh_rate_millihertz,
+    }
+}
+
+/// Describes a monitor. Windows names monitors like `\\.\DISPLAY1`, so the
+/// name of the model is taken from the display device behind it instead.
+unsafe fn get_monitor(monitor: HMONITOR) -> Option<Monitor> {
+    let mut info = MONITORINFOEXW::default();
+    info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+    if !GetMonitorInfoW(
+        monitor,
+        &mut info as *mut MONITORINFOEXW as *mut MONITORINFO,
+    )
+    .as_bool()
+    {
+        return None;
+    }
+    let device = PCWSTR(info.szDevice.as_ptr());
+    let rect = info.monitorInfo.rcMonitor;
+
+    let mut display_device = DISPLAY_DEVICEW {
+        cb: std::mem::size_of::<DISPLAY_DEVICEW>() as u32,
+        ..Default::default()
+    };
+    let name = if EnumDisplayDevicesW(device, 0, &mut display_device, 0).as_bool() {
+        from_wide(&display_device.DeviceString)
+    } else {
+        from_wide(&info.szDevice)
+    };
+
+    // The same mode shows up once for every way it can be scaled.
+    let mut mode = DEVMODEW {
+        dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+        ..Default::default()
+    };
+    let mut video_modes = Vec::new();
+    let mut index = 0;
+    while EnumDisplaySettingsW(device, ENUM_DISPLAY_SETTINGS_MODE(index), &mut mode).as_bool() {
+        let video_mode = to_video_mode(&mode);
+        if !video_modes.contains(&video_mode) {
+            video_modes.push(video_mode);
+        }
+        index += 1;
+    }
+    let refresh_rate_millihertz =
+        if EnumDisplaySettingsW(device, ENUM_CURRENT_SETTINGS, &mut mode).as_bool() {
+            to_video_mode(&mode).refresh_rate_millihertz
+        } else {
+            0
+        };
+
+    let dc = CreateDCW(w!("DISPLAY"), device, PCWSTR::null(), std::ptr::null());
+    let physical_size = if dc == HDC::default() {
+        (0, 0)
+    } else {
+        let size = (
+            GetDeviceCaps(dc, HORZSIZE).max(0) as u32,
+            GetDeviceCaps(dc, VERTSIZE).max(0) as u32,
+        );
+        DeleteDC(dc);
+        size
+    };
+
+    // The effective DPI already includes the scaling the user picked.
+    let (mut dpi_x, mut dpi_y) = (0, 0);
+    let scale_factor =
+        if GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y).is_ok() {
+            f64::from(dpi_x) / 96.0
+        } else {
+            1.0
+        };
+
+    Some(Monitor {
+        name,
+        position: (rect.left, rect.top),
+        size: (
+            (rect.right - rect.left) as u32,
+            (rect.bottom - rect.top) as u32,
+        ),
+        physical_size,
+        refresh_rate_millihertz,
+        scale_factor,
+        is_primary: info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY != 0,
+        video_modes,
+    })
+}
+
+unsafe extern "system" fn collect_monitor(
+    monitor: HMONITOR,
+    _dc: HDC,
+    _rect: *mut RECT,
+    data: LPARAM,
+) -> BOOL {
+    let monitors = &mut *(data.0 as *mut Vec<Monitor>);
+    monitors.extend(get_monitor(monitor));
+
+    true.into()
+}
+
+unsafe fn get_monitors() -> Vec<Monitor> {
+    let mut monitors: Vec<Monitor> = Vec::new();
+    EnumDisplayMonitors(
+        HDC::default(),
+        std::ptr::null(),
+        Some(collect_monitor),
+        LPARAM(&mut monitors as *mut Vec<Monitor> as isize),
+    );
+
+    monitors
 }
 
 unsafe extern "system" fn window_proc(
@@ -27,85 +487,1173 @@ unsafe extern "system" fn window_proc(
     w_param: WPARAM,
     l_param: LPARAM,
 ) -> LRESULT {
+    if message == WM_NCCREATE {
+        let create_struct = l_param.0 as *const CREATESTRUCTW;
+        let state = (*create_struct).lpCreateParams as *mut WindowState;
+        (*state).id = WindowId(window.0 as u64);
+        SetWindowLongPtrW(window, GWLP_USERDATA, state as isize);
+    }
+
+    let state = GetWindowLongPtrW(window, GWLP_USERDATA) as *mut WindowState;
+    if state == std::ptr::null_mut() {
+        return DefWindowProcW(window, message, w_param, l_param);
+    }
+
     match message {
         WM_CLOSE => {
-            PostQuitMessage(0);
-            windows::Win32::Foundation::LRESULT(0)
+            // `DefWindowProcW` would destroy the window here, instead it is up
+            // to the user to call `close`.
+            (*state).push(Event::CloseRequested);
+            LRESULT(0)
+        }
+        WM_SIZE => {
+            // Minimizing sends a size of zero, which is not a real resize.
+            if w_param.0 as u32 != SIZE_MINIMIZED {
+                (*state).push(Event::Resized {
+                    width: (l_param.0 & 0xFFFF) as u32,
+                    height: ((l_param.0 >> 16) & 0xFFFF) as u32,
+                });
+            }
+
+            // The clip rectangle does not follow the window on its own.
+            if (*state).cursor_grab != CursorGrab::None && GetFocus() == window {
+                clip_cursor(window, (*state).cursor_grab);
+            }
+
+            LRESULT(0)
+        }
+        WM_DPICHANGED => {
+            // Both words of `w_param` hold the new DPI. Windows suggests a new
+            // rectangle for the window, which keeps it the same size relative
+            // to what is around it, and moving there sends `Resized`.
+            let dpi = (w_param.0 & 0xFFFF) as u32;
+            (*state).push(Event::ScaleFactorChanged {
+                scale_factor: f64::from(dpi) / 96.0,
+            });
+
+            let rect = *(l_param.0 as *const RECT);
+            SetWindowPos(
+                window,
+                HWND::default(),
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            );
+
+            LRESULT(0)
+        }
+        WM_MOVE => {
+            let (x, y) = mouse::lparam_to_point(l_param.0);
+            (*state).push(Event::Moved { x, y });
+
+            if (*state).cursor_grab != CursorGrab::None && GetFocus() == window {
+                clip_cursor(window, (*state).cursor_grab);
+            }
+
+            LRESULT(0)
+        }
+        WM_SETFOCUS => {
+            if (*state).cursor_grab != CursorGrab::None {
+                clip_cursor(window, (*state).cursor_grab);
+            }
+
+            (*state).push(Event::FocusGained);
+            LRESULT(0)
+        }
+        WM_KILLFOCUS => {
+            if (*state).cursor_grab != CursorGrab::None {
+                clip_cursor(window, CursorGrab::None);
+            }
+
+            (*state).push(Event::FocusLost);
+            LRESULT(0)
+        }
+        WM_MOUSEMOVE => {
+            // There is no message for the cursor entering the window, so the
+            // first move after leaving counts as one.
+            if !(*state).is_tracking_mouse {
+                let mut track_mouse_event = TRACKMOUSEEVENT {
+                    cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as u32,
+                    dwFlags: TME_LEAVE,
+                    hwndTrack: window,
+                    dwHoverTime: 0,
+                };
+                TrackMouseEvent(&mut track_mouse_event);
+
+                (*state).is_tracking_mouse = true;
+                (*state).push(Event::CursorEntered);
+            }
+
+            // A locked cursor sits on the middle pixel, so its position means
+            // nothing.
+            if (*state).cursor_grab != CursorGrab::Locked {
+                let (x, y) = mouse::lparam_to_point(l_param.0);
+                (*state).push(Event::MouseMove {
+                    x: x.into(),
+                    y: y.into(),
+                });
+            }
+
+            LRESULT(0)
+        }
+        WM_INPUT => {
+            let mut raw_input: RAWINPUT = std::mem::zeroed();
+            let mut size = std::mem::size_of::<RAWINPUT>() as u32;
+            let result = GetRawInputData(
+                HRAWINPUT(l_param.0),
+                RID_INPUT,
+                &mut raw_input as *mut RAWINPUT as *mut std::ffi::c_void,
+                &mut size,
+                std::mem::size_of::<RAWINPUTHEADER>() as u32,
+            );
+
+            if result != u32::MAX && raw_input.header.dwType == RIM_TYPEMOUSE.0 {
+                let mouse = raw_input.data.mouse;
+
+                // Tablets and remote desktop send absolute positions, which are
+                // no use as motion. Button presses come without any motion.
+                if mouse.usFlags & MOUSE_MOVE_ABSOLUTE == 0
+                    && (mouse.lLastX != 0 || mouse.lLastY != 0)
+                {
+                    (*state).push(Event::MouseDelta {
+                        dx: mouse.lLastX.into(),
+                        dy: mouse.lLastY.into(),
+                    });
+                }
+            }
+
+            // `DefWindowProcW` cleans up after the raw input.
+            DefWindowProcW(window, message, w_param, l_param)
+        }
+        WM_DROPFILES => {
+            let drop = w_param.0 as isize;
+            let paths = drop_target::query_dropped_files(drop);
+
+            let mut point = POINT::default();
+            DragQueryPoint(drop, &mut point);
+            DragFinish(drop);
+
+            (*state).push(Event::FileDropped {
+                paths,
+                position: (point.x.into(), point.y.into()),
+            });
+            LRESULT(0)
+        }
+        WM_MOUSELEAVE => {
+            (*state).is_tracking_mouse = false;
+            (*state).push(Event::CursorLeft);
+            LRESULT(0)
+        }
+        WM_GETMINMAXINFO => {
+            let min_max_info = l_param.0 as *mut MINMAXINFO;
+
+            if let Some((width, height)) = (*state).min_size {
+                let (width, height) = get_outer_size(window, width, height);
+                (*min_max_info).ptMinTrackSize.x = width;
+                (*min_max_info).ptMinTrackSize.y = height;
+            }
+            if let Some((width, height)) = (*state).max_size {
+                let (width, height) = get_outer_size(window, width, height);
+                (*min_max_info).ptMaxTrackSize.x = width;
+                (*min_max_info).ptMaxTrackSize.y = height;
+            }
+
+            LRESULT(0)
+        }
+        WM_SETCURSOR => {
+            // The low word says which part of the window the cursor is over,
+            // the cursor is only changed over the client area.
+            if (l_param.0 & 0xFFFF) as u32 == HTCLIENT {
+                if (*state).is_cursor_visible {
+                    SetCursor((*state).cursor);
+                } else {
+                    SetCursor(HCURSOR(0));
+                }
+                return LRESULT(1);
+            }
+
+            DefWindowProcW(window, message, w_param, l_param)
+        }
+        WM_PAINT => {
+            // The update region has to be validated, otherwise `WM_PAINT` just
+            // keeps coming.
+            ValidateRect(window, std::ptr::null());
+            (*state).push(Event::RedrawRequested);
+            LRESULT(0)
+        }
+        WM_KEYDOWN | WM_KEYUP | WM_SYSKEYDOWN | WM_SYSKEYUP => {
+            let scancode = keyboard::translate_scancode(keyboard::lparam_to_scancode(l_param.0));
+            let keycode = keyboard::translate_virtual_key(w_param.0 as u16).unwrap_or(scancode);
+            let is_press = message == WM_KEYDOWN || message == WM_SYSKEYDOWN;
+
+            (*state).push(Event::Key {
+                keycode,
+                scancode,
+                is_press,
+                // Bit 30 holds the previous key state, which is only set for
+                // auto-repeated presses.
+                is_repeat: is_press && l_param.0 & (1 << 30) != 0,
+                modifiers: get_modifiers(),
+            });
+
+            // The system keys, which are F10 and anything held together with
+            // Alt, still have to reach `DefWindowProcW` so that Alt+F4 and the
+            // window menu keep working.
+            if message == WM_SYSKEYDOWN || message == WM_SYSKEYUP {
+                return DefWindowProcW(window, message, w_param, l_param);
+            }
+
+            LRESULT(0)
+        }
+        WM_LBUTTONDOWN | WM_LBUTTONUP | WM_RBUTTONDOWN | WM_RBUTTONUP | WM_MBUTTONDOWN
+        | WM_MBUTTONUP => {
+            let (button, is_press) = match message {
+                WM_LBUTTONDOWN => (MouseButton::Left, true),
+                WM_LBUTTONUP => (MouseButton::Left, false),
+                WM_RBUTTONDOWN => (MouseButton::Right, true),
+                WM_RBUTTONUP => (MouseButton::Right, false),
+                WM_MBUTTONDOWN => (MouseButton::Middle, true),
+                _ => (MouseButton::Middle, false),
+            };
+            (*state).push(Event::MouseButton { button, is_press });
+
+            LRESULT(0)
+        }
+        WM_XBUTTONDOWN | WM_XBUTTONUP => {
+            if let Some(button) = mouse::translate_xbutton(w_param.0) {
+                (*state).push(Event::MouseButton {
+                    button,
+                    is_press: message == WM_XBUTTONDOWN,
+                });
+            }
+
+            // Unlike the other buttons, these want TRUE back.
+            LRESULT(1)
+        }
+        WM_MOUSEWHEEL => {
+            (*state).push(Event::MouseScroll {
+                x: 0.0,
+                y: mouse::wparam_to_wheel_delta(w_param.0),
+                unit: ScrollUnit::Lines,
+            });
+
+            LRESULT(0)
+        }
+        WM_MOUSEHWHEEL => {
+            (*state).push(Event::MouseScroll {
+                x: mouse::wparam_to_wheel_delta(w_param.0),
+                y: 0.0,
+                unit: ScrollUnit::Lines,
+            });
+
+            LRESULT(0)
+        }
+        WM_CHAR => {
+            if let Some(text) = decode_utf16_char(&mut (*state).high_surrogate, w_param.0 as u16) {
+                (*state).push(Event::TextInput { text });
+            }
+
+            LRESULT(0)
+        }
+        WM_UNICHAR => {
+            // Returning TRUE for `UNICODE_NOCHAR` tells the sender that we und-
+            // erstand `WM_UNICHAR`.
+            if w_param.0 as u32 == UNICODE_NOCHAR {
+                return LRESULT(1);
+            }
+
+            if let Some(character) = char::from_u32(w_param.0 as u32) {
+                if !character.is_control() {
+                    (*state).push(Event::TextInput {
+                        text: character.to_string(),
+                    });
+                }
+            }
+
+            LRESULT(0)
+        }
+        WM_IME_SETCONTEXT => {
+            // The window shows the text that is being composed itself, so the
+            // input method only has to show the candidates.
+            let l_param = LPARAM(l_param.0 & !(ISC_SHOWUICOMPOSITIONWINDOW as isize));
+            DefWindowProcW(window, message, w_param, l_param)
+        }
+        WM_IME_STARTCOMPOSITION => {
+            // Not passing it on keeps the composition window of the input me-
+            // thod hidden.
+            set_candidate_position(window, (*state).ime_position);
+            LRESULT(0)
+        }
+        WM_IME_COMPOSITION => {
+            // Not passing it on keeps the result from also coming as `WM_CHAR`.
+            let context = ImmGetContext(window);
+            let flags = l_param.0 as u32;
+
+            if flags & GCS_RESULTSTR != 0 {
+                if let Some(text) = get_composition_string(context, GCS_RESULTSTR) {
+                    (*state).push(Event::ImeCommit {
+                        text: String::from_utf16_lossy(&text),
+                    });
+                }
+            }
+            if flags & GCS_COMPSTR != 0 {
+                if let Some(text) = get_composition_string(context, GCS_COMPSTR) {
+                    // The cursor is counted in UTF-16 code units.
+                    let cursor =
+                        ImmGetCompositionStringW(context, GCS_CURSORPOS, std::ptr::null_mut(), 0);
+                    let cursor = usize::try_from(cursor)
+                        .ok()
+                        .filter(|&cursor| cursor <= text.len())
+                        .map(|cursor| String::from_utf16_lossy(&text[..cursor]).len());
+
+                    (*state).push(Event::ImePreedit {
+                        text: String::from_utf16_lossy(&text),
+                        cursor,
+                    });
+                }
+            }
+
+            ImmReleaseContext(window, context);
+            LRESULT(0)
+        }
+        WM_IME_ENDCOMPOSITION => {
+            (*state).push(Event::ImePreedit {
+                text: String::new(),
+                cursor: None,
+            });
+            LRESULT(0)
         }
         _ => DefWindowProcW(window, message, w_param, l_param),
     }
 }
 
-impl super::CrossPlatformWindow for Window {
-    fn new(width: u32, height: u32, title: &str, _fullscreen: bool) -> Window {
+/// Reads one of the strings of the composition in an input context, or None
+/// if it has none.
+unsafe fn get_composition_string(context: isize, index: u32) -> Option<Vec<u16>> {
+    // The length is in bytes.
+    let length = ImmGetCompositionStringW(context, index, std::ptr::null_mut(), 0);
+    if length < 0 {
+        return None;
+    }
+
+    let mut text = vec![0; length as usize / 2];
+    ImmGetCompositionStringW(
+        context,
+        index,
+        text.as_mut_ptr() as *mut std::ffi::c_void,
+        length as u32,
+    );
+
+    Some(text)
+}
+
+/// Moves the candidate window of the input method of a window next to a point
+/// in its client area.
+unsafe fn set_candidate_position(window: HWND, (x, y): (i32, i32)) {
+    let context = ImmGetContext(window);
+    if context == 0 {
+        return;
+    }
+
+    let form = CandidateForm {
+        index: 0,
+        style: CFS_CANDIDATEPOS,
+        current_position: POINT { x, y },
+        area: RECT::default(),
+    };
+    ImmSetCandidateWindow(context, &form);
+    ImmReleaseContext(window, context);
+}
+
+impl Window {
+    /// Switches the monitor the window is on to a video mode, undoing any ea-
+    /// rlier switch first. The switch is not saved in the registry, so Win-
+    /// dows also undoes it if the program goes away without doing so.
+    unsafe fn switch_video_mode(&mut self, video_mode: VideoMode) {
+        self.restore_video_mode();
+
+        let monitor = MonitorFromWindow(self.raw_handle, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFOEXW::default();
+        info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+        if !GetMonitorInfoW(
+            monitor,
+            &mut info as *mut MONITORINFOEXW as *mut MONITORINFO,
+        )
+        .as_bool()
+        {
+            return;
+        }
+        let device = PCWSTR(info.szDevice.as_ptr());
+
+        let mut mode = DEVMODEW {
+            dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+            ..Default::default()
+        };
+        let mut index = 0;
+        while EnumDisplaySettingsW(device, ENUM_DISPLAY_SETTINGS_MODE(index), &mut mode).as_bool() {
+            if to_video_mode(&mode) == video_mode {
+                let result = ChangeDisplaySettingsExW(
+                    device,
+                    &mode,
+                    HWND::default(),
+                    CDS_FULLSCREEN,
+                    std::ptr::null(),
+                );
+                if result == DISP_CHANGE_SUCCESSFUL {
+                    self.switched_device = Some(info.szDevice);
+                }
+                return;
+            }
+            index += 1;
+        }
+    }
+
+    /// Switches the monitor back to its mode from the registry, if exclusive
+    /// fullscreen switched it.
+    unsafe fn restore_video_mode(&mut self) {
+        if let Some(device) = self.switched_device.take() {
+            ChangeDisplaySettingsExW(
+                PCWSTR(device.as_ptr()),
+                std::ptr::null(),
+                HWND::default(),
+                CDS_TYPE(0),
+                std::ptr::null(),
+            );
+        }
+    }
+
+    /// Destroys the cursor made from a custom cursor image, if there is one.
+    /// It must no longer be the cursor of the window by then.
+    unsafe fn destroy_custom_cursor(&mut self) {
+        if self.custom_cursor != HCURSOR::default() {
+            DestroyCursor(self.custom_cursor);
+            self.custom_cursor = HCURSOR::default();
+        }
+    }
+
+    /// Turns an RGBA icon into an `HICON` and sets it as both the big and the
+    /// small icon. Windows scales it to the sizes it needs.
+    unsafe fn set_icon_from(&mut self, icon: &Icon) -> Result<(), WindowError> {
+        // Icons want BGRA, and a monochrome mask with rows padded to 16 bits.
+        // The mask is left empty, since the alpha channel is used instead.
+        let bgra: Vec<u8> = icon
+            .rgba
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+            .collect();
+        let mask_stride = (icon.width as usize + 15) / 16 * 2;
+        let mask = vec![0u8; mask_stride * icon.height as usize];
+
+        let h_icon = CreateIcon(
+            GetModuleHandleW(None)?,
+            icon.width.try_into().unwrap(),
+            icon.height.try_into().unwrap(),
+            1,
+            32,
+            mask.as_ptr(),
+            bgra.as_ptr(),
+        )?;
+
+        SendMessageW(
+            self.raw_handle,
+            WM_SETICON,
+            WPARAM(ICON_BIG as usize),
+            LPARAM(h_icon.0),
+        );
+        SendMessageW(
+            self.raw_handle,
+            WM_SETICON,
+            WPARAM(ICON_SMALL as usize),
+            LPARAM(h_icon.0),
+        );
+
+        if self.icon != HICON::default() {
+            DestroyIcon(self.icon);
+        }
+        self.icon = h_icon;
+
+        Ok(())
+    }
+}
+
+impl CrossPlatformEventLoop for EventLoop {
+    fn new() -> Result<EventLoop, WindowError> {
         unsafe {
-            let class_name = w!("NENGINE_WINDOW_CLASS");
+            // Without this, Windows scales the windows up on its own and they
+            // look blurry. It fails if the awareness was already set, e.g. by
+            // an earlier event loop or the manifest, which is fine.
+            SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
 
-            let h_instance = GetModuleHandleW(None).unwrap();
             let window_class = WNDCLASSW {
-                hInstance: h_instance,
-                lpszClassName: PCWSTR::from(class_name),
+                hInstance: GetModuleHandleW(None)?,
+                lpszClassName: w!("NENGINE_WINDOW_CLASS"),
                 lpfnWndProc: Some(window_proc),
-                hCursor: LoadCursorW(HINSTANCE(0), IDC_ARROW).unwrap(),
+                hCursor: LoadCursorW(HINSTANCE(0), IDC_ARROW)?,
                 ..Default::default()
             };
 
-            RegisterClassW(&window_class);
-
-            let title = title.to_owned() + "\0";
-            let title = OsString::from_str(title.as_str()).unwrap();
-            let title = title.as_os_str();
-            let title = title.encode_wide();
-            let title: Vec<u16> = title.collect();
-
-            let window = CreateWindowExW(
-                WINDOW_EX_STYLE::default(),
-                class_name,
-                PCWSTR::from_raw(title.as_ptr()),
-                WS_OVERLAPPEDWINDOW,
-                CW_USEDEFAULT,
-                CW_USEDEFAULT,
-                width.try_into().unwrap(),
-                height.try_into().unwrap(),
+            // The class is shared by every window and every event loop, so it
+            // only fails to register for real the first time.
+            if RegisterClassW(&window_class) == 0 && GetLastError() != ERROR_CLASS_ALREADY_EXISTS {
+                return Err(windows::core::Error::from_win32().into());
+            }
+
+            // Raw mouse input is where `MouseDelta` comes from. Without a tar-
+            // get window it follows the keyboard focus, so one registration
+            // covers every window of the thread.
+            let device = RAWINPUTDEVICE {
+                usUsagePage: HID_USAGE_PAGE_GENERIC,
+                usUsage: HID_USAGE_GENERIC_MOUSE,
+                dwFlags: RAWINPUTDEVICE_FLAGS::default(),
+                hwndTarget: HWND::default(),
+            };
+            RegisterRawInputDevices(&[device], std::mem::size_of::<RAWINPUTDEVICE>() as u32)
+                .ok()?;
+        }
+
+        Ok(EventLoop {
+            queue: Rc::new(RefCell::new(EventQueue::new())),
+            events: VecDeque::new(),
+        })
+    }
+
+    fn create_window(&self, builder: &WindowBuilder) -> Result<Window, WindowError> {
+        builder.validate()?;
+        unsafe { Window::create(self.queue.clone(), builder) }
+    }
+
+    fn monitors(&self) -> Vec<Monitor> {
+        unsafe { get_monitors() }
+    }
+
+    fn poll_events(&mut self) -> WindowEvents<'_> {
+        unsafe {
+            pump_messages();
+        }
+        run_callbacks(&self.queue, |queue| queue);
+        self.events.extend(self.queue.borrow_mut().take_all());
+
+        self.events.drain(..)
+    }
+}
+
+impl Window {
+    /// Creates a window that pushes its events onto `queue`. The class is al-
+    /// ready registered by the event loop.
+    unsafe fn create(
+        queue: Rc<RefCell<EventQueue>>,
+        builder: &WindowBuilder,
+    ) -> Result<Window, WindowError> {
+        if builder.class.is_some() {
+            return Err(WindowError::Unsupported("WM_CLASS"));
+        }
+
+        let h_instance = GetModuleHandleW(None)?;
+
+        let title = builder.title.to_owned() + "\0";
+        let title = OsString::from_str(title.as_str()).unwrap();
+        let title = title.as_os_str();
+        let title = title.encode_wide();
+        let title: Vec<u16> = title.collect();
+
+        let mut state = Box::new(WindowState {
+            id: WindowId(0),
+            queue,
+            high_surrogate: None,
+            is_tracking_mouse: false,
+            min_size: None,
+            max_size: None,
+            is_cursor_visible: true,
+            cursor: LoadCursorW(HINSTANCE(0), IDC_ARROW)?,
+            cursor_grab: CursorGrab::None,
+            ime_position: (0, 0),
+        });
+
+        let mut style = if builder.decorations {
+            WS_OVERLAPPEDWINDOW
+        } else {
+            WS_POPUP
+        };
+        if !builder.resizable {
+            style &= !(WS_THICKFRAME | WS_MAXIMIZEBOX);
+        }
+
+        let mut ex_style = WINDOW_EX_STYLE::default();
+        if builder.always_on_top {
+            ex_style |= WS_EX_TOPMOST;
+        }
+
+        // `CreateWindowExW` wants the outer size, which includes the frame.
+        let (width, height) = add_frame_size(builder.size.0, builder.size.1, style, ex_style);
+
+        let (x, y) = builder.position.unwrap_or((CW_USEDEFAULT, CW_USEDEFAULT));
+
+        // A parent here makes the new window an owned window, not a child
+        // window inside of the parent.
+        let parent = match builder.parent {
+            Some(parent) => HWND(parent.0 as isize),
+            None => HWND::default(),
+        };
+
+        let window = CreateWindowExW(
+            ex_style,
+            w!("NENGINE_WINDOW_CLASS"),
+            PCWSTR::from_raw(title.as_ptr()),
+            style,
+            x,
+            y,
+            width,
+            height,
+            parent,
+            HMENU::default(),
+            h_instance,
+            &mut *state as *mut WindowState as *const std::ffi::c_void,
+        );
+        if window == HWND::default() {
+            return Err(windows::core::Error::from_win32().into());
+        }
+
+        let mut window = Window {
+            raw_handle: window,
+            is_open: true,
+            is_shown: false,
+            show_command: SW_SHOWNORMAL,
+            windowed_placement: None,
+            switched_device: None,
+            icon: HICON::default(),
+            custom_cursor: HCURSOR::default(),
+            has_drop_target: false,
+            state,
+            events: VecDeque::new(),
+        };
+
+        window.has_drop_target =
+            drop_target::register(window.raw_handle, window.id(), window.state.queue.clone());
+        if !window.has_drop_target {
+            DragAcceptFiles(window.raw_handle, true.into());
+        }
+        window.set_ime_allowed(false);
+
+        if builder.transparent {
+            // Blurring behind an empty region makes DWM use the alpha ch-
+            // annel of the window without actually blurring anything.
+            let region = CreateRectRgn(0, 0, -1, -1);
+            let blur_behind = DWM_BLURBEHIND {
+                dwFlags: DWM_BB_ENABLE | DWM_BB_BLURREGION,
+                fEnable: true.into(),
+                hRgnBlur: region,
+                fTransitionOnMaximized: false.into(),
+            };
+            let result = DwmEnableBlurBehindWindow(window.raw_handle, &blur_behind);
+            DeleteObject(region);
+            result?;
+        }
+
+        if let Some(icon) = &builder.icon {
+            window.set_icon_from(icon)?;
+        }
+
+        if builder.fullscreen != FullscreenMode::Windowed {
+            window.set_fullscreen(builder.fullscreen);
+        }
+
+        Ok(window)
+    }
+}
+
+impl CrossPlatformWindow for Window {
+    fn new(builder: &WindowBuilder) -> Result<Window, WindowError> {
+        EventLoop::new()?.create_window(builder)
+    }
+
+    fn id(&self) -> WindowId {
+        WindowId(self.raw_handle.0 as u64)
+    }
+
+    fn set_event_callback(&mut self, callback: Box<dyn FnMut(&Event)>) {
+        self.state
+            .queue
+            .borrow_mut()
+            .set_callback(self.id(), callback);
+    }
+
+    fn show(&mut self) {
+        if self.is_shown {
+            return;
+        }
+
+        unsafe {
+            ShowWindow(self.raw_handle, self.show_command);
+        }
+
+        self.is_shown = true;
+    }
+
+    fn set_title(&mut self, title: &str) {
+        let title: Vec<u16> = title.encode_utf16().chain(std::iter::once(0)).collect();
+
+        unsafe {
+            SetWindowTextW(self.raw_handle, PCWSTR::from_raw(title.as_ptr()));
+        }
+    }
+
+    fn set_size(&mut self, width: u32, height: u32) {
+        unsafe {
+            let (width, height) = get_outer_size(self.raw_handle, width, height);
+            SetWindowPos(
+                self.raw_handle,
                 HWND::default(),
-                HMENU::default(),
-                h_instance,
-                std::ptr::null(),
+                0,
+                0,
+                width,
+                height,
+                SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE,
             );
+        }
+    }
+
+    fn set_position(&mut self, x: i32, y: i32) {
+        unsafe {
+            SetWindowPos(
+                self.raw_handle,
+                HWND::default(),
+                x,
+                y,
+                0,
+                0,
+                SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+            );
+        }
+    }
+
+    fn set_fullscreen(&mut self, mode: FullscreenMode) {
+        unsafe {
+            if mode == FullscreenMode::Windowed {
+                self.restore_video_mode();
+
+                if let Some((style, rect)) = self.windowed_placement.take() {
+                    SetWindowLongPtrW(self.raw_handle, GWL_STYLE, style);
+                    SetWindowPos(
+                        self.raw_handle,
+                        HWND_NOTOPMOST,
+                        rect.left,
+                        rect.top,
+                        rect.right - rect.left,
+                        rect.bottom - rect.top,
+                        SWP_FRAMECHANGED | SWP_NOACTIVATE,
+                    );
+                }
+            } else {
+                if self.windowed_placement.is_none() {
+                    let style = GetWindowLongPtrW(self.raw_handle, GWL_STYLE);
+                    let mut rect = RECT::default();
+                    GetWindowRect(self.raw_handle, &mut rect);
+                    self.windowed_placement = Some((style, rect));
+
+                    let style = (style as u32 & !WS_OVERLAPPEDWINDOW.0) | WS_POPUP.0;
+                    SetWindowLongPtrW(self.raw_handle, GWL_STYLE, style as isize);
+                }
+
+                // The monitor has to be in the new mode before its size is
+                // looked up.
+                match mode {
+                    FullscreenMode::Exclusive(video_mode) => self.switch_video_mode(video_mode),
+                    _ => self.restore_video_mode(),
+                }
 
-            Window {
-                raw_handle: window,
-                is_open: true,
+                let monitor = MonitorFromWindow(self.raw_handle, MONITOR_DEFAULTTONEAREST);
+                let mut monitor_info = MONITORINFO {
+                    cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+                    ..Default::default()
+                };
+                GetMonitorInfoW(monitor, &mut monitor_info);
+                let rect = monitor_info.rcMonitor;
+
+                // A topmost window that covers the whole monitor is flipped to
+                // the screen directly by DWM.
+                let insert_after = if matches!(mode, FullscreenMode::Exclusive(_)) {
+                    HWND_TOPMOST
+                } else {
+                    HWND_NOTOPMOST
+                };
+                SetWindowPos(
+                    self.raw_handle,
+                    insert_after,
+                    rect.left,
+                    rect.top,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top,
+                    SWP_FRAMECHANGED | SWP_NOACTIVATE,
+                );
             }
         }
     }
 
-    fn set_event_callback(&mut self, _: fn(crate::Event)) {
-        // TODO: Fill this out later on.
+    fn monitors(&self) -> Vec<Monitor> {
+        unsafe { get_monitors() }
+    }
+
+    fn current_monitor(&self) -> Option<Monitor> {
+        unsafe { get_monitor(MonitorFromWindow(self.raw_handle, MONITOR_DEFAULTTONEAREST)) }
+    }
+
+    fn scale_factor(&self) -> f64 {
+        // 0 means the window handle is no longer valid.
+        match unsafe { GetDpiForWindow(self.raw_handle) } {
+            0 => 1.0,
+            dpi => f64::from(dpi) / 96.0,
+        }
     }
 
-    fn show(&self) {
+    fn set_resizable(&mut self, resizable: bool) {
+        let flags = (WS_THICKFRAME | WS_MAXIMIZEBOX).0 as isize;
+        let update = |style: isize| {
+            if resizable {
+                style | flags
+            } else {
+                style & !flags
+            }
+        };
+
+        // Fullscreen windows have no frame, so the change waits until the old
+        // style is restored.
+        if let Some((style, _)) = &mut self.windowed_placement {
+            *style = update(*style);
+            return;
+        }
+
         unsafe {
-            ShowWindow(self.raw_handle, SW_SHOWNORMAL);
+            let style = update(GetWindowLongPtrW(self.raw_handle, GWL_STYLE));
+            SetWindowLongPtrW(self.raw_handle, GWL_STYLE, style);
+            SetWindowPos(
+                self.raw_handle,
+                HWND::default(),
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE | SWP_FRAMECHANGED,
+            );
+        }
+    }
+
+    fn set_min_size(&mut self, size: Option<(u32, u32)>) {
+        self.state.min_size = size;
+    }
+
+    fn set_max_size(&mut self, size: Option<(u32, u32)>) {
+        self.state.max_size = size;
+    }
+
+    fn minimize(&mut self) {
+        if !self.is_shown {
+            self.show_command = SW_SHOWMINIMIZED;
+            return;
+        }
+
+        unsafe {
+            ShowWindow(self.raw_handle, SW_MINIMIZE);
+        }
+    }
+
+    // Windows remembers the size and position from before maximizing, which
+    // `SW_RESTORE` goes back to.
+    fn set_maximized(&mut self, maximized: bool) {
+        if !self.is_shown {
+            self.show_command = if maximized {
+                SW_SHOWMAXIMIZED
+            } else {
+                SW_SHOWNORMAL
+            };
+            return;
+        }
+
+        unsafe {
+            ShowWindow(
+                self.raw_handle,
+                if maximized { SW_MAXIMIZE } else { SW_RESTORE },
+            );
+        }
+    }
+
+    fn set_icon(&mut self, rgba: &[u8], width: u32, height: u32) -> Result<(), WindowError> {
+        let icon = Icon {
+            rgba: rgba.to_vec(),
+            width,
+            height,
+        };
+        icon.validate()?;
+
+        unsafe { self.set_icon_from(&icon) }
+    }
+
+    fn request_attention(&mut self, attention: UserAttention) {
+        // `FLASHW_TIMERNOFG` keeps going until the window is in the foreground.
+        // Flashing the taskbar button once leaves it highlighted until then.
+        let (flags, count) = match attention {
+            UserAttention::Critical => (FLASHW_ALL | FLASHW_TIMERNOFG, 0),
+            UserAttention::Informational => (FLASHW_TRAY, 1),
+        };
+        let flash_info = FLASHWINFO {
+            cbSize: std::mem::size_of::<FLASHWINFO>() as u32,
+            hwnd: self.raw_handle,
+            dwFlags: flags,
+            uCount: count,
+            dwTimeout: 0,
+        };
+
+        unsafe {
+            FlashWindowEx(&flash_info);
+        }
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        // Takes effect the next time the cursor moves, which is when Windows
+        // sends `WM_SETCURSOR`.
+        self.state.is_cursor_visible = visible;
+    }
+
+    fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        let name = match icon {
+            CursorIcon::Default => IDC_ARROW,
+            CursorIcon::Text => IDC_IBEAM,
+            CursorIcon::Pointer => IDC_HAND,
+            CursorIcon::Crosshair => IDC_CROSS,
+            CursorIcon::Wait => IDC_WAIT,
+            CursorIcon::NotAllowed => IDC_NO,
+            CursorIcon::Move => IDC_SIZEALL,
+            CursorIcon::EwResize => IDC_SIZEWE,
+            CursorIcon::NsResize => IDC_SIZENS,
+            CursorIcon::NwseResize => IDC_SIZENWSE,
+            CursorIcon::NeswResize => IDC_SIZENESW,
+        };
+
+        // The system cursors are shared and must not be destroyed. Like hid-
+        // ing the cursor, this takes effect the next time the cursor moves.
+        unsafe {
+            if let Ok(cursor) = LoadCursorW(HINSTANCE(0), name) {
+                self.state.cursor = cursor;
+                self.destroy_custom_cursor();
+            }
+        }
+    }
+
+    fn set_custom_cursor(&mut self, cursor: &CustomCursor) -> Result<(), WindowError> {
+        unsafe {
+            // The alpha channel decides what is see-through, so the mask is
+            // left empty like the one of the window icon.
+            let bgra: Vec<u8> = cursor
+                .rgba
+                .chunks_exact(4)
+                .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+                .collect();
+            let mask_stride = (cursor.width as usize + 15) / 16 * 2;
+            let mask = vec![0u8; mask_stride * cursor.height as usize];
+
+            let width = cursor.width.try_into().unwrap();
+            let height = cursor.height.try_into().unwrap();
+            let color_bitmap = CreateBitmap(
+                width,
+                height,
+                1,
+                32,
+                bgra.as_ptr() as *const std::ffi::c_void,
+            );
+            let mask_bitmap = CreateBitmap(
+                width,
+                height,
+                1,
+                1,
+                mask.as_ptr() as *const std::ffi::c_void,
+            );
+
+            // Unlike `CreateCursor`, this takes a color image, and the hotspot
+            // along with it.
+            let created = CreateIconIndirect(&ICONINFO {
+                fIcon: false.into(),
+                xHotspot: cursor.hotspot.0,
+                yHotspot: cursor.hotspot.1,
+                hbmMask: mask_bitmap,
+                hbmColor: color_bitmap,
+            });
+
+            // The cursor keeps its own copies of the bitmaps.
+            DeleteObject(color_bitmap);
+            DeleteObject(mask_bitmap);
+
+            let created = HCURSOR(created?.0);
+            self.state.cursor = created;
+            self.destroy_custom_cursor();
+            self.custom_cursor = created;
+        }
+        Ok(())
+    }
+
+    fn set_cursor_grab(&mut self, grab: CursorGrab) {
+        self.state.cursor_grab = grab;
+
+        // Otherwise the clip waits for `WM_SETFOCUS`.
+        unsafe {
+            if GetFocus() == self.raw_handle {
+                clip_cursor(self.raw_handle, grab);
+            }
+        }
+    }
+
+    fn set_ime_allowed(&mut self, allowed: bool) {
+        // Without an input context the keys go straight to the window.
+        let flags = if allowed { IACE_DEFAULT } else { 0 };
+        unsafe {
+            ImmAssociateContextEx(self.raw_handle, 0, flags);
+        }
+    }
+
+    fn set_ime_position(&mut self, x: i32, y: i32) {
+        self.state.ime_position = (x, y);
+        unsafe {
+            set_candidate_position(self.raw_handle, (x, y));
+        }
+    }
+
+    fn get_clipboard_text(&mut self, selection: Selection) -> Option<String> {
+        // Windows has no primary selection.
+        if selection != Selection::Clipboard {
+            return None;
+        }
+
+        unsafe {
+            if !open_clipboard(self.raw_handle) {
+                return None;
+            }
+
+            let text = GetClipboardData(CF_UNICODETEXT).ok().and_then(|data| {
+                let text = GlobalLock(data.0) as *const u16;
+                if text.is_null() {
+                    return None;
+                }
+
+                let mut length = 0;
+                while *text.add(length) != 0 {
+                    length += 1;
+                }
+                let decoded = String::from_utf16_lossy(std::slice::from_raw_parts(text, length));
+                GlobalUnlock(data.0);
+
+                Some(decoded)
+            });
+            CloseClipboard();
+
+            text
+        }
+    }
+
+    fn set_clipboard_text(&mut self, selection: Selection, text: &str) {
+        if selection != Selection::Clipboard {
+            return;
+        }
+
+        // The clipboard takes over a block of movable memory, which holds the
+        // text with a null at the end.
+        let text: Vec<u16> = text.encode_utf16().chain(Some(0)).collect();
+        unsafe {
+            let memory = GlobalAlloc(GMEM_MOVEABLE, text.len() * 2);
+            if memory == 0 {
+                return;
+            }
+
+            let destination = GlobalLock(memory) as *mut u16;
+            if destination.is_null() {
+                GlobalFree(memory);
+                return;
+            }
+            std::ptr::copy_nonoverlapping(text.as_ptr(), destination, text.len());
+            GlobalUnlock(memory);
+
+            if !open_clipboard(self.raw_handle) {
+                GlobalFree(memory);
+                return;
+            }
+
+            // The memory is only ours again if the clipboard did not take it.
+            EmptyClipboard();
+            if SetClipboardData(CF_UNICODETEXT, HANDLE(memory)).is_err() {
+                GlobalFree(memory);
+            }
+            CloseClipboard();
         }
     }
 
-    // Todo: needed to add a proper closing mechanism
     fn is_open(&self) -> bool {
         self.is_open
     }
 
-    fn poll_events(&mut self) {
+    fn close(&mut self) {
+        if !self.is_open {
+            return;
+        }
+
         unsafe {
-            let mut message: MSG = { Default::default() };
-            while PeekMessageW(&mut message, self.raw_handle, 0, 0, PM_REMOVE).as_bool() {
-                if message.message == WM_QUIT {
-                    self.is_open = false;
-                } else {
-                    TranslateMessage(&message);
-                    DispatchMessageW(&message);
-                }
+            self.restore_video_mode();
+            if self.has_drop_target {
+                drop_target::revoke(self.raw_handle);
+            }
+            DestroyWindow(self.raw_handle);
+        }
+
+        self.is_open = false;
+    }
+
+    fn poll_events(&mut self) -> Events<'_> {
+        unsafe {
+            pump_messages();
+        }
+        run_callbacks(&self.state.queue, |queue| queue);
+        self.state
+            .queue
+            .borrow_mut()
+            .take_window(self.id(), &mut self.events);
+
+        self.events.drain(..)
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        // The window still points at `state` through `GWLP_USERDATA`, so it
+        // has to go away before the state does.
+        self.close();
+        self.state.queue.borrow_mut().remove_window(self.id());
+
+        unsafe {
+            if self.icon != HICON::default() {
+                DestroyIcon(self.icon);
             }
+            self.destroy_custom_cursor();
         }
     }
 }
+
+impl HasWindowHandle for Window {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        if !self.is_open {
+            return Err(HandleError::Unavailable);
+        }
+
+        let hwnd = NonZeroIsize::new(self.raw_handle.0).ok_or(HandleError::Unavailable)?;
+        let mut handle = Win32WindowHandle::new(hwnd);
+        // The window class is registered with the module of the executable.
+        handle.hinstance = unsafe { GetModuleHandleW(None) }
+            .ok()
+            .and_then(|h_instance| NonZeroIsize::new(h_instance.0));
+
+        unsafe { Ok(WindowHandle::borrow_raw(handle.into())) }
+    }
+}
+
+impl HasDisplayHandle for Window {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        Ok(DisplayHandle::windows())
+    }
+}
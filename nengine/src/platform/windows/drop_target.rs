@@ -0,0 +1,300 @@
+// Files dragged over a window only come through OLE, which wants the window
+// to register an `IDropTarget`. The windows crate only has the COM interfaces
+// behind its `implement` machinery and a few large features, so the object
+// and the bits of `IDataObject` it uses are written out by hand, like the
+// other functions this backend declares itself.
+
+use super::DragQueryFileW;
+use crate::platform::{EventQueue, WindowId};
+use crate::Event;
+use std::cell::{Cell, RefCell};
+use std::ffi::{c_void, OsString};
+use std::os::windows::ffi::OsStringExt;
+use std::path::PathBuf;
+use std::rc::Rc;
+use windows::{
+    core::{GUID, HRESULT},
+    Win32::{
+        Foundation::{E_NOINTERFACE, HWND, POINT, POINTL, S_OK},
+        Graphics::Gdi::ScreenToClient,
+    },
+};
+
+const IID_IUNKNOWN: GUID = GUID::from_u128(0x00000000_0000_0000_c000_000000000046);
+const IID_IDROPTARGET: GUID = GUID::from_u128(0x00000122_0000_0000_c000_000000000046);
+
+// Files come as an `HDROP` in global memory, the same handle `WM_DROPFILES`
+// hands out.
+const CF_HDROP: u16 = 15;
+const DVASPECT_CONTENT: u32 = 1;
+const TYMED_HGLOBAL: u32 = 1;
+const DROPEFFECT_NONE: u32 = 0;
+const DROPEFFECT_COPY: u32 = 1;
+
+#[repr(C)]
+struct FormatEtc {
+    format: u16,
+    target_device: *mut c_void,
+    aspect: u32,
+    index: i32,
+    medium: u32,
+}
+
+/// `STGMEDIUM`. The union only holds an `HGLOBAL` for the one medium asked
+/// for.
+#[repr(C)]
+struct StgMedium {
+    medium: u32,
+    global: isize,
+    release_with: *mut c_void,
+}
+
+/// The start of the `IDataObject` vtable, up to the two methods needed.
+#[repr(C)]
+struct DataObjectVtbl {
+    query_interface: usize,
+    add_ref: usize,
+    release: usize,
+    get_data: unsafe extern "system" fn(*mut c_void, *const FormatEtc, *mut StgMedium) -> HRESULT,
+    get_data_here: usize,
+    query_get_data: unsafe extern "system" fn(*mut c_void, *const FormatEtc) -> HRESULT,
+}
+
+#[link(name = "ole32")]
+extern "system" {
+    fn OleInitialize(reserved: *mut c_void) -> HRESULT;
+    fn OleUninitialize();
+    fn RegisterDragDrop(window: HWND, drop_target: *mut DropTarget) -> HRESULT;
+    fn RevokeDragDrop(window: HWND) -> HRESULT;
+    fn ReleaseStgMedium(medium: *mut StgMedium);
+}
+
+#[repr(C)]
+struct DropTargetVtbl {
+    query_interface:
+        unsafe extern "system" fn(*mut DropTarget, *const GUID, *mut *mut c_void) -> HRESULT,
+    add_ref: unsafe extern "system" fn(*mut DropTarget) -> u32,
+    release: unsafe extern "system" fn(*mut DropTarget) -> u32,
+    drag_enter:
+        unsafe extern "system" fn(*mut DropTarget, *mut c_void, u32, POINTL, *mut u32) -> HRESULT,
+    drag_over: unsafe extern "system" fn(*mut DropTarget, u32, POINTL, *mut u32) -> HRESULT,
+    drag_leave: unsafe extern "system" fn(*mut DropTarget) -> HRESULT,
+    drop: unsafe extern "system" fn(*mut DropTarget, *mut c_void, u32, POINTL, *mut u32) -> HRESULT,
+}
+
+static DROP_TARGET_VTBL: DropTargetVtbl = DropTargetVtbl {
+    query_interface,
+    add_ref,
+    release,
+    drag_enter,
+    drag_over,
+    drag_leave,
+    drop: drop_files,
+};
+
+/// The `IDropTarget` of a window. OLE holds a reference to it until the win-
+/// dow is revoked, and frees it with the last `Release`.
+#[repr(C)]
+struct DropTarget {
+    vtbl: *const DropTargetVtbl,
+    references: Cell<u32>,
+    window: HWND,
+    id: WindowId,
+    queue: Rc<RefCell<EventQueue>>,
+    /// Whether the drag over the window has files. Other drags, e.g. of text,
+    /// are refused and send no events.
+    has_files: Cell<bool>,
+}
+
+/// Makes a window take files through OLE, which also says when they are dra-
+/// gged over it. Returns false if OLE is not available on the thread, e.g.
+/// because COM was set up there for another apartment, and then only
+/// `WM_DROPFILES` is left.
+pub(super) unsafe fn register(window: HWND, id: WindowId, queue: Rc<RefCell<EventQueue>>) -> bool {
+    if OleInitialize(std::ptr::null_mut()).is_err() {
+        return false;
+    }
+
+    let drop_target = Box::into_raw(Box::new(DropTarget {
+        vtbl: &DROP_TARGET_VTBL,
+        references: Cell::new(1),
+        window,
+        id,
+        queue,
+        has_files: Cell::new(false),
+    }));
+
+    // OLE takes its own reference, so ours is given up either way.
+    let result = RegisterDragDrop(window, drop_target);
+    release(drop_target);
+
+    if result.is_err() {
+        OleUninitialize();
+        return false;
+    }
+    true
+}
+
+/// Undoes `register`, which has to happen before the window is destroyed.
+pub(super) unsafe fn revoke(window: HWND) {
+    RevokeDragDrop(window);
+    OleUninitialize();
+}
+
+/// Reads the paths out of an `HDROP`. Index `u32::MAX` asks for the number of
+/// files, and no buffer for the length of one without the null.
+pub(super) unsafe fn query_dropped_files(drop: isize) -> Vec<PathBuf> {
+    let count = DragQueryFileW(drop, u32::MAX, std::ptr::null_mut(), 0);
+    (0..count)
+        .map(|index| {
+            let length = DragQueryFileW(drop, index, std::ptr::null_mut(), 0);
+            let mut path = vec![0; length as usize + 1];
+            DragQueryFileW(drop, index, path.as_mut_ptr(), length + 1);
+            path.truncate(length as usize);
+            PathBuf::from(OsString::from_wide(&path))
+        })
+        .collect()
+}
+
+fn hdrop_format() -> FormatEtc {
+    FormatEtc {
+        format: CF_HDROP,
+        target_device: std::ptr::null_mut(),
+        aspect: DVASPECT_CONTENT,
+        index: -1,
+        medium: TYMED_HGLOBAL,
+    }
+}
+
+unsafe fn data_object_vtbl(data_object: *mut c_void) -> &'static DataObjectVtbl {
+    &**(data_object as *const *const DataObjectVtbl)
+}
+
+unsafe fn has_files(data_object: *mut c_void) -> bool {
+    data_object != std::ptr::null_mut()
+        && (data_object_vtbl(data_object).query_get_data)(data_object, &hdrop_format()) == S_OK
+}
+
+unsafe fn read_files(data_object: *mut c_void) -> Vec<PathBuf> {
+    let mut medium = StgMedium {
+        medium: 0,
+        global: 0,
+        release_with: std::ptr::null_mut(),
+    };
+    if (data_object_vtbl(data_object).get_data)(data_object, &hdrop_format(), &mut medium).is_err()
+    {
+        return Vec::new();
+    }
+
+    let paths = query_dropped_files(medium.global);
+    ReleaseStgMedium(&mut medium);
+    paths
+}
+
+impl DropTarget {
+    fn push(&self, event: Event) {
+        self.queue.borrow_mut().push(self.id, event);
+    }
+
+    /// The effect shown to the source of the drag. Files are copied, which is
+    /// the only thing the window could be doing with them.
+    fn effect(&self) -> u32 {
+        if self.has_files.get() {
+            DROPEFFECT_COPY
+        } else {
+            DROPEFFECT_NONE
+        }
+    }
+}
+
+unsafe extern "system" fn query_interface(
+    this: *mut DropTarget,
+    iid: *const GUID,
+    object: *mut *mut c_void,
+) -> HRESULT {
+    if *iid == IID_IUNKNOWN || *iid == IID_IDROPTARGET {
+        add_ref(this);
+        *object = this as *mut c_void;
+        S_OK
+    } else {
+        *object = std::ptr::null_mut();
+        E_NOINTERFACE
+    }
+}
+
+unsafe extern "system" fn add_ref(this: *mut DropTarget) -> u32 {
+    let references = (*this).references.get() + 1;
+    (*this).references.set(references);
+    references
+}
+
+unsafe extern "system" fn release(this: *mut DropTarget) -> u32 {
+    let references = (*this).references.get() - 1;
+    (*this).references.set(references);
+    if references == 0 {
+        drop(Box::from_raw(this));
+    }
+    references
+}
+
+unsafe extern "system" fn drag_enter(
+    this: *mut DropTarget,
+    data_object: *mut c_void,
+    _key_state: u32,
+    _point: POINTL,
+    effect: *mut u32,
+) -> HRESULT {
+    let this = &*this;
+    this.has_files.set(has_files(data_object));
+    if this.has_files.get() {
+        this.push(Event::FileHoverStarted);
+    }
+
+    *effect = this.effect();
+    S_OK
+}
+
+unsafe extern "system" fn drag_over(
+    this: *mut DropTarget,
+    _key_state: u32,
+    _point: POINTL,
+    effect: *mut u32,
+) -> HRESULT {
+    *effect = (*this).effect();
+    S_OK
+}
+
+unsafe extern "system" fn drag_leave(this: *mut DropTarget) -> HRESULT {
+    let this = &*this;
+    if this.has_files.replace(false) {
+        this.push(Event::FileHoverCancelled);
+    }
+    S_OK
+}
+
+unsafe extern "system" fn drop_files(
+    this: *mut DropTarget,
+    data_object: *mut c_void,
+    _key_state: u32,
+    point: POINTL,
+    effect: *mut u32,
+) -> HRESULT {
+    let this = &*this;
+    *effect = this.effect();
+    if !this.has_files.replace(false) {
+        return S_OK;
+    }
+
+    // The point is in screen coordinates.
+    let mut position = POINT {
+        x: point.x,
+        y: point.y,
+    };
+    ScreenToClient(this.window, &mut position);
+
+    this.push(Event::FileDropped {
+        paths: read_files(data_object),
+        position: (position.x.into(), position.y.into()),
+    });
+    S_OK
+}
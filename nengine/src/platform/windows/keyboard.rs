@@ -0,0 +1,344 @@
+use crate::Key;
+
+/// Pulls the scancode out of the `LPARAM` of a keyboard message. Extended ke-
+/// ys get the `0xE0` prefix they have on the wire, so e.g. the right Control
+/// becomes `0xE01D`.
+pub(super) fn lparam_to_scancode(l_param: isize) -> u32 {
+    let scancode = ((l_param >> 16) & 0xFF) as u32;
+    let is_extended = l_param & (1 << 24) != 0;
+
+    if is_extended {
+        0xE000 | scancode
+    } else {
+        scancode
+    }
+}
+
+/// Translates a Set 1 scancode into the physical `Key` at that position, ig-
+/// noring the keyboard layout.
+pub(super) fn translate_scancode(scancode: u32) -> Key {
+    match scancode {
+        0x0001 => Key::Escape,
+        0x0002 => Key::One,
+        0x0003 => Key::Two,
+        0x0004 => Key::Three,
+        0x0005 => Key::Four,
+        0x0006 => Key::Five,
+        0x0007 => Key::Six,
+        0x0008 => Key::Seven,
+        0x0009 => Key::Eight,
+        0x000A => Key::Nine,
+        0x000B => Key::Zero,
+        0x000C => Key::Hyphen,
+        0x000D => Key::EqualSign,
+        0x000E => Key::Backspace,
+        0x000F => Key::Tab,
+        0x0010 => Key::Q,
+        0x0011 => Key::W,
+        0x0012 => Key::E,
+        0x0013 => Key::R,
+        0x0014 => Key::T,
+        0x0015 => Key::Y,
+        0x0016 => Key::U,
+        0x0017 => Key::I,
+        0x0018 => Key::O,
+        0x0019 => Key::P,
+        0x001A => Key::LeftSquareBracket,
+        0x001B => Key::RightSquareBracket,
+        0x001C => Key::Enter,
+        0x001D => Key::LeftControl,
+        0x001E => Key::A,
+        0x001F => Key::S,
+        0x0020 => Key::D,
+        0x0021 => Key::F,
+        0x0022 => Key::G,
+        0x0023 => Key::H,
+        0x0024 => Key::J,
+        0x0025 => Key::K,
+        0x0026 => Key::L,
+        0x0027 => Key::Semicolon,
+        0x0028 => Key::Quote,
+        0x0029 => Key::GraveAccent,
+        0x002A => Key::LeftShift,
+        0x002B => Key::BackSlash,
+        0x002C => Key::Z,
+        0x002D => Key::X,
+        0x002E => Key::C,
+        0x002F => Key::V,
+        0x0030 => Key::B,
+        0x0031 => Key::N,
+        0x0032 => Key::M,
+        0x0033 => Key::Comma,
+        0x0034 => Key::Period,
+        0x0035 => Key::ForwardSlash,
+        0x0036 => Key::RightShift,
+        0x0037 => Key::NumpadMultiply,
+        0x0038 => Key::LeftAlt,
+        0x0039 => Key::Spacebar,
+        0x003A => Key::CapsLock,
+        0x003B => Key::F1,
+        0x003C => Key::F2,
+        0x003D => Key::F3,
+        0x003E => Key::F4,
+        0x003F => Key::F5,
+        0x0040 => Key::F6,
+        0x0041 => Key::F7,
+        0x0042 => Key::F8,
+        0x0043 => Key::F9,
+        0x0044 => Key::F10,
+        // Pause shares its scancode with NumLock, only without the prefix.
+        0x0045 | 0xE046 => Key::Pause,
+        0x0046 => Key::ScrollLock,
+        0x0047 => Key::NumpadSeven,
+        0x0048 => Key::NumpadEight,
+        0x0049 => Key::NumpadNine,
+        0x004A => Key::NumpadSubtract,
+        0x004B => Key::NumpadFour,
+        0x004C => Key::NumpadFive,
+        0x004D => Key::NumpadSix,
+        0x004E => Key::NumpadAdd,
+        0x004F => Key::NumpadOne,
+        0x0050 => Key::NumpadTwo,
+        0x0051 => Key::NumpadThree,
+        0x0052 => Key::NumpadZero,
+        0x0053 => Key::NumpadDecimal,
+        // Alt + PrintScreen.
+        0x0054 | 0xE037 => Key::PrintScreen,
+        0x0056 => Key::IntlBackslash,
+        0x0057 => Key::F11,
+        0x0058 => Key::F12,
+        0x0059 => Key::NumpadEqual,
+        0x0064 => Key::F13,
+        0x0065 => Key::F14,
+        0x0066 => Key::F15,
+        0x0067 => Key::F16,
+        0x0068 => Key::F17,
+        0x0069 => Key::F18,
+        0x006A => Key::F19,
+        0x006B => Key::F20,
+        0x006C => Key::F21,
+        0x006D => Key::F22,
+        0x006E => Key::F23,
+        0x0070 => Key::KanaMode,
+        0x0071 | 0xE0F1 => Key::Lang2,
+        0x0072 | 0xE0F2 => Key::Lang1,
+        0x0073 => Key::IntlRo,
+        0x0076 => Key::F24,
+        0x0079 => Key::Convert,
+        0x007B => Key::NonConvert,
+        0x007D => Key::IntlYen,
+        0x007E => Key::NumpadComma,
+        0xE010 => Key::MediaPreviousTrack,
+        0xE019 => Key::MediaNextTrack,
+        0xE01C => Key::NumpadEnter,
+        0xE01D => Key::RightControl,
+        0xE020 => Key::VolumeMute,
+        0xE021 => Key::LaunchCalculator,
+        0xE022 => Key::MediaPlayPause,
+        0xE024 => Key::MediaStop,
+        0xE02E => Key::VolumeDown,
+        0xE030 => Key::VolumeUp,
+        0xE032 => Key::BrowserHome,
+        0xE035 => Key::NumpadDivide,
+        0xE038 => Key::RightAlt,
+        0xE045 => Key::NumLock,
+        0xE047 => Key::Home,
+        0xE048 => Key::UpArrow,
+        0xE049 => Key::PageUp,
+        0xE04B => Key::LeftArrow,
+        0xE04D => Key::RightArrow,
+        0xE04F => Key::End,
+        0xE050 => Key::DownArrow,
+        0xE051 => Key::PageDown,
+        0xE052 => Key::Insert,
+        0xE053 => Key::Delete,
+        0xE05B => Key::LeftSuper,
+        0xE05C => Key::RightSuper,
+        0xE05D => Key::Menu,
+        0xE065 => Key::BrowserSearch,
+        0xE066 => Key::BrowserFavorites,
+        0xE067 => Key::BrowserRefresh,
+        0xE068 => Key::BrowserStop,
+        0xE069 => Key::BrowserForward,
+        0xE06A => Key::BrowserBack,
+        0xE06C => Key::LaunchMail,
+        _ => Key::Unknown(scancode),
+    }
+}
+
+/// Translates a virtual-key code into a `Key`, which follows the keyboard la-
+/// yout. Returns `None` for virtual keys that do not say which physical key
+/// was used (such as `VK_SHIFT`), in which case the scancode has to decide.
+pub(super) fn translate_virtual_key(virtual_key: u16) -> Option<Key> {
+    let key = match virtual_key {
+        0x08 => Key::Backspace,          // VK_BACK
+        0x09 => Key::Tab,                // VK_TAB
+        0x13 => Key::Pause,              // VK_PAUSE
+        0x14 => Key::CapsLock,           // VK_CAPITAL
+        0x15 => Key::KanaMode,           // VK_KANA
+        0x19 => Key::Lang2,              // VK_HANJA
+        0x1B => Key::Escape,             // VK_ESCAPE
+        0x1C => Key::Convert,            // VK_CONVERT
+        0x1D => Key::NonConvert,         // VK_NONCONVERT
+        0x20 => Key::Spacebar,           // VK_SPACE
+        0x21 => Key::PageUp,             // VK_PRIOR
+        0x22 => Key::PageDown,           // VK_NEXT
+        0x23 => Key::End,                // VK_END
+        0x24 => Key::Home,               // VK_HOME
+        0x25 => Key::LeftArrow,          // VK_LEFT
+        0x26 => Key::UpArrow,            // VK_UP
+        0x27 => Key::RightArrow,         // VK_RIGHT
+        0x28 => Key::DownArrow,          // VK_DOWN
+        0x2C => Key::PrintScreen,        // VK_SNAPSHOT
+        0x2D => Key::Insert,             // VK_INSERT
+        0x2E => Key::Delete,             // VK_DELETE
+        0x30 => Key::Zero,               // VK_0
+        0x31 => Key::One,                // VK_1
+        0x32 => Key::Two,                // VK_2
+        0x33 => Key::Three,              // VK_3
+        0x34 => Key::Four,               // VK_4
+        0x35 => Key::Five,               // VK_5
+        0x36 => Key::Six,                // VK_6
+        0x37 => Key::Seven,              // VK_7
+        0x38 => Key::Eight,              // VK_8
+        0x39 => Key::Nine,               // VK_9
+        0x41 => Key::A,                  // VK_A
+        0x42 => Key::B,                  // VK_B
+        0x43 => Key::C,                  // VK_C
+        0x44 => Key::D,                  // VK_D
+        0x45 => Key::E,                  // VK_E
+        0x46 => Key::F,                  // VK_F
+        0x47 => Key::G,                  // VK_G
+        0x48 => Key::H,                  // VK_H
+        0x49 => Key::I,                  // VK_I
+        0x4A => Key::J,                  // VK_J
+        0x4B => Key::K,                  // VK_K
+        0x4C => Key::L,                  // VK_L
+        0x4D => Key::M,                  // VK_M
+        0x4E => Key::N,                  // VK_N
+        0x4F => Key::O,                  // VK_O
+        0x50 => Key::P,                  // VK_P
+        0x51 => Key::Q,                  // VK_Q
+        0x52 => Key::R,                  // VK_R
+        0x53 => Key::S,                  // VK_S
+        0x54 => Key::T,                  // VK_T
+        0x55 => Key::U,                  // VK_U
+        0x56 => Key::V,                  // VK_V
+        0x57 => Key::W,                  // VK_W
+        0x58 => Key::X,                  // VK_X
+        0x59 => Key::Y,                  // VK_Y
+        0x5A => Key::Z,                  // VK_Z
+        0x5B => Key::LeftSuper,          // VK_LWIN
+        0x5C => Key::RightSuper,         // VK_RWIN
+        0x5D => Key::Menu,               // VK_APPS
+        0x60 => Key::NumpadZero,         // VK_NUMPAD0
+        0x61 => Key::NumpadOne,          // VK_NUMPAD1
+        0x62 => Key::NumpadTwo,          // VK_NUMPAD2
+        0x63 => Key::NumpadThree,        // VK_NUMPAD3
+        0x64 => Key::NumpadFour,         // VK_NUMPAD4
+        0x65 => Key::NumpadFive,         // VK_NUMPAD5
+        0x66 => Key::NumpadSix,          // VK_NUMPAD6
+        0x67 => Key::NumpadSeven,        // VK_NUMPAD7
+        0x68 => Key::NumpadEight,        // VK_NUMPAD8
+        0x69 => Key::NumpadNine,         // VK_NUMPAD9
+        0x6A => Key::NumpadMultiply,     // VK_MULTIPLY
+        0x6B => Key::NumpadAdd,          // VK_ADD
+        0x6C => Key::NumpadComma,        // VK_SEPARATOR
+        0x6D => Key::NumpadSubtract,     // VK_SUBTRACT
+        0x6E => Key::NumpadDecimal,      // VK_DECIMAL
+        0x6F => Key::NumpadDivide,       // VK_DIVIDE
+        0x70 => Key::F1,                 // VK_F1
+        0x71 => Key::F2,                 // VK_F2
+        0x72 => Key::F3,                 // VK_F3
+        0x73 => Key::F4,                 // VK_F4
+        0x74 => Key::F5,                 // VK_F5
+        0x75 => Key::F6,                 // VK_F6
+        0x76 => Key::F7,                 // VK_F7
+        0x77 => Key::F8,                 // VK_F8
+        0x78 => Key::F9,                 // VK_F9
+        0x79 => Key::F10,                // VK_F10
+        0x7A => Key::F11,                // VK_F11
+        0x7B => Key::F12,                // VK_F12
+        0x7C => Key::F13,                // VK_F13
+        0x7D => Key::F14,                // VK_F14
+        0x7E => Key::F15,                // VK_F15
+        0x7F => Key::F16,                // VK_F16
+        0x80 => Key::F17,                // VK_F17
+        0x81 => Key::F18,                // VK_F18
+        0x82 => Key::F19,                // VK_F19
+        0x83 => Key::F20,                // VK_F20
+        0x84 => Key::F21,                // VK_F21
+        0x85 => Key::F22,                // VK_F22
+        0x86 => Key::F23,                // VK_F23
+        0x87 => Key::F24,                // VK_F24
+        0x90 => Key::NumLock,            // VK_NUMLOCK
+        0x91 => Key::ScrollLock,         // VK_SCROLL
+        0x92 => Key::NumpadEqual,        // VK_OEM_NEC_EQUAL
+        0xA0 => Key::LeftShift,          // VK_LSHIFT
+        0xA1 => Key::RightShift,         // VK_RSHIFT
+        0xA2 => Key::LeftControl,        // VK_LCONTROL
+        0xA3 => Key::RightControl,       // VK_RCONTROL
+        0xA4 => Key::LeftAlt,            // VK_LMENU
+        0xA5 => Key::RightAlt,           // VK_RMENU
+        0xA6 => Key::BrowserBack,        // VK_BROWSER_BACK
+        0xA7 => Key::BrowserForward,     // VK_BROWSER_FORWARD
+        0xA8 => Key::BrowserRefresh,     // VK_BROWSER_REFRESH
+        0xA9 => Key::BrowserStop,        // VK_BROWSER_STOP
+        0xAA => Key::BrowserSearch,      // VK_BROWSER_SEARCH
+        0xAB => Key::BrowserFavorites,   // VK_BROWSER_FAVORITES
+        0xAC => Key::BrowserHome,        // VK_BROWSER_HOME
+        0xAD => Key::VolumeMute,         // VK_VOLUME_MUTE
+        0xAE => Key::VolumeDown,         // VK_VOLUME_DOWN
+        0xAF => Key::VolumeUp,           // VK_VOLUME_UP
+        0xB0 => Key::MediaNextTrack,     // VK_MEDIA_NEXT_TRACK
+        0xB1 => Key::MediaPreviousTrack, // VK_MEDIA_PREV_TRACK
+        0xB2 => Key::MediaStop,          // VK_MEDIA_STOP
+        0xB3 => Key::MediaPlayPause,     // VK_MEDIA_PLAY_PAUSE
+        0xB4 => Key::LaunchMail,         // VK_LAUNCH_MAIL
+        0xBA => Key::Semicolon,          // VK_OEM_1
+        0xBB => Key::EqualSign,          // VK_OEM_PLUS
+        0xBC => Key::Comma,              // VK_OEM_COMMA
+        0xBD => Key::Hyphen,             // VK_OEM_MINUS
+        0xBE => Key::Period,             // VK_OEM_PERIOD
+        0xBF => Key::ForwardSlash,       // VK_OEM_2
+        0xC0 => Key::GraveAccent,        // VK_OEM_3
+        0xDB => Key::LeftSquareBracket,  // VK_OEM_4
+        0xDC => Key::BackSlash,          // VK_OEM_5
+        0xDD => Key::RightSquareBracket, // VK_OEM_6
+        0xDE => Key::Quote,              // VK_OEM_7
+        0xE2 => Key::IntlBackslash,      // VK_OEM_102
+        // VK_SHIFT, VK_CONTROL, VK_MENU and VK_RETURN do not tell left from
+        // right or the main Enter from the keypad one.
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extended_keys_get_a_prefix() {
+        // Right Control: scancode 0x1D with the extended bit set.
+        assert_eq!(lparam_to_scancode(0x011D_0001), 0xE01D);
+        // Left Control.
+        assert_eq!(lparam_to_scancode(0x001D_0001), 0x001D);
+    }
+
+    #[test]
+    fn scancodes_ignore_the_layout() {
+        assert_eq!(translate_scancode(0x0010), Key::Q);
+        assert_eq!(translate_scancode(0xE01D), Key::RightControl);
+        assert_eq!(translate_scancode(0xE048), Key::UpArrow);
+        assert_eq!(translate_scancode(0x0FFF), Key::Unknown(0x0FFF));
+    }
+
+    #[test]
+    fn ambiguous_virtual_keys_are_left_to_the_scancode() {
+        assert_eq!(translate_virtual_key(0x41), Some(Key::A));
+        assert_eq!(translate_virtual_key(0x10), None); // VK_SHIFT
+        assert_eq!(translate_virtual_key(0x0D), None); // VK_RETURN
+    }
+}
@@ -0,0 +1,59 @@
+use crate::MouseButton;
+
+/// How far the wheel has to turn for one notch, as defined by Win32.
+const WHEEL_DELTA: f64 = 120.0;
+
+/// Splits the `LPARAM` of a mouse or move message into its coordinates. They
+/// are signed, since the cursor can be left or above of the window while it
+/// is captured and windows can sit on a monitor left of the primary one.
+pub(super) fn lparam_to_point(l_param: isize) -> (i32, i32) {
+    let x = (l_param & 0xFFFF) as u16 as i16;
+    let y = ((l_param >> 16) & 0xFFFF) as u16 as i16;
+
+    (x.into(), y.into())
+}
+
+/// Turns the `WPARAM` of `WM_MOUSEWHEEL` or `WM_MOUSEHWHEEL` into notches. A
+/// positive value means up or right, which matches the X11 backend. Precise
+/// touchpads send fractions of a notch.
+pub(super) fn wparam_to_wheel_delta(w_param: usize) -> f64 {
+    let delta = ((w_param >> 16) & 0xFFFF) as u16 as i16;
+
+    f64::from(delta) / WHEEL_DELTA
+}
+
+/// Translates the high word of the `WPARAM` of `WM_XBUTTONDOWN` and `WM_XBUT-
+/// TONUP`. The side buttons are the eighth and ninth button on X11 too. Re-
+/// turns None for buttons Win32 does not define.
+pub(super) fn translate_xbutton(w_param: usize) -> Option<MouseButton> {
+    match (w_param >> 16) & 0xFFFF {
+        0x0001 => Some(MouseButton::Eight), // XBUTTON1
+        0x0002 => Some(MouseButton::Nine),  // XBUTTON2
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lparam_to_point_is_signed() {
+        assert_eq!(lparam_to_point(0x0020_0010), (16, 32));
+        assert_eq!(lparam_to_point(0xFFFE_FFFF), (-1, -2));
+    }
+
+    #[test]
+    fn wheel_delta_is_in_notches() {
+        assert_eq!(wparam_to_wheel_delta(0x0078_0000), 1.0);
+        assert_eq!(wparam_to_wheel_delta(0xFF88_0000), -1.0);
+        assert_eq!(wparam_to_wheel_delta(0x003C_0008), 0.5);
+    }
+
+    #[test]
+    fn xbuttons_are_side_buttons() {
+        assert_eq!(translate_xbutton(0x0001_0020), Some(MouseButton::Eight));
+        assert_eq!(translate_xbutton(0x0002_0000), Some(MouseButton::Nine));
+        assert_eq!(translate_xbutton(0x0004_0000), None);
+    }
+}
@@ -1,18 +1,85 @@
+use nengine::platform::CrossPlatformEventLoop;
 use nengine::platform::CrossPlatformWindow;
-use nengine::platform::Window;
-use nengine::Event;
+use nengine::platform::EventLoop;
+use nengine::platform::FullscreenMode;
+use nengine::platform::WindowBuilder;
+use nengine::{Event, Key};
 
 fn main() {
-    let mut window = Window::new(800, 600, "Nengine Sandbox", false);
-    window.set_event_callback(|event| {
-        if let Event::MouseMove { x, y } = event {
-            println!("[INFO]: Cursor position: {} {}", x, y);
+    let mut event_loop = match EventLoop::new() {
+        Ok(event_loop) => event_loop,
+        Err(error) => {
+            eprintln!("[ERROR]: Could not start the event loop: {}", error);
+            return;
         }
-    });
+    };
+
+    let builder = WindowBuilder::new()
+        .with_title("Nengine Sandbox")
+        .with_size(800, 600);
+
+    let mut window = match builder.build_in(&event_loop) {
+        Ok(window) => window,
+        Err(error) => {
+            eprintln!("[ERROR]: Could not create a window: {}", error);
+            return;
+        }
+    };
+
+    let inspector_builder = WindowBuilder::new()
+        .with_title("Nengine Inspector")
+        .with_size(300, 600)
+        .with_parent(window.id());
+
+    let mut inspector = match inspector_builder.build_in(&event_loop) {
+        Ok(window) => window,
+        Err(error) => {
+            eprintln!("[ERROR]: Could not create a window: {}", error);
+            return;
+        }
+    };
+
+    let mut key_presses = 0;
+    window.set_event_callback(Box::new(move |event: &Event| {
+        if let Event::Key { is_press: true, .. } = event {
+            key_presses += 1;
+            println!("[INFO]: {} keys pressed so far", key_presses);
+        }
+    }));
 
     window.show();
+    inspector.show();
+
+    let mut is_fullscreen = false;
+
+    while window.is_open() || inspector.is_open() {
+        let mut toggle_fullscreen = false;
 
-    while window.is_open() {
-        window.poll_events();
+        for (id, event) in event_loop.poll_events() {
+            match event {
+                Event::MouseMove { x, y } if id == window.id() => {
+                    println!("[INFO]: Cursor position: {} {}", x, y);
+                }
+                Event::Key {
+                    keycode: Key::F11,
+                    is_press: true,
+                    is_repeat: false,
+                    ..
+                } if id == window.id() => toggle_fullscreen = true,
+                // Closing one window leaves the other one running.
+                Event::CloseRequested if id == window.id() => window.close(),
+                Event::CloseRequested if id == inspector.id() => inspector.close(),
+                _ => {}
+            }
+        }
+
+        if toggle_fullscreen {
+            is_fullscreen = !is_fullscreen;
+            window.set_fullscreen(if is_fullscreen {
+                FullscreenMode::Borderless
+            } else {
+                FullscreenMode::Windowed
+            });
+        }
     }
 }